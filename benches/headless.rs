@@ -0,0 +1,89 @@
+// Copyright (C) 2025 Dayton Fishell
+// Nexel-24 Game Console Emulator
+// This file is part of Nexel-24.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version. See the LICENSE file in the project root for details.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Compare `step_frame` with and without rendering
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use nexel_core::Nexel24;
+use nexel_core::vdp::{IrqFlags, SpriteAttr, SpriteSize, Vdp, VdpRegister};
+
+fn setup(mut emu: Nexel24) -> Nexel24 {
+    // A game-style main loop: a burst of work, then WFI until VBLANK
+    let mut program = vec![0u8; 0x41];
+    program[0..3].copy_from_slice(&[0x20, 0x00, 0xFF]); // Reset vector: 0xFF0020
+    program[0x15..0x18].copy_from_slice(&[0x40, 0x00, 0xFF]); // IRQ 6: 0xFF0040
+    program[0x20..0x2B].copy_from_slice(&[
+        0x41, // CLI
+        0x03, 0x00, 0x10, // LDX #0x1000
+        0x19, 0x01, // DEC X
+        0x32, 0xFC, // BNE -4
+        0x43, // WFI
+        0x30, 0xF6, // BRA -10
+    ]);
+    program[0x40] = 0x42; // RTI
+    emu.load_bios(&program);
+    emu.reset_cpu();
+    emu.vdp_mut()
+        .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::VBLANK.bits());
+    emu.vdp_mut().set_display_enable(true);
+    emu.vdp_mut().set_layer_enable(true, true, true);
+    emu.vdp_mut().set_backdrop_color(0x10, 0x20, 0x30);
+    // A busy scene: every sprite slot filled with large opaque sprites
+    emu.vdp_mut().load_tile_data(0, &[0x11; 0x8000]);
+    emu.vdp_mut()
+        .load_palette_16(0, &[(0, 0, 0), (0x3F, 0x20, 0x10), (0x10, 0x3F, 0x20)]);
+    for i in 0..Vdp::OAM_SPRITES {
+        let x = (i as u16 * 37) % 384;
+        let y = (i as u16 * 53) % 288;
+        let sprite =
+            SpriteAttr::from_parts(x, y, 0, 0, SpriteSize::Size64x64, false, false, 0, true);
+        emu.vdp_mut().set_sprite(i, sprite);
+    }
+    emu
+}
+
+fn step_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step_frame");
+
+    let mut emu = setup(Nexel24::new());
+    group.bench_function("rendering", |b| b.iter(|| black_box(&mut emu).step_frame()));
+
+    let mut emu = setup(Nexel24::new_headless());
+    group.bench_function("headless", |b| b.iter(|| black_box(&mut emu).step_frame()));
+
+    group.finish();
+}
+
+/// Fail the run if headless mode stops paying for itself
+fn headless_speedup(_: &mut Criterion) {
+    const FRAMES: u32 = 60;
+    let time = |mut emu: Nexel24| {
+        emu.step_frame();
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            black_box(&mut emu).step_frame();
+        }
+        start.elapsed()
+    };
+    let rendering = time(setup(Nexel24::new()));
+    let headless = time(setup(Nexel24::new_headless()));
+    let speedup = rendering.as_secs_f64() / headless.as_secs_f64();
+    println!("step_frame headless speedup: {speedup:.1}x");
+    assert!(
+        speedup >= 2.0,
+        "headless step_frame is only {speedup:.1}x faster than rendering"
+    );
+}
+
+criterion_group!(benches, step_frame, headless_speedup);
+criterion_main!(benches);
@@ -0,0 +1,108 @@
+// Copyright (C) 2025 Dayton Fishell
+// Nexel-24 Game Console Emulator
+// This file is part of Nexel-24.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version. See the LICENSE file in the project root for details.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Measure VDP rendering of a full 384x288 frame
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use nexel_core::vdp::{BgControl, Vdp, VdpRegister};
+
+const FRAME_CYCLES: u64 = Vdp::CYCLES_PER_SCANLINE * Vdp::SCANLINES_PER_FRAME as u64;
+
+/// BG1 with every tilemap entry set, cycling through 64 distinct tiles
+fn bg1_scene() -> Vdp {
+    let mut vdp = Vdp::new();
+    vdp.set_display_enable(true);
+    vdp.set_layer_enable(false, true, false);
+    vdp.write_reg(VdpRegister::Bg1Control as u32, BgControl::ENABLE.bits());
+    vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x8000);
+
+    let tiles: Vec<u8> = (0..64 * 32).map(|i| (i * 7 % 251) as u8 | 0x11).collect();
+    vdp.load_tile_data(0, &tiles);
+    let palette: Vec<_> = (0..16u8).map(|i| (i * 4, 0x3F - i * 4, i * 2)).collect();
+    vdp.load_palette_16(0, &palette);
+    for entry in 0..32 * 32u32 {
+        let tile = (entry % 64) as u16 | if entry % 3 == 0 { 0x0400 } else { 0 };
+        vdp.load_tile_data(0x8000 + entry * 2, &tile.to_le_bytes());
+    }
+    vdp
+}
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render_frame");
+
+    let mut vdp = bg1_scene();
+    group.bench_function("bg1", |b| b.iter(|| black_box(&mut vdp).step(FRAME_CYCLES)));
+
+    let mut vdp = bg1_scene();
+    vdp.set_scalar_bg1(true);
+    group.bench_function("bg1_scalar", |b| {
+        b.iter(|| black_box(&mut vdp).step(FRAME_CYCLES))
+    });
+
+    let mut vdp = bg1_scene();
+    vdp.set_tile_cache_bypass(true);
+    group.bench_function("bg1_uncached", |b| {
+        b.iter(|| black_box(&mut vdp).step(FRAME_CYCLES))
+    });
+
+    group.finish();
+}
+
+/// Time `frames` full frames of a scene after one warm-up frame
+fn time_frames(mut vdp: Vdp, frames: u32) -> f64 {
+    vdp.step(FRAME_CYCLES);
+    let start = Instant::now();
+    for _ in 0..frames {
+        black_box(&mut vdp).step(FRAME_CYCLES);
+    }
+    start.elapsed().as_secs_f64()
+}
+
+/// Fail the run if the 8-pixel span path stops paying for itself
+fn span_speedup(_: &mut Criterion) {
+    let mut scalar = bg1_scene();
+    scalar.set_scalar_bg1(true);
+    let speedup = time_frames(scalar, 60) / time_frames(bg1_scene(), 60);
+    println!("bg1 span speedup over per-pixel: {speedup:.1}x");
+    assert!(
+        speedup >= 2.0,
+        "bg1 spans are only {speedup:.1}x faster than per-pixel drawing"
+    );
+}
+
+/// Fail the run if the cached renderer saves less than 30% of the frame
+/// time of reading every pixel straight from VRAM
+fn tile_cache_saving(_: &mut Criterion) {
+    let uncached = || {
+        let mut vdp = bg1_scene();
+        vdp.set_tile_cache_bypass(true);
+        time_frames(vdp, 60)
+    };
+    let mut per_pixel = bg1_scene();
+    per_pixel.set_scalar_bg1(true);
+    let per_pixel_saving = 1.0 - time_frames(per_pixel, 60) / uncached();
+    let saving = 1.0 - time_frames(bg1_scene(), 60) / uncached();
+    println!(
+        "bg1 saving over uncached: {:.0}% (per-pixel path alone: {:.0}%)",
+        saving * 100.0,
+        per_pixel_saving * 100.0
+    );
+    assert!(
+        saving >= 0.3,
+        "the cached renderer only saves {:.0}% of an uncached bg1 frame",
+        saving * 100.0
+    );
+}
+
+criterion_group!(benches, render, span_speedup, tile_cache_saving);
+criterion_main!(benches);
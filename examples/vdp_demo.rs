@@ -16,7 +16,7 @@
 //! - Sprite rendering
 //! - VRAM/CRAM access
 
-use nexel_core::vdp::{SpriteAttr, SpriteSize, Vdp};
+use nexel_core::vdp::{SpriteAttr, Vdp, VdpRegister};
 
 fn main() {
     println!("Nexel-24 VDP-T Demo");
@@ -72,6 +72,8 @@ fn main() {
 
     // Configure sprites
     println!("\nConfiguring sprites...");
+    // The tile above is 8bpp, so sprites use 256-color mode
+    vdp.write_reg(VdpRegister::SpriteControl as u32, 0x0080);
 
     // Sprite 0: Simple 8x8 sprite
     let sprite0 = SpriteAttr {
@@ -12,25 +12,54 @@
 
 use bitflags::bitflags;
 
+use crate::core::Bus24;
+
 /// Number of audio channels supported by APU-6
 pub const APU_CHANNEL_COUNT: usize = 6;
 
+/// Output sample rate in Hz
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// CPU cycles per output sample (18.432 MHz / 48 kHz)
+pub const CYCLES_PER_SAMPLE: u64 = 384;
+
+/// Stereo frames kept when nobody drains the output buffer (one second)
+const MAX_BUFFERED_SAMPLES: usize = SAMPLE_RATE as usize;
+
 /// Each channel has a fixed window of registers
 const CHANNEL_STRIDE: u32 = 0x10;
-const STATUS_OFFSET: u32 = (APU_CHANNEL_COUNT as u32) * CHANNEL_STRIDE;
+/// Offset of the global status register (see [`StatusFlags`])
+pub const STATUS_OFFSET: u32 = (APU_CHANNEL_COUNT as u32) * CHANNEL_STRIDE;
 const GLOBAL_CONTROL_OFFSET: u32 = STATUS_OFFSET + 0x01;
 const GLOBAL_VERSION_OFFSET: u32 = STATUS_OFFSET + 0x02;
+/// Echo delay in stereo frames (16-bit, zero disables the echo)
+const ECHO_DELAY_LO_OFFSET: u32 = STATUS_OFFSET + 0x03;
+const ECHO_DELAY_HI_OFFSET: u32 = STATUS_OFFSET + 0x04;
+/// Echo feedback in 1/256 steps (0x80 = 50%)
+const ECHO_FEEDBACK_OFFSET: u32 = STATUS_OFFSET + 0x05;
+/// Wavetable RAM byte address and data port (auto-increments)
+const WAVETABLE_ADDR_OFFSET: u32 = STATUS_OFFSET + 0x06;
+const WAVETABLE_DATA_OFFSET: u32 = STATUS_OFFSET + 0x07;
+
+/// Wavetable RAM holds 32 waveforms of 8 unsigned samples each
+pub const WAVETABLE_SLOTS: usize = 32;
+pub const WAVETABLE_LENGTH: usize = 8;
 const SUPPORTED_VERSION: u8 = 0x10;
 
 bitflags! {
-    struct StatusFlags: u8 {
+    /// Bits of the global status register
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StatusFlags: u8 {
+        /// An enabled channel ran out of sample data; write 1 to acknowledge
         const BUFFER_EMPTY = 0x01;
+        /// At least one channel is enabled
         const CHANNEL_ACTIVE = 0x02;
     }
 }
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct EffectMask: u8 {
         const ECHO = 0x01;
         const CHORUS = 0x02;
@@ -40,6 +69,7 @@ bitflags! {
 
 /// Voice types supported per channel
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ChannelVoice {
     Pcm = 0,
@@ -69,7 +99,230 @@ impl ChannelVoice {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// One FM operator (sine oscillator)
+///
+/// `frequency` is a multiplier applied to the channel frequency and
+/// `phase` is measured in cycles (0.0-1.0). For the modulator, `amplitude`
+/// is the modulation index; for the carrier it is the output level. Both
+/// operators modulate themselves by `feedback`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FmOperator {
+    pub frequency: f32,
+    pub phase: f32,
+    pub amplitude: f32,
+    pub feedback: f32,
+}
+
+impl Default for FmOperator {
+    fn default() -> Self {
+        Self {
+            frequency: 1.0,
+            phase: 0.0,
+            amplitude: 1.0,
+            feedback: 0.0,
+        }
+    }
+}
+
+impl FmOperator {
+    /// Sine output at the current phase offset by `modulation`, and
+    /// self-modulated by `feedback`
+    fn output(&self, modulation: f32) -> f32 {
+        let angle = self.phase * std::f32::consts::TAU;
+        (angle + modulation + self.feedback * angle.sin()).sin()
+    }
+
+    fn advance(&mut self, channel_frequency: f32) {
+        self.phase += channel_frequency * self.frequency / SAMPLE_RATE as f32;
+        self.phase = self.phase.fract();
+    }
+}
+
+/// Galois LFSR taps for x^7 + x^6 + 1 (period 127)
+const LFSR_SHORT_TAPS: u16 = 0x0060;
+/// Galois LFSR taps for x^15 + x^14 + 1 (period 32767)
+const LFSR_LONG_TAPS: u16 = 0x6000;
+
+/// Channel control bit selecting short-period noise
+const CONTROL_SHORT_NOISE: u8 = 0x08;
+
+/// Resampling filter used when PCM plays at a rate other than `SAMPLE_RATE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterpolationMode {
+    /// No resampling: one byte per output sample, whatever the frequency
+    None = 0,
+    Linear = 1,
+    Cubic = 2,
+}
+
+impl InterpolationMode {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0x03 {
+            1 => InterpolationMode::Linear,
+            2 => InterpolationMode::Cubic,
+            _ => InterpolationMode::None,
+        }
+    }
+}
+
+/// Linear feedback shift register driving a noise channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Lfsr {
+    state: u16,
+    tap_mask: u16,
+}
+
+impl Lfsr {
+    fn new(short: bool) -> Self {
+        if short {
+            Self {
+                state: 0x007F,
+                tap_mask: LFSR_SHORT_TAPS,
+            }
+        } else {
+            Self {
+                state: 0x7FFF,
+                tap_mask: LFSR_LONG_TAPS,
+            }
+        }
+    }
+
+    fn is_short(&self) -> bool {
+        self.tap_mask == LFSR_SHORT_TAPS
+    }
+
+    /// Shift once, feeding the dropped bit back through the taps
+    fn step(&mut self) -> u16 {
+        let out = self.state & 1;
+        self.state >>= 1;
+        if out != 0 {
+            self.state ^= self.tap_mask;
+        }
+        self.state & 1
+    }
+}
+
+/// Envelope stage; `Off` once a release has run to silence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum EnvelopePhase {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Off,
+}
+
+/// ADSR volume envelope, with times measured in output samples
+///
+/// A time of zero jumps straight to the end of that stage, so the default
+/// envelope (no attack, full sustain) plays at the channel volume at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Envelope {
+    attack: u16,
+    decay: u16,
+    sustain: u8,
+    release: u16,
+    current_volume: f32,
+    phase: EnvelopePhase,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self {
+            attack: 0,
+            decay: 0,
+            sustain: 0xFF,
+            release: 0,
+            current_volume: 0.0,
+            phase: EnvelopePhase::Off,
+        }
+    }
+}
+
+impl Envelope {
+    fn key_on(&mut self) {
+        self.current_volume = 0.0;
+        self.phase = EnvelopePhase::Attack;
+    }
+
+    fn key_off(&mut self) {
+        if matches!(
+            self.phase,
+            EnvelopePhase::Attack | EnvelopePhase::Decay | EnvelopePhase::Sustain
+        ) {
+            self.phase = EnvelopePhase::Release;
+        }
+    }
+
+    fn sustain_level(&self) -> f32 {
+        self.sustain as f32 / 255.0
+    }
+
+    /// Step the envelope by one output sample
+    fn advance(&mut self) {
+        match self.phase {
+            EnvelopePhase::Attack => {
+                self.current_volume += 1.0 / self.attack.max(1) as f32;
+                if self.attack == 0 || self.current_volume >= 1.0 {
+                    self.current_volume = 1.0;
+                    self.phase = EnvelopePhase::Decay;
+                }
+            }
+            EnvelopePhase::Decay => {
+                let target = self.sustain_level();
+                self.current_volume -= (1.0 - target) / self.decay.max(1) as f32;
+                if self.decay == 0 || self.current_volume <= target {
+                    self.current_volume = target;
+                    self.phase = EnvelopePhase::Sustain;
+                }
+            }
+            EnvelopePhase::Sustain => self.current_volume = self.sustain_level(),
+            EnvelopePhase::Release => {
+                self.current_volume -= 1.0 / self.release.max(1) as f32;
+                if self.release == 0 || self.current_volume <= 0.0 {
+                    self.current_volume = 0.0;
+                    self.phase = EnvelopePhase::Off;
+                }
+            }
+            EnvelopePhase::Off => self.current_volume = 0.0,
+        }
+    }
+
+    /// Indirect envelope register file behind channel registers 13/14
+    fn read(&self, index: u8) -> u8 {
+        match index {
+            0 => self.attack as u8,
+            1 => (self.attack >> 8) as u8,
+            2 => self.decay as u8,
+            3 => (self.decay >> 8) as u8,
+            4 => self.sustain,
+            5 => self.release as u8,
+            6 => (self.release >> 8) as u8,
+            _ => (self.current_volume * 255.0).round() as u8,
+        }
+    }
+
+    fn write(&mut self, index: u8, value: u8) {
+        match index {
+            0 => self.attack = (self.attack & 0xFF00) | value as u16,
+            1 => self.attack = (self.attack & 0x00FF) | ((value as u16) << 8),
+            2 => self.decay = (self.decay & 0xFF00) | value as u16,
+            3 => self.decay = (self.decay & 0x00FF) | ((value as u16) << 8),
+            4 => self.sustain = value,
+            5 => self.release = (self.release & 0xFF00) | value as u16,
+            6 => self.release = (self.release & 0x00FF) | ((value as u16) << 8),
+            _ => {} // Current level is read-only
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct ChannelState {
     enabled: bool,
     voice: ChannelVoice,
@@ -80,6 +333,24 @@ struct ChannelState {
     sample_address: u32,
     sample_length: u16,
     buffer_empty: bool,
+    modulator: FmOperator,
+    carrier: FmOperator,
+    pcm_position: u32, // Bytes of PCM data played from sample_address
+    pcm_frac: f32,     // Fractional position between pcm_position and the next byte
+    interpolation: InterpolationMode,
+    envelope: Envelope,
+    envelope_index: u8,
+    wavetable_slot: u8,
+    wavetable_phase: f32, // Position in the waveform, in cycles (0.0-1.0)
+    lfsr: Lfsr,
+    noise_counter: u16, // Samples until the next LFSR clock
+}
+
+impl ChannelState {
+    /// Enabled, or disabled but still fading out
+    fn sounding(&self) -> bool {
+        self.enabled || self.envelope.phase == EnvelopePhase::Release
+    }
 }
 
 impl Default for ChannelState {
@@ -94,16 +365,55 @@ impl Default for ChannelState {
             sample_address: 0,
             sample_length: 0,
             buffer_empty: true,
+            modulator: FmOperator::default(),
+            carrier: FmOperator::default(),
+            pcm_position: 0,
+            pcm_frac: 0.0,
+            interpolation: InterpolationMode::None,
+            envelope: Envelope::default(),
+            envelope_index: 0,
+            wavetable_slot: 0,
+            wavetable_phase: 0.0,
+            lfsr: Lfsr::new(false),
+            noise_counter: 0,
         }
     }
 }
 
+/// Snapshot of the APU-6 registers, voices and mixer, used for save states
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApuState {
+    channels: [ChannelState; APU_CHANNEL_COUNT],
+    status: u8,
+    global_control: u8,
+    buffer_empty_latch: bool,
+    audio_buffer: Vec<i16>,
+    sample_cycles: u64,
+    echo_delay: u16,
+    echo_feedback: u8,
+    echo_buffer: Vec<i16>,
+    echo_pos: usize,
+    echo_live: usize,
+    wavetable: Vec<u8>,
+    wavetable_addr: u8,
+}
+
 /// Software representation of the APU-6 subsystem
 pub struct Apu {
     channels: [ChannelState; APU_CHANNEL_COUNT],
     status: StatusFlags,
     global_control: u8,
     buffer_empty_latch: bool,
+    audio_buffer: Vec<i16>, // Interleaved left/right samples
+    sample_cycles: u64,     // Cycles not yet turned into an output sample
+    echo_delay: u16,
+    echo_feedback: u8,
+    echo_buffer: Vec<i16>, // Interleaved left/right ring buffer, `echo_delay` frames
+    echo_pos: usize,
+    echo_live: usize, // Non-zero entries in echo_buffer
+    wavetable: [u8; WAVETABLE_SLOTS * WAVETABLE_LENGTH],
+    wavetable_addr: u8,
 }
 
 impl Apu {
@@ -113,9 +423,63 @@ impl Apu {
             status: StatusFlags::BUFFER_EMPTY,
             global_control: 0,
             buffer_empty_latch: false,
+            audio_buffer: Vec::new(),
+            sample_cycles: 0,
+            echo_delay: 0,
+            echo_feedback: 0,
+            echo_buffer: Vec::new(),
+            echo_pos: 0,
+            echo_live: 0,
+            wavetable: [0x80; WAVETABLE_SLOTS * WAVETABLE_LENGTH],
+            wavetable_addr: 0,
+        }
+    }
+
+    /// Return every register to its power-on value, keeping wavetable RAM
+    pub fn reset_registers(&mut self) {
+        *self = Self {
+            wavetable: self.wavetable,
+            ..Self::new()
+        };
+    }
+
+    /// Capture the full APU state for a save state
+    pub fn save(&self) -> ApuState {
+        ApuState {
+            channels: self.channels,
+            status: self.status.bits(),
+            global_control: self.global_control,
+            buffer_empty_latch: self.buffer_empty_latch,
+            audio_buffer: self.audio_buffer.clone(),
+            sample_cycles: self.sample_cycles,
+            echo_delay: self.echo_delay,
+            echo_feedback: self.echo_feedback,
+            echo_buffer: self.echo_buffer.clone(),
+            echo_pos: self.echo_pos,
+            echo_live: self.echo_live,
+            wavetable: self.wavetable.to_vec(),
+            wavetable_addr: self.wavetable_addr,
         }
     }
 
+    /// Restore APU state previously captured with [`Apu::save`]
+    pub fn load(&mut self, state: &ApuState) {
+        self.channels = state.channels;
+        self.status = StatusFlags::from_bits_truncate(state.status);
+        self.global_control = state.global_control;
+        self.buffer_empty_latch = state.buffer_empty_latch;
+        self.audio_buffer = state.audio_buffer.clone();
+        self.sample_cycles = state.sample_cycles;
+        self.echo_delay = state.echo_delay;
+        self.echo_feedback = state.echo_feedback;
+        self.echo_buffer = state.echo_buffer.clone();
+        self.echo_pos = state.echo_pos;
+        self.echo_live = state.echo_live;
+        let len = state.wavetable.len().min(self.wavetable.len());
+        self.wavetable[..len].copy_from_slice(&state.wavetable[..len]);
+        self.wavetable_addr = state.wavetable_addr;
+    }
+
     fn channel_index(offset: u32) -> Option<(usize, u32)> {
         if offset < STATUS_OFFSET {
             let idx = (offset / CHANNEL_STRIDE) as usize;
@@ -145,30 +509,49 @@ impl Apu {
         let channel = &mut self.channels[idx];
         match reg {
             0 => {
+                let was_enabled = channel.enabled;
                 channel.enabled = value & 0x01 != 0;
                 channel.voice = ChannelVoice::from_bits((value >> 1) & 0x03);
+                channel.interpolation = InterpolationMode::from_bits(value >> 4);
+                let short = value & CONTROL_SHORT_NOISE != 0;
+                if short != channel.lfsr.is_short() {
+                    channel.lfsr = Lfsr::new(short);
+                }
+                if channel.enabled && !was_enabled {
+                    channel.envelope.key_on();
+                    channel.wavetable_phase = 0.0;
+                } else if !channel.enabled && was_enabled {
+                    channel.envelope.key_off();
+                }
                 if channel.enabled {
                     channel.buffer_empty = channel.sample_length == 0;
+                    channel.pcm_position = 0;
+                    channel.pcm_frac = 0.0;
                 }
             }
             1 => channel.volume = value,
             2 => channel.pan = value,
-            3 => {
-                if value & 0x01 != 0 {
-                    channel.buffer_empty = false;
-                }
-            }
+            3 if value & 0x01 != 0 => channel.buffer_empty = false,
             4 => channel.frequency = (channel.frequency & 0xFF00) | value as u16,
             5 => channel.frequency = (channel.frequency & 0x00FF) | ((value as u16) << 8),
             6 => channel.effect = EffectMask::from_bits_truncate(value),
-            8 => channel.sample_address = (channel.sample_address & 0xFFFF_FF00) | value as u32,
+            7 => channel.wavetable_slot = value % WAVETABLE_SLOTS as u8,
+            8 => {
+                channel.sample_address = (channel.sample_address & 0xFFFF_FF00) | value as u32;
+                channel.pcm_position = 0;
+                channel.pcm_frac = 0.0;
+            }
             9 => {
                 channel.sample_address =
-                    (channel.sample_address & 0xFFFF_00FF) | ((value as u32) << 8)
+                    (channel.sample_address & 0xFFFF_00FF) | ((value as u32) << 8);
+                channel.pcm_position = 0;
+                channel.pcm_frac = 0.0;
             }
             10 => {
                 channel.sample_address =
-                    (channel.sample_address & 0xFF00_FFFF) | ((value as u32) << 16)
+                    (channel.sample_address & 0xFF00_FFFF) | ((value as u32) << 16);
+                channel.pcm_position = 0;
+                channel.pcm_frac = 0.0;
             }
             11 => {
                 channel.sample_length = (channel.sample_length & 0x00FF) | ((value as u16) << 8);
@@ -188,6 +571,8 @@ impl Apu {
                     channel.buffer_empty = false;
                 }
             }
+            13 => channel.envelope_index = value & 0x07,
+            14 => channel.envelope.write(channel.envelope_index, value),
             _ => {}
         }
         self.update_status();
@@ -201,7 +586,10 @@ impl Apu {
                 if channel.enabled {
                     value |= 0x01;
                 }
-                value | (channel.voice.bits() << 1)
+                if channel.lfsr.is_short() {
+                    value |= CONTROL_SHORT_NOISE;
+                }
+                value | (channel.voice.bits() << 1) | ((channel.interpolation as u8) << 4)
             }
             1 => channel.volume,
             2 => channel.pan,
@@ -218,11 +606,14 @@ impl Apu {
             4 => (channel.frequency & 0x00FF) as u8,
             5 => (channel.frequency >> 8) as u8,
             6 => channel.effect.bits(),
+            7 => channel.wavetable_slot,
             8 => (channel.sample_address & 0xFF) as u8,
             9 => ((channel.sample_address >> 8) & 0xFF) as u8,
             10 => ((channel.sample_address >> 16) & 0xFF) as u8,
             11 => (channel.sample_length >> 8) as u8,
             12 => (channel.sample_length & 0x00FF) as u8,
+            13 => channel.envelope_index,
+            14 => channel.envelope.read(channel.envelope_index),
             _ => 0xFF,
         }
     }
@@ -236,6 +627,11 @@ impl Apu {
             STATUS_OFFSET => self.status.bits(),
             GLOBAL_CONTROL_OFFSET => self.global_control,
             GLOBAL_VERSION_OFFSET => SUPPORTED_VERSION,
+            ECHO_DELAY_LO_OFFSET => self.echo_delay as u8,
+            ECHO_DELAY_HI_OFFSET => (self.echo_delay >> 8) as u8,
+            ECHO_FEEDBACK_OFFSET => self.echo_feedback,
+            WAVETABLE_ADDR_OFFSET => self.wavetable_addr,
+            WAVETABLE_DATA_OFFSET => self.wavetable[self.wavetable_addr as usize],
             _ => 0xFF,
         }
     }
@@ -247,26 +643,94 @@ impl Apu {
             return;
         }
         match offset {
-            STATUS_OFFSET => {
-                if value & 0x01 != 0 {
-                    self.buffer_empty_latch = false;
-                    self.channels
-                        .iter_mut()
-                        .for_each(|chan| chan.buffer_empty = false);
-                }
+            STATUS_OFFSET if value & 0x01 != 0 => {
+                self.buffer_empty_latch = false;
+                self.channels
+                    .iter_mut()
+                    .for_each(|chan| chan.buffer_empty = false);
             }
             GLOBAL_CONTROL_OFFSET => {
                 self.global_control = value;
             }
+            ECHO_DELAY_LO_OFFSET => self.set_echo_delay((self.echo_delay & 0xFF00) | value as u16),
+            ECHO_DELAY_HI_OFFSET => {
+                self.set_echo_delay((self.echo_delay & 0x00FF) | ((value as u16) << 8))
+            }
+            ECHO_FEEDBACK_OFFSET => self.echo_feedback = value,
+            WAVETABLE_ADDR_OFFSET => self.wavetable_addr = value,
+            WAVETABLE_DATA_OFFSET => {
+                self.wavetable[self.wavetable_addr as usize] = value;
+                self.wavetable_addr = self.wavetable_addr.wrapping_add(1);
+            }
             _ => {}
         }
     }
 
+    /// Resize the echo ring buffer, discarding anything still echoing
+    fn set_echo_delay(&mut self, frames: u16) {
+        self.echo_delay = frames;
+        self.echo_buffer = vec![0; frames as usize * 2];
+        self.echo_pos = 0;
+        self.echo_live = 0;
+    }
+
+    /// Apply the echo to one stereo frame and feed `send` into the delay line
+    fn process_echo(&mut self, dry: (f32, f32), send: (f32, f32)) -> (f32, f32) {
+        if self.echo_buffer.is_empty() {
+            return dry;
+        }
+        let feedback = self.echo_feedback as f32 / 256.0;
+        let index = self.echo_pos * 2;
+        let mut out = [dry.0, dry.1];
+        for (side, send) in [send.0, send.1].into_iter().enumerate() {
+            let slot = &mut self.echo_buffer[index + side];
+            let wet = feedback * *slot as f32 / 32768.0;
+            let was_live = *slot != 0;
+            *slot = Self::to_i16(send + wet);
+            match (was_live, *slot != 0) {
+                (false, true) => self.echo_live += 1,
+                (true, false) => self.echo_live -= 1,
+                _ => {}
+            }
+            out[side] += wet;
+        }
+        self.echo_pos = (self.echo_pos + 1) % self.echo_delay as usize;
+        (out[0], out[1])
+    }
+
     /// Advance audio processing by the specified number of CPU cycles.
+    ///
+    /// PCM channels have no access to sample memory here and only count down
+    /// their sample length; use [`Self::step_with_bus`] to play them back.
     pub fn step(&mut self, cycles: u64) {
+        self.advance(cycles, None);
+    }
+
+    /// Whether any PCM channel is playing and needs sample memory
+    ///
+    /// When this is false, [`Self::step`] and [`Self::step_with_bus`] behave
+    /// identically.
+    pub fn reads_bus(&self) -> bool {
+        self.channels.iter().any(|chan| {
+            chan.voice == ChannelVoice::Pcm && chan.sample_length > 0 && chan.sounding()
+        })
+    }
+
+    /// Advance audio processing, reading PCM sample data from the bus.
+    pub fn step_with_bus(&mut self, cycles: u64, bus: &Bus24) {
+        self.advance(cycles, Some(bus));
+    }
+
+    fn advance(&mut self, cycles: u64, bus: Option<&Bus24>) {
         if cycles == 0 {
             return;
         }
+        // Nothing playing: only the output sample clock moves
+        if self.echo_live == 0 && !self.channels.iter().any(ChannelState::sounding) {
+            self.sample_cycles = (self.sample_cycles + cycles) % CYCLES_PER_SAMPLE;
+            self.update_status();
+            return;
+        }
         let ticks = (cycles / 64).max(1);
         let mut saw_empty = false;
         for chan in &mut self.channels {
@@ -274,6 +738,10 @@ impl Apu {
                 continue;
             }
             if chan.sample_length > 0 {
+                // PCM playback consumes the length one sample at a time
+                if bus.is_some() && chan.voice == ChannelVoice::Pcm {
+                    continue;
+                }
                 let consumed = ticks.min(chan.sample_length as u64) as u16;
                 chan.sample_length = chan.sample_length.saturating_sub(consumed);
                 if chan.sample_length == 0 {
@@ -288,9 +756,187 @@ impl Apu {
         if saw_empty {
             self.buffer_empty_latch = true;
         }
+        self.generate_samples(cycles, bus);
         self.update_status();
     }
 
+    /// Produce one stereo frame per `CYCLES_PER_SAMPLE` elapsed cycles
+    fn generate_samples(&mut self, cycles: u64, bus: Option<&Bus24>) {
+        self.sample_cycles += cycles;
+        let count = self.sample_cycles / CYCLES_PER_SAMPLE;
+        self.sample_cycles %= CYCLES_PER_SAMPLE;
+
+        let audible = self.channels.iter().any(|chan| {
+            chan.sounding()
+                && match chan.voice {
+                    ChannelVoice::Fm | ChannelVoice::Wavetable | ChannelVoice::Noise => true,
+                    ChannelVoice::Pcm => bus.is_some() && chan.sample_length > 0,
+                }
+        });
+        if !audible && self.echo_live == 0 {
+            return;
+        }
+
+        for _ in 0..count {
+            let (mut left, mut right) = (0.0f32, 0.0f32);
+            let (mut send_left, mut send_right) = (0.0f32, 0.0f32);
+            for chan in &mut self.channels {
+                if !chan.sounding() {
+                    continue;
+                }
+                chan.envelope.advance();
+                let level = chan.envelope.current_volume;
+                let sample = match (chan.voice, bus) {
+                    (ChannelVoice::Fm, _) => Self::fm_sample(chan),
+                    (ChannelVoice::Wavetable, _) => Self::wavetable_sample(chan, &self.wavetable),
+                    (ChannelVoice::Noise, _) => Self::noise_sample(chan),
+                    (ChannelVoice::Pcm, Some(bus)) => {
+                        let sample = Self::pcm_sample(chan, bus);
+                        if chan.sample_length == 0 && !chan.buffer_empty {
+                            chan.buffer_empty = true;
+                            self.buffer_empty_latch = true;
+                        }
+                        sample
+                    }
+                    _ => continue,
+                } * chan.volume as f32
+                    / 255.0
+                    * level;
+                let (pan_left, pan_right) = Self::pan_gains(chan.pan);
+                left += sample * pan_left;
+                right += sample * pan_right;
+                if chan.effect.contains(EffectMask::ECHO) {
+                    send_left += sample * pan_left;
+                    send_right += sample * pan_right;
+                }
+            }
+            let (left, right) = self.process_echo((left, right), (send_left, send_right));
+            self.audio_buffer.push(Self::to_i16(left));
+            self.audio_buffer.push(Self::to_i16(right));
+        }
+
+        if self.audio_buffer.len() > MAX_BUFFERED_SAMPLES * 2 {
+            let excess = self.audio_buffer.len() - MAX_BUFFERED_SAMPLES * 2;
+            self.audio_buffer.drain(..excess);
+        }
+    }
+
+    fn to_i16(sample: f32) -> i16 {
+        (sample * 32768.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    /// Left/right gains for a pan value; 0x80 is centered at full volume
+    fn pan_gains(pan: u8) -> (f32, f32) {
+        let left = ((255 - pan) as f32 / 127.0).min(1.0);
+        let right = (pan as f32 / 128.0).min(1.0);
+        (left, right)
+    }
+
+    /// Produce the next PCM sample and advance the read pointer
+    ///
+    /// Without interpolation, or at a channel frequency of zero, each output
+    /// sample plays the next byte; otherwise the frequency is the PCM sample
+    /// rate in Hz.
+    fn pcm_sample(chan: &mut ChannelState, bus: &Bus24) -> f32 {
+        if chan.sample_length == 0 {
+            return 0.0;
+        }
+        if chan.interpolation == InterpolationMode::None {
+            let byte = bus.read_u8(chan.sample_address.wrapping_add(chan.pcm_position));
+            chan.pcm_position += 1;
+            chan.sample_length -= 1;
+            return (byte as f32 - 128.0) / 128.0;
+        }
+        // Neighbouring bytes, clamped to the sample data
+        let first = -(chan.pcm_position as i64);
+        let last = chan.sample_length as i64 - 1;
+        let fetch = |offset: i64| {
+            let offset = offset.clamp(first, last);
+            let addr = (chan.sample_address as i64 + chan.pcm_position as i64 + offset) as u32;
+            (bus.read_u8(addr) as f32 - 128.0) / 128.0
+        };
+        let t = chan.pcm_frac;
+        let sample = match chan.interpolation {
+            InterpolationMode::None => unreachable!("handled above"),
+            InterpolationMode::Linear => {
+                let (a, b) = (fetch(0), fetch(1));
+                a + (b - a) * t
+            }
+            InterpolationMode::Cubic => {
+                // Catmull-Rom spline through p1..p2
+                let (p0, p1, p2, p3) = (fetch(-1), fetch(0), fetch(1), fetch(2));
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                    + (3.0 * (p1 - p2) + p3 - p0) * t * t * t)
+            }
+        };
+
+        let step = match chan.frequency {
+            0 => 1.0,
+            hz => hz as f32 / SAMPLE_RATE as f32,
+        };
+        chan.pcm_frac += step;
+        while chan.pcm_frac >= 1.0 && chan.sample_length > 0 {
+            chan.pcm_frac -= 1.0;
+            chan.pcm_position += 1;
+            chan.sample_length -= 1;
+        }
+        sample
+    }
+
+    /// Play the channel's selected waveform, one table pass per period
+    fn wavetable_sample(chan: &mut ChannelState, wavetable: &[u8]) -> f32 {
+        let base = chan.wavetable_slot as usize * WAVETABLE_LENGTH;
+        let index = (chan.wavetable_phase * WAVETABLE_LENGTH as f32) as usize;
+        let byte = wavetable[base + index.min(WAVETABLE_LENGTH - 1)];
+        chan.wavetable_phase += chan.frequency as f32 / SAMPLE_RATE as f32;
+        chan.wavetable_phase = chan.wavetable_phase.fract();
+        (byte as f32 - 128.0) / 128.0
+    }
+
+    /// Noise output; the LFSR clocks once every `frequency` samples
+    fn noise_sample(chan: &mut ChannelState) -> f32 {
+        if chan.noise_counter == 0 {
+            chan.lfsr.step();
+            chan.noise_counter = chan.frequency.max(1);
+        }
+        chan.noise_counter -= 1;
+        (chan.lfsr.state & 1) as f32
+    }
+
+    /// Two-operator FM: the modulator's output offsets the carrier's phase
+    fn fm_sample(chan: &mut ChannelState) -> f32 {
+        let modulation = chan.modulator.amplitude * chan.modulator.output(0.0);
+        let sample = chan.carrier.amplitude * chan.carrier.output(modulation);
+
+        let frequency = chan.frequency as f32;
+        chan.modulator.advance(frequency);
+        chan.carrier.advance(frequency);
+        sample
+    }
+
+    /// Configure the modulator and carrier of an FM channel.
+    pub fn set_fm_operators(&mut self, channel: usize, modulator: FmOperator, carrier: FmOperator) {
+        if let Some(chan) = self.channels.get_mut(channel) {
+            chan.modulator = modulator;
+            chan.carrier = carrier;
+        }
+    }
+
+    /// Load one 8-sample waveform into wavetable RAM.
+    pub fn load_wavetable(&mut self, slot: usize, data: &[u8; WAVETABLE_LENGTH]) {
+        if slot < WAVETABLE_SLOTS {
+            let start = slot * WAVETABLE_LENGTH;
+            self.wavetable[start..start + WAVETABLE_LENGTH].copy_from_slice(data);
+        }
+    }
+
+    /// Take all audio generated since the last call as interleaved L/R samples.
+    pub fn drain_audio(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.audio_buffer)
+    }
+
     /// Consume the buffer-empty latch and report whether an interrupt should fire.
     pub fn take_buffer_empty(&mut self) -> bool {
         let ready = self.buffer_empty_latch;
@@ -346,4 +992,333 @@ mod tests {
         apu.write_register(12, 0x00);
         assert_eq!(apu.read_register(STATUS_OFFSET) & 0x03, 0x03);
     }
+
+    #[test]
+    fn fm_channel_generates_tone() {
+        let mut apu = Apu::new();
+        apu.write_register(0, 0x03); // Enable, FM voice
+        apu.write_register(4, 0xB8); // 440 Hz
+        apu.write_register(5, 0x01);
+        let pure = FmOperator {
+            amplitude: 0.0,
+            ..FmOperator::default()
+        };
+        apu.set_fm_operators(0, pure, FmOperator::default());
+
+        // 0.1 seconds of audio
+        apu.step(CYCLES_PER_SAMPLE * 4800);
+        let stereo = apu.drain_audio();
+        assert_eq!(stereo.len(), 4800 * 2);
+        assert!(apu.drain_audio().is_empty());
+        // Centered pan: both sides carry the full signal
+        let samples: Vec<i16> = stereo.chunks(2).map(|lr| lr[0]).collect();
+        assert!(stereo.chunks(2).all(|lr| lr[0] == lr[1]));
+
+        let rising = samples.windows(2).filter(|w| w[0] < 0 && w[1] >= 0).count();
+        assert!((43..=45).contains(&rising), "{rising} periods");
+        let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        assert!(peak > 32000);
+    }
+
+    #[test]
+    fn fm_modulation_changes_waveform() {
+        let mut apu = Apu::new();
+        apu.write_register(0, 0x03);
+        apu.write_register(4, 0xB8);
+        apu.write_register(5, 0x01);
+        apu.step(CYCLES_PER_SAMPLE * 100);
+        let modulated = apu.drain_audio();
+
+        let mut plain = Apu::new();
+        plain.write_register(0, 0x03);
+        plain.write_register(4, 0xB8);
+        plain.write_register(5, 0x01);
+        let pure = FmOperator {
+            amplitude: 0.0,
+            ..FmOperator::default()
+        };
+        plain.set_fm_operators(0, pure, FmOperator::default());
+        plain.step(CYCLES_PER_SAMPLE * 100);
+
+        assert_eq!(modulated.len(), 200);
+        assert_ne!(modulated, plain.drain_audio());
+    }
+
+    #[test]
+    fn fm_carrier_feedback_changes_waveform() {
+        let play = |feedback: f32| {
+            let mut apu = Apu::new();
+            apu.write_register(0, 0x03);
+            apu.write_register(4, 0xB8);
+            apu.write_register(5, 0x01);
+            let carrier = FmOperator {
+                feedback,
+                ..FmOperator::default()
+            };
+            apu.set_fm_operators(0, FmOperator::default(), carrier);
+            apu.step(CYCLES_PER_SAMPLE * 100);
+            apu.drain_audio()
+        };
+        assert_ne!(play(0.0), play(0.8));
+    }
+
+    #[test]
+    fn silent_apu_produces_no_samples() {
+        let mut apu = Apu::new();
+        apu.step(CYCLES_PER_SAMPLE * 10);
+        assert!(apu.drain_audio().is_empty());
+    }
+
+    #[test]
+    fn pcm_channel_plays_samples_from_memory() {
+        let mut bus = Bus24::new();
+        let waveform = [128u8, 192, 255, 64, 0];
+        for (i, &byte) in waveform.iter().enumerate() {
+            bus.write_u8(0x1000 + i as u32, byte);
+        }
+
+        let mut apu = Apu::new();
+        apu.write_register(8, 0x00); // Sample address 0x001000
+        apu.write_register(9, 0x10);
+        apu.write_register(10, 0x00);
+        apu.write_register(11, 0x00); // Length 5
+        apu.write_register(12, 0x05);
+        apu.write_register(0, 0x01); // Enable, PCM voice
+
+        apu.step_with_bus(CYCLES_PER_SAMPLE * 3, &bus);
+        assert!(!apu.take_buffer_empty());
+        apu.step_with_bus(CYCLES_PER_SAMPLE * 3, &bus);
+        assert!(apu.take_buffer_empty());
+        assert_eq!(apu.read_register(12), 0);
+
+        let expected: Vec<i16> = [0, 16384, 32512, -16384, -32768, 0]
+            .iter()
+            .flat_map(|&s| [s, s])
+            .collect();
+        assert_eq!(apu.drain_audio(), expected);
+    }
+
+    #[test]
+    fn pcm_pan_and_volume() {
+        let mut bus = Bus24::new();
+        bus.write_u8(0x2000, 255);
+
+        let mut apu = Apu::new();
+        apu.write_register(9, 0x20);
+        apu.write_register(12, 0x01);
+        apu.write_register(1, 0x80); // Roughly half volume
+        apu.write_register(2, 0x00); // Hard left
+        apu.write_register(0, 0x01);
+
+        apu.step_with_bus(CYCLES_PER_SAMPLE, &bus);
+        let out = apu.drain_audio();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0], (32512.0f32 * 128.0 / 255.0) as i16);
+        assert_eq!(out[1], 0);
+    }
+
+    #[test]
+    fn envelope_attacks_decays_and_releases() {
+        let mut apu = Apu::new();
+        let set_env = |apu: &mut Apu, index: u32, value: u8| {
+            apu.write_register(13, index as u8);
+            apu.write_register(14, value);
+        };
+        set_env(&mut apu, 0, 10); // Attack: 10 samples
+        set_env(&mut apu, 2, 20); // Decay: 20 samples
+        set_env(&mut apu, 4, 0x80); // Sustain at ~half
+        set_env(&mut apu, 5, 5); // Release: 5 samples
+        apu.write_register(13, 7);
+
+        apu.write_register(4, 0xB8); // 440 Hz
+        apu.write_register(5, 0x01);
+        apu.write_register(0, 0x03); // Enable, FM voice
+
+        let mut levels = Vec::new();
+        for _ in 0..40 {
+            apu.step(CYCLES_PER_SAMPLE);
+            levels.push(apu.read_register(14));
+        }
+        let peak = levels.iter().position(|&level| level == 255).unwrap();
+        assert_eq!(peak, 9);
+        assert!(levels[..peak].windows(2).all(|w| w[0] < w[1]));
+        assert!(levels[peak..30].windows(2).all(|w| w[0] > w[1]));
+        assert!(levels[30..].iter().all(|&level| level == 0x80));
+
+        // Key off fades out over the release time, then the channel goes quiet
+        apu.drain_audio();
+        apu.write_register(0, 0x02);
+        apu.step(CYCLES_PER_SAMPLE);
+        assert!(apu.drain_audio().iter().any(|&s| s != 0));
+        apu.step(CYCLES_PER_SAMPLE * 4);
+        assert_eq!(apu.read_register(14), 0);
+        apu.drain_audio();
+        apu.step(CYCLES_PER_SAMPLE);
+        assert!(apu.drain_audio().is_empty());
+    }
+
+    #[test]
+    fn echo_repeats_pulse_at_feedback_level() {
+        let mut bus = Bus24::new();
+        bus.write_u8(0x3000, 255);
+
+        let mut apu = Apu::new();
+        apu.write_register(ECHO_DELAY_LO_OFFSET, 100);
+        apu.write_register(ECHO_FEEDBACK_OFFSET, 0x80); // 50%
+        assert_eq!(apu.read_register(ECHO_DELAY_LO_OFFSET), 100);
+        assert_eq!(apu.read_register(GLOBAL_VERSION_OFFSET), SUPPORTED_VERSION);
+
+        // One-sample PCM pulse sent to the echo
+        apu.write_register(9, 0x30);
+        apu.write_register(12, 0x01);
+        apu.write_register(6, EffectMask::ECHO.bits());
+        apu.write_register(0, 0x01);
+
+        apu.step_with_bus(CYCLES_PER_SAMPLE * 201, &bus);
+        let left: Vec<i16> = apu.drain_audio().chunks(2).map(|lr| lr[0]).collect();
+        assert_eq!(left.len(), 201);
+        assert_eq!(left[0], 32512);
+        assert!(left[1..100].iter().all(|&s| s == 0));
+        assert!((left[100] - 16256).abs() <= 1);
+        assert!(left[101..200].iter().all(|&s| s == 0));
+        assert!((left[200] - 8128).abs() <= 1);
+    }
+
+    #[test]
+    fn echo_ignores_channels_without_echo_bit() {
+        let mut bus = Bus24::new();
+        bus.write_u8(0x3000, 255);
+
+        let mut apu = Apu::new();
+        apu.write_register(ECHO_DELAY_LO_OFFSET, 10);
+        apu.write_register(ECHO_FEEDBACK_OFFSET, 0x80);
+        apu.write_register(9, 0x30);
+        apu.write_register(12, 0x01);
+        apu.write_register(0, 0x01);
+
+        apu.step_with_bus(CYCLES_PER_SAMPLE * 20, &bus);
+        let out = apu.drain_audio();
+        assert_eq!(out[0], 32512);
+        assert!(out[2..].iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn wavetable_channel_plays_square_wave() {
+        let mut apu = Apu::new();
+        apu.load_wavetable(3, &[255, 255, 255, 255, 0, 0, 0, 0]);
+        apu.write_register(7, 3);
+        apu.write_register(4, 0x70); // 6 kHz: one sample per table entry
+        apu.write_register(5, 0x17);
+        apu.write_register(0, 0x05); // Enable, wavetable voice
+
+        apu.step(CYCLES_PER_SAMPLE * 16);
+        let left: Vec<i16> = apu.drain_audio().chunks(2).map(|lr| lr[0]).collect();
+        let period = [32512, 32512, 32512, 32512, -32768, -32768, -32768, -32768];
+        assert_eq!(left[..8], period);
+        assert_eq!(left[8..], period);
+    }
+
+    #[test]
+    fn wavetable_ram_is_accessible_through_registers() {
+        let mut apu = Apu::new();
+        apu.write_register(WAVETABLE_ADDR_OFFSET, 0x10);
+        for value in [1, 2, 3] {
+            apu.write_register(WAVETABLE_DATA_OFFSET, value);
+        }
+        assert_eq!(apu.read_register(WAVETABLE_ADDR_OFFSET), 0x13);
+        assert_eq!(apu.wavetable[0x10..0x13], [1, 2, 3]);
+        apu.write_register(WAVETABLE_ADDR_OFFSET, 0x11);
+        assert_eq!(apu.read_register(WAVETABLE_DATA_OFFSET), 2);
+    }
+
+    #[test]
+    fn short_lfsr_matches_reference_sequence() {
+        let mut lfsr = Lfsr::new(true);
+        let bits: Vec<u16> = (0..20).map(|_| lfsr.step()).collect();
+        assert_eq!(
+            bits,
+            [1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 1, 0]
+        );
+
+        let period = |short| {
+            let mut lfsr = Lfsr::new(short);
+            let seed = lfsr.state;
+            (1..).find(|_| {
+                lfsr.step();
+                lfsr.state == seed
+            })
+        };
+        assert_eq!(period(true), Some(127));
+        assert_eq!(period(false), Some(32767));
+    }
+
+    #[test]
+    fn noise_channel_outputs_lfsr_bits() {
+        let mut apu = Apu::new();
+        apu.write_register(4, 2); // Clock the LFSR every other sample
+        apu.write_register(0, 0x07 | CONTROL_SHORT_NOISE); // Enable, noise voice
+        assert_eq!(
+            apu.read_register(0) & CONTROL_SHORT_NOISE,
+            CONTROL_SHORT_NOISE
+        );
+
+        apu.step(CYCLES_PER_SAMPLE * 12);
+        let left: Vec<i16> = apu.drain_audio().chunks(2).map(|lr| lr[0]).collect();
+        let high = i16::MAX;
+        assert_eq!(
+            left,
+            [
+                high, high, high, high, high, high, high, high, high, high, 0, 0
+            ]
+        );
+    }
+
+    #[test]
+    fn pcm_interpolation_at_half_rate() {
+        let mut bus = Bus24::new();
+        for (i, byte) in [128u8, 160, 192, 224].into_iter().enumerate() {
+            bus.write_u8(0x4000 + i as u32, byte);
+        }
+        let play = |control: u8| {
+            let mut apu = Apu::new();
+            apu.write_register(9, 0x40);
+            apu.write_register(12, 4);
+            apu.write_register(4, 0xC0); // 24 kHz: each byte spans two samples
+            apu.write_register(5, 0x5D);
+            apu.write_register(0, 0x01 | control);
+            apu.step_with_bus(CYCLES_PER_SAMPLE * 8, &bus);
+            apu.drain_audio()
+                .chunks(2)
+                .map(|lr| lr[0])
+                .collect::<Vec<i16>>()
+        };
+
+        // Without interpolation the frequency is ignored
+        assert_eq!(play(0x00), [0, 8192, 16384, 24576, 0, 0, 0, 0]);
+        // Linear: odd samples are the means of their neighbours
+        let linear = play(0x10);
+        assert_eq!(linear, [0, 4096, 8192, 12288, 16384, 20480, 24576, 24576]);
+        // Catmull-Rom reproduces a straight ramp between interior points
+        let cubic = play(0x20);
+        assert_eq!(cubic[2..5], linear[2..5]);
+    }
+
+    #[test]
+    fn pcm_without_interpolation_steps_one_byte_per_sample() {
+        let mut bus = Bus24::new();
+        for (i, byte) in [128u8, 192, 255, 64, 0].into_iter().enumerate() {
+            bus.write_u8(0x1000 + i as u32, byte);
+        }
+        let mut apu = Apu::new();
+        apu.write_register(9, 0x10);
+        apu.write_register(12, 5);
+        apu.write_register(4, 0x11); // 11025 Hz
+        apu.write_register(5, 0x2B);
+        apu.write_register(0, 0x01); // Enable, PCM voice, no interpolation
+        apu.step_with_bus(CYCLES_PER_SAMPLE * 6, &bus);
+
+        // Output from before interpolation existed, when frequency was ignored
+        let left: Vec<i16> = apu.drain_audio().chunks(2).map(|lr| lr[0]).collect();
+        assert_eq!(left, [0, 16384, 32512, -16384, -32768, 0]);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::cpu::Cpu;
 use crate::nraw::assemble;
 
 const BIOS_SIZE: usize = 0x10000;
@@ -123,33 +124,46 @@ nmi_handler:
     RTI
 "#;
 
+/// Handler labels for interrupts 0 (SWI) through 7 (NMI)
+const INTERRUPT_HANDLERS: [&str; 8] = [
+    "swi_handler",
+    "pad_event_handler",
+    "timer0_handler",
+    "apu_buf_empty_handler",
+    "vlu_done_handler",
+    "dma_done_handler",
+    "hblank_handler",
+    "nmi_handler",
+];
+
+/// Offset within the BIOS of interrupt `int`'s vector
+fn vector_offset(int: u8) -> usize {
+    (Cpu::interrupt_vector(int) - 0xFF0000) as usize
+}
+
 /// Produce the default BIOS image used by the emulator.
 pub fn default_bios() -> Vec<u8> {
     let program = assemble(BIOS_SOURCE).expect("invalid BIOS source");
     let mut bios = vec![0xFF; BIOS_SIZE];
-    
-    // Set up interrupt vector table
-    let vectors = [
-        ("start", 0x00),           // Reset vector
-        ("swi_handler", 0x03),     // SWI
-        ("pad_event_handler", 0x06), // PAD_EVENT
-        ("timer0_handler", 0x09),  // TIMER0
-        ("apu_buf_empty_handler", 0x0C), // APU_BUF_EMPTY
-        ("vlu_done_handler", 0x0F), // VLU_DONE
-        ("dma_done_handler", 0x12), // DMA_DONE
-        ("hblank_handler", 0x15),  // HBLANK
-        ("nmi_handler", 0x18),     // NMI
-    ];
-    
-    for (label, offset) in vectors.iter() {
-        if let Some(&label_addr) = program.labels.get(*label) {
+
+    // Set up interrupt vector table, laid out the way the CPU reads it
+    let vectors = std::iter::once(("start", 0x00)) // Reset vector
+        .chain(
+            INTERRUPT_HANDLERS
+                .iter()
+                .zip(0u8..)
+                .map(|(&label, int)| (label, vector_offset(int))),
+        );
+
+    for (label, offset) in vectors {
+        if let Some(&label_addr) = program.labels.get(label) {
             let entry = 0xFF0000 + BIOS_CODE_OFFSET as u32 + label_addr;
-            bios[*offset] = (entry & 0xFF) as u8;
-            bios[*offset + 1] = ((entry >> 8) & 0xFF) as u8;
-            bios[*offset + 2] = ((entry >> 16) & 0xFF) as u8;
+            bios[offset] = (entry & 0xFF) as u8;
+            bios[offset + 1] = ((entry >> 8) & 0xFF) as u8;
+            bios[offset + 2] = ((entry >> 16) & 0xFF) as u8;
         }
     }
-    
+
     // Set up system call entry point at 0x100 (0xFF0100)
     if let Some(&syscall_addr) = program.labels.get("syscall_entry") {
         let entry = 0xFF0000 + BIOS_CODE_OFFSET as u32 + syscall_addr;
@@ -167,6 +181,7 @@ pub fn default_bios() -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::Bus24;
 
     #[test]
     fn default_bios_is_sized() {
@@ -206,7 +221,25 @@ mod tests {
             }
         }
     }
-    
+
+    #[test]
+    fn each_interrupt_enters_its_handler() {
+        let program = assemble(BIOS_SOURCE).expect("assemble BIOS source");
+        for (int, label) in (0u8..).zip(INTERRUPT_HANDLERS) {
+            let mut cpu = Cpu::new();
+            let mut bus = Bus24::new();
+            bus.load_bios(&default_bios());
+            cpu.pc = 0x000100;
+            cpu.sr.interrupt_disable = false;
+            cpu.request_interrupt(int);
+
+            cpu.step(&mut bus);
+
+            let entry = 0xFF0000 + BIOS_CODE_OFFSET as u32 + program.labels[label];
+            assert_eq!(cpu.pc, entry, "interrupt {int} should enter {label}");
+        }
+    }
+
     #[test]
     fn syscall_entry_exists() {
         let bios = default_bios();
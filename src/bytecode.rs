@@ -26,6 +26,7 @@ pub enum Value {
 }
 
 /// Header of a .bpx file
+#[allow(dead_code)] // Fields are parsed up front; the VM only consumes the code section so far
 #[derive(Debug)]
 struct Header {
     magic: [u8; 4],
@@ -38,17 +39,32 @@ struct Header {
     crc32: u32,
 }
 
+/// A named code location in a module's link tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// Offset in the module's code section
+    pub offset: u32,
+}
+
 /// Represents a parsed bytecode module
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct BytecodeModule {
     /// Parsed header
     header: Header,
     /// Constant pool values (only numbers for now)
     constants: Vec<Value>,
+    /// Size of the constant pool section in bytes
+    constant_pool_len: usize,
     /// Raw bytecode section
     code: Vec<u8>,
     /// Entry point function index
     entry_point: u16,
+    /// Functions other modules may CALL
+    exports: Vec<Symbol>,
+    /// CALL instructions whose target lives in another module
+    imports: Vec<Symbol>,
 }
 
 impl BytecodeModule {
@@ -57,6 +73,11 @@ impl BytecodeModule {
         let mut file = File::open(path)?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
+        Self::from_bytes(&buf)
+    }
+
+    /// Parse a .bpx image already in memory
+    pub fn from_bytes(buf: &[u8]) -> io::Result<Self> {
         if buf.len() < 23 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -72,9 +93,9 @@ impl BytecodeModule {
         }
         let version = u16::from_le_bytes([buf[4], buf[5]]);
         let flags = u16::from_le_bytes([buf[6], buf[7]]);
-        let cp_offset = Self::read_u24_le(&buf, 8);
-        let code_offset = Self::read_u24_le(&buf, 11);
-        let meta_offset = Self::read_u24_le(&buf, 14);
+        let cp_offset = Self::read_u24_le(buf, 8);
+        let code_offset = Self::read_u24_le(buf, 11);
+        let meta_offset = Self::read_u24_le(buf, 14);
         let entry_point = u16::from_le_bytes([buf[17], buf[18]]);
         let crc32 = u32::from_le_bytes([buf[19], buf[20], buf[21], buf[22]]);
         let header = Header {
@@ -90,20 +111,53 @@ impl BytecodeModule {
         // Constant pool section
         let constants_bytes = &buf[cp_offset as usize..code_offset as usize];
         let constants = Self::parse_constants(constants_bytes);
+        let constant_pool_len = constants_bytes.len();
         // Code section
-        let code = if meta_offset > 0 && meta_offset as usize <= buf.len() {
-            buf[code_offset as usize..meta_offset as usize].to_vec()
+        let (code, meta) = if meta_offset > 0 && meta_offset as usize <= buf.len() {
+            (
+                buf[code_offset as usize..meta_offset as usize].to_vec(),
+                &buf[meta_offset as usize..],
+            )
         } else {
-            buf[code_offset as usize..].to_vec()
+            (buf[code_offset as usize..].to_vec(), &[][..])
         };
+        // Metadata section: export table, then import table
+        let mut cursor = 0;
+        let exports = Self::parse_symbols(meta, &mut cursor);
+        let imports = Self::parse_symbols(meta, &mut cursor);
         Ok(Self {
             header,
             constants,
+            constant_pool_len,
             code,
             entry_point,
+            exports,
+            imports,
         })
     }
 
+    /// Wrap a raw code section with an empty constant pool
+    pub fn from_code(code: Vec<u8>) -> Self {
+        Self {
+            header: Header {
+                magic: *b"BPX0",
+                version: 0,
+                flags: 0,
+                cp_offset: 0,
+                code_offset: 0,
+                meta_offset: 0,
+                entry_point: 0,
+                crc32: 0,
+            },
+            constants: Vec::new(),
+            constant_pool_len: 0,
+            code,
+            entry_point: 0,
+            exports: Vec::new(),
+            imports: Vec::new(),
+        }
+    }
+
     fn read_u24_le(buf: &[u8], offset: usize) -> u32 {
         let b0 = buf[offset] as u32;
         let b1 = buf[offset + 1] as u32;
@@ -111,6 +165,34 @@ impl BytecodeModule {
         (b2 << 16) | (b1 << 8) | b0
     }
 
+    /// Read a symbol table: a count byte, then per entry a length-prefixed
+    /// name and a 24-bit code offset. A short table ends early.
+    fn parse_symbols(meta: &[u8], cursor: &mut usize) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+        let Some(&count) = meta.get(*cursor) else {
+            return symbols;
+        };
+        *cursor += 1;
+        for _ in 0..count {
+            let Some(&len) = meta.get(*cursor) else {
+                break;
+            };
+            let start = *cursor + 1;
+            let Some(name) = meta.get(start..start + len as usize) else {
+                break;
+            };
+            let Some(offset) = meta.get(start + len as usize..start + len as usize + 3) else {
+                break;
+            };
+            symbols.push(Symbol {
+                name: String::from_utf8_lossy(name).into_owned(),
+                offset: Self::read_u24_le(offset, 0),
+            });
+            *cursor = start + len as usize + 3;
+        }
+        symbols
+    }
+
     fn parse_constants(bytes: &[u8]) -> Vec<Value> {
         let mut v = Vec::new();
         let mut i = 0;
@@ -128,6 +210,26 @@ impl BytecodeModule {
         v
     }
 
+    /// Size of the constant pool section in bytes (3 per entry when intact)
+    pub fn constant_pool_len(&self) -> usize {
+        self.constant_pool_len
+    }
+
+    /// Index of the function execution starts in
+    pub fn entry_point(&self) -> u16 {
+        self.entry_point
+    }
+
+    /// Functions this module exports, by code offset
+    pub fn exports(&self) -> &[Symbol] {
+        &self.exports
+    }
+
+    /// CALL sites that refer to another module's exports
+    pub fn imports(&self) -> &[Symbol] {
+        &self.imports
+    }
+
     /// Return raw bytecode slice
     pub fn bytecode(&self) -> &[u8] {
         &self.code
@@ -8,7 +8,164 @@
 // (at your option) any later version. See the LICENSE file in the project root for details.
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::cell::{Cell, RefCell};
+
 use crate::apu::Apu;
+use crate::timer::Timers;
+use crate::vdp::Vdp;
+
+/// Kind of memory access a watchpoint reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchMode {
+    fn matches(self, access: WatchMode) -> bool {
+        self == WatchMode::ReadWrite || self == access
+    }
+}
+
+/// Memory watchpoint on a single byte address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addr: u32,
+    pub mode: WatchMode,
+}
+
+/// Area of the 24-bit address space, as reported by [`Bus24::region_at`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryRegion {
+    WorkRam,
+    ExpandedRam,
+    Io,
+    Vram,
+    Cram,
+    CartRom,
+    CartSave,
+    Bios,
+    Unmapped,
+}
+
+impl MemoryRegion {
+    /// Whether reads return stored data; unmapped addresses read as 0xFF
+    pub fn is_readable(&self) -> bool {
+        *self != MemoryRegion::Unmapped
+    }
+
+    /// Whether writes are stored; ROM and unmapped writes are dropped
+    pub fn is_writable(&self) -> bool {
+        !matches!(
+            self,
+            MemoryRegion::CartRom | MemoryRegion::Bios | MemoryRegion::Unmapped
+        )
+    }
+}
+
+/// A mapped range of the address space, see [`Bus24::regions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegionInfo {
+    pub base: u32,
+    pub size: u32,
+    pub region: MemoryRegion,
+}
+
+impl MemoryRegionInfo {
+    /// Whether `addr` falls inside this range
+    pub fn contains(&self, addr: u32) -> bool {
+        addr >= self.base && addr - self.base < self.size
+    }
+}
+
+/// Every mapped region in address order
+const MEMORY_MAP: [MemoryRegionInfo; 8] = [
+    MemoryRegionInfo {
+        base: Bus24::WORKRAM_BASE,
+        size: Bus24::WORKRAM_SIZE as u32,
+        region: MemoryRegion::WorkRam,
+    },
+    MemoryRegionInfo {
+        base: Bus24::EXPANDED_RAM_BASE,
+        size: Bus24::EXPANDED_RAM_SIZE as u32,
+        region: MemoryRegion::ExpandedRam,
+    },
+    MemoryRegionInfo {
+        base: Bus24::IO_BASE,
+        size: Bus24::IO_SIZE as u32,
+        region: MemoryRegion::Io,
+    },
+    MemoryRegionInfo {
+        base: Bus24::VRAM_BASE,
+        size: Bus24::VRAM_SIZE as u32,
+        region: MemoryRegion::Vram,
+    },
+    MemoryRegionInfo {
+        base: Bus24::CRAM_BASE,
+        size: Bus24::CRAM_SIZE as u32,
+        region: MemoryRegion::Cram,
+    },
+    MemoryRegionInfo {
+        base: Bus24::CART_ROM_BASE,
+        size: Bus24::CART_ROM_SIZE as u32,
+        region: MemoryRegion::CartRom,
+    },
+    MemoryRegionInfo {
+        base: Bus24::CART_SAVE_BASE,
+        size: Bus24::CART_SAVE_SIZE as u32,
+        region: MemoryRegion::CartSave,
+    },
+    MemoryRegionInfo {
+        base: Bus24::BIOS_BASE,
+        size: Bus24::BIOS_SIZE as u32,
+        region: MemoryRegion::Bios,
+    },
+];
+
+/// Callback invoked for reads within a registered I/O region
+pub type IoReadHandler = Box<dyn FnMut(u32) -> u8>;
+/// Callback invoked for writes within a registered I/O region
+pub type IoWriteHandler = Box<dyn FnMut(u32, u8)>;
+/// Callback invoked with the address and value of a write that was dropped
+/// because it targeted a write-protected range
+pub type WriteFaultHandler = Box<dyn FnMut(u32, u8)>;
+
+/// Size of the 24-bit address space
+const ADDRESS_SPACE: u32 = 0x0100_0000;
+
+enum IoHandler {
+    // Reads go through `&self`, so the handler needs interior mutability
+    Read(RefCell<IoReadHandler>),
+    Write(IoWriteHandler),
+}
+
+/// Peripheral handler mapped over part of the I/O region
+struct IoRegion {
+    base: u32,
+    len: u32,
+    handler: IoHandler,
+}
+
+impl IoRegion {
+    fn contains(&self, addr: u32) -> bool {
+        addr >= self.base && addr - self.base < self.len
+    }
+}
+
+/// Snapshot of the bus memory regions, used for save states
+///
+/// CartROM is read-only and is not included; peripheral state such as the
+/// APU and the VDP (including VRAM and CRAM) is saved by the emulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BusState {
+    pub workram: Vec<u8>,
+    pub expanded_ram: Vec<u8>,
+    pub io: Vec<u8>,
+    pub cart_save: Vec<u8>,
+    pub bios: Vec<u8>,
+}
 
 /// 24-bit address bus with full memory map support
 ///
@@ -18,6 +175,8 @@ use crate::apu::Apu;
 /// - 0x100000..0x10FFFF: I/O (64KB) - Memory-mapped coprocessors
 ///   - 0x100000..0x103FFF: VDP-T registers
 ///   - 0x108000..0x10BFFF: VLU-24 coprocessor
+///   - 0x10A000..0x10A003: Gamepad buttons (player 1, player 2)
+///   - 0x10B000..0x10B00F: Interval timers
 ///   - 0x10C000..0x10FFFF: APU-6 coprocessor
 /// - 0x200000..0x27FFFF: VRAM (512KB) - VDP-T video memory
 /// - 0x280000..0x28FFFF: CRAM (64KB) - VDP-T palette memory
@@ -31,12 +190,15 @@ pub struct Bus24 {
     cart_rom: Vec<u8>,     // 0x400000..0x9FFFFF (6MB)
     cart_save: Vec<u8>,    // 0xA00000..0xA3FFFF (256KB)
     bios: Vec<u8>,         // 0xFF0000..0xFFFFFF (64KB)
-    // Added internal storage for VRAM/CRAM when VDP routing disabled
-    vram: Vec<u8>, // 0x200000..0x27FFFF (512KB)
-    cram: Vec<u8>, // 0x280000..0x28FFFF (64KB)
-    // VDP is handled separately via routing since it has its own VRAM/CRAM
-    vdp_routing: bool, // When true, route VDP regions to external VDP
-    apu: Apu,          // Shared APU-6 coprocessor state
+    vdp: Vdp,              // VDP-T registers, VRAM and CRAM
+    apu: Apu,              // Shared APU-6 coprocessor state
+    timers: Timers,        // Interval timers, clocked by the emulator
+    // Watchpoints installed by the CPU for the duration of an instruction
+    watchpoints: Vec<Watchpoint>,
+    watch_hit: Cell<Option<u32>>,     // First watched address accessed
+    io_regions: Vec<IoRegion>,        // Peripheral handlers, sorted by base
+    write_protected: Vec<(u32, u32)>, // Disjoint start..end ranges that drop writes
+    write_fault_handler: Option<WriteFaultHandler>,
 }
 
 impl Bus24 {
@@ -56,6 +218,9 @@ impl Bus24 {
     pub const IO_BASE: u32 = 0x100000;
     pub const VDP_IO_BASE: u32 = 0x100000; // VDP-T registers within I/O
     pub const VLU_IO_BASE: u32 = 0x108000; // VLU-24 within I/O
+    pub const GAMEPAD_IO_BASE: u32 = 0x10A000; // Player 1 at +0, player 2 at +2
+    pub const TIMER_IO_BASE: u32 = 0x10B000; // Interval timers within I/O
+    pub const TIMER_IO_SIZE: u32 = 0x10;
     pub const APU_IO_BASE: u32 = 0x10C000; // APU-6 within I/O
     pub const APU_IO_SIZE: u32 = 0x4000;
     pub const VRAM_BASE: u32 = 0x200000;
@@ -64,7 +229,15 @@ impl Bus24 {
     pub const CART_SAVE_BASE: u32 = 0xA00000;
     pub const BIOS_BASE: u32 = 0xFF0000;
 
+    // CRC32 of the save data lives in the last 4 bytes of CartSave
+    pub const CART_SAVE_CHECKSUM_OFFSET: usize = Self::CART_SAVE_SIZE - 4;
+
     pub fn new() -> Self {
+        Self::with_vdp(Vdp::new())
+    }
+
+    /// Create a bus whose VDP regions are served by `vdp`
+    pub fn with_vdp(vdp: Vdp) -> Self {
         Self {
             workram: vec![0; Self::WORKRAM_SIZE],
             expanded_ram: vec![0; Self::EXPANDED_RAM_SIZE],
@@ -72,13 +245,27 @@ impl Bus24 {
             cart_rom: vec![0; Self::CART_ROM_SIZE],
             cart_save: vec![0; Self::CART_SAVE_SIZE],
             bios: vec![0; Self::BIOS_SIZE],
-            vram: vec![0; Self::VRAM_SIZE],
-            cram: vec![0; Self::CRAM_SIZE],
-            vdp_routing: false,
+            vdp,
             apu: Apu::new(),
+            timers: Timers::new(),
+            watchpoints: Vec::new(),
+            watch_hit: Cell::new(None),
+            io_regions: Vec::new(),
+            write_protected: Vec::new(),
+            write_fault_handler: None,
         }
     }
 
+    /// Access the VDP-T behind the register, VRAM and CRAM regions.
+    pub fn vdp(&self) -> &Vdp {
+        &self.vdp
+    }
+
+    /// Mutable access to the VDP-T.
+    pub fn vdp_mut(&mut self) -> &mut Vdp {
+        &mut self.vdp
+    }
+
     /// Access the shared APU-6 coprocessor state.
     pub fn apu(&self) -> &Apu {
         &self.apu
@@ -89,9 +276,153 @@ impl Bus24 {
         &mut self.apu
     }
 
-    /// Enable VDP routing for external VDP coprocessor
-    pub fn enable_vdp_routing(&mut self) {
-        self.vdp_routing = true;
+    /// Access the interval timers.
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    /// Mutable access to the interval timers.
+    pub fn timers_mut(&mut self) -> &mut Timers {
+        &mut self.timers
+    }
+
+    /// Watchpoints checked on every byte access.
+    pub(crate) fn watchpoints_mut(&mut self) -> &mut Vec<Watchpoint> {
+        &mut self.watchpoints
+    }
+
+    /// Consume the address of the first watchpoint hit since the last call.
+    pub(crate) fn take_watch_hit(&self) -> Option<u32> {
+        self.watch_hit.take()
+    }
+
+    /// Register a handler for reads from `len` bytes of I/O starting at `base`.
+    ///
+    /// The handler receives the full 24-bit address and takes precedence over
+    /// the built-in I/O storage and coprocessor registers.
+    pub fn register_io_read(&mut self, base: u32, len: u32, callback: IoReadHandler) {
+        self.insert_io_region(base, len, IoHandler::Read(RefCell::new(callback)));
+    }
+
+    /// Register a handler for writes to `len` bytes of I/O starting at `base`.
+    ///
+    /// The handler receives the full 24-bit address and the written value.
+    pub fn register_io_write(&mut self, base: u32, len: u32, callback: IoWriteHandler) {
+        self.insert_io_region(base, len, IoHandler::Write(callback));
+    }
+
+    fn insert_io_region(&mut self, base: u32, len: u32, handler: IoHandler) {
+        let base = base & 0x00FF_FFFF;
+        let index = self
+            .io_regions
+            .partition_point(|region| region.base <= base);
+        self.io_regions
+            .insert(index, IoRegion { base, len, handler });
+    }
+
+    fn is_io(addr: u32) -> bool {
+        (Self::IO_BASE..Self::IO_BASE + Self::IO_SIZE as u32).contains(&addr)
+    }
+
+    fn io_read_handler(&self, addr: u32) -> Option<u8> {
+        let region = self
+            .io_regions
+            .iter()
+            .take_while(|region| region.base <= addr)
+            .find(|region| region.contains(addr) && matches!(region.handler, IoHandler::Read(_)))?;
+        match &region.handler {
+            IoHandler::Read(callback) => Some((callback.borrow_mut())(addr)),
+            IoHandler::Write(_) => None,
+        }
+    }
+
+    fn io_write_handler(&mut self, addr: u32, value: u8) -> bool {
+        for region in self.io_regions.iter_mut() {
+            if region.base > addr {
+                break;
+            }
+            if let (true, IoHandler::Write(callback)) = (region.contains(addr), &mut region.handler)
+            {
+                callback(addr, value);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Drop writes to `len` bytes starting at `base`, wrapping at 24 bits
+    ///
+    /// Protected writes are ignored like writes to ROM, after notifying the
+    /// handler set with [`Bus24::set_write_fault_handler`].
+    pub fn write_protect(&mut self, base: u32, len: u32) {
+        for (start, end) in Self::split_range(base, len) {
+            self.remove_protection(start, end);
+            self.write_protected.push((start, end));
+        }
+    }
+
+    /// Allow writes again to `len` bytes starting at `base`
+    ///
+    /// Only the given bytes are unprotected; the rest of a larger protected
+    /// range stays protected.
+    pub fn write_unprotect(&mut self, base: u32, len: u32) {
+        for (start, end) in Self::split_range(base, len) {
+            self.remove_protection(start, end);
+        }
+    }
+
+    /// Whether writes to `addr` are currently dropped by [`Bus24::write_protect`]
+    pub fn is_write_protected(&self, addr: u32) -> bool {
+        let addr = addr & 0x00FF_FFFF;
+        self.write_protected
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&addr))
+    }
+
+    /// Call `handler` for every write dropped by write protection
+    pub fn set_write_fault_handler(&mut self, handler: WriteFaultHandler) {
+        self.write_fault_handler = Some(handler);
+    }
+
+    /// Split `base..base + len` at the top of the address space; the second
+    /// range is empty unless the first wraps
+    fn split_range(base: u32, len: u32) -> impl Iterator<Item = (u32, u32)> {
+        let base = base & 0x00FF_FFFF;
+        let end = base as u64 + len.min(ADDRESS_SPACE) as u64;
+        let wrapped = end.saturating_sub(ADDRESS_SPACE as u64) as u32;
+        [(base, end.min(ADDRESS_SPACE as u64) as u32), (0, wrapped)]
+            .into_iter()
+            .filter(|(start, end)| start < end)
+    }
+
+    fn remove_protection(&mut self, start: u32, end: u32) {
+        let mut kept = Vec::with_capacity(self.write_protected.len() + 1);
+        for &(s, e) in &self.write_protected {
+            if e <= start || s >= end {
+                kept.push((s, e));
+                continue;
+            }
+            if s < start {
+                kept.push((s, start));
+            }
+            if e > end {
+                kept.push((end, e));
+            }
+        }
+        self.write_protected = kept;
+    }
+
+    fn check_watchpoints(&self, addr: u32, access: WatchMode) {
+        if self.watchpoints.is_empty() || self.watch_hit.get().is_some() {
+            return;
+        }
+        if self
+            .watchpoints
+            .iter()
+            .any(|wp| wp.addr == addr && wp.mode.matches(access))
+        {
+            self.watch_hit.set(Some(addr));
+        }
     }
 
     /// Load cartridge ROM data
@@ -106,59 +437,112 @@ impl Bus24 {
         self.bios[..len].copy_from_slice(&data[..len]);
     }
 
-    /// Read a byte from the 24-bit address space
+    /// Load cartridge save data and report whether its checksum is intact
+    pub fn load_cart_save(&mut self, data: &[u8]) -> bool {
+        let len = data.len().min(Self::CART_SAVE_SIZE);
+        self.cart_save[..len].copy_from_slice(&data[..len]);
+        self.validate_cart_save()
+    }
+
+    /// Finalize the checksum and return the cartridge save data for writing to disk
+    pub fn save_cart_save(&mut self) -> &[u8] {
+        self.finalize_cart_save();
+        &self.cart_save
+    }
+
+    /// Capture the writable memory regions for a save state
+    pub fn save(&self) -> BusState {
+        BusState {
+            workram: self.workram.clone(),
+            expanded_ram: self.expanded_ram.clone(),
+            io: self.io.clone(),
+            cart_save: self.cart_save.clone(),
+            bios: self.bios.clone(),
+        }
+    }
+
+    /// Restore memory regions captured by [`Bus24::save`]
     ///
-    /// Note: VDP regions (I/O 0x100000-0x103FFF, VRAM 0x200000-0x27FFFF, CRAM 0x280000-0x28FFFF)
-    /// should be routed through external VDP coprocessor when vdp_routing is enabled.
-    /// This returns 0xFF for those regions when routing is enabled.
+    /// Regions are copied up to their fixed size, so a truncated state leaves
+    /// the tail of a region untouched.
+    pub fn load(&mut self, state: &BusState) {
+        fn restore(dst: &mut [u8], src: &[u8]) {
+            let len = src.len().min(dst.len());
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+        restore(&mut self.workram, &state.workram);
+        restore(&mut self.expanded_ram, &state.expanded_ram);
+        restore(&mut self.io, &state.io);
+        restore(&mut self.cart_save, &state.cart_save);
+        restore(&mut self.bios, &state.bios);
+    }
+
+    /// Check the CRC32 stored in the last 4 bytes of CartSave (0xA3FFFC)
+    /// against the rest of the save region
+    pub fn validate_cart_save(&self) -> bool {
+        let stored = u32::from_le_bytes([
+            self.cart_save[Self::CART_SAVE_CHECKSUM_OFFSET],
+            self.cart_save[Self::CART_SAVE_CHECKSUM_OFFSET + 1],
+            self.cart_save[Self::CART_SAVE_CHECKSUM_OFFSET + 2],
+            self.cart_save[Self::CART_SAVE_CHECKSUM_OFFSET + 3],
+        ]);
+        stored == crc32(&self.cart_save[..Self::CART_SAVE_CHECKSUM_OFFSET])
+    }
+
+    /// Compute the CRC32 of the save region and store it in the last 4 bytes
+    pub fn finalize_cart_save(&mut self) {
+        let checksum = crc32(&self.cart_save[..Self::CART_SAVE_CHECKSUM_OFFSET]);
+        self.cart_save[Self::CART_SAVE_CHECKSUM_OFFSET..].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    /// Read a byte from the 24-bit address space
     pub fn read_u8(&self, addr: u32) -> u8 {
         let addr = addr & 0x00FF_FFFF; // Mask to 24-bit
+        self.check_watchpoints(addr, WatchMode::Read);
+
+        if Self::is_io(addr)
+            && let Some(value) = self.io_read_handler(addr)
+        {
+            return value;
+        }
 
         match addr {
             // WorkRAM: 0x000000..0x00FFFF
             a if a < Self::EXPANDED_RAM_BASE => self.workram[a as usize],
             // ExpandedRAM: 0x010000..0x03FFFF
-            a if a >= Self::EXPANDED_RAM_BASE && a < 0x040000 => {
+            a if (Self::EXPANDED_RAM_BASE..0x040000).contains(&a) => {
                 let offset = (a - Self::EXPANDED_RAM_BASE) as usize;
                 self.expanded_ram[offset]
             }
             // I/O: 0x100000..0x10FFFF
-            // VDP-T I/O: 0x100000..0x103FFF (should be routed to VDP externally)
-            a if a >= Self::VDP_IO_BASE && a < Self::VDP_IO_BASE + 0x4000 => {
-                if self.vdp_routing {
-                    0xFF // Caller should route to VDP
-                } else {
-                    let offset = (a - Self::IO_BASE) as usize;
-                    self.io.get(offset).copied().unwrap_or(0xFF)
-                }
+            // VDP-T I/O: 0x100000..0x103FFF
+            a if (Self::VDP_IO_BASE..Self::VDP_IO_BASE + 0x4000).contains(&a) => {
+                let offset = a - Self::VDP_IO_BASE;
+                // VDP registers are 16-bit, read as bytes
+                let [lo, hi] = self.vdp.read_reg(offset & !1).to_le_bytes();
+                if offset & 1 == 0 { lo } else { hi }
             }
             // APU-6 coprocessor: 0x10C000..0x10FFFF
-            a if a >= Self::APU_IO_BASE && a < Self::APU_IO_BASE + Self::APU_IO_SIZE => {
+            a if (Self::APU_IO_BASE..Self::APU_IO_BASE + Self::APU_IO_SIZE).contains(&a) => {
                 let offset = a - Self::APU_IO_BASE;
                 self.apu.read_register(offset)
             }
+            // Interval timers: 0x10B000..0x10B00F
+            a if (Self::TIMER_IO_BASE..Self::TIMER_IO_BASE + Self::TIMER_IO_SIZE).contains(&a) => {
+                self.timers.read_register(a - Self::TIMER_IO_BASE)
+            }
             // Other I/O: VLU, etc.
             a if a >= Self::IO_BASE && a < Self::IO_BASE + Self::IO_SIZE as u32 => {
                 let offset = (a - Self::IO_BASE) as usize;
                 self.io.get(offset).copied().unwrap_or(0xFF)
             }
-            // VRAM: 0x200000..0x27FFFF (should be routed to VDP externally)
-            a if a >= Self::VRAM_BASE && a < Self::VRAM_BASE + Self::VRAM_SIZE as u32 => {
-                if self.vdp_routing {
-                    0xFF // Caller should route to VDP
-                } else {
-                    let offset = (a - Self::VRAM_BASE) as usize;
-                    self.vram[offset]
-                }
+            // VRAM: 0x200000..0x27FFFF
+            a if (Self::VRAM_BASE..Self::VRAM_BASE + Self::VRAM_SIZE as u32).contains(&a) => {
+                self.vdp.read_vram(a - Self::VRAM_BASE)
             }
-            // CRAM: 0x280000..0x28FFFF (should be routed to VDP externally)
-            a if a >= Self::CRAM_BASE && a < Self::CRAM_BASE + Self::CRAM_SIZE as u32 => {
-                if self.vdp_routing {
-                    0xFF // Caller should route to VDP
-                } else {
-                    let offset = (a - Self::CRAM_BASE) as usize;
-                    self.cram[offset]
-                }
+            // CRAM: 0x280000..0x28FFFF
+            a if (Self::CRAM_BASE..Self::CRAM_BASE + Self::CRAM_SIZE as u32).contains(&a) => {
+                self.vdp.read_cram(a - Self::CRAM_BASE)
             }
             // CartROM: 0x400000..0x9FFFFF
             a if a >= Self::CART_ROM_BASE
@@ -189,12 +573,20 @@ impl Bus24 {
     }
 
     /// Write a byte to the 24-bit address space
-    ///
-    /// Note: VDP regions (I/O 0x100000-0x103FFF, VRAM 0x200000-0x27FFFF, CRAM 0x280000-0x28FFFF)
-    /// should be routed through external VDP coprocessor when vdp_routing is enabled.
-    /// Writes to those regions are ignored when routing is enabled.
     pub fn write_u8(&mut self, addr: u32, value: u8) {
         let addr = addr & 0x00FF_FFFF; // Mask to 24-bit
+        self.check_watchpoints(addr, WatchMode::Write);
+
+        if !self.write_protected.is_empty() && self.is_write_protected(addr) {
+            if let Some(handler) = &mut self.write_fault_handler {
+                handler(addr, value);
+            }
+            return;
+        }
+
+        if Self::is_io(addr) && self.io_write_handler(addr, value) {
+            return;
+        }
 
         match addr {
             // WorkRAM: 0x000000..0x00FFFF
@@ -202,26 +594,28 @@ impl Bus24 {
                 self.workram[a as usize] = value;
             }
             // ExpandedRAM: 0x010000..0x03FFFF
-            a if a >= Self::EXPANDED_RAM_BASE && a < 0x040000 => {
+            a if (Self::EXPANDED_RAM_BASE..0x040000).contains(&a) => {
                 let offset = (a - Self::EXPANDED_RAM_BASE) as usize;
                 self.expanded_ram[offset] = value;
             }
-            // VDP-T I/O: 0x100000..0x103FFF (should be routed to VDP externally)
-            a if a >= Self::VDP_IO_BASE && a < Self::VDP_IO_BASE + 0x4000 => {
-                if self.vdp_routing {
-                    // Caller should route to VDP, ignore here
-                } else {
-                    let offset = (a - Self::IO_BASE) as usize;
-                    if let Some(cell) = self.io.get_mut(offset) {
-                        *cell = value;
-                    }
-                }
+            // VDP-T I/O: 0x100000..0x103FFF
+            a if (Self::VDP_IO_BASE..Self::VDP_IO_BASE + 0x4000).contains(&a) => {
+                // VDP registers are 16-bit; a byte write replaces one half
+                let offset = a - Self::VDP_IO_BASE;
+                let reg = offset & !1;
+                let mut bytes = self.vdp.read_reg(reg).to_le_bytes();
+                bytes[(offset & 1) as usize] = value;
+                self.vdp.write_reg(reg, u16::from_le_bytes(bytes));
             }
             // APU-6 coprocessor: 0x10C000..0x10FFFF
-            a if a >= Self::APU_IO_BASE && a < Self::APU_IO_BASE + Self::APU_IO_SIZE => {
+            a if (Self::APU_IO_BASE..Self::APU_IO_BASE + Self::APU_IO_SIZE).contains(&a) => {
                 let offset = a - Self::APU_IO_BASE;
                 self.apu.write_register(offset, value);
             }
+            // Interval timers: 0x10B000..0x10B00F
+            a if (Self::TIMER_IO_BASE..Self::TIMER_IO_BASE + Self::TIMER_IO_SIZE).contains(&a) => {
+                self.timers.write_register(a - Self::TIMER_IO_BASE, value);
+            }
             // Other I/O: VLU, etc.
             a if a >= Self::IO_BASE && a < Self::IO_BASE + Self::IO_SIZE as u32 => {
                 let offset = (a - Self::IO_BASE) as usize;
@@ -229,23 +623,13 @@ impl Bus24 {
                     *cell = value;
                 }
             }
-            // VRAM: 0x200000..0x27FFFF (should be routed to VDP externally)
-            a if a >= Self::VRAM_BASE && a < Self::VRAM_BASE + Self::VRAM_SIZE as u32 => {
-                if !self.vdp_routing {
-                    let offset = (a - Self::VRAM_BASE) as usize;
-                    if let Some(cell) = self.vram.get_mut(offset) {
-                        *cell = value;
-                    }
-                }
+            // VRAM: 0x200000..0x27FFFF
+            a if (Self::VRAM_BASE..Self::VRAM_BASE + Self::VRAM_SIZE as u32).contains(&a) => {
+                self.vdp.write_vram(a - Self::VRAM_BASE, value);
             }
-            // CRAM: 0x280000..0x28FFFF (should be routed to VDP externally)
-            a if a >= Self::CRAM_BASE && a < Self::CRAM_BASE + Self::CRAM_SIZE as u32 => {
-                if !self.vdp_routing {
-                    let offset = (a - Self::CRAM_BASE) as usize;
-                    if let Some(cell) = self.cram.get_mut(offset) {
-                        *cell = value;
-                    }
-                }
+            // CRAM: 0x280000..0x28FFFF
+            a if (Self::CRAM_BASE..Self::CRAM_BASE + Self::CRAM_SIZE as u32).contains(&a) => {
+                self.vdp.write_cram(a - Self::CRAM_BASE, value);
             }
             // CartROM: 0x400000..0x9FFFFF (read-only, writes ignored)
             a if a >= Self::CART_ROM_BASE
@@ -298,6 +682,53 @@ impl Bus24 {
         self.write_u8(addr.wrapping_add(1), ((v >> 8) & 0xFF) as u8);
         self.write_u8(addr.wrapping_add(2), ((v >> 16) & 0xFF) as u8);
     }
+
+    /// Read little-endian u32; the address need not be aligned
+    pub fn read_u32(&self, addr: u32) -> u32 {
+        let lo = self.read_u16(addr) as u32;
+        let hi = self.read_u16(addr.wrapping_add(2)) as u32;
+        lo | (hi << 16)
+    }
+
+    /// Write little-endian u32; the address need not be aligned
+    pub fn write_u32(&mut self, addr: u32, v: u32) {
+        self.write_u16(addr, (v & 0xFFFF) as u16);
+        self.write_u16(addr.wrapping_add(2), (v >> 16) as u16);
+    }
+
+    /// Every mapped region of the address space, in address order
+    pub fn regions() -> &'static [MemoryRegionInfo] {
+        &MEMORY_MAP
+    }
+
+    /// Region containing `addr`, masked to 24 bits
+    pub fn region_at(addr: u32) -> MemoryRegion {
+        let addr = addr & 0x00FF_FFFF;
+        MEMORY_MAP
+            .iter()
+            .find(|info| info.contains(addr))
+            .map_or(MemoryRegion::Unmapped, |info| info.region)
+    }
+
+    /// Copy `len` bytes from `src` to `dst` one byte at a time
+    ///
+    /// Bytes go through [`Bus24::read_u8`] and [`Bus24::write_u8`], so I/O
+    /// handlers and watchpoints fire and writes to ROM are dropped. Both
+    /// addresses wrap at 24 bits; overlapping ranges copy forwards like a
+    /// DMA transfer.
+    pub fn copy_region(&mut self, dst: u32, src: u32, len: usize) {
+        for i in 0..len as u32 {
+            let value = self.read_u8(src.wrapping_add(i));
+            self.write_u8(dst.wrapping_add(i), value);
+        }
+    }
+
+    /// Write `value` to `len` bytes starting at `dst`, wrapping at 24 bits
+    pub fn fill_region(&mut self, dst: u32, value: u8, len: usize) {
+        for i in 0..len as u32 {
+            self.write_u8(dst.wrapping_add(i), value);
+        }
+    }
 }
 
 impl Default for Bus24 {
@@ -306,6 +737,19 @@ impl Default for Bus24 {
     }
 }
 
+/// CRC-32 (IEEE 802.3, reflected polynomial 0xEDB88320)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -369,6 +813,9 @@ mod tests {
         assert_eq!(bus.read_u8(0x200000), 0x11);
         bus.write_u8(0x27FFFF, 0x22);
         assert_eq!(bus.read_u8(0x27FFFF), 0x22);
+        // The VDP sees the same memory
+        assert_eq!(bus.vdp().read_vram(0), 0x11);
+        assert_eq!(bus.vdp().read_vram(0x7FFFF), 0x22);
     }
 
     #[test]
@@ -379,6 +826,7 @@ mod tests {
         assert_eq!(bus.read_u8(0x280000), 0x33);
         bus.write_u8(0x28FFFF, 0x44);
         assert_eq!(bus.read_u8(0x28FFFF), 0x44);
+        assert_eq!(bus.vdp().read_cram(0), 0x33);
     }
 
     #[test]
@@ -416,6 +864,36 @@ mod tests {
         assert_eq!(bus.read_u8(0xA3FFFF), 0x88);
     }
 
+    #[test]
+    fn bus_cart_save_checksum_detects_corruption() {
+        let mut bus = Bus24::new();
+        for i in 0..0x100u32 {
+            bus.write_u8(Bus24::CART_SAVE_BASE + i, (i * 7) as u8);
+        }
+        bus.finalize_cart_save();
+        assert!(bus.validate_cart_save());
+
+        // Flip a single byte in the middle of the region
+        let mid = Bus24::CART_SAVE_BASE + (Bus24::CART_SAVE_SIZE as u32 / 2);
+        bus.write_u8(mid, bus.read_u8(mid) ^ 0x01);
+        assert!(!bus.validate_cart_save());
+    }
+
+    #[test]
+    fn bus_cart_save_round_trip() {
+        let mut bus = Bus24::new();
+        bus.write_u8(Bus24::CART_SAVE_BASE, 0x5A);
+        let image = bus.save_cart_save().to_vec();
+        assert_eq!(image.len(), Bus24::CART_SAVE_SIZE);
+
+        let mut restored = Bus24::new();
+        assert!(restored.load_cart_save(&image));
+        assert_eq!(restored.read_u8(Bus24::CART_SAVE_BASE), 0x5A);
+
+        // Known CRC32 check value
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
     #[test]
     fn bus_read_bios() {
         let mut bus = Bus24::new();
@@ -474,6 +952,20 @@ mod tests {
         assert_eq!(bus.read_u24(0x200), 0xAB1234);
     }
 
+    #[test]
+    fn bus_read_write_u32_unaligned() {
+        let mut bus = Bus24::new();
+        bus.write_u32(0x000001, 0xDEADBEEF);
+        assert_eq!(bus.read_u32(0x000001), 0xDEADBEEF);
+        assert_eq!(bus.read_u8(0x000001), 0xEF);
+
+        // Straddle the WorkRAM/ExpandedRAM boundary
+        bus.write_u32(0x00FFFD, 0xDEADBEEF);
+        assert_eq!(bus.read_u32(0x00FFFD), 0xDEADBEEF);
+        assert_eq!(bus.read_u8(0x00FFFF), 0xAD);
+        assert_eq!(bus.read_u8(Bus24::EXPANDED_RAM_BASE), 0xDE);
+    }
+
     #[test]
     fn bus_address_masking() {
         let mut bus = Bus24::new();
@@ -481,4 +973,259 @@ mod tests {
         bus.write_u8(0x01000000, 0x42); // Should wrap to 0x000000
         assert_eq!(bus.read_u8(0x000000), 0x42);
     }
+
+    #[test]
+    fn region_at_matches_memory_map() {
+        let boundaries = [
+            (0x000000, MemoryRegion::WorkRam),
+            (0x00FFFF, MemoryRegion::WorkRam),
+            (0x010000, MemoryRegion::ExpandedRam),
+            (0x03FFFF, MemoryRegion::ExpandedRam),
+            (0x040000, MemoryRegion::Unmapped),
+            (0x0FFFFF, MemoryRegion::Unmapped),
+            (0x100000, MemoryRegion::Io),
+            (0x10FFFF, MemoryRegion::Io),
+            (0x110000, MemoryRegion::Unmapped),
+            (0x1FFFFF, MemoryRegion::Unmapped),
+            (0x200000, MemoryRegion::Vram),
+            (0x27FFFF, MemoryRegion::Vram),
+            (0x280000, MemoryRegion::Cram),
+            (0x28FFFF, MemoryRegion::Cram),
+            (0x290000, MemoryRegion::Unmapped),
+            (0x3FFFFF, MemoryRegion::Unmapped),
+            (0x400000, MemoryRegion::CartRom),
+            (0x9FFFFF, MemoryRegion::CartRom),
+            (0xA00000, MemoryRegion::CartSave),
+            (0xA3FFFF, MemoryRegion::CartSave),
+            (0xA40000, MemoryRegion::Unmapped),
+            (0xFEFFFF, MemoryRegion::Unmapped),
+            (0xFF0000, MemoryRegion::Bios),
+            (0xFFFFFF, MemoryRegion::Bios),
+            (0x1000000, MemoryRegion::WorkRam), // Masked to 24 bits
+        ];
+        for (addr, region) in boundaries {
+            assert_eq!(Bus24::region_at(addr), region, "addr {addr:#08X}");
+        }
+
+        for info in Bus24::regions() {
+            assert_eq!(Bus24::region_at(info.base), info.region);
+            assert_eq!(Bus24::region_at(info.base + info.size - 1), info.region);
+            assert!(info.region.is_readable());
+        }
+        assert!(!MemoryRegion::CartRom.is_writable());
+        assert!(!MemoryRegion::Bios.is_writable());
+        assert!(MemoryRegion::CartSave.is_writable());
+        assert!(!MemoryRegion::Unmapped.is_readable());
+    }
+
+    #[test]
+    fn fill_then_copy_region() {
+        let mut bus = Bus24::new();
+        bus.fill_region(0x2000, 0xCC, 0x100);
+        bus.write_u8(0x20FF, 0x11);
+        bus.copy_region(Bus24::EXPANDED_RAM_BASE + 0x80, 0x2000, 0x100);
+
+        for i in 0..0x100 {
+            assert_eq!(
+                bus.read_u8(Bus24::EXPANDED_RAM_BASE + 0x80 + i),
+                bus.read_u8(0x2000 + i)
+            );
+        }
+        assert_eq!(bus.read_u8(Bus24::EXPANDED_RAM_BASE + 0x17F), 0x11);
+        assert_eq!(bus.read_u8(Bus24::EXPANDED_RAM_BASE + 0x180), 0x00);
+    }
+
+    #[test]
+    fn region_helpers_wrap_and_skip_rom() {
+        let mut bus = Bus24::new();
+        bus.load_bios(&[0x42; 4]);
+        // Fill across the top of the address space into WorkRAM
+        bus.fill_region(0xFFFFFE, 0x77, 4);
+        assert_eq!(bus.read_u8(0xFFFFFE), 0x00);
+        assert_eq!(bus.read_u8(0x000000), 0x77);
+        assert_eq!(bus.read_u8(0x000001), 0x77);
+
+        bus.copy_region(Bus24::BIOS_BASE, 0x000000, 2);
+        assert_eq!(bus.read_u8(Bus24::BIOS_BASE), 0x42);
+        bus.copy_region(0x000100, Bus24::BIOS_BASE, 4);
+        assert_eq!(bus.read_u24(0x000100), 0x424242);
+    }
+
+    #[test]
+    fn write_protect_drops_writes_until_unprotected() {
+        let mut bus = Bus24::new();
+        bus.write_u8(0x0010, 0x5A);
+        bus.write_protect(0x0000, 0x100);
+
+        bus.write_u8(0x0010, 0xFF);
+        bus.write_u16(0x00FF, 0x1234);
+        bus.fill_region(0x0000, 0xEE, 0x100);
+        assert_eq!(bus.read_u8(0x0010), 0x5A);
+        assert_eq!(bus.read_u8(0x00FF), 0x00);
+        assert_eq!(bus.read_u8(0x0100), 0x12);
+        assert!(bus.is_write_protected(0x00FF));
+        assert!(!bus.is_write_protected(0x0100));
+
+        // Punch a hole in the middle of the protected range
+        bus.write_unprotect(0x0040, 0x10);
+        bus.write_u8(0x0040, 0x01);
+        bus.write_u8(0x0050, 0x02);
+        assert_eq!(bus.read_u8(0x0040), 0x01);
+        assert_eq!(bus.read_u8(0x0050), 0x00);
+
+        bus.write_unprotect(0x0000, 0x100);
+        bus.fill_region(0x0000, 0xEE, 0x100);
+        assert_eq!(bus.read_u8(0x0010), 0xEE);
+        assert_eq!(bus.read_u8(0x00FF), 0xEE);
+    }
+
+    #[test]
+    fn write_fault_handler_sees_dropped_writes() {
+        let faults = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let log = std::rc::Rc::clone(&faults);
+        let mut bus = Bus24::new();
+        bus.set_write_fault_handler(Box::new(move |addr, value| {
+            log.borrow_mut().push((addr, value));
+        }));
+
+        // The range wraps from the top of BIOS into WorkRAM
+        bus.write_protect(0xFFFFFF, 2);
+        bus.write_u8(0x000000, 0xAB);
+        bus.write_u8(0x000001, 0xCD);
+        bus.write_u8(0xFFFFFF, 0x11);
+
+        assert_eq!(*faults.borrow(), vec![(0x000000, 0xAB), (0xFFFFFF, 0x11)]);
+        assert_eq!(bus.read_u8(0x000000), 0x00);
+        assert_eq!(bus.read_u8(0x000001), 0xCD);
+    }
+
+    #[test]
+    fn io_write_handler_receives_writes_in_order() {
+        use std::rc::Rc;
+
+        let mut bus = Bus24::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&log);
+        bus.register_io_write(
+            Bus24::VLU_IO_BASE,
+            0x10,
+            Box::new(move |addr, value| sink.borrow_mut().push((addr, value))),
+        );
+
+        bus.write_u8(Bus24::VLU_IO_BASE, 0x11);
+        bus.write_u8(Bus24::VLU_IO_BASE + 4, 0x22);
+        bus.write_u8(Bus24::VLU_IO_BASE + 0x0F, 0x33);
+        // Outside the region falls back to plain I/O storage
+        bus.write_u8(Bus24::VLU_IO_BASE + 0x10, 0x44);
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (Bus24::VLU_IO_BASE, 0x11),
+                (Bus24::VLU_IO_BASE + 4, 0x22),
+                (Bus24::VLU_IO_BASE + 0x0F, 0x33),
+            ]
+        );
+        assert_eq!(bus.read_u8(Bus24::VLU_IO_BASE + 0x10), 0x44);
+        // The write handler does not intercept reads
+        assert_eq!(bus.read_u8(Bus24::VLU_IO_BASE), 0x00);
+    }
+
+    #[test]
+    fn io_read_handler_overrides_storage() {
+        let mut bus = Bus24::new();
+        bus.register_io_read(
+            Bus24::APU_IO_BASE,
+            2,
+            Box::new(|addr| (addr & 0xFF) as u8 | 0x80),
+        );
+        assert_eq!(bus.read_u8(Bus24::APU_IO_BASE + 1), 0x81);
+        assert_eq!(bus.read_u16(Bus24::APU_IO_BASE), 0x8180);
+    }
+
+    /// Bus operation for the save-state property test: a read, or a write
+    /// of the given value
+    #[derive(Debug, Clone)]
+    enum BusOp {
+        Read(u32),
+        Write(u32, u8),
+    }
+
+    fn bus_addr() -> impl proptest::strategy::Strategy<Value = u32> {
+        use proptest::prelude::*;
+        // Bias addresses into the mapped regions so most writes land somewhere
+        let regions = [
+            (Bus24::WORKRAM_BASE, Bus24::WORKRAM_SIZE),
+            (Bus24::EXPANDED_RAM_BASE, Bus24::EXPANDED_RAM_SIZE),
+            (Bus24::IO_BASE, Bus24::IO_SIZE),
+            (Bus24::VRAM_BASE, Bus24::VRAM_SIZE),
+            (Bus24::CRAM_BASE, Bus24::CRAM_SIZE),
+            (Bus24::CART_SAVE_BASE, Bus24::CART_SAVE_SIZE),
+            (Bus24::BIOS_BASE, Bus24::BIOS_SIZE),
+        ];
+        (0..regions.len(), any::<u32>(), any::<bool>()).prop_map(move |(region, offset, raw)| {
+            if raw {
+                offset & 0x00FF_FFFF
+            } else {
+                let (base, size) = regions[region];
+                base + offset % size as u32
+            }
+        })
+    }
+
+    fn bus_ops() -> impl proptest::strategy::Strategy<Value = Vec<BusOp>> {
+        use proptest::prelude::*;
+        let op = prop_oneof![
+            bus_addr().prop_map(BusOp::Read),
+            (bus_addr(), any::<u8>()).prop_map(|(addr, value)| BusOp::Write(addr, value)),
+        ];
+        proptest::collection::vec(op, 0..64)
+    }
+
+    fn apply(bus: &mut Bus24, ops: &[BusOp]) {
+        for op in ops {
+            match *op {
+                BusOp::Read(addr) => {
+                    bus.read_u8(addr);
+                }
+                BusOp::Write(addr, value) => bus.write_u8(addr, value),
+            }
+        }
+    }
+
+    #[test]
+    fn save_state_captures_cart_save_and_skips_rom() {
+        let mut bus = Bus24::new();
+        bus.load_cart_rom(&[0xAA]);
+        bus.write_u8(Bus24::CART_SAVE_BASE, 0x5A);
+        let state = bus.save();
+
+        bus.write_u8(Bus24::CART_SAVE_BASE, 0x00);
+        bus.load_cart_rom(&[0xBB]);
+        bus.load(&state);
+
+        assert_eq!(bus.read_u8(Bus24::CART_SAVE_BASE), 0x5A);
+        // ROM contents are not part of the snapshot
+        assert_eq!(bus.read_u8(Bus24::CART_ROM_BASE), 0xBB);
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn save_state_round_trip(setup in bus_ops(), before in bus_ops(), after in bus_ops()) {
+            let mut bus = Bus24::new();
+            apply(&mut bus, &setup);
+            let state = bus.save();
+
+            apply(&mut bus, &before);
+            let expected = bus.save();
+            apply(&mut bus, &after);
+
+            bus.load(&state);
+            proptest::prop_assert_eq!(&bus.save(), &state);
+            apply(&mut bus, &before);
+            proptest::prop_assert_eq!(&bus.save(), &expected);
+        }
+    }
 }
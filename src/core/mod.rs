@@ -11,4 +11,7 @@
 pub mod bus;
 
 // Re-export commonly used core types here
-pub use bus::Bus24;
+pub use bus::{
+    Bus24, BusState, IoReadHandler, IoWriteHandler, MemoryRegion, MemoryRegionInfo, WatchMode,
+    Watchpoint, WriteFaultHandler,
+};
@@ -16,10 +16,101 @@
 //! - 8 general-purpose registers + special registers (A, X, Y, SP, PC, SR)
 //! - Memory-mapped coprocessor access
 
+use std::collections::HashSet;
+
 use crate::core::Bus24;
+pub use crate::core::{WatchMode, Watchpoint};
+
+/// Snapshot of the architectural CPU state, used for save states
+///
+/// Debugging aids (breakpoints, trace buffer) are not part of the snapshot.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuState {
+    pub a: u16,
+    pub x: u16,
+    pub y: u16,
+    pub sp: u16,
+    pub pc: u32,
+    pub sr: StatusFlags,
+    pub r: [u16; 8],
+    pub cycles: u64,
+    pub halted: bool,
+    pub waiting_for_interrupt: bool,
+    pub pending_interrupts: Vec<u8>,
+    pub cop_request: Option<u8>,
+}
+
+/// Code breakpoints and memory watchpoints
+#[derive(Debug, Clone, Default)]
+pub struct BreakpointSet {
+    pub code: HashSet<u32>,
+    pub watchpoints: Vec<Watchpoint>,
+}
+
+/// CPU state captured just before an instruction executes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u32,
+    pub opcode: u8,
+    pub a: u16,
+    pub x: u16,
+    pub y: u16,
+    pub sr: u8,
+    pub cycles: u64,
+}
+
+/// Ring buffer holding the most recent trace entries
+#[derive(Debug, Clone)]
+pub struct TraceBuffer {
+    // Holds up to twice the capacity so the newest entries stay contiguous;
+    // the older half is dropped in one go when it fills up.
+    entries: Vec<TraceEntry>,
+    capacity: usize,
+}
+
+impl TraceBuffer {
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: Vec::with_capacity(capacity * 2),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, entry: TraceEntry) {
+        if self.entries.len() == self.capacity * 2 {
+            self.entries.drain(..self.capacity);
+        }
+        self.entries.push(entry);
+    }
+
+    /// Recorded entries, oldest first
+    pub fn entries(&self) -> &[TraceEntry] {
+        let start = self.entries.len().saturating_sub(self.capacity);
+        &self.entries[start..]
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for TraceBuffer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
 
 /// CPU Status Register flags
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StatusFlags {
     pub carry: bool,
     pub zero: bool,
@@ -109,8 +200,23 @@ pub struct Cpu {
     // Halted state
     pub halted: bool,
 
+    // Set by WFI; the next accepted interrupt request clears `halted`
+    pub waiting_for_interrupt: bool,
+
     // Add pending interrupt queue and interrupt handling
     pub pending_interrupts: Vec<u8>,
+
+    // Command byte latched by the last COP, awaiting dispatch by the system
+    pub cop_request: Option<u8>,
+
+    // Debugging: breakpoints/watchpoints and the flag set when one triggers
+    pub breakpoints: BreakpointSet,
+    pub breakpoint_hit: bool,
+    pub breakpoint_callback: Option<Box<dyn Fn(u32)>>,
+    breakpoint_resume: Option<u32>,
+
+    // Instruction trace, allocated only while tracing is enabled
+    trace: Option<Box<TraceBuffer>>,
 }
 
 impl Cpu {
@@ -124,6 +230,14 @@ impl Cpu {
     const PAD_EVENT_PRIORITY: u8 = 1;
     const SWI_PRIORITY: u8 = 0;
 
+    /// Address of the 24-bit handler pointer for interrupt `int`
+    ///
+    /// The reset vector takes the first slot of the table at 0xFF0000, so
+    /// interrupt N's pointer lives at 0xFF0000 + (N + 1) * 3.
+    pub const fn interrupt_vector(int: u8) -> u32 {
+        0xFF0000 + (int as u32 + 1) * 3
+    }
+
     pub fn new() -> Self {
         Self {
             a: 0,
@@ -135,7 +249,14 @@ impl Cpu {
             r: [0; 8],
             cycles: 0,
             halted: false,
+            waiting_for_interrupt: false,
             pending_interrupts: Vec::new(),
+            cop_request: None,
+            breakpoints: BreakpointSet::default(),
+            breakpoint_hit: false,
+            breakpoint_callback: None,
+            breakpoint_resume: None,
+            trace: None,
         }
     }
 
@@ -148,6 +269,10 @@ impl Cpu {
         self.r = [0; 8];
         self.sr = StatusFlags::new();
         self.halted = false;
+        self.waiting_for_interrupt = false;
+        self.cop_request = None;
+        self.breakpoint_hit = false;
+        self.breakpoint_resume = None;
 
         // Load reset vector from BIOS (0xFF0000)
         self.pc = bus.read_u24(0xFF0000);
@@ -160,6 +285,7 @@ impl Cpu {
         if int != 7 && self.sr.interrupt_disable {
             return;
         }
+        self.wake_from_wfi();
         // Simple deduplication: only add if not already pending
         if !self.pending_interrupts.contains(&int) {
             self.pending_interrupts.push(int);
@@ -185,6 +311,7 @@ impl Cpu {
 
     /// Trigger a non‑maskable interrupt (NMI)
     pub fn trigger_nmi(&mut self) {
+        self.wake_from_wfi();
         // NMI has highest priority (7) and will be sorted to front of queue
         // Insert at position 0 to ensure immediate priority
         if !self.pending_interrupts.contains(&7) {
@@ -192,6 +319,22 @@ impl Cpu {
         }
     }
 
+    /// Whether the CPU stopped on HLT
+    ///
+    /// A CPU waiting in WFI is also `halted`, but resumes on the next
+    /// interrupt request.
+    pub fn is_stopped(&self) -> bool {
+        self.halted && !self.waiting_for_interrupt
+    }
+
+    // Resume execution if the CPU is parked in WFI (HLT stays halted)
+    fn wake_from_wfi(&mut self) {
+        if self.waiting_for_interrupt {
+            self.waiting_for_interrupt = false;
+            self.halted = false;
+        }
+    }
+
     // Handle highest priority pending interrupt if interrupts are enabled
     // Returns true if an interrupt was handled
     fn handle_interrupts(&mut self, bus: &mut Bus24) -> bool {
@@ -203,11 +346,10 @@ impl Cpu {
                 return false;
             }
 
-            // Simple vector table: each interrupt has a 24-bit address at 0xFF0000 + int*3
-            let vector_addr = 0xFF0000 + (int as u32) * 3;
-            let handler_addr = bus.read_u24(vector_addr);
-            // Push PC onto stack (24-bit)
+            let handler_addr = bus.read_u24(Self::interrupt_vector(int));
+            // Push PC (24-bit) then the status register so RTI can restore both
             self.push_u24(bus, self.pc);
+            self.push_u8(bus, self.sr.to_byte());
             // Set interrupt disable flag
             self.sr.interrupt_disable = true;
             // Jump to handler
@@ -223,6 +365,7 @@ impl Cpu {
 
     /// Execute a single instruction
     pub fn step(&mut self, bus: &mut Bus24) {
+        self.breakpoint_hit = false;
         if self.halted {
             self.cycles += 1;
             return;
@@ -233,14 +376,150 @@ impl Cpu {
             return;
         }
 
+        // Stop before a breakpointed instruction; the next step executes it
+        if self.breakpoint_resume.take() != Some(self.pc)
+            && self.breakpoints.code.contains(&self.pc)
+        {
+            self.breakpoint_resume = Some(self.pc);
+            self.trigger_breakpoint(self.pc);
+            return;
+        }
+
+        // Lend the watchpoints to the bus while the instruction runs
+        let watching = !self.breakpoints.watchpoints.is_empty();
+        if watching {
+            std::mem::swap(&mut self.breakpoints.watchpoints, bus.watchpoints_mut());
+        }
+
         let opcode = bus.read_u8(self.pc);
         self.pc = self.pc.wrapping_add(1);
 
         self.execute_instruction(opcode, bus);
+
+        if watching {
+            std::mem::swap(&mut self.breakpoints.watchpoints, bus.watchpoints_mut());
+            if let Some(addr) = bus.take_watch_hit() {
+                self.trigger_breakpoint(addr);
+            }
+        }
+    }
+
+    /// Capture the CPU state for a save state.
+    pub fn save(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            sp: self.sp,
+            pc: self.pc,
+            sr: self.sr,
+            r: self.r,
+            cycles: self.cycles,
+            halted: self.halted,
+            waiting_for_interrupt: self.waiting_for_interrupt,
+            pending_interrupts: self.pending_interrupts.clone(),
+            cop_request: self.cop_request,
+        }
+    }
+
+    /// Restore CPU state previously captured with [`Cpu::save`].
+    pub fn load(&mut self, state: &CpuState) {
+        self.a = state.a;
+        self.x = state.x;
+        self.y = state.y;
+        self.sp = state.sp;
+        self.pc = state.pc;
+        self.sr = state.sr;
+        self.r = state.r;
+        self.cycles = state.cycles;
+        self.halted = state.halted;
+        self.waiting_for_interrupt = state.waiting_for_interrupt;
+        self.pending_interrupts = state.pending_interrupts.clone();
+        self.cop_request = state.cop_request;
+        self.breakpoint_hit = false;
+        self.breakpoint_resume = None;
+    }
+
+    /// Add a code breakpoint at `addr`.
+    pub fn add_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.code.insert(addr & 0xFFFFFF);
+    }
+
+    /// Remove the code breakpoint at `addr`, if any.
+    pub fn remove_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.code.remove(&(addr & 0xFFFFFF));
+    }
+
+    /// Watch a byte address for the given kind of access.
+    pub fn add_watchpoint(&mut self, addr: u32, mode: WatchMode) {
+        self.breakpoints.watchpoints.push(Watchpoint {
+            addr: addr & 0xFFFFFF,
+            mode,
+        });
+    }
+
+    /// Remove all watchpoints on `addr`.
+    pub fn remove_watchpoint(&mut self, addr: u32) {
+        let addr = addr & 0xFFFFFF;
+        self.breakpoints.watchpoints.retain(|wp| wp.addr != addr);
+    }
+
+    /// Start recording executed instructions, keeping the last `capacity`.
+    pub fn enable_tracing(&mut self, capacity: usize) {
+        self.trace = Some(Box::new(TraceBuffer::new(capacity)));
+    }
+
+    /// Stop recording and free the trace buffer.
+    pub fn disable_tracing(&mut self) {
+        self.trace = None;
+    }
+
+    /// Recorded trace entries, oldest first (empty when tracing is disabled).
+    pub fn trace_log(&self) -> &[TraceEntry] {
+        self.trace.as_deref().map_or(&[], TraceBuffer::entries)
+    }
+
+    /// Format the trace log as one line of text per instruction.
+    pub fn disassemble_trace(&self) -> Vec<String> {
+        self.trace_log()
+            .iter()
+            .map(|e| {
+                format!(
+                    "{:06X}  {:02X}  {:<5} A={:04X} X={:04X} Y={:04X} SR={:02X} CYC={}",
+                    e.pc,
+                    e.opcode,
+                    opcode_mnemonic(e.opcode),
+                    e.a,
+                    e.x,
+                    e.y,
+                    e.sr,
+                    e.cycles
+                )
+            })
+            .collect()
+    }
+
+    fn trigger_breakpoint(&mut self, addr: u32) {
+        self.breakpoint_hit = true;
+        if let Some(callback) = &self.breakpoint_callback {
+            callback(addr);
+        }
     }
 
     /// Execute an instruction based on opcode
     fn execute_instruction(&mut self, opcode: u8, bus: &mut Bus24) {
+        if let Some(trace) = self.trace.as_deref_mut() {
+            trace.push(TraceEntry {
+                pc: self.pc.wrapping_sub(1),
+                opcode,
+                a: self.a,
+                x: self.x,
+                y: self.y,
+                sr: self.sr.to_byte(),
+                cycles: self.cycles,
+            });
+        }
+
         match opcode {
             // NOP - No operation
             0x00 => {
@@ -325,6 +604,66 @@ impl Cpu {
                 self.cycles += 4;
             }
 
+            // LDA - Load Accumulator (absolute 24-bit address indexed by X)
+            0x0A => {
+                let addr = self.fetch_indexed_address(bus, self.x);
+                self.a = bus.read_u16(addr);
+                self.sr.update_zn(self.a);
+                self.cycles += 5;
+            }
+
+            // LDA - Load Accumulator (absolute 24-bit address indexed by Y)
+            0x0B => {
+                let addr = self.fetch_indexed_address(bus, self.y);
+                self.a = bus.read_u16(addr);
+                self.sr.update_zn(self.a);
+                self.cycles += 5;
+            }
+
+            // STA - Store Accumulator (absolute 24-bit address indexed by X)
+            0x0C => {
+                let addr = self.fetch_indexed_address(bus, self.x);
+                bus.write_u16(addr, self.a);
+                self.cycles += 4;
+            }
+
+            // STA - Store Accumulator (absolute 24-bit address indexed by Y)
+            0x0D => {
+                let addr = self.fetch_indexed_address(bus, self.y);
+                bus.write_u16(addr, self.a);
+                self.cycles += 4;
+            }
+
+            // LDX - Load X register (absolute 24-bit address indexed by Y)
+            0x0E => {
+                let addr = self.fetch_indexed_address(bus, self.y);
+                self.x = bus.read_u16(addr);
+                self.sr.update_zn(self.x);
+                self.cycles += 5;
+            }
+
+            // LDY - Load Y register (absolute 24-bit address indexed by X)
+            0x0F => {
+                let addr = self.fetch_indexed_address(bus, self.x);
+                self.y = bus.read_u16(addr);
+                self.sr.update_zn(self.y);
+                self.cycles += 5;
+            }
+
+            // STX - Store X register (absolute 24-bit address indexed by Y)
+            0x1D => {
+                let addr = self.fetch_indexed_address(bus, self.y);
+                bus.write_u16(addr, self.x);
+                self.cycles += 4;
+            }
+
+            // STY - Store Y register (absolute 24-bit address indexed by X)
+            0x1E => {
+                let addr = self.fetch_indexed_address(bus, self.x);
+                bus.write_u16(addr, self.y);
+                self.cycles += 4;
+            }
+
             // ADD - Add to accumulator (immediate 16-bit)
             0x10 => {
                 let value = bus.read_u16(self.pc);
@@ -381,10 +720,11 @@ impl Cpu {
             0x15 => {
                 let value = bus.read_u16(self.pc);
                 self.pc = self.pc.wrapping_add(2);
-                let result = (self.a as u32).wrapping_mul(value as u32);
+                let result = (self.a as u32) * (value as u32);
                 self.a = (result & 0xFFFF) as u16;
-                // Store high word in X register
+                // Store high word in X register; carry flags a result wider than 16 bits
                 self.x = ((result >> 16) & 0xFFFF) as u16;
+                self.sr.carry = result > 0xFFFF;
                 self.sr.update_zn(self.a);
                 self.cycles += 4;
             }
@@ -393,19 +733,19 @@ impl Cpu {
             0x16 => {
                 let value = bus.read_u16(self.pc);
                 self.pc = self.pc.wrapping_add(2);
-                if value == 0 {
-                    // Division by zero - set carry flag and leave A unchanged
-                    self.sr.carry = true;
-                    self.cycles += 2;
-                } else {
-                    let quotient = self.a / value;
-                    let remainder = self.a % value;
-                    self.a = quotient;
-                    self.x = remainder;
-                    self.sr.carry = false;
-                    self.sr.update_zn(self.a);
-                    self.cycles += 12;
+                match (self.a.checked_div(value), self.a.checked_rem(value)) {
+                    (Some(quotient), Some(remainder)) => {
+                        self.a = quotient;
+                        self.x = remainder;
+                        self.sr.overflow = false;
+                        self.sr.update_zn(self.a);
+                    }
+                    _ => {
+                        // Division by zero - set overflow flag and leave A/X unchanged
+                        self.sr.overflow = true;
+                    }
                 }
+                self.cycles += 4;
             }
 
             // MOV - Move between registers (2 byte instruction: opcode + reg spec)
@@ -518,6 +858,57 @@ impl Cpu {
                 self.cycles += 5;
             }
 
+            // JMP - Jump indirect (24-bit pointer to 24-bit address)
+            0x23 => {
+                let ptr = bus.read_u24(self.pc);
+                self.pc = bus.read_u24(ptr);
+                self.cycles += 5;
+            }
+
+            // JSR - Jump to subroutine indirect (24-bit pointer to 24-bit address)
+            0x24 => {
+                let ptr = bus.read_u24(self.pc);
+                self.pc = self.pc.wrapping_add(3);
+                let addr = bus.read_u24(ptr);
+
+                // Push return address to stack (24-bit)
+                self.push_u24(bus, self.pc);
+                self.pc = addr;
+                self.cycles += 7;
+            }
+
+            // LCALL - Call subroutine saving the full register context
+            0x25 => {
+                let addr = bus.read_u24(self.pc);
+                self.pc = self.pc.wrapping_add(3);
+
+                // Return address, A, X, Y, R0-R7, then SR (26 bytes)
+                self.push_u24(bus, self.pc);
+                for value in [self.a, self.x, self.y] {
+                    self.push_u16(bus, value);
+                }
+                for i in 0..self.r.len() {
+                    self.push_u16(bus, self.r[i]);
+                }
+                self.push_u8(bus, self.sr.to_byte());
+                self.pc = addr;
+                self.cycles += 16;
+            }
+
+            // LRET - Return from LCALL, restoring the saved context
+            0x26 => {
+                let sr = self.pop_u8(bus);
+                self.sr = StatusFlags::from_byte(sr);
+                for i in (0..self.r.len()).rev() {
+                    self.r[i] = self.pop_u16(bus);
+                }
+                self.y = self.pop_u16(bus);
+                self.x = self.pop_u16(bus);
+                self.a = self.pop_u16(bus);
+                self.pc = self.pop_u24(bus);
+                self.cycles += 16;
+            }
+
             // RTS - Return from subroutine
             0x22 => {
                 self.pc = self.pop_u24(bus);
@@ -630,6 +1021,171 @@ impl Cpu {
                 }
             }
 
+            // LBRA - Long branch always (relative 16-bit signed)
+            0x39 => {
+                let offset = bus.read_u16(self.pc) as i16 as i32;
+                self.pc = self.pc.wrapping_add(2);
+                self.pc = self.pc.wrapping_add(offset as u32);
+                self.cycles += 3;
+            }
+
+            // LBEQ - Long branch if equal (zero set)
+            0x3A => {
+                let offset = bus.read_u16(self.pc) as i16 as i32;
+                self.pc = self.pc.wrapping_add(2);
+                if self.sr.zero {
+                    self.pc = self.pc.wrapping_add(offset as u32);
+                    self.cycles += 4; // Branch taken adds cycle
+                } else {
+                    self.cycles += 3;
+                }
+            }
+
+            // LBNE - Long branch if not equal (zero clear)
+            0x3B => {
+                let offset = bus.read_u16(self.pc) as i16 as i32;
+                self.pc = self.pc.wrapping_add(2);
+                if !self.sr.zero {
+                    self.pc = self.pc.wrapping_add(offset as u32);
+                    self.cycles += 4; // Branch taken adds cycle
+                } else {
+                    self.cycles += 3;
+                }
+            }
+
+            // CMP - Compare accumulator (immediate 16-bit)
+            0x50 => {
+                let value = bus.read_u16(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+                self.compare(self.a, value);
+                self.cycles += 2;
+            }
+
+            // CPX - Compare X register (immediate 16-bit)
+            0x51 => {
+                let value = bus.read_u16(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+                self.compare(self.x, value);
+                self.cycles += 2;
+            }
+
+            // CPY - Compare Y register (immediate 16-bit)
+            0x52 => {
+                let value = bus.read_u16(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+                self.compare(self.y, value);
+                self.cycles += 2;
+            }
+
+            // LSL - Logical shift accumulator left (bit 15 -> carry)
+            0x60 => {
+                self.sr.carry = self.a & 0x8000 != 0;
+                self.a <<= 1;
+                self.sr.update_zn(self.a);
+                self.cycles += 1;
+            }
+
+            // LSR - Logical shift accumulator right (bit 0 -> carry)
+            0x61 => {
+                self.sr.carry = self.a & 0x0001 != 0;
+                self.a >>= 1;
+                self.sr.update_zn(self.a);
+                self.cycles += 1;
+            }
+
+            // ROL - Rotate accumulator left through carry
+            0x62 => {
+                let carry_in = self.sr.carry as u16;
+                self.sr.carry = self.a & 0x8000 != 0;
+                self.a = (self.a << 1) | carry_in;
+                self.sr.update_zn(self.a);
+                self.cycles += 1;
+            }
+
+            // ROR - Rotate accumulator right through carry
+            0x63 => {
+                let carry_in = (self.sr.carry as u16) << 15;
+                self.sr.carry = self.a & 0x0001 != 0;
+                self.a = (self.a >> 1) | carry_in;
+                self.sr.update_zn(self.a);
+                self.cycles += 1;
+            }
+
+            // PUSH Rn - Push general-purpose register (0x70 + n)
+            0x70..=0x77 => {
+                let value = self.r[(opcode - 0x70) as usize];
+                self.push_u16(bus, value);
+                self.cycles += 2;
+            }
+
+            // POP Rn - Pop general-purpose register (0x78 + n)
+            0x78..=0x7F => {
+                self.r[(opcode - 0x78) as usize] = self.pop_u16(bus);
+                self.cycles += 2;
+            }
+
+            // PUSH A/X/Y and POP A/X/Y (flags unaffected)
+            0x90 => {
+                self.push_u16(bus, self.a);
+                self.cycles += 2;
+            }
+            0x91 => {
+                self.a = self.pop_u16(bus);
+                self.cycles += 2;
+            }
+            0x92 => {
+                self.push_u16(bus, self.x);
+                self.cycles += 2;
+            }
+            0x93 => {
+                self.x = self.pop_u16(bus);
+                self.cycles += 2;
+            }
+            0x94 => {
+                self.push_u16(bus, self.y);
+                self.cycles += 2;
+            }
+            0x95 => {
+                self.y = self.pop_u16(bus);
+                self.cycles += 2;
+            }
+
+            // EXG AX - Exchange A and X (flags unaffected)
+            0x80 => {
+                std::mem::swap(&mut self.a, &mut self.x);
+                self.cycles += 2;
+            }
+
+            // EXG AY - Exchange A and Y (flags unaffected)
+            0x81 => {
+                std::mem::swap(&mut self.a, &mut self.y);
+                self.cycles += 2;
+            }
+
+            // SEC - Set carry
+            0x82 => {
+                self.sr.carry = true;
+                self.cycles += 1;
+            }
+
+            // CLC - Clear carry
+            0x83 => {
+                self.sr.carry = false;
+                self.cycles += 1;
+            }
+
+            // SEV - Set overflow
+            0x84 => {
+                self.sr.overflow = true;
+                self.cycles += 1;
+            }
+
+            // CLV - Clear overflow
+            0x85 => {
+                self.sr.overflow = false;
+                self.cycles += 1;
+            }
+
             // SEI - Set interrupt disable
             0x40 => {
                 self.sr.interrupt_disable = true;
@@ -644,33 +1200,32 @@ impl Cpu {
 
             // RTI - Return from interrupt
             0x42 => {
+                // Restore the status register saved on entry, then the PC
+                let sr = self.pop_u8(bus);
+                self.sr = StatusFlags::from_byte(sr);
                 self.pc = self.pop_u24(bus);
-                self.sr.interrupt_disable = false;
                 self.cycles += 5; // Pop takes cycles, similar to RTS
             }
 
             // WFI - Wait for interrupt
             0x43 => {
                 // Wait for interrupt: CPU idles until an interrupt occurs
-                // We model this by not incrementing PC but allowing interrupt handling
-                // If no interrupts are pending, this effectively halts until one arrives
+                // If nothing is pending, halt until the next request_interrupt/trigger_nmi
                 if self.pending_interrupts.is_empty() {
-                    // No interrupts pending - stay at this instruction
-                    self.pc = self.pc.wrapping_sub(1);
+                    self.halted = true;
+                    self.waiting_for_interrupt = true;
                 }
                 self.cycles += 1;
             }
 
             // COP - Coprocessor instruction (1 byte opcode + 1 byte coprocessor command)
             0x44 => {
-                let _cop_cmd = bus.read_u8(self.pc);
+                let cop_cmd = bus.read_u8(self.pc);
                 self.pc = self.pc.wrapping_add(1);
-                // Coprocessor commands are handled through memory-mapped I/O.
-                // This instruction serves as a synchronization point for coprocessor operations.
-                // Actual coprocessor work is done through direct bus writes to coprocessor
-                // register addresses (VDP: 0x100000, VLU: 0x108000, APU: 0x10C000).
-                // The COP instruction can be extended in the future to trigger specific
-                // coprocessor operations based on the command byte.
+                // The CPU only sees the bus, so the command is latched here and
+                // routed to the selected coprocessor by the system (see Nexel24::step).
+                // Top nibble selects the coprocessor, low nibble is the sub-command.
+                self.cop_request = Some(cop_cmd);
                 self.cycles += 3;
             }
 
@@ -687,6 +1242,55 @@ impl Cpu {
         }
     }
 
+    /// Consume the command latched by the last COP instruction, if any.
+    pub fn take_cop_request(&mut self) -> Option<u8> {
+        self.cop_request.take()
+    }
+
+    /// Set flags as SUB would for `reg - value`, discarding the result
+    fn compare(&mut self, reg: u16, value: u16) {
+        let (result, borrow) = reg.overflowing_sub(value);
+        self.sr.carry = !borrow;
+        self.sr.overflow = ((reg ^ value) & (reg ^ result) & 0x8000) != 0;
+        self.sr.update_zn(result);
+    }
+
+    /// Fetch a 24-bit base address and add an index register, wrapping at 24 bits
+    fn fetch_indexed_address(&mut self, bus: &Bus24, index: u16) -> u32 {
+        let base = bus.read_u24(self.pc);
+        self.pc = self.pc.wrapping_add(3);
+        base.wrapping_add(index as u32) & 0xFFFFFF
+    }
+
+    /// Push a byte to the stack
+    fn push_u8(&mut self, bus: &mut Bus24, value: u8) {
+        bus.write_u8(self.sp as u32, value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    /// Pop a byte from the stack
+    fn pop_u8(&mut self, bus: &Bus24) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        bus.read_u8(self.sp as u32)
+    }
+
+    /// Push a 16-bit value to the stack
+    fn push_u16(&mut self, bus: &mut Bus24, value: u16) {
+        bus.write_u8(self.sp as u32, (value & 0xFF) as u8);
+        self.sp = self.sp.wrapping_sub(1);
+        bus.write_u8(self.sp as u32, (value >> 8) as u8);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    /// Pop a 16-bit value from the stack
+    fn pop_u16(&mut self, bus: &Bus24) -> u16 {
+        self.sp = self.sp.wrapping_add(1);
+        let hi = bus.read_u8(self.sp as u32) as u16;
+        self.sp = self.sp.wrapping_add(1);
+        let lo = bus.read_u8(self.sp as u32) as u16;
+        lo | (hi << 8)
+    }
+
     /// Push a 24-bit value to the stack
     fn push_u24(&mut self, bus: &mut Bus24, value: u32) {
         bus.write_u8(self.sp as u32, (value & 0xFF) as u8);
@@ -709,9 +1313,231 @@ impl Cpu {
     }
 }
 
-impl Default for Cpu {
-    fn default() -> Self {
-        Self::new()
+/// Operand encoding following an opcode byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperandKind {
+    None,
+    Accumulator,
+    StackReg, // PUSH/POP: register index encoded in the opcode
+    Fixed(&'static str),
+    Imm8,
+    Imm16,
+    Abs24,
+    AbsX,
+    AbsY,
+    Indirect,
+    Rel8,
+    Rel16,
+    Reg,
+    RegPair,
+}
+
+impl OperandKind {
+    pub(crate) fn len(self) -> usize {
+        match self {
+            Self::None | Self::Accumulator | Self::StackReg | Self::Fixed(_) => 0,
+            Self::Imm8 | Self::Rel8 | Self::Reg | Self::RegPair => 1,
+            Self::Imm16 | Self::Rel16 => 2,
+            Self::Abs24 | Self::AbsX | Self::AbsY | Self::Indirect => 3,
+        }
+    }
+}
+
+/// Mnemonic and operand encoding for an opcode byte
+pub(crate) fn decode_opcode(opcode: u8) -> Option<(&'static str, OperandKind)> {
+    use OperandKind::*;
+    let decoded = match opcode {
+        0x00 => ("NOP", None),
+        0x01 => ("LDA", Imm16),
+        0x02 => ("STA", Abs24),
+        0x03 => ("LDX", Imm16),
+        0x04 => ("STX", Abs24),
+        0x05 => ("LDY", Imm16),
+        0x06 => ("STY", Abs24),
+        0x07 => ("LDA", Abs24),
+        0x08 => ("LDX", Abs24),
+        0x09 => ("LDY", Abs24),
+        0x0A => ("LDA", AbsX),
+        0x0B => ("LDA", AbsY),
+        0x0C => ("STA", AbsX),
+        0x0D => ("STA", AbsY),
+        0x0E => ("LDX", AbsY),
+        0x0F => ("LDY", AbsX),
+        0x10 => ("ADD", Imm16),
+        0x11 => ("SUB", Imm16),
+        0x12 => ("AND", Imm16),
+        0x13 => ("OR", Imm16),
+        0x14 => ("XOR", Imm16),
+        0x15 => ("MUL", Imm16),
+        0x16 => ("DIV", Imm16),
+        0x17 => ("MOV", RegPair),
+        0x18 => ("INC", Reg),
+        0x19 => ("DEC", Reg),
+        0x1A => ("BIT", Imm16),
+        0x1B => ("BSET", Imm16),
+        0x1C => ("BCLR", Imm16),
+        0x1D => ("STX", AbsY),
+        0x1E => ("STY", AbsX),
+        0x20 => ("JMP", Abs24),
+        0x21 => ("JSR", Abs24),
+        0x22 => ("RTS", None),
+        0x23 => ("JMP", Indirect),
+        0x24 => ("JSR", Indirect),
+        0x25 => ("LCALL", Abs24),
+        0x26 => ("LRET", None),
+        0x30 => ("BRA", Rel8),
+        0x31 => ("BEQ", Rel8),
+        0x32 => ("BNE", Rel8),
+        0x33 => ("BCS", Rel8),
+        0x34 => ("BCC", Rel8),
+        0x35 => ("BMI", Rel8),
+        0x36 => ("BPL", Rel8),
+        0x37 => ("BVS", Rel8),
+        0x38 => ("BVC", Rel8),
+        0x39 => ("LBRA", Rel16),
+        0x3A => ("LBEQ", Rel16),
+        0x3B => ("LBNE", Rel16),
+        0x40 => ("SEI", None),
+        0x41 => ("CLI", None),
+        0x42 => ("RTI", None),
+        0x43 => ("WFI", None),
+        0x44 => ("COP", Imm8),
+        0x50 => ("CMP", Imm16),
+        0x51 => ("CPX", Imm16),
+        0x52 => ("CPY", Imm16),
+        0x60 => ("LSL", Accumulator),
+        0x61 => ("LSR", Accumulator),
+        0x62 => ("ROL", Accumulator),
+        0x63 => ("ROR", Accumulator),
+        0x70..=0x77 => ("PUSH", StackReg),
+        0x78..=0x7F => ("POP", StackReg),
+        0x80 => ("EXG", Fixed("AX")),
+        0x81 => ("EXG", Fixed("AY")),
+        0x82 => ("SEC", None),
+        0x83 => ("CLC", None),
+        0x84 => ("SEV", None),
+        0x85 => ("CLV", None),
+        0x90 => ("PUSH", Fixed("A")),
+        0x91 => ("POP", Fixed("A")),
+        0x92 => ("PUSH", Fixed("X")),
+        0x93 => ("POP", Fixed("X")),
+        0x94 => ("PUSH", Fixed("Y")),
+        0x95 => ("POP", Fixed("Y")),
+        0xFF => ("HLT", None),
+        _ => return Option::None,
+    };
+    Some(decoded)
+}
+
+/// Mnemonic for an opcode byte ("???" for unassigned opcodes)
+fn opcode_mnemonic(opcode: u8) -> &'static str {
+    decode_opcode(opcode).map_or("???", |(mnemonic, _)| mnemonic)
+}
+
+/// Assembler name for a register spec nibble (0=A, 1=X, 2=Y, 3=SP, 4-11=R0-R7)
+pub(crate) fn register_name(spec: u8) -> String {
+    match spec {
+        0 => "A".to_string(),
+        1 => "X".to_string(),
+        2 => "Y".to_string(),
+        3 => "SP".to_string(),
+        4..=11 => format!("R{}", spec - 4),
+        _ => format!("?{}", spec),
+    }
+}
+
+/// One decoded instruction produced by [`disassemble`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisasmEntry {
+    pub addr: u32,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+/// Decode a byte stream into instructions, starting at `base_addr`.
+///
+/// Each entry's text is `"AAAAAA  MNEMONIC operand"`. Unassigned opcodes are
+/// shown as `?? [0xNN]`, and an instruction cut short by the end of the
+/// stream is returned with whatever bytes remain.
+pub fn disassemble(bytes: &[u8], base_addr: u32) -> Vec<DisasmEntry> {
+    let mut entries = Vec::new();
+    let mut cursor = 0;
+
+    while cursor < bytes.len() {
+        let addr = base_addr.wrapping_add(cursor as u32) & 0xFFFFFF;
+        let opcode = bytes[cursor];
+
+        let Some((mnemonic, kind)) = decode_opcode(opcode) else {
+            entries.push(DisasmEntry {
+                addr,
+                bytes: vec![opcode],
+                text: format!("{:06X}  ?? [0x{:02X}]", addr, opcode),
+            });
+            cursor += 1;
+            continue;
+        };
+
+        let end = cursor + 1 + kind.len();
+        if end > bytes.len() {
+            entries.push(DisasmEntry {
+                addr,
+                bytes: bytes[cursor..].to_vec(),
+                text: format!("{:06X}  {} <truncated>", addr, mnemonic),
+            });
+            break;
+        }
+
+        let operand = &bytes[cursor + 1..end];
+        let u16_operand = || u16::from_le_bytes([operand[0], operand[1]]);
+        let u24_operand = || u32::from_le_bytes([operand[0], operand[1], operand[2], 0]);
+        let next_pc = addr.wrapping_add(1 + kind.len() as u32);
+
+        let operand_text = match kind {
+            OperandKind::None => String::new(),
+            OperandKind::Accumulator => "A".to_string(),
+            OperandKind::StackReg => format!("R{}", opcode & 0x07),
+            OperandKind::Fixed(text) => text.to_string(),
+            OperandKind::Imm8 => format!("#${:02X}", operand[0]),
+            OperandKind::Imm16 => format!("#${:04X}", u16_operand()),
+            OperandKind::Abs24 => format!("${:06X}", u24_operand()),
+            OperandKind::AbsX => format!("${:06X},X", u24_operand()),
+            OperandKind::AbsY => format!("${:06X},Y", u24_operand()),
+            OperandKind::Indirect => format!("[${:06X}]", u24_operand()),
+            OperandKind::Rel8 => {
+                let target = next_pc.wrapping_add(operand[0] as i8 as u32) & 0xFFFFFF;
+                format!("${:06X}", target)
+            }
+            OperandKind::Rel16 => {
+                let target = next_pc.wrapping_add(u16_operand() as i16 as u32) & 0xFFFFFF;
+                format!("${:06X}", target)
+            }
+            OperandKind::Reg => register_name(operand[0] & 0x0F),
+            OperandKind::RegPair => format!(
+                "{}, {}",
+                register_name(operand[0] >> 4),
+                register_name(operand[0] & 0x0F)
+            ),
+        };
+
+        let text = if operand_text.is_empty() {
+            format!("{:06X}  {}", addr, mnemonic)
+        } else {
+            format!("{:06X}  {} {}", addr, mnemonic, operand_text)
+        };
+        entries.push(DisasmEntry {
+            addr,
+            bytes: bytes[cursor..end].to_vec(),
+            text,
+        });
+        cursor = end;
+    }
+
+    entries
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -977,12 +1803,12 @@ mod tests {
         let mut bus = Bus24::new();
 
         // Set up BIOS with interrupt vector table
-        // Interrupt 4 (VLU_DONE) vector at offset 0x0C (0xFF0000 + 4*3)
+        // Interrupt 4 (VLU_DONE) vector at offset 0x0F (0xFF0000 + (4+1)*3)
         let mut bios = vec![0; 0x100]; // Small BIOS with vectors
         // Set vector for interrupt 4 to point to 0x200000
-        bios[0x0C] = 0x00; // Low byte
-        bios[0x0D] = 0x00; // Mid byte
-        bios[0x0E] = 0x20; // High byte (0x200000)
+        bios[0x0F] = 0x00; // Low byte
+        bios[0x10] = 0x00; // Mid byte
+        bios[0x11] = 0x20; // High byte (0x200000)
         // Put a NOP at the start
         bios[0] = 0x00;
         bus.load_bios(&bios);
@@ -1004,12 +1830,14 @@ mod tests {
         // Check that PC jumped to handler
         assert_eq!(cpu.pc, 0x200000);
 
-        // Check that old PC was pushed to stack (SP decreased by 3)
-        assert_eq!(cpu.sp, old_sp.wrapping_sub(3));
+        // Check that old PC and SR were pushed to stack (SP decreased by 3 + 1)
+        assert_eq!(cpu.sp, old_sp.wrapping_sub(4));
 
-        // Verify the pushed value by popping it back
+        // Verify the pushed values by popping them back
         let mut test_cpu = Cpu::new();
         test_cpu.sp = cpu.sp;
+        let popped_sr = test_cpu.pop_u8(&bus);
+        assert_eq!(popped_sr, 0x00);
         let popped_pc = test_cpu.pop_u24(&bus);
         assert_eq!(popped_pc, old_pc);
 
@@ -1093,14 +1921,14 @@ mod tests {
 
         // Set up BIOS with interrupt vectors
         let mut bios = vec![0; 0x100];
-        // INT 4 vector at offset 0x0C -> 0x200000
-        bios[0x0C] = 0x00;
-        bios[0x0D] = 0x00;
-        bios[0x0E] = 0x20;
-        // INT 5 vector at offset 0x0F -> 0x201000
+        // INT 4 vector at offset 0x0F -> 0x200000
         bios[0x0F] = 0x00;
-        bios[0x10] = 0x10;
+        bios[0x10] = 0x00;
         bios[0x11] = 0x20;
+        // INT 5 vector at offset 0x12 -> 0x201000
+        bios[0x12] = 0x00;
+        bios[0x13] = 0x10;
+        bios[0x14] = 0x20;
         // NOP at start
         bios[0] = 0x00;
         bus.load_bios(&bios);
@@ -1133,10 +1961,10 @@ mod tests {
 
         // Set up BIOS with interrupt vector
         let mut bios = vec![0; 0x100];
-        // INT 4 vector at offset 0x0C -> 0x200000
-        bios[0x0C] = 0x00;
-        bios[0x0D] = 0x00;
-        bios[0x0E] = 0x20;
+        // INT 4 vector at offset 0x0F -> 0x200000
+        bios[0x0F] = 0x00;
+        bios[0x10] = 0x00;
+        bios[0x11] = 0x20;
         // NOP at start
         bios[0] = 0x00;
         bus.load_bios(&bios);
@@ -1227,10 +2055,10 @@ mod tests {
 
         // Set up BIOS with NMI vector
         let mut bios = vec![0; 0x100];
-        // NMI (interrupt 7) vector at offset 0x15 (0xFF0000 + 7*3) -> 0x200000
-        bios[0x15] = 0x00;
-        bios[0x16] = 0x00;
-        bios[0x17] = 0x20;
+        // NMI (interrupt 7) vector at offset 0x18 (0xFF0000 + (7+1)*3) -> 0x200000
+        bios[0x18] = 0x00;
+        bios[0x19] = 0x00;
+        bios[0x1A] = 0x20;
         // NOP at start
         bios[0] = 0x00;
         bus.load_bios(&bios);
@@ -1250,9 +2078,11 @@ mod tests {
         // PC should have jumped to NMI handler
         assert_eq!(cpu.pc, 0x200000);
 
-        // Verify that PC was pushed to stack
+        // Verify that SR (with I still set) and PC were pushed to stack
         let mut test_cpu = Cpu::new();
         test_cpu.sp = cpu.sp;
+        let popped_sr = test_cpu.pop_u8(&bus);
+        assert_eq!(popped_sr, 0x04);
         let popped_pc = test_cpu.pop_u24(&bus);
         assert_eq!(popped_pc, old_pc);
 
@@ -1274,4 +2104,1283 @@ mod tests {
         assert_eq!(cpu.pending_interrupts.len(), 1);
         assert_eq!(cpu.pending_interrupts[0], 4);
     }
+
+    #[test]
+    fn cpu_load_absolute() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.write_u16(0x001000, 0x8001);
+        bus.write_u16(0x001002, 0x0000);
+        bus.write_u16(0x001004, 0x1234);
+
+        let program = vec![
+            0x07, 0x00, 0x10, 0x00, // LDA $001000
+            0x08, 0x02, 0x10, 0x00, // LDX $001002
+            0x09, 0x04, 0x10, 0x00, // LDY $001004
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x8001);
+        assert!(cpu.sr.negative);
+        assert_eq!(cpu.pc, 0xFF0004);
+        assert_eq!(cpu.cycles, 4);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.x, 0x0000);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.pc, 0xFF0008);
+        assert_eq!(cpu.cycles, 8);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.y, 0x1234);
+        assert!(!cpu.sr.zero && !cpu.sr.negative);
+        assert_eq!(cpu.pc, 0xFF000C);
+        assert_eq!(cpu.cycles, 12);
+    }
+
+    #[test]
+    fn cpu_mul_immediate() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 0x1234;
+
+        // MUL #0x0100
+        bus.load_bios(&[0x15, 0x00, 0x01]);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.a, 0x3400); // Low word
+        assert_eq!(cpu.x, 0x0012); // High word
+        assert!(cpu.sr.carry); // Result exceeds 16 bits
+        assert_eq!(cpu.pc, 0xFF0003);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn cpu_mul_high_word_and_carry() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x15, 0xFF, 0xFF, // MUL #0xFFFF
+            0x15, 0x02, 0x00, // MUL #2
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0xFFFF;
+
+        // 0xFFFF * 0xFFFF = 0xFFFE_0001
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0001);
+        assert_eq!(cpu.x, 0xFFFE);
+        assert!(cpu.sr.carry);
+
+        // 1 * 2 fits in 16 bits
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0002);
+        assert_eq!(cpu.x, 0x0000);
+        assert!(!cpu.sr.carry);
+        assert_eq!(cpu.cycles, 8);
+    }
+
+    #[test]
+    fn cpu_mul_by_zero() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 0x1234;
+        cpu.x = 0x5555;
+        cpu.sr.carry = true;
+
+        // MUL #0
+        bus.load_bios(&[0x15, 0x00, 0x00]);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.x, 0);
+        assert!(cpu.sr.zero);
+        assert!(!cpu.sr.carry);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn cpu_div_immediate() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 100;
+
+        // DIV #7
+        bus.load_bios(&[0x16, 0x07, 0x00]);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.a, 14); // Quotient
+        assert_eq!(cpu.x, 2); // Remainder
+        assert!(!cpu.sr.overflow);
+        assert_eq!(cpu.pc, 0xFF0003);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn cpu_div_by_zero() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 0x4321;
+        cpu.x = 0x0055;
+
+        // DIV #0
+        bus.load_bios(&[0x16, 0x00, 0x00]);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.a, 0x4321); // Unchanged
+        assert_eq!(cpu.x, 0x0055); // Unchanged
+        assert!(cpu.sr.overflow);
+        assert_eq!(cpu.pc, 0xFF0003);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn cpu_mov_registers() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 0xBEEF;
+
+        let program = vec![
+            0x17, 0x04, // MOV A -> R0
+            0x17, 0x41, // MOV R0 -> X
+            0x17, 0x60, // MOV R2 -> A (R2 is zero)
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.r[0], 0xBEEF);
+        assert_eq!(cpu.pc, 0xFF0002);
+        assert_eq!(cpu.cycles, 2);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.x, 0xBEEF);
+        assert!(cpu.sr.negative);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn cpu_inc_dec_registers() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 0xFFFF;
+        cpu.r[3] = 0x0001;
+
+        let program = vec![
+            0x18, 0x00, // INC A
+            0x19, 0x07, // DEC R3
+            0x19, 0x02, // DEC Y
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0000);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.pc, 0xFF0002);
+        assert_eq!(cpu.cycles, 2);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.r[3], 0x0000);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.y, 0xFFFF);
+        assert!(cpu.sr.negative);
+        assert!(!cpu.sr.zero);
+        assert_eq!(cpu.pc, 0xFF0006);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn cpu_bit_bset_bclr() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        cpu.a = 0x00F0;
+
+        let program = vec![
+            0x1A, 0x0F, 0x00, // BIT #0x000F
+            0x1B, 0x00, 0x80, // BSET #0x8000
+            0x1C, 0xF0, 0x00, // BCLR #0x00F0
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x00F0); // BIT leaves A untouched
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.pc, 0xFF0003);
+        assert_eq!(cpu.cycles, 2);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x80F0);
+        assert!(cpu.sr.negative);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x8000);
+        assert_eq!(cpu.pc, 0xFF0009);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn cpu_conditional_branches() {
+        type FlagSetter = fn(&mut StatusFlags, bool);
+
+        // (opcode, flag setter, condition value that takes the branch)
+        let cases: [(u8, FlagSetter, bool); 6] = [
+            (0x33, |sr, v| sr.carry = v, true),     // BCS
+            (0x34, |sr, v| sr.carry = v, false),    // BCC
+            (0x35, |sr, v| sr.negative = v, true),  // BMI
+            (0x36, |sr, v| sr.negative = v, false), // BPL
+            (0x37, |sr, v| sr.overflow = v, true),  // BVS
+            (0x38, |sr, v| sr.overflow = v, false), // BVC
+        ];
+
+        for (opcode, set_flag, taken_when) in cases {
+            // Branch taken
+            let mut cpu = Cpu::new();
+            let mut bus = Bus24::new();
+            bus.load_bios(&[opcode, 0xFC]); // -4
+            cpu.pc = 0xFF0000;
+            set_flag(&mut cpu.sr, taken_when);
+            cpu.step(&mut bus);
+            assert_eq!(cpu.pc, 0xFF0002 - 4, "opcode {:02X} taken", opcode);
+            assert_eq!(cpu.cycles, 3);
+
+            // Branch not taken
+            let mut cpu = Cpu::new();
+            cpu.pc = 0xFF0000;
+            set_flag(&mut cpu.sr, !taken_when);
+            cpu.step(&mut bus);
+            assert_eq!(cpu.pc, 0xFF0002, "opcode {:02X} not taken", opcode);
+            assert_eq!(cpu.cycles, 2);
+        }
+    }
+
+    #[test]
+    fn cpu_wfi_waits_for_interrupt() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let mut bios = vec![0; 0x100];
+        bios[0] = 0x43; // WFI
+        // INT 4 vector at offset 0x0F -> 0x200000
+        bios[0x0F] = 0x00;
+        bios[0x10] = 0x00;
+        bios[0x11] = 0x20;
+        bus.load_bios(&bios);
+
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert!(cpu.halted);
+        assert_eq!(cpu.pc, 0xFF0001);
+        assert_eq!(cpu.cycles, 1);
+
+        // Stays parked until an interrupt is requested
+        cpu.step(&mut bus);
+        assert!(cpu.halted);
+        assert_eq!(cpu.pc, 0xFF0001);
+
+        cpu.request_interrupt(4);
+        assert!(!cpu.halted);
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0x200000);
+    }
+
+    #[test]
+    fn cpu_wfi_masked_interrupt_does_not_wake() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.load_bios(&[0x43]); // WFI
+        cpu.pc = 0xFF0000;
+        cpu.sr.interrupt_disable = true;
+        cpu.step(&mut bus);
+
+        cpu.request_interrupt(4);
+        assert!(cpu.halted);
+
+        // NMI always wakes the CPU
+        cpu.trigger_nmi();
+        assert!(!cpu.halted);
+    }
+
+    #[test]
+    fn cpu_hlt_not_woken_by_interrupt() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.load_bios(&[0xFF]); // HLT
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+
+        cpu.request_interrupt(4);
+        assert!(cpu.halted);
+    }
+
+    #[test]
+    fn cpu_lda_indexed() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.write_u16(0x001010, 0xCAFE);
+        bus.write_u16(0x001020, 0x8000);
+
+        let program = vec![
+            0x0A, 0x00, 0x10, 0x00, // LDA $001000,X
+            0x0B, 0x00, 0x10, 0x00, // LDA $001000,Y
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.x = 0x0010;
+        cpu.y = 0x0020;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0xCAFE);
+        assert_eq!(cpu.pc, 0xFF0004);
+        assert_eq!(cpu.cycles, 5);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x8000);
+        assert!(cpu.sr.negative);
+        assert_eq!(cpu.pc, 0xFF0008);
+        assert_eq!(cpu.cycles, 10);
+    }
+
+    #[test]
+    fn cpu_store_indexed() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x0C, 0x00, 0x20, 0x00, // STA $002000,X
+            0x0D, 0x00, 0x20, 0x00, // STA $002000,Y
+            0x1D, 0x00, 0x30, 0x00, // STX $003000,Y
+            0x1E, 0x00, 0x30, 0x00, // STY $003000,X
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x1234;
+        cpu.x = 0x0002;
+        cpu.y = 0x0004;
+
+        cpu.step(&mut bus);
+        assert_eq!(bus.read_u16(0x002002), 0x1234);
+        assert_eq!(cpu.cycles, 4);
+
+        cpu.step(&mut bus);
+        assert_eq!(bus.read_u16(0x002004), 0x1234);
+
+        cpu.step(&mut bus);
+        assert_eq!(bus.read_u16(0x003004), 0x0002);
+
+        cpu.step(&mut bus);
+        assert_eq!(bus.read_u16(0x003002), 0x0004);
+        assert_eq!(cpu.pc, 0xFF0010);
+        assert_eq!(cpu.cycles, 16);
+    }
+
+    #[test]
+    fn cpu_ldx_ldy_indexed() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.write_u16(0x001004, 0x0042);
+        bus.write_u16(0x001008, 0x0000);
+
+        let program = vec![
+            0x0E, 0x00, 0x10, 0x00, // LDX $001000,Y
+            0x0F, 0x00, 0x10, 0x00, // LDY $001000,X
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.y = 0x0004;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.x, 0x0042);
+        assert_eq!(cpu.cycles, 5);
+
+        cpu.x = 0x0008;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.y, 0x0000);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.cycles, 10);
+    }
+
+    #[test]
+    fn cpu_indexed_address_wraps_at_24_bits() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.write_u16(0x000010, 0x5A5A);
+
+        let program = vec![
+            0x0A, 0xF0, 0xFF, 0xFF, // LDA $FFFFF0,X
+            0x0D, 0xFF, 0xFF, 0xFF, // STA $FFFFFF,Y
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.x = 0x0020;
+        cpu.y = 0x0041;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x5A5A);
+
+        cpu.step(&mut bus);
+        assert_eq!(bus.read_u16(0x000040), 0x5A5A);
+    }
+
+    #[test]
+    fn cpu_jmp_indirect() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        // JMP [$001000]
+        bus.load_bios(&[0x23, 0x00, 0x10, 0x00]);
+        bus.write_u24(0x001000, 0x002000);
+
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0x002000);
+        assert_eq!(cpu.cycles, 5);
+
+        // Retarget the pointer without touching the instruction bytes
+        bus.write_u24(0x001000, 0x003456);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0x003456);
+    }
+
+    #[test]
+    fn cpu_jsr_indirect_rts() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        // JSR [$001000]
+        bus.load_bios(&[0x24, 0x00, 0x10, 0x00]);
+        bus.write_u24(0x001000, 0x002000);
+        bus.write_u8(0x002000, 0x22); // RTS
+
+        cpu.pc = 0xFF0000;
+        let initial_sp = cpu.sp;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0x002000);
+        assert_eq!(cpu.sp, initial_sp.wrapping_sub(3));
+        assert_eq!(cpu.cycles, 7);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0xFF0004);
+        assert_eq!(cpu.sp, initial_sp);
+
+        bus.write_u24(0x001000, 0x002100);
+        bus.write_u8(0x002100, 0x22); // RTS
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0x002100);
+    }
+
+    #[test]
+    fn cpu_jmp_indirect_pointer_wraps_at_24_bits() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        // Pointer straddles the top of the address space: $FFFFFF, $000000, $000001
+        bus.load_bios(&[0x23, 0xFF, 0xFF, 0xFF]);
+        bus.write_u8(0x000000, 0x34);
+        bus.write_u8(0x000001, 0x12);
+
+        // Low byte of the pointer lives in BIOS ROM at $FFFFFF (zero)
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0x123400);
+    }
+
+    #[test]
+    fn cpu_cmp_immediate() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x01, 0x05, 0x00, // LDA #5
+            0x50, 0x05, 0x00, // CMP #5
+            0x50, 0x06, 0x00, // CMP #6
+            0x50, 0x04, 0x00, // CMP #4
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 5);
+        assert!(cpu.sr.zero);
+        assert!(cpu.sr.carry);
+        assert!(!cpu.sr.negative);
+        assert_eq!(cpu.cycles, 4);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 5);
+        assert!(!cpu.sr.zero);
+        assert!(cpu.sr.negative);
+        assert!(!cpu.sr.carry);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 5);
+        assert!(!cpu.sr.zero && !cpu.sr.negative);
+        assert!(cpu.sr.carry);
+    }
+
+    #[test]
+    fn cpu_cpx_cpy_immediate() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x51, 0x10, 0x00, // CPX #0x10
+            0x52, 0x01, 0x00, // CPY #1
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.x = 0x0010;
+        cpu.y = 0x8000;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.x, 0x0010);
+        assert!(cpu.sr.zero);
+
+        // 0x8000 - 1 overflows from negative to positive
+        cpu.step(&mut bus);
+        assert_eq!(cpu.y, 0x8000);
+        assert!(cpu.sr.overflow);
+        assert!(cpu.sr.carry);
+        assert_eq!(cpu.pc, 0xFF0006);
+    }
+
+    #[test]
+    fn cpu_lsl_lsr() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x60, // LSL A
+            0x61, // LSR A
+            0x61, // LSR A
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x8000;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0000);
+        assert!(cpu.sr.carry);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.cycles, 1);
+
+        cpu.a = 0x0003;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0001);
+        assert!(cpu.sr.carry);
+        assert!(!cpu.sr.zero);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0000);
+        assert!(cpu.sr.carry);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.pc, 0xFF0003);
+        assert_eq!(cpu.cycles, 3);
+    }
+
+    #[test]
+    fn cpu_rol_ror_carry_propagation() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x62, // ROL A
+            0x62, // ROL A
+            0x63, // ROR A
+            0x63, // ROR A
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x8001;
+        cpu.sr.carry = false;
+
+        // Bit 15 moves into carry, carry (0) enters bit 0
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0002);
+        assert!(cpu.sr.carry);
+
+        // Previous carry re-enters at bit 0
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0005);
+        assert!(!cpu.sr.carry);
+
+        // Rotating back right restores the original value
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0002);
+        assert!(cpu.sr.carry);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x8001);
+        assert!(!cpu.sr.carry);
+        assert!(cpu.sr.negative);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn cpu_push_pop_registers() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let mut program: Vec<u8> = (0x70..=0x77).collect(); // PUSH R0..R7
+        program.extend((0x78..=0x7F).rev()); // POP R7..R0
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        let original = [
+            0x1111, 0x2222, 0x3333, 0x4444, 0x5555, 0x6666, 0x7777, 0x8888,
+        ];
+        cpu.r = original;
+        let initial_sp = cpu.sp;
+
+        for _ in 0..8 {
+            cpu.step(&mut bus);
+        }
+        assert_eq!(cpu.sp, initial_sp.wrapping_sub(16));
+        assert_eq!(cpu.cycles, 16);
+
+        cpu.r = [0; 8];
+
+        for _ in 0..8 {
+            cpu.step(&mut bus);
+        }
+        assert_eq!(cpu.r, original);
+        assert_eq!(cpu.sp, initial_sp);
+        assert_eq!(cpu.cycles, 32);
+    }
+
+    #[test]
+    fn cpu_cop_latches_command() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        // COP #0x21
+        bus.load_bios(&[0x44, 0x21]);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+
+        assert_eq!(cpu.pc, 0xFF0002);
+        assert_eq!(cpu.cycles, 3);
+        assert_eq!(cpu.take_cop_request(), Some(0x21));
+        assert_eq!(cpu.take_cop_request(), None);
+    }
+
+    #[test]
+    fn cpu_long_branches() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x39, 0x00, 0x10, // LBRA +0x1000
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0xFF1003);
+        assert_eq!(cpu.cycles, 3);
+
+        // LBEQ -0x1003 (back to start), taken
+        bus.load_bios(&[0x3A, 0xFD, 0xEF]);
+        cpu.pc = 0xFF0000;
+        cpu.cycles = 0;
+        cpu.sr.zero = true;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0xFF0003u32.wrapping_sub(0x1003));
+        assert_eq!(cpu.cycles, 4);
+
+        // LBNE with zero set is not taken
+        bus.load_bios(&[0x3B, 0x00, 0x80]);
+        cpu.pc = 0xFF0000;
+        cpu.cycles = 0;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0xFF0003);
+        assert_eq!(cpu.cycles, 3);
+
+        // LBNE with zero clear branches back by 0x8000
+        cpu.pc = 0xFF0000;
+        cpu.sr.zero = false;
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0xFF0003 - 0x8000);
+    }
+
+    #[test]
+    fn cpu_breakpoint_stops_before_instruction() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x00, // NOP
+            0x01, 0x34, 0x12, // LDA #0x1234
+            0xFF, // HLT
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.add_breakpoint(0xFF0001);
+
+        cpu.step(&mut bus);
+        assert!(!cpu.breakpoint_hit);
+
+        // Breakpoint triggers without executing LDA
+        cpu.step(&mut bus);
+        assert!(cpu.breakpoint_hit);
+        assert_eq!(cpu.pc, 0xFF0001);
+        assert_eq!(cpu.a, 0);
+
+        // Stepping again resumes past the breakpoint
+        cpu.step(&mut bus);
+        assert!(!cpu.breakpoint_hit);
+        assert_eq!(cpu.a, 0x1234);
+
+        cpu.remove_breakpoint(0xFF0001);
+        cpu.pc = 0xFF0001;
+        cpu.step(&mut bus);
+        assert!(!cpu.breakpoint_hit);
+    }
+
+    #[test]
+    fn cpu_watchpoint_fires_callback() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x07, 0x00, 0x10, 0x00, // LDA $001000
+            0x02, 0x00, 0x10, 0x00, // STA $001000
+            0x02, 0x00, 0x20, 0x00, // STA $002000
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.add_watchpoint(0x001000, WatchMode::Write);
+
+        let hits = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&hits);
+        cpu.breakpoint_callback = Some(Box::new(move |addr| sink.borrow_mut().push(addr)));
+
+        // Reads do not trigger a write watchpoint
+        cpu.step(&mut bus);
+        assert!(!cpu.breakpoint_hit);
+
+        cpu.step(&mut bus);
+        assert!(cpu.breakpoint_hit);
+        assert_eq!(*hits.borrow(), vec![0x001000]);
+
+        cpu.step(&mut bus);
+        assert!(!cpu.breakpoint_hit);
+
+        cpu.remove_watchpoint(0x001000);
+        cpu.add_watchpoint(0x002001, WatchMode::ReadWrite);
+        cpu.pc = 0xFF0008;
+        cpu.step(&mut bus);
+        assert!(cpu.breakpoint_hit);
+        assert_eq!(*hits.borrow(), vec![0x001000, 0x002001]);
+    }
+
+    #[test]
+    fn cpu_trace_disabled_by_default() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        bus.load_bios(&[0x00]);
+        cpu.pc = 0xFF0000;
+        cpu.step(&mut bus);
+        assert!(cpu.trace_log().is_empty());
+        assert!(cpu.disassemble_trace().is_empty());
+    }
+
+    #[test]
+    fn cpu_trace_records_last_entries() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x01, 0x34, 0x12, // LDA #0x1234
+            0x00, // NOP
+            0x00, // NOP
+            0x00, // NOP
+            0xFF, // HLT
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.enable_tracing(2);
+
+        for _ in 0..5 {
+            cpu.step(&mut bus);
+        }
+
+        let log = cpu.trace_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(
+            log[0],
+            TraceEntry {
+                pc: 0xFF0005,
+                opcode: 0x00,
+                a: 0x1234,
+                x: 0,
+                y: 0,
+                sr: 0,
+                cycles: 4,
+            }
+        );
+        assert_eq!(log[1].pc, 0xFF0006);
+        assert_eq!(log[1].opcode, 0xFF);
+
+        let text = cpu.disassemble_trace();
+        assert_eq!(
+            text[1],
+            "FF0006  FF  HLT   A=1234 X=0000 Y=0000 SR=00 CYC=5"
+        );
+
+        cpu.disable_tracing();
+        assert!(cpu.trace_log().is_empty());
+    }
+
+    #[test]
+    fn disassemble_demo_program() {
+        // Demo program from main.rs, starting after the reset vector
+        let program = [
+            0x01, 0x34, 0x12, // LDA #0x1234
+            0x02, 0x00, 0x10, 0x00, // STA $001000
+            0x03, 0x78, 0x56, // LDX #0x5678
+            0x04, 0x02, 0x10, 0x00, // STX $001002
+            0xFF, // HLT
+        ];
+        let entries = disassemble(&program, 0xFF0003);
+        let text: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "FF0003  LDA #$1234",
+                "FF0006  STA $001000",
+                "FF000A  LDX #$5678",
+                "FF000D  STX $001002",
+                "FF0011  HLT",
+            ]
+        );
+        assert_eq!(entries[1].addr, 0xFF0006);
+        assert_eq!(entries[1].bytes, vec![0x02, 0x00, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn disassemble_operand_forms() {
+        let program = [
+            0x20, 0x00, 0x00, 0x40, // JMP $400000
+            0x30, 0xFC, // BRA -4
+            0x0A, 0x00, 0x10, 0x00, // LDA $001000,X
+            0x23, 0x00, 0x20, 0x00, // JMP [$002000]
+            0x17, 0x04, // MOV A, R0
+            0x72, // PUSH R2
+            0x60, // LSL A
+            0x44, 0x21, // COP #$21
+            0xEE, // unknown
+            0x01, 0x34, // truncated LDA
+        ];
+        let text: Vec<String> = disassemble(&program, 0x000000)
+            .into_iter()
+            .map(|e| e.text)
+            .collect();
+        assert_eq!(
+            text,
+            vec![
+                "000000  JMP $400000",
+                "000004  BRA $000002",
+                "000006  LDA $001000,X",
+                "00000A  JMP [$002000]",
+                "00000E  MOV A, R0",
+                "000010  PUSH R2",
+                "000011  LSL A",
+                "000012  COP #$21",
+                "000014  ?? [0xEE]",
+                "000015  LDA <truncated>",
+            ]
+        );
+    }
+
+    fn cpu_state() -> impl proptest::strategy::Strategy<Value = CpuState> {
+        use proptest::prelude::*;
+        let registers = (
+            any::<[u16; 4]>(),
+            0..0x100_0000u32,
+            any::<u8>(),
+            any::<[u16; 8]>(),
+        );
+        let control = (
+            any::<u64>(),
+            any::<bool>(),
+            any::<bool>(),
+            proptest::collection::vec(0..8u8, 0..4),
+            proptest::option::of(any::<u8>()),
+        );
+        (registers, control).prop_map(
+            |(([a, x, y, sp], pc, sr, r), (cycles, halted, wfi, pending, cop))| CpuState {
+                a,
+                x,
+                y,
+                sp,
+                pc,
+                sr: StatusFlags::from_byte(sr),
+                r,
+                cycles,
+                halted,
+                waiting_for_interrupt: wfi,
+                pending_interrupts: pending,
+                cop_request: cop,
+            },
+        )
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn cpu_state_round_trip_random(state in cpu_state()) {
+            let mut cpu = Cpu::new();
+            cpu.load(&state);
+            proptest::prop_assert_eq!(cpu.save(), state.clone());
+
+            // A CPU restored from the snapshot runs exactly like the original
+            let mut restored = Cpu::new();
+            restored.load(&cpu.save());
+            let program = [
+                0x01, 0x01, 0x00, // LDA #1
+                0x62, // ROL A
+                0x10, 0x03, 0x00, // ADD #3
+                0x70, // PUSH R0
+                0x18, 0x04, // INC R0
+                0x30, 0xF7, // BRA back to ROL
+            ];
+            let (mut bus, mut restored_bus) = (Bus24::new(), Bus24::new());
+            bus.load_bios(&program);
+            restored_bus.load_bios(&program);
+            for _ in 0..16 {
+                cpu.step(&mut bus);
+                restored.step(&mut restored_bus);
+            }
+            proptest::prop_assert_eq!(cpu.save(), restored.save());
+        }
+    }
+
+    #[test]
+    fn cpu_state_restore_replays_identically() {
+        let mut bus = Bus24::new();
+        let program = vec![
+            0x01, 0x01, 0x00, // LDA #1
+            0x62, // ROL A
+            0x10, 0x03, 0x00, // ADD #3
+            0x70, // PUSH R0
+            0x18, 0x04, // INC R0
+            0x30, 0xF7, // BRA back to ROL
+        ];
+        bus.load_bios(&program);
+
+        let mut cpu = Cpu::new();
+        cpu.pc = 0xFF0000;
+        for _ in 0..7 {
+            cpu.step(&mut bus);
+        }
+        let snapshot = cpu.save();
+
+        // The program never reads back what it pushes, so a fresh bus suffices
+        let mut restored = Cpu::new();
+        restored.load(&snapshot);
+        let mut restored_bus = Bus24::new();
+        restored_bus.load_bios(&program);
+
+        for _ in 0..50 {
+            cpu.step(&mut bus);
+            restored.step(&mut restored_bus);
+        }
+        assert_eq!(cpu.save(), restored.save());
+        assert_ne!(cpu.save(), snapshot);
+    }
+
+    #[test]
+    fn cpu_bit_preserves_accumulator() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = crate::nraw::assemble("LDA #0xFF00\nBIT #0x00FF\nBCLR #0xFF00")
+            .expect("assemble")
+            .bytes;
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        cpu.step(&mut bus);
+        assert!(cpu.sr.zero);
+        assert!(!cpu.sr.negative);
+        assert_eq!(cpu.a, 0xFF00);
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0000);
+        assert!(cpu.sr.zero);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn cpu_exg_swaps_without_touching_flags() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x80, // EXG AX
+            0x81, // EXG AY
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x1234;
+        cpu.x = 0x5678;
+        cpu.y = 0x0000;
+        cpu.sr = StatusFlags::from_byte(0xC3);
+        let flags = cpu.sr;
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x5678);
+        assert_eq!(cpu.x, 0x1234);
+        assert_eq!(cpu.sr, flags);
+        assert_eq!(cpu.cycles, 2);
+
+        // Swapping a zero into A still leaves the flags alone
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0000);
+        assert_eq!(cpu.y, 0x5678);
+        assert_eq!(cpu.sr, flags);
+        assert_eq!(cpu.pc, 0xFF0002);
+    }
+
+    #[test]
+    fn cpu_set_clear_carry_overflow() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x82, // SEC
+            0x84, // SEV
+            0x83, // CLC
+            0x85, // CLV
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+
+        cpu.step(&mut bus);
+        assert!(cpu.sr.carry);
+        cpu.step(&mut bus);
+        assert!(cpu.sr.overflow);
+        cpu.step(&mut bus);
+        assert!(!cpu.sr.carry);
+        assert!(cpu.sr.overflow);
+        cpu.step(&mut bus);
+        assert!(!cpu.sr.overflow);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn cpu_clc_add_ignores_previous_carry() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x82, // SEC
+            0x83, // CLC
+            0x10, 0xFF, 0xFF, // ADD #0xFFFF
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x0001;
+
+        cpu.step(&mut bus);
+        cpu.step(&mut bus);
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x0000);
+        assert!(cpu.sr.carry);
+        assert!(cpu.sr.zero);
+    }
+
+    #[test]
+    fn cpu_sec_rol_rotates_carry_in() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x82, // SEC
+            0x62, // ROL A
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x4000;
+
+        cpu.step(&mut bus);
+        cpu.step(&mut bus);
+        assert_eq!(cpu.a, 0x8001);
+        assert!(!cpu.sr.carry);
+        assert!(cpu.sr.negative);
+    }
+
+    #[test]
+    fn cpu_rti_restores_status_register() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let mut bios = vec![0; 0x100];
+        // INT 4 vector -> 0x002000
+        bios[0x0F] = 0x00;
+        bios[0x10] = 0x20;
+        bios[0x11] = 0x00;
+        bus.load_bios(&bios);
+
+        // Handler clobbers every flag before returning
+        let handler = [
+            0x83, // CLC
+            0x85, // CLV
+            0x01, 0x00, 0x00, // LDA #0 (sets Z, clears N)
+            0x40, // SEI
+            0x42, // RTI
+        ];
+        for (i, byte) in handler.iter().enumerate() {
+            bus.write_u8(0x002000 + i as u32, *byte);
+        }
+
+        cpu.pc = 0xFF0010;
+        cpu.sr = StatusFlags::from_byte(0xC9); // N, V, D, C set; I and Z clear
+        let original = cpu.sr;
+        let original_sp = cpu.sp;
+
+        cpu.request_interrupt(4);
+        cpu.step(&mut bus); // Enter handler
+        assert_eq!(cpu.pc, 0x002000);
+        for _ in 0..5 {
+            cpu.step(&mut bus);
+        }
+
+        assert_eq!(cpu.pc, 0xFF0010);
+        assert_eq!(cpu.sp, original_sp);
+        assert_eq!(cpu.sr, original);
+    }
+
+    #[test]
+    fn cpu_push_pop_special_registers_in_subroutine() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x21, 0x04, 0x00, 0xFF, // JSR $FF0004
+            // subroutine:
+            0x90, // PUSH A
+            0x92, // PUSH X
+            0x94, // PUSH Y
+            0x01, 0x00, 0x00, // LDA #0
+            0x03, 0x00, 0x00, // LDX #0
+            0x05, 0x00, 0x00, // LDY #0
+            0x95, // POP Y
+            0x93, // POP X
+            0x91, // POP A
+            0x22, // RTS
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x1111;
+        cpu.x = 0x2222;
+        cpu.y = 0x8000;
+        let initial_sp = cpu.sp;
+
+        // JSR + 3 pushes
+        for _ in 0..4 {
+            cpu.step(&mut bus);
+        }
+        assert_eq!(cpu.sp, initial_sp.wrapping_sub(3 + 6));
+
+        // Clobber all three; flags now say zero
+        for _ in 0..3 {
+            cpu.step(&mut bus);
+        }
+        assert!(cpu.sr.zero);
+
+        // 3 pops + RTS
+        for _ in 0..4 {
+            cpu.step(&mut bus);
+        }
+        assert_eq!((cpu.a, cpu.x, cpu.y), (0x1111, 0x2222, 0x8000));
+        assert!(cpu.sr.zero); // POP leaves flags alone
+        assert_eq!(cpu.sp, initial_sp);
+        assert_eq!(cpu.pc, 0xFF0004);
+    }
+
+    #[test]
+    fn cpu_lcall_lret_preserves_context() {
+        let mut cpu = Cpu::new();
+        let mut bus = Bus24::new();
+
+        let program = vec![
+            0x25, 0x05, 0x00, 0xFF, // LCALL $FF0005
+            0xFF, // HLT
+            // subroutine: clobber everything
+            0x01, 0x00, 0x00, // LDA #0
+            0x03, 0x00, 0x00, // LDX #0
+            0x05, 0x00, 0x00, // LDY #0
+            0x18, 0x04, // INC R0
+            0x18, 0x0B, // INC R7
+            0x82, // SEC
+            0x84, // SEV
+            0x26, // LRET
+        ];
+        bus.load_bios(&program);
+        cpu.pc = 0xFF0000;
+        cpu.a = 0x8001;
+        cpu.x = 0x1234;
+        cpu.y = 0x5678;
+        cpu.r = [1, 2, 3, 4, 5, 6, 7, 0xFFFF];
+        cpu.sr = StatusFlags::from_byte(0x86); // N, I, Z
+        let before = cpu.save();
+
+        cpu.step(&mut bus);
+        assert_eq!(cpu.pc, 0xFF0005);
+        assert_eq!(cpu.sp, before.sp.wrapping_sub(26));
+        assert_eq!(cpu.cycles, 16);
+
+        // Run the subroutine body up to the LRET
+        while bus.read_u8(cpu.pc) != 0x26 {
+            cpu.step(&mut bus);
+        }
+        assert_ne!(cpu.r, before.r);
+
+        cpu.step(&mut bus);
+        let after = cpu.save();
+        assert_eq!(after.pc, 0xFF0004);
+        assert_eq!(after.a, before.a);
+        assert_eq!(after.x, before.x);
+        assert_eq!(after.y, before.y);
+        assert_eq!(after.r, before.r);
+        assert_eq!(after.sr, before.sr);
+        assert_eq!(after.sp, before.sp);
+    }
 }
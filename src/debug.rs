@@ -0,0 +1,403 @@
+// Copyright (C) 2025 Dayton Fishell
+// Nexel-24 Game Console Emulator
+// This file is part of Nexel-24.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version. See the LICENSE file in the project root for details.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! GDB remote serial protocol stub
+//!
+//! [`GdbStub`] listens on a local TCP port and serves one debugger session,
+//! driving a [`Nexel24`] from the calling thread. Supported packets are `?`,
+//! `g`/`G`, `m`/`M`, `s`, `c`, `Z0`/`z0`, `qSupported`, `D` and `k`; anything
+//! else gets the empty "unsupported" reply. `m`/`M` transfer at most
+//! [`MAX_MEMORY_BYTES`] per packet and answer `E01` beyond that.
+//!
+//! Registers are sent little-endian in this order:
+//!
+//! | Index | Register | Bytes |
+//! |-------|----------|-------|
+//! | 0-3   | A, X, Y, SP | 2 |
+//! | 4     | PC (24-bit) | 4 |
+//! | 5     | SR | 1 |
+//! | 6-13  | R0-R7 | 2 |
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crate::cpu::StatusFlags;
+use crate::emulator::Nexel24;
+
+/// Size of the `g`/`G` register block in bytes
+pub const REGISTER_BYTES: usize = 4 * 2 + 4 + 1 + 8 * 2;
+
+/// Largest packet payload, advertised to the debugger through `qSupported`
+pub const PACKET_SIZE: usize = 0x1000;
+
+/// Most bytes one `m`/`M` packet may transfer, at two hex digits per byte
+pub const MAX_MEMORY_BYTES: usize = PACKET_SIZE / 2;
+
+/// Stop reply for SIGTRAP, sent after a step, breakpoint or interrupt
+const STOP_REPLY: &str = "S05";
+
+/// Instructions run between checks for a ^C from the debugger
+const INTERRUPT_POLL: u32 = 4096;
+
+/// Byte a debugger sends to interrupt a running target
+const INTERRUPT: u8 = 0x03;
+
+/// GDB remote stub listening for a debugger connection
+pub struct GdbStub {
+    listener: TcpListener,
+}
+
+impl GdbStub {
+    /// Listen on `port` on the loopback interface; port 0 picks a free port
+    pub fn bind(port: u16) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(("127.0.0.1", port))?,
+        })
+    }
+
+    /// Address the stub is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept one debugger connection and serve it until it detaches,
+    /// kills the session or disconnects
+    pub fn serve(&self, emu: &mut Nexel24) -> io::Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        let mut session = Session { stream };
+        while let Some(packet) = session.read_packet()? {
+            match packet.as_bytes().first() {
+                Some(b'D') => return session.send_packet("OK"),
+                Some(b'k') => return Ok(()),
+                _ => {
+                    let reply = session.handle(emu, &packet)?;
+                    session.send_packet(&reply)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One connected debugger
+struct Session {
+    stream: TcpStream,
+}
+
+impl Session {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8];
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Read the next `$payload#checksum` packet, acknowledging it
+    ///
+    /// Acks from the debugger are skipped. A ^C while stopped is answered
+    /// as a halt-reason query. Returns `None` once the debugger disconnects.
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                Some(INTERRUPT) => return Ok(Some("?".to_string())),
+                Some(_) => continue,
+            }
+            let mut payload = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => payload.push(byte),
+                }
+            }
+            let mut checksum = [0u8; 2];
+            self.stream.read_exact(&mut checksum)?;
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if expected == Some(checksum_of(&payload)) {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&payload).into_owned()));
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn send_packet(&mut self, payload: &str) -> io::Result<()> {
+        let packet = format!("${payload}#{:02x}", checksum_of(payload.as_bytes()));
+        self.stream.write_all(packet.as_bytes())
+    }
+
+    /// Check for a ^C without blocking
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut byte = [0u8];
+        let peeked = self.stream.peek(&mut byte);
+        self.stream.set_nonblocking(false)?;
+        match peeked {
+            Ok(1) if byte[0] == INTERRUPT => {
+                self.stream.read_exact(&mut byte)?;
+                Ok(true)
+            }
+            Ok(_) => Ok(false),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Build the reply for a packet
+    fn handle(&mut self, emu: &mut Nexel24, packet: &str) -> io::Result<String> {
+        let Some((command, args)) = packet.split_at_checked(1) else {
+            return Ok(String::new());
+        };
+        let reply = match command {
+            "?" => STOP_REPLY.to_string(),
+            "g" => to_hex(&read_registers(emu)),
+            "G" => match from_hex(args) {
+                Some(bytes) if write_registers(emu, &bytes) => "OK".to_string(),
+                _ => "E01".to_string(),
+            },
+            "m" => match parse_range(args) {
+                Some((addr, len)) => to_hex(&emu.read_region(addr, len)),
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let write = args.split_once(':').and_then(|(range, data)| {
+                    let (addr, len) = parse_range(range)?;
+                    let bytes = from_hex(data)?;
+                    (bytes.len() == len).then_some((addr, bytes))
+                });
+                match write {
+                    Some((addr, bytes)) => {
+                        emu.write_region(addr, &bytes);
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            "s" | "c" => {
+                if let Ok(addr) = u32::from_str_radix(args, 16) {
+                    emu.cpu.pc = addr & 0xFF_FFFF;
+                }
+                if command == "s" {
+                    emu.step_instruction();
+                } else {
+                    self.resume(emu)?;
+                }
+                STOP_REPLY.to_string()
+            }
+            "q" if args.starts_with("Supported") => format!("PacketSize={PACKET_SIZE:x}"),
+            "Z" | "z" => match args.strip_prefix("0,").and_then(parse_range) {
+                Some((addr, _)) => {
+                    if command == "Z" {
+                        emu.cpu.add_breakpoint(addr);
+                    } else {
+                        emu.cpu.remove_breakpoint(addr);
+                    }
+                    "OK".to_string()
+                }
+                // Only software breakpoints are supported
+                None => String::new(),
+            },
+            _ => String::new(),
+        };
+        Ok(reply)
+    }
+
+    /// Run until a breakpoint, a halt or a ^C from the debugger
+    fn resume(&mut self, emu: &mut Nexel24) -> io::Result<()> {
+        let mut since_poll = 0;
+        loop {
+            emu.step_instruction();
+            if emu.cpu.breakpoint_hit || emu.cpu.is_stopped() {
+                return Ok(());
+            }
+            since_poll += 1;
+            if since_poll == INTERRUPT_POLL {
+                since_poll = 0;
+                if self.interrupted()? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn checksum_of(payload: &[u8]) -> u8 {
+    payload
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parse `addr,len` (both hex); trailing fields such as a breakpoint kind
+/// are ignored
+fn parse_range(args: &str) -> Option<(u32, usize)> {
+    let mut fields = args.split(',');
+    let addr = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let len = usize::from_str_radix(fields.next()?, 16).ok()?;
+    (len <= MAX_MEMORY_BYTES).then_some((addr, len))
+}
+
+fn read_registers(emu: &Nexel24) -> Vec<u8> {
+    let cpu = &emu.cpu;
+    let mut bytes = Vec::with_capacity(REGISTER_BYTES);
+    for value in [cpu.a, cpu.x, cpu.y, cpu.sp] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&cpu.pc.to_le_bytes());
+    bytes.push(cpu.sr.to_byte());
+    for value in cpu.r {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+fn write_registers(emu: &mut Nexel24, bytes: &[u8]) -> bool {
+    if bytes.len() != REGISTER_BYTES {
+        return false;
+    }
+    let word = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let cpu = &mut emu.cpu;
+    cpu.a = word(0);
+    cpu.x = word(2);
+    cpu.y = word(4);
+    cpu.sp = word(6);
+    cpu.pc = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) & 0xFF_FFFF;
+    cpu.sr = StatusFlags::from_byte(bytes[12]);
+    for (i, reg) in cpu.r.iter_mut().enumerate() {
+        *reg = word(13 + i * 2);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Send a packet and return the payload of the reply
+    fn exchange(stream: &mut TcpStream, payload: &str) -> String {
+        let packet = format!("${payload}#{:02x}", checksum_of(payload.as_bytes()));
+        stream.write_all(packet.as_bytes()).unwrap();
+        let mut reply = Vec::new();
+        let mut byte = [0u8];
+        // Skip the ack, then read through the checksum
+        while reply.last() != Some(&b'#') {
+            stream.read_exact(&mut byte).unwrap();
+            if !(reply.is_empty() && byte[0] == b'+') {
+                reply.push(byte[0]);
+            }
+        }
+        let mut checksum = [0u8; 2];
+        stream.read_exact(&mut checksum).unwrap();
+        let body = &reply[1..reply.len() - 1];
+        assert_eq!(reply[0], b'$');
+        assert_eq!(
+            u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap(),
+            checksum_of(body)
+        );
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn gdb_session_over_loopback() {
+        let mut emu = Nexel24::new_headless();
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x01, 0x34, 0x12, // LDA #$1234
+            0x00, // NOP
+            0x30, 0xFE, // BRA -2
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        let stub = GdbStub::bind(0).unwrap();
+        let addr = stub.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            [
+                exchange(&mut stream, "?"),
+                exchange(&mut stream, "mff0003,3"),
+                exchange(&mut stream, "Z0,ff0006,1"),
+                exchange(&mut stream, "c"),
+                exchange(&mut stream, "g"),
+                exchange(&mut stream, "M100,2:beef"),
+                exchange(&mut stream, "m100,2"),
+                exchange(&mut stream, "vMustReplyEmpty"),
+                exchange(&mut stream, "D"),
+            ]
+        });
+        stub.serve(&mut emu).unwrap();
+        let replies = client.join().unwrap();
+
+        assert_eq!(replies[0], "S05");
+        assert_eq!(replies[1], "013412");
+        assert_eq!(replies[2], "OK");
+        assert_eq!(replies[3], "S05");
+        // A = 0x1234, PC stopped at the breakpoint
+        assert_eq!(&replies[4][..4], "3412");
+        assert_eq!(&replies[4][16..24], "0600ff00");
+        assert_eq!(replies[4].len(), REGISTER_BYTES * 2);
+        assert_eq!(replies[5], "OK");
+        assert_eq!(replies[6], "beef");
+        assert_eq!(replies[7], "");
+        assert_eq!(replies[8], "OK");
+        assert_eq!(emu.cpu.pc, 0xFF0006);
+    }
+
+    #[test]
+    fn memory_packets_are_limited_to_the_packet_size() {
+        let mut emu = Nexel24::new_headless();
+        let stub = GdbStub::bind(0).unwrap();
+        let addr = stub.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let oversized_write = format!(
+                "M0,{:x}:{}",
+                MAX_MEMORY_BYTES + 1,
+                "00".repeat(MAX_MEMORY_BYTES + 1)
+            );
+            [
+                exchange(&mut stream, "qSupported:multiprocess+"),
+                exchange(&mut stream, &format!("m0,{MAX_MEMORY_BYTES:x}")),
+                exchange(&mut stream, &format!("m0,{:x}", MAX_MEMORY_BYTES + 1)),
+                exchange(&mut stream, "m0,ffffffffffff"),
+                exchange(&mut stream, &oversized_write),
+                exchange(&mut stream, "D"),
+            ]
+        });
+        stub.serve(&mut emu).unwrap();
+        let replies = client.join().unwrap();
+
+        assert_eq!(replies[0], format!("PacketSize={PACKET_SIZE:x}"));
+        assert_eq!(replies[1].len(), MAX_MEMORY_BYTES * 2);
+        assert_eq!(replies[2], "E01");
+        assert_eq!(replies[3], "E01");
+        assert_eq!(replies[4], "E01");
+        assert_eq!(replies[5], "OK");
+    }
+}
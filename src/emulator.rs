@@ -13,25 +13,196 @@
 //! This module provides the main emulator struct that coordinates the CPU,
 //! memory bus, and coprocessors.
 
-use crate::apu::Apu;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io;
+use std::rc::{Rc, Weak};
+use std::time::Instant;
+
+use bitflags::bitflags;
+
+use crate::apu::{APU_CHANNEL_COUNT, Apu, ApuState};
 use crate::bios::default_bios;
-use crate::core::Bus24;
-use crate::cpu::Cpu;
-use crate::vdp::Vdp;
-use crate::vlu::Vlu;
+use crate::core::{Bus24, BusState};
+use crate::cpu::{Cpu, CpuState};
+use crate::debug::GdbStub;
+use crate::movie::{FrameInput, Movie, MovieRecorder};
+use crate::timer::Timers;
+use crate::vdp::{DmaMode, IrqFlags, Vdp, VdpMemoryDump, VdpRegisterDump};
+use crate::vlu::{self, Vlu, VluJob, VluState};
 use crate::vm::BaseplateVm;
 
+/// Number of gamepad ports
+pub const GAMEPAD_COUNT: usize = 2;
+
+/// Cycles the peripherals advance per step while the CPU waits in WFI
+///
+/// Short enough not to skip over an HBLANK or an APU tick.
+const WFI_IDLE_CYCLES: u64 = 64;
+
+bitflags! {
+    /// Gamepad button bits as read from the gamepad registers; bits 12-15
+    /// are reserved and read as zero
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Buttons: u16 {
+        const A = 1 << 0;
+        const B = 1 << 1;
+        const C = 1 << 2;
+        const D = 1 << 3;
+        const UP = 1 << 4;
+        const DOWN = 1 << 5;
+        const LEFT = 1 << 6;
+        const RIGHT = 1 << 7;
+        const START = 1 << 8;
+        const SELECT = 1 << 9;
+        const L = 1 << 10;
+        const R = 1 << 11;
+    }
+}
+
+/// Video standard, which sets the frame rate and scanline count
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Region {
+    /// 60 Hz, 288 scanlines
+    #[default]
+    Ntsc,
+    /// 50 Hz, 312 scanlines
+    Pal,
+}
+
+impl Region {
+    /// Frames per second
+    pub fn fps(self) -> u64 {
+        match self {
+            Region::Ntsc => 60,
+            Region::Pal => 50,
+        }
+    }
+
+    /// CPU cycles in one frame
+    pub fn cycles_per_frame(self) -> u64 {
+        Nexel24::CPU_CLOCK_HZ / self.fps()
+    }
+
+    /// Total VDP scanlines in one frame, including blanking
+    pub fn scanlines_per_frame(self) -> u16 {
+        match self {
+            Region::Ntsc => Vdp::SCANLINES_PER_FRAME,
+            Region::Pal => Vdp::PAL_SCANLINES_PER_FRAME,
+        }
+    }
+}
+
+/// Snapshot of every subsystem, produced by [`Nexel24::save_state`]
+///
+/// Debugger state (breakpoints, watchpoints, trace) and the loaded
+/// cartridge ROM are not included.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmulatorState {
+    pub cpu: CpuState,
+    pub bus: BusState,
+    pub vdp: VdpRegisterDump,
+    pub vdp_memory: VdpMemoryDump,
+    pub apu: ApuState,
+    pub vlu: VluState,
+    pub timers: Timers,
+    pub frame_count: u64,
+}
+
+/// Host callback receiving interleaved stereo samples, see
+/// [`Nexel24::set_audio_callback`]
+pub type AudioCallback = Box<dyn FnMut(&[i16])>;
+
+/// Host-side execution counters accumulated by [`Nexel24::step_frame`]
+#[derive(Debug, Clone, Copy)]
+pub struct PerfCounter {
+    pub instruction_count: u64,
+    pub total_cycles: u64,
+    /// Part of `total_cycles` spent waiting in WFI, left out of the CPI
+    pub idle_cycles: u64,
+    pub elapsed_ns: u64,
+    pub last_reset: Instant,
+}
+
+impl PerfCounter {
+    fn new() -> Self {
+        Self {
+            instruction_count: 0,
+            total_cycles: 0,
+            idle_cycles: 0,
+            elapsed_ns: 0,
+            last_reset: Instant::now(),
+        }
+    }
+}
+
+/// Emulation speed derived from a [`PerfCounter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerfStats {
+    /// Instructions executed per wall-clock second
+    pub ips: f64,
+    /// Average CPU cycles per instruction
+    pub cpi: f64,
+    /// Emulated cycles per wall-clock microsecond; 18.432 is full speed
+    pub wall_clock_mhz: f64,
+}
+
+/// Memory patch reapplied at the start of every frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheatCode {
+    pub addr: u32,
+    /// Written little-endian to `addr` and `addr + 1`
+    pub value: u16,
+    pub active: bool,
+}
+
+/// Ring of recent frame states used to rewind
+///
+/// The newest entry is the state at the end of the most recent frame, so
+/// rewinding drops it and restores the one before.
+#[derive(Debug, Clone)]
+pub struct RewindBuffer {
+    pub states: VecDeque<EmulatorState>,
+    pub capacity: usize,
+}
+
+impl RewindBuffer {
+    /// Record a state, discarding the oldest once `capacity` is exceeded
+    pub fn push(&mut self, state: EmulatorState) {
+        if self.states.len() > self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(state);
+    }
+}
+
 /// Main Nexel-24 emulator state
 pub struct Nexel24 {
     pub cpu: Cpu,
     pub bus: Bus24,
-    pub vdp: Vdp,
     pub vlu: Vlu,
     pub vm: Option<BaseplateVm>,
 
     // Frame timing
     pub frame_count: u64,
     pub target_cycles_per_frame: u64,
+    frame_skip: u8,
+    region: Region,
+
+    // Job code written to the VLU register window, run after the write
+    vlu_job: Rc<Cell<Option<u8>>>,
+    // Buttons held on each gamepad, served by the gamepad registers
+    buttons: Rc<Cell<[u16; GAMEPAD_COUNT]>>,
+    rewind_buffer: Option<RewindBuffer>,
+    // Frames of an active recording, owned by the MovieRecorder
+    recording: Weak<RefCell<Vec<FrameInput>>>,
+    // Movie being replayed and the index of its next frame
+    playback: Option<(Movie, usize)>,
+    audio_callback: Option<AudioCallback>,
+    perf: PerfCounter,
+    cheats: Vec<CheatCode>,
 }
 
 impl Nexel24 {
@@ -46,26 +217,101 @@ impl Nexel24 {
 
     /// Create a new emulator instance
     pub fn new() -> Self {
-        let mut bus = Bus24::new();
-        bus.enable_vdp_routing(); // Enable VDP routing through emulator
+        Self::with_vdp(Vdp::new())
+    }
+
+    /// Create an emulator that never renders, for tests and CI
+    ///
+    /// VDP timing, DMA and interrupts behave as normal but no framebuffer is
+    /// allocated or drawn.
+    pub fn new_headless() -> Self {
+        Self::with_vdp(Vdp::new_headless())
+    }
+
+    /// Create an emulator for the given video standard
+    pub fn new_with_region(region: Region) -> Self {
+        let mut emu = Self::new();
+        emu.set_region(region);
+        emu
+    }
+
+    /// Switch video standard, updating the frame length and VDP timing
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.target_cycles_per_frame = region.cycles_per_frame();
+        self.bus
+            .vdp_mut()
+            .set_scanlines_per_frame(region.scanlines_per_frame());
+    }
+
+    /// Current video standard
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    fn with_vdp(vdp: Vdp) -> Self {
+        let mut bus = Bus24::with_vdp(vdp);
+
+        let vlu_job = Rc::new(Cell::new(None));
+        let latch = Rc::clone(&vlu_job);
+        bus.register_io_write(
+            Bus24::VLU_IO_BASE + vlu::MMIO_JOB,
+            1,
+            Box::new(move |_, code| latch.set(Some(code))),
+        );
+
+        let buttons = Rc::new(Cell::new([0; GAMEPAD_COUNT]));
+        let pads = Rc::clone(&buttons);
+        bus.register_io_read(
+            Bus24::GAMEPAD_IO_BASE,
+            (GAMEPAD_COUNT * 2) as u32,
+            Box::new(move |addr| {
+                let offset = (addr - Bus24::GAMEPAD_IO_BASE) as usize;
+                (pads.get()[offset / 2] >> ((offset % 2) * 8)) as u8
+            }),
+        );
 
         Self {
             cpu: Cpu::new(),
             bus,
-            vdp: Vdp::new(),
             vlu: Vlu::new(),
             vm: None,
             frame_count: 0,
             target_cycles_per_frame: Self::CYCLES_PER_FRAME,
+            frame_skip: 0,
+            region: Region::Ntsc,
+            vlu_job,
+            buttons,
+            rewind_buffer: None,
+            recording: Weak::new(),
+            playback: None,
+            audio_callback: None,
+            perf: PerfCounter::new(),
+            cheats: Vec::new(),
         }
     }
 
-    /// Reset the entire system
-    pub fn reset(&mut self) {
+    /// Reset only the CPU, reloading PC from the reset vector
+    ///
+    /// Coprocessor registers and memory are left as they are; see
+    /// [`Nexel24::soft_reset`] for a console-level reset.
+    pub fn reset_cpu(&mut self) {
         self.cpu.reset(&self.bus);
         self.frame_count = 0;
     }
 
+    /// Press the console's reset button
+    ///
+    /// Resets the CPU and the VDP, APU and timer registers but keeps the
+    /// contents of WorkRAM, ExpandedRAM, cartridge save RAM, VRAM and CRAM.
+    pub fn soft_reset(&mut self) {
+        self.bus.vdp_mut().reset_registers();
+        self.apu_mut().reset_registers();
+        *self.bus.timers_mut() = Timers::new();
+        self.vlu_job.set(None);
+        self.reset_cpu();
+    }
+
     /// Load the built-in BIOS image.
     pub fn load_default_bios(&mut self) {
         self.load_bios(&default_bios());
@@ -81,6 +327,16 @@ impl Nexel24 {
         self.bus.apu_mut()
     }
 
+    /// Access the VDP-T, which lives on the bus so the CPU can reach it.
+    pub fn vdp(&self) -> &Vdp {
+        self.bus.vdp()
+    }
+
+    /// Mutable access to the VDP-T.
+    pub fn vdp_mut(&mut self) -> &mut Vdp {
+        self.bus.vdp_mut()
+    }
+
     /// Load a BIOS ROM
     pub fn load_bios(&mut self, data: &[u8]) {
         self.bus.load_bios(data);
@@ -91,117 +347,510 @@ impl Nexel24 {
         self.bus.load_cart_rom(data);
     }
 
-    /// Execute a single CPU instruction with VDP routing
-    pub fn step(&mut self) {
+    /// Set the buttons held on a gamepad (0 = player 1, 1 = player 2)
+    ///
+    /// Reserved bits are cleared; out-of-range players are ignored, as is
+    /// every call while a movie is playing.
+    pub fn set_buttons(&mut self, player: usize, buttons: u16) {
+        if self.playback.is_some() {
+            return;
+        }
+        let mut pads = self.buttons.get();
+        if let Some(pad) = pads.get_mut(player) {
+            *pad = Buttons::from_bits_truncate(buttons).bits();
+            self.buttons.set(pads);
+        }
+    }
+
+    /// Capture the state of every subsystem
+    pub fn save_state(&self) -> EmulatorState {
+        EmulatorState {
+            cpu: self.cpu.save(),
+            bus: self.bus.save(),
+            vdp: self.bus.vdp().dump_registers(),
+            vdp_memory: self.bus.vdp().dump_memory(),
+            apu: self.apu().save(),
+            vlu: self.vlu.save(),
+            timers: self.bus.timers().clone(),
+            frame_count: self.frame_count,
+        }
+    }
+
+    /// Restore a state captured by [`Nexel24::save_state`]
+    ///
+    /// Execution continues exactly as it would have from the point the
+    /// state was saved.
+    pub fn load_state(&mut self, state: &EmulatorState) {
+        self.cpu.load(&state.cpu);
+        self.bus.load(&state.bus);
+        self.bus.vdp_mut().restore_registers(&state.vdp);
+        self.bus.vdp_mut().restore_memory(&state.vdp_memory);
+        self.apu_mut().load(&state.apu);
+        self.vlu.load(&state.vlu);
+        *self.bus.timers_mut() = state.timers.clone();
+        self.frame_count = state.frame_count;
+        self.vlu_job.set(None);
+    }
+
+    /// Execute a single CPU instruction, or service one pending interrupt,
+    /// and advance the coprocessors by the cycles it took
+    ///
+    /// While the CPU waits in WFI nothing executes and the coprocessors run
+    /// on by a short idle slice, so one of them can raise the interrupt that
+    /// wakes it. Returns false for such an idle slice.
+    pub fn step_instruction(&mut self) -> bool {
         let cycles_before = self.cpu.cycles;
-        self.cpu.step(&mut self.bus);
+        let executed = !self.cpu.waiting_for_interrupt;
+        if !executed {
+            self.cpu.breakpoint_hit = false;
+            self.cpu.cycles += WFI_IDLE_CYCLES;
+        } else {
+            self.cpu.step(&mut self.bus);
+            self.dispatch_cop();
+            self.dispatch_vlu_mmio();
+        }
         let cycles_elapsed = self.cpu.cycles - cycles_before;
 
         // VDP runs in parallel, advance it by the same number of cycles
-        self.vdp.step(cycles_elapsed);
+        self.advance_vdp(cycles_elapsed);
         self.advance_apu(cycles_elapsed);
+        self.advance_timers(cycles_elapsed);
+        executed
+    }
+
+    /// Skip rendering of `n` frames out of every `n + 1` (0 = no skip)
+    ///
+    /// Each [`Nexel24::step_frame`] call then runs `n + 1` frames of CPU,
+    /// audio and timer logic and only draws the last VDP frame that
+    /// completes within them, from its first line to its last.
+    pub fn set_frame_skip(&mut self, n: u8) {
+        self.frame_skip = n;
     }
 
     /// Execute instructions for one frame (approximately 307,200 cycles at 60 FPS)
+    ///
+    /// With frame skip enabled this runs several frames; see
+    /// [`Nexel24::set_frame_skip`].
     pub fn step_frame(&mut self) {
+        if self.frame_skip > 0 {
+            let cycles = (self.frame_skip as u64 + 1) * self.target_cycles_per_frame;
+            self.bus.vdp_mut().skip_rendering_for(cycles);
+        }
+        for _ in 0..=self.frame_skip {
+            self.run_frame();
+            if self.cpu.breakpoint_hit {
+                break;
+            }
+        }
+    }
+
+    /// Run a single logical frame
+    fn run_frame(&mut self) {
+        self.update_movie();
+        self.apply_cheats();
+        let started = Instant::now();
         let start_cycles = self.cpu.cycles;
         let target_cycles = start_cycles + self.target_cycles_per_frame;
 
-        while self.cpu.cycles < target_cycles && !self.cpu.halted {
-            let cycles_before = self.cpu.cycles;
-            self.cpu.step(&mut self.bus);
-            let cycles_elapsed = self.cpu.cycles - cycles_before;
+        while self.cpu.cycles < target_cycles && !self.cpu.is_stopped() {
+            if self.step_instruction() {
+                self.perf.instruction_count += 1;
+            } else {
+                self.perf.idle_cycles += WFI_IDLE_CYCLES;
+            }
 
-            // Advance VDP by the same number of cycles
-            let vblank_triggered = self.vdp.step(cycles_elapsed);
+            if self.cpu.breakpoint_hit {
+                // Leave the frame unfinished so a debugger can inspect state
+                break;
+            }
+        }
 
-            self.advance_apu(cycles_elapsed);
+        self.perf.total_cycles += self.cpu.cycles - start_cycles;
+        self.perf.elapsed_ns += started.elapsed().as_nanos() as u64;
+        if self.cpu.breakpoint_hit {
+            return;
+        }
 
-            // TODO: Handle VBLANK interrupt
-            if vblank_triggered && self.vdp.in_vblank() {
-                // Trigger VBLANK interrupt to CPU if enabled
+        self.frame_count += 1;
+        if let Some(callback) = &mut self.audio_callback {
+            let samples = self.bus.apu_mut().drain_audio();
+            callback(&samples);
+        }
+        if self.rewind_buffer.is_some() {
+            let state = self.save_state();
+            if let Some(buffer) = &mut self.rewind_buffer {
+                buffer.push(state);
             }
         }
+    }
 
-        self.frame_count += 1;
+    /// Keep the state of the last `max_frames` frames so they can be rewound
+    ///
+    /// Replaces any existing rewind history with the current state.
+    pub fn enable_rewind(&mut self, max_frames: usize) {
+        let mut states = VecDeque::with_capacity(max_frames + 1);
+        states.push_back(self.save_state());
+        self.rewind_buffer = Some(RewindBuffer {
+            states,
+            capacity: max_frames,
+        });
     }
 
-    /// Advance the APU by the given number of CPU cycles and raise interrupts.
-    fn advance_apu(&mut self, cycles: u64) {
-        if cycles == 0 {
-            return;
+    /// Step back to the state at the end of the previous frame
+    ///
+    /// Returns false if rewind is disabled or the history is exhausted.
+    pub fn rewind_one_frame(&mut self) -> bool {
+        let Some(buffer) = &mut self.rewind_buffer else {
+            return false;
+        };
+        if buffer.states.len() < 2 {
+            return false;
         }
-        let apu = self.bus.apu_mut();
-        apu.step(cycles);
-        if apu.take_buffer_empty() {
-            self.cpu.request_interrupt(3);
+        buffer.states.pop_back();
+        let Some(state) = buffer.states.back().cloned() else {
+            return false;
+        };
+        self.load_state(&state);
+        true
+    }
+
+    /// Install a callback that receives the APU output after every frame
+    ///
+    /// The slice holds interleaved left/right `i16` samples produced during
+    /// the frame. The callback runs on the thread calling
+    /// [`Nexel24::step_frame`], before it returns, so it should hand the
+    /// samples off (e.g. to a ring buffer) rather than block.
+    pub fn set_audio_callback(&mut self, callback: AudioCallback) {
+        self.audio_callback = Some(callback);
+    }
+
+    /// Record the buttons for each frame until the recorder is finished
+    ///
+    /// Starting a new recording ends any previous one.
+    pub fn start_recording(&mut self) -> MovieRecorder {
+        let frames = Rc::new(RefCell::new(Vec::new()));
+        self.recording = Rc::downgrade(&frames);
+        MovieRecorder { frames }
+    }
+
+    /// Replay a movie, driving the gamepads from its frames
+    ///
+    /// Host input from [`Nexel24::set_buttons`] is ignored until the movie
+    /// ends.
+    pub fn play_movie(&mut self, movie: &Movie) {
+        self.playback = Some((movie.clone(), 0));
+    }
+
+    /// Whether a movie is currently driving the gamepads
+    pub fn is_playing_movie(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Apply movie input and record the buttons for the frame about to run
+    fn update_movie(&mut self) {
+        if let Some((movie, next)) = &mut self.playback {
+            if let Some(input) = movie.frames.get(*next) {
+                let pads = [input.player1, input.player2]
+                    .map(|buttons| Buttons::from_bits_truncate(buttons).bits());
+                self.buttons.set(pads);
+                *next += 1;
+            }
+            if *next >= movie.frames.len() {
+                self.playback = None;
+            }
+        }
+        if let Some(frames) = self.recording.upgrade() {
+            let [player1, player2] = self.buttons.get();
+            frames.borrow_mut().push(FrameInput { player1, player2 });
         }
     }
 
-    /// Read from memory with VDP routing
-    pub fn read_memory(&self, addr: u32) -> u8 {
-        let addr = addr & 0x00FFFFFF;
-
-        // Route VDP regions
-        match addr {
-            // VDP-T registers: 0x100000..0x103FFF
-            a if a >= Bus24::VDP_IO_BASE && a < Bus24::VDP_IO_BASE + 0x4000 => {
-                let offset = a - Bus24::VDP_IO_BASE;
-                // VDP registers are 16-bit, read as bytes
-                if offset & 1 == 0 {
-                    (self.vdp.read_reg(offset) & 0xFF) as u8
-                } else {
-                    (self.vdp.read_reg(offset - 1) >> 8) as u8
-                }
+    /// Add a cheat; it is identified by its position in the cheat list
+    pub fn add_cheat(&mut self, code: CheatCode) {
+        self.cheats.push(code);
+    }
+
+    /// Remove the cheat at `index`, shifting later cheats down
+    pub fn remove_cheat(&mut self, index: usize) -> Option<CheatCode> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+
+    /// Enable or disable the cheat at `index`; returns false if there is none
+    pub fn set_cheat_active(&mut self, index: usize, active: bool) -> bool {
+        match self.cheats.get_mut(index) {
+            Some(cheat) => {
+                cheat.active = active;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Installed cheats, in the order they are applied
+    pub fn cheats(&self) -> &[CheatCode] {
+        &self.cheats
+    }
+
+    /// Write every active cheat through the routed memory map
+    fn apply_cheats(&mut self) {
+        for i in 0..self.cheats.len() {
+            let cheat = self.cheats[i];
+            if cheat.active {
+                let [low, high] = cheat.value.to_le_bytes();
+                self.write_memory(cheat.addr, low);
+                self.write_memory(cheat.addr.wrapping_add(1), high);
             }
-            // VRAM: 0x200000..0x27FFFF
-            a if a >= Bus24::VRAM_BASE && a < Bus24::VRAM_BASE + 0x80000 => {
-                let offset = a - Bus24::VRAM_BASE;
-                self.vdp.read_vram(offset)
+        }
+    }
+
+    /// Clear the performance counters
+    pub fn reset_perf(&mut self) {
+        self.perf = PerfCounter::new();
+    }
+
+    /// Emulation speed since the last [`Nexel24::reset_perf`]
+    ///
+    /// Only time spent inside [`Nexel24::step_frame`] is counted. Rates are
+    /// zero until a frame has run.
+    pub fn perf_stats(&self) -> PerfStats {
+        let perf = &self.perf;
+        let seconds = perf.elapsed_ns as f64 / 1e9;
+        let rate = |count: u64| {
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
             }
-            // CRAM: 0x280000..0x28FFFF
-            a if a >= Bus24::CRAM_BASE && a < Bus24::CRAM_BASE + 0x10000 => {
-                let offset = a - Bus24::CRAM_BASE;
-                self.vdp.read_cram(offset)
+        };
+        PerfStats {
+            ips: rate(perf.instruction_count),
+            cpi: if perf.instruction_count > 0 {
+                (perf.total_cycles - perf.idle_cycles) as f64 / perf.instruction_count as f64
+            } else {
+                0.0
+            },
+            wall_clock_mhz: rate(perf.total_cycles) / 1e6,
+        }
+    }
+
+    /// Run until the VDP enters VBLANK and return the number of
+    /// instructions executed
+    ///
+    /// If VBLANK is already active, runs until the next one begins. Stops
+    /// early if a breakpoint is hit.
+    pub fn step_to_vblank(&mut self) -> u64 {
+        let mut instructions = 0;
+        loop {
+            let was_vblank = self.bus.vdp().in_vblank();
+            if self.step_instruction() {
+                instructions += 1;
+            }
+            if (!was_vblank && self.bus.vdp().in_vblank()) || self.cpu.breakpoint_hit {
+                return instructions;
             }
-            // Everything else goes through bus
-            _ => self.bus.read_u8(addr),
         }
     }
 
-    /// Write to memory with VDP routing
-    pub fn write_memory(&mut self, addr: u32, value: u8) {
-        let addr = addr & 0x00FFFFFF;
-
-        // Route VDP regions
-        match addr {
-            // VDP-T registers: 0x100000..0x103FFF
-            a if a >= Bus24::VDP_IO_BASE && a < Bus24::VDP_IO_BASE + 0x4000 => {
-                let offset = a - Bus24::VDP_IO_BASE;
-                // VDP registers are 16-bit, handle byte writes
-                // For simplicity, only process writes on even addresses
-                if offset & 1 == 0 {
-                    let current = self.vdp.read_reg(offset);
-                    let new_value = (current & 0xFF00) | (value as u16);
-                    self.vdp.write_reg(offset, new_value);
-                } else {
-                    let current = self.vdp.read_reg(offset - 1);
-                    let new_value = (current & 0x00FF) | ((value as u16) << 8);
-                    self.vdp.write_reg(offset - 1, new_value);
-                }
+    /// Route a command latched by the COP instruction to its coprocessor.
+    ///
+    /// The top nibble selects the coprocessor (0=VDP, 1=APU, 2=VLU, 3-15
+    /// reserved) and the low nibble is the sub-command:
+    /// - VDP: 0 renders the current frame immediately.
+    /// - APU: n acknowledges the buffer refill for channel n.
+    /// - VLU: the job code from [`VluJob::from_code`] with operands in R0-R3,
+    ///   e.g. 0 Transform(R0 <- M[R2] * V[R1]), 1 Dot(V[R0], V[R1]).
+    ///   Carry is set if a register index is invalid.
+    fn dispatch_cop(&mut self) {
+        let Some(cmd) = self.cpu.take_cop_request() else {
+            return;
+        };
+        let sub = cmd & 0x0F;
+        match cmd >> 4 {
+            // VDP-T
+            0 if sub == 0 => self.bus.vdp_mut().render_frame(),
+            // APU-6: write the channel's buffer-ack register through the bus
+            1 if (sub as usize) < APU_CHANNEL_COUNT => {
+                let addr = Bus24::APU_IO_BASE + (sub as u32) * 0x10 + 3;
+                self.bus.write_u8(addr, 0x01);
             }
-            // VRAM: 0x200000..0x27FFFF
-            a if a >= Bus24::VRAM_BASE && a < Bus24::VRAM_BASE + 0x80000 => {
-                let offset = a - Bus24::VRAM_BASE;
-                self.vdp.write_vram(offset, value);
+            // VLU-24: operands are register indices held in R0-R3
+            2 => {
+                let operands = [0, 1, 2, 3].map(|i| self.cpu.r[i] as usize);
+                let Some(job) = VluJob::from_code(sub, operands) else {
+                    return;
+                };
+                // Jobs may set carry themselves (e.g. a singular inverse)
+                self.cpu.sr.carry = false;
+                if !self.run_vlu_job(job) {
+                    self.cpu.sr.carry = true;
+                }
             }
-            // CRAM: 0x280000..0x28FFFF
-            a if a >= Bus24::CRAM_BASE && a < Bus24::CRAM_BASE + 0x10000 => {
-                let offset = a - Bus24::CRAM_BASE;
-                self.vdp.write_cram(offset, value);
+            // Unknown sub-commands and reserved coprocessor slots
+            _ => {}
+        }
+    }
+
+    /// Run a VLU job, charging its cycles to the CPU and raising the VLU
+    /// completion interrupt. Returns false if the job named a bad register.
+    fn run_vlu_job(&mut self, job: VluJob) -> bool {
+        let Ok((_, interrupt)) = self.vlu.compute(job) else {
+            return false;
+        };
+        self.cpu.cycles += job.cycles();
+        if let VluJob::MatInverse { .. } = job {
+            // A singular matrix is reported through carry
+            self.cpu.sr.carry = self.vlu.last_inverse_singular();
+        }
+        if interrupt {
+            self.cpu.request_interrupt(4);
+        }
+        true
+    }
+
+    /// Run a job written to the VLU register window.
+    ///
+    /// The window's vectors and matrices are loaded into the VLU first and
+    /// the results, scalar and status are copied back once the job finishes.
+    fn dispatch_vlu_mmio(&mut self) {
+        let Some(code) = self.vlu_job.take() else {
+            return;
+        };
+        let operand = |offset| self.bus.read_u8(Bus24::VLU_IO_BASE + offset) as usize;
+        let operands = [
+            operand(vlu::MMIO_JOB + 1),
+            operand(vlu::MMIO_JOB + 2),
+            operand(vlu::MMIO_JOB + 3),
+            operand(vlu::MMIO_OPERAND3),
+        ];
+        self.vlu.load_mmio(&self.bus);
+        let failed = match VluJob::from_code(code, operands) {
+            Some(job) => !self.run_vlu_job(job),
+            None => true,
+        };
+        self.vlu.store_mmio(&mut self.bus, failed);
+    }
+
+    /// Advance the VDP by the given number of CPU cycles and raise interrupts.
+    ///
+    /// VBLANK, HBLANK and line-compare all use interrupt 6; handlers tell
+    /// them apart through the VDP's IRQ_STATUS register.
+    fn advance_vdp(&mut self, cycles: u64) {
+        let was_hblank = self.bus.vdp().in_hblank();
+        let entered_vblank = self.bus.vdp_mut().step(cycles);
+
+        if entered_vblank && self.bus.vdp_mut().raise_irq(IrqFlags::VBLANK) {
+            self.cpu.request_interrupt(6);
+        }
+        if !was_hblank
+            && self.bus.vdp().in_hblank()
+            && self.bus.vdp_mut().raise_irq(IrqFlags::HBLANK)
+        {
+            self.cpu.request_interrupt(6);
+        }
+        if self.bus.vdp_mut().take_line_compare() {
+            self.cpu.request_interrupt(6);
+        }
+
+        self.run_main_dma(cycles);
+        if self.bus.vdp_mut().take_dma_done() {
+            self.cpu.request_interrupt(5);
+        }
+    }
+
+    /// Move up to one byte per cycle of a DMA between main memory and VRAM.
+    ///
+    /// Spreading the copy across instructions keeps the CPU running while a
+    /// large transfer is in flight.
+    fn run_main_dma(&mut self, cycles: u64) {
+        if !self.bus.vdp_mut().main_dma_active() {
+            return;
+        }
+        for _ in 0..cycles {
+            let Some((mode, src, dst)) = self.bus.vdp_mut().next_dma_transfer() else {
+                break;
+            };
+            match mode {
+                DmaMode::MainToVram => {
+                    let value = self.bus.read_u8(src);
+                    self.bus.vdp_mut().write_vram(dst, value);
+                }
+                DmaMode::VramToMain => {
+                    let value = self.bus.vdp().read_vram(src);
+                    self.bus.write_u8(dst, value);
+                }
+                DmaMode::VramToVram => {}
             }
-            // Everything else goes through bus
-            _ => self.bus.write_u8(addr, value),
+        }
+    }
+
+    /// Advance the APU by the given number of CPU cycles and raise interrupts.
+    fn advance_apu(&mut self, cycles: u64) {
+        if cycles == 0 {
+            return;
+        }
+        let buffer_empty = if self.bus.apu().reads_bus() {
+            // Lift the APU out of the bus so PCM channels can read sample memory
+            let mut apu = std::mem::take(self.bus.apu_mut());
+            apu.step_with_bus(cycles, &self.bus);
+            let buffer_empty = apu.take_buffer_empty();
+            *self.bus.apu_mut() = apu;
+            buffer_empty
+        } else {
+            // Moving the APU out costs more than stepping it, so skip that
+            // unless sample memory is needed
+            let apu = self.bus.apu_mut();
+            apu.step(cycles);
+            apu.take_buffer_empty()
+        };
+        if buffer_empty {
+            self.cpu.request_interrupt(3);
+        }
+    }
+
+    /// Advance the interval timers; either timer expiring raises interrupt 2.
+    fn advance_timers(&mut self, cycles: u64) {
+        if self.bus.timers_mut().step(cycles) {
+            self.cpu.request_interrupt(2);
+        }
+    }
+
+    /// Read a byte from the 24-bit address space
+    ///
+    /// Same as reading through the bus; VDP registers, VRAM and CRAM are
+    /// served by the VDP on the bus.
+    pub fn read_memory(&self, addr: u32) -> u8 {
+        self.bus.read_u8(addr)
+    }
+
+    /// Write a byte to the 24-bit address space, running any VLU job the
+    /// write starts
+    pub fn write_memory(&mut self, addr: u32, value: u8) {
+        self.bus.write_u8(addr, value);
+        self.dispatch_vlu_mmio();
+    }
+
+    /// Wait for a GDB connection on `port` and serve it until the debugger
+    /// detaches
+    ///
+    /// Blocks the calling thread; the emulator only runs when the debugger
+    /// steps or continues it.
+    pub fn attach_gdb(&mut self, port: u16) -> io::Result<()> {
+        GdbStub::bind(port)?.serve(self)
+    }
+
+    /// Read `len` bytes starting at `addr`, routed like [`Nexel24::read_memory`]
+    ///
+    /// Addresses wrap at the end of the 24-bit address space.
+    pub fn read_region(&self, addr: u32, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| self.read_memory(addr.wrapping_add(i as u32)))
+            .collect()
+    }
+
+    /// Write `data` starting at `addr`, routed like [`Nexel24::write_memory`]
+    pub fn write_region(&mut self, addr: u32, data: &[u8]) {
+        for (i, &value) in data.iter().enumerate() {
+            self.write_memory(addr.wrapping_add(i as u32), value);
         }
     }
 
@@ -210,7 +859,7 @@ impl Nexel24 {
         for _ in 0..num_frames {
             self.step_frame();
 
-            if self.cpu.halted {
+            if self.cpu.is_stopped() {
                 break;
             }
         }
@@ -245,6 +894,10 @@ pub struct EmulatorStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apu::{self, CYCLES_PER_SAMPLE};
+    use crate::timer;
+    use crate::vdp::{DisplayStatus, SpriteAttr, SpriteSize, VdpRegister};
+    use crate::vlu::VluResult;
 
     #[test]
     fn emulator_initialization() {
@@ -265,7 +918,7 @@ mod tests {
         emu.cpu.a = 0x1234;
         emu.frame_count = 10;
 
-        emu.reset();
+        emu.reset_cpu();
 
         assert_eq!(emu.cpu.a, 0);
         assert_eq!(emu.cpu.pc, 0x400400);
@@ -283,14 +936,14 @@ mod tests {
             0xFF, // HLT
         ]);
         emu.load_bios(&program);
-        emu.reset();
+        emu.reset_cpu();
 
         // Execute the program
-        emu.step(); // LDA
+        emu.step_instruction(); // LDA
         assert_eq!(emu.cpu.a, 0x1234);
         assert!(!emu.cpu.halted);
 
-        emu.step(); // HLT
+        emu.step_instruction(); // HLT
         assert!(emu.cpu.halted);
     }
 
@@ -300,10 +953,10 @@ mod tests {
 
         // Create a program that does some NOPs then halts
         let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
-        program.extend_from_slice(&vec![0x00; 100]); // 100 NOPs
+        program.extend_from_slice(&[0x00; 100]); // 100 NOPs
         program.push(0xFF); // HLT
         emu.load_bios(&program);
-        emu.reset();
+        emu.reset_cpu();
 
         let initial_cycles = emu.cpu.cycles;
         emu.step_frame();
@@ -323,7 +976,7 @@ mod tests {
             0x30, 0xFE, // BRA -2 (infinite loop)
         ]);
         emu.load_bios(&program);
-        emu.reset();
+        emu.reset_cpu();
 
         emu.run_frames(5);
 
@@ -339,10 +992,10 @@ mod tests {
         let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
         program.extend_from_slice(&[0x01, 0x34, 0x12, 0xFF]); // LDA #0x1234, HLT
         emu.load_bios(&program);
-        emu.reset();
+        emu.reset_cpu();
 
-        emu.step();
-        emu.step();
+        emu.step_instruction();
+        emu.step_instruction();
 
         let stats = emu.stats();
         assert_eq!(stats.total_cycles, 3); // LDA (2) + HLT (1)
@@ -371,7 +1024,7 @@ mod tests {
 
         assert_eq!(value, 0x07);
         // Verify that the VDP received the write by checking it can read the register
-        assert_eq!(emu.vdp.read_reg(0), 0x07);
+        assert_eq!(emu.vdp().read_reg(0), 0x07);
     }
 
     #[test]
@@ -404,20 +1057,51 @@ mod tests {
         assert_eq!(emu.read_memory(0x280002), 0x00);
     }
 
+    #[test]
+    fn cpu_loads_and_stores_reach_vdp_memory() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x01, 0xEF, 0xBE, // LDA #0xBEEF
+            0x02, 0x10, 0x00, 0x20, // STA $200010
+            0x01, 0x07, 0x3F, // LDA #0x3F07
+            0x02, 0x00, 0x00, 0x28, // STA $280000
+            0x07, 0x20, 0x00, 0x20, // LDA $200020
+            0xFF, // HLT
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.vdp_mut().write_vram(0x20, 0x34);
+        emu.vdp_mut().write_vram(0x21, 0x12);
+
+        for _ in 0..6 {
+            emu.step_instruction();
+        }
+
+        // Stores land in the VDP's memory and loads read it back
+        assert_eq!(emu.vdp().read_vram(0x10), 0xEF);
+        assert_eq!(emu.vdp().read_vram(0x11), 0xBE);
+        assert_eq!(emu.vdp().read_cram(0), 0x07);
+        assert_eq!(emu.vdp().read_cram(1), 0x3F);
+        assert_eq!(emu.cpu.a, 0x1234);
+        assert!(emu.cpu.halted);
+    }
+
     #[test]
     fn emulator_vdp_timing_integration() {
         let mut emu = Nexel24::new();
 
         // Enable VDP display
-        emu.vdp.set_display_enable(true);
+        emu.vdp_mut().set_display_enable(true);
 
-        let initial_frame_count = emu.vdp.frame_count();
+        let initial_frame_count = emu.vdp().frame_count();
 
         // Run for one frame
         emu.step_frame();
 
         // VDP should have advanced
-        assert!(emu.vdp.frame_count() > initial_frame_count || emu.cpu.halted);
+        assert!(emu.vdp().frame_count() > initial_frame_count || emu.cpu.halted);
     }
 
     #[test]
@@ -427,8 +1111,764 @@ mod tests {
         emu.write_memory(Bus24::APU_IO_BASE + 11, 0x00);
         emu.write_memory(Bus24::APU_IO_BASE + 12, 0x01);
 
-        emu.step();
+        emu.step_instruction();
 
         assert!(emu.cpu.pending_interrupts.contains(&3));
     }
+
+    #[test]
+    fn apu_buffer_empty_interrupt_fires_during_step_frame() {
+        let mut emu = Nexel24::new();
+        // Set the length before enabling so no empty buffer is latched early
+        emu.write_memory(Bus24::APU_IO_BASE + 12, 0x01);
+        emu.write_memory(Bus24::APU_IO_BASE, 0x01);
+        // A one-byte PCM sample runs out after one output sample; ending the
+        // frame there leaves the interrupt pending rather than serviced
+        emu.target_cycles_per_frame = CYCLES_PER_SAMPLE;
+        assert!(emu.cpu.pending_interrupts.is_empty());
+
+        emu.step_frame();
+
+        assert!(emu.cpu.pending_interrupts.contains(&3));
+        let status = emu.read_memory(Bus24::APU_IO_BASE + apu::STATUS_OFFSET);
+        assert_ne!(status & apu::StatusFlags::BUFFER_EMPTY.bits(), 0);
+    }
+
+    #[test]
+    fn cop_dispatches_vlu_job() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x44, 0x21, // COP VLU Dot
+            0x44, 0x21, // COP VLU Dot (invalid register)
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        emu.vlu.set_vector(0, [1.0, 2.0, 3.0]).unwrap();
+        emu.vlu.set_vector(1, [4.0, 5.0, 6.0]).unwrap();
+        emu.cpu.r[0] = 0;
+        emu.cpu.r[1] = 1;
+        // Mask the VLU completion interrupt so the next COP runs straight away
+        emu.cpu.sr.interrupt_disable = true;
+
+        emu.step_instruction();
+        assert_eq!(emu.vlu.scalar_result(), 32.0);
+        assert!(!emu.cpu.sr.carry);
+
+        emu.cpu.r[1] = 0xFF;
+        emu.step_instruction();
+        assert!(emu.cpu.sr.carry);
+    }
+
+    #[test]
+    fn cop_dispatches_apu_and_vdp() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x44, 0x12, // COP APU ack channel 2
+            0x44, 0x00, // COP VDP render
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        // Channel 2 starts with its buffer-empty flag set
+        let chan2 = Bus24::APU_IO_BASE + 0x20;
+        assert_eq!(emu.bus.read_u8(chan2 + 3) & 0x01, 0x01);
+
+        emu.step_instruction();
+        assert_eq!(emu.bus.read_u8(chan2 + 3) & 0x01, 0x00);
+
+        emu.vdp_mut().set_display_enable(true);
+        emu.vdp_mut().set_backdrop_color(0x3F, 0x00, 0x00);
+        emu.step_instruction();
+        assert_eq!(emu.vdp().framebuffer()[0], 0x00FF0000);
+    }
+
+    #[test]
+    fn step_frame_stops_at_breakpoint() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x00, // NOP
+            0x30, 0xFE, // BRA -2 (infinite loop)
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.cpu.add_breakpoint(0xFF0004);
+
+        emu.step_frame();
+        assert!(emu.cpu.breakpoint_hit);
+        assert_eq!(emu.cpu.pc, 0xFF0004);
+        assert_eq!(emu.frame_count, 0);
+    }
+
+    #[test]
+    fn hblank_interrupt_runs_handler_every_scanline() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0u8; 0x40];
+        program[0..3].copy_from_slice(&[0x20, 0x00, 0xFF]); // Reset vector: 0xFF0020
+        program[0x15..0x18].copy_from_slice(&[0x30, 0x00, 0xFF]); // IRQ 6: 0xFF0030
+        program[0x20..0x23].copy_from_slice(&[
+            0x41, // CLI
+            0x30, 0xFE, // BRA -2
+        ]);
+        program[0x30..0x3B].copy_from_slice(&[
+            0x07, 0x00, 0x01, 0x00, // LDA $000100
+            0x10, 0x01, 0x00, // ADD #1
+            0x02, 0x00, 0x01, 0x00, // STA $000100
+        ]);
+        program[0x3B] = 0x42; // RTI
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        emu.vdp_mut()
+            .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::HBLANK.bits());
+        emu.step_frame();
+
+        // One HBLANK per scanline over the frame's worth of cycles
+        let expected = Nexel24::CYCLES_PER_FRAME / Vdp::CYCLES_PER_SCANLINE;
+        assert_eq!(emu.bus.read_u16(0x000100) as u64, expected);
+        assert_eq!(
+            emu.vdp().read_reg(VdpRegister::IrqStatus as u32) & IrqFlags::HBLANK.bits(),
+            IrqFlags::HBLANK.bits()
+        );
+    }
+
+    #[test]
+    fn vblank_wakes_cpu_from_wfi_during_step_frame() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0u8; 0x40];
+        program[0..3].copy_from_slice(&[0x20, 0x00, 0xFF]); // Reset vector: 0xFF0020
+        program[0x15..0x18].copy_from_slice(&[0x30, 0x00, 0xFF]); // IRQ 6: 0xFF0030
+        program[0x20..0x2B].copy_from_slice(&[
+            0x41, // CLI
+            0x43, // WFI
+            0x01, 0xAA, 0x00, // LDA #0x00AA
+            0x02, 0x00, 0x01, 0x00, // STA $000100
+            0x30, 0xFE, // BRA -2
+        ]);
+        program[0x30] = 0x42; // RTI
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        emu.vdp_mut()
+            .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::VBLANK.bits());
+        emu.step_frame();
+
+        // The peripherals kept running through the wait, so VBLANK woke the
+        // CPU and the code after WFI ran within the same frame
+        assert!(!emu.cpu.halted);
+        assert!(!emu.cpu.waiting_for_interrupt);
+        assert_eq!(emu.bus.read_u16(0x000100), 0x00AA);
+        assert!(emu.vdp().frame_count() > 0);
+    }
+
+    #[test]
+    fn line_compare_requests_interrupt() {
+        let mut emu = Nexel24::new();
+        emu.cpu.sr.interrupt_disable = false;
+        emu.vdp_mut()
+            .write_reg(VdpRegister::IrqLineCompare as u32, 120);
+        emu.vdp_mut()
+            .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::LINECMP.bits());
+
+        while emu.vdp().scanline() < 120 {
+            emu.step_instruction();
+        }
+        assert!(emu.cpu.pending_interrupts.contains(&6));
+    }
+
+    #[test]
+    fn dma_copies_cart_rom_to_vram() {
+        let mut emu = Nexel24::new();
+        let tile: Vec<u8> = (0..64).map(|i| i as u8 ^ 0x5A).collect();
+        let mut rom = vec![0u8; 0x200];
+        rom[0x100..0x140].copy_from_slice(&tile);
+        emu.load_cartridge(&rom);
+        emu.cpu.sr.interrupt_disable = false;
+        let busy = DisplayStatus::DMA_BUSY.bits();
+
+        emu.vdp_mut()
+            .write_reg(VdpRegister::DmaSource as u32, 0x0100);
+        emu.vdp_mut()
+            .write_reg(VdpRegister::DmaSource as u32 + 2, 0x0040);
+        emu.vdp_mut()
+            .write_reg(VdpRegister::DmaDestination as u32, 0x2000);
+        emu.vdp_mut().write_reg(VdpRegister::DmaLength as u32, 64);
+        emu.vdp_mut()
+            .write_reg(VdpRegister::DmaMode as u32, DmaMode::MainToVram as u16);
+        emu.vdp_mut()
+            .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::DMA_DONE.bits());
+        emu.vdp_mut()
+            .write_reg(VdpRegister::DmaControl as u32, 0x8000);
+
+        // The copy advances alongside the CPU instead of completing at once
+        emu.step_instruction();
+        assert_ne!(emu.vdp().read_vram(0x2000 + 63), tile[63]);
+        assert_ne!(
+            emu.vdp().read_reg(VdpRegister::DisplayStatus as u32) & busy,
+            0
+        );
+
+        while emu.vdp().read_reg(VdpRegister::DisplayStatus as u32) & busy != 0 {
+            emu.step_instruction();
+        }
+        let copied: Vec<u8> = (0..64).map(|i| emu.vdp().read_vram(0x2000 + i)).collect();
+        assert_eq!(copied, tile);
+        assert!(emu.cpu.pending_interrupts.contains(&5));
+    }
+
+    #[test]
+    fn vlu_jobs_run_through_register_window() {
+        let mut emu = Nexel24::new();
+        let write_f32s = |emu: &mut Nexel24, offset: u32, values: &[f32]| {
+            for (i, value) in values.iter().enumerate() {
+                for (j, byte) in value.to_le_bytes().into_iter().enumerate() {
+                    let addr = Bus24::VLU_IO_BASE + offset + (i * 4 + j) as u32;
+                    emu.write_memory(addr, byte);
+                }
+            }
+        };
+        let read_f32 = |emu: &Nexel24, offset: u32| {
+            f32::from_le_bytes(
+                [0, 1, 2, 3].map(|i| emu.read_memory(Bus24::VLU_IO_BASE + offset + i)),
+            )
+        };
+        let run = |emu: &mut Nexel24, code: u8, operands: [u8; 3]| {
+            for (i, operand) in operands.into_iter().enumerate() {
+                emu.write_memory(Bus24::VLU_IO_BASE + vlu::MMIO_JOB + 1 + i as u32, operand);
+            }
+            emu.write_memory(Bus24::VLU_IO_BASE + vlu::MMIO_JOB, code);
+        };
+
+        let vector = [1.0, 2.0, 3.0];
+        let matrix = [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 2.0]];
+        write_f32s(&mut emu, vlu::MMIO_VECTOR_BASE, &vector);
+        write_f32s(&mut emu, vlu::MMIO_MATRIX_BASE, matrix.as_flattened());
+
+        // Transform V1 <- M0 * V0, raising the completion interrupt
+        emu.cpu.sr.interrupt_disable = false;
+        let cycles = emu.cpu.cycles;
+        run(&mut emu, 0, [1, 0, 0]);
+        assert_eq!(emu.cpu.pending_interrupts, vec![4]);
+        assert_eq!(
+            emu.cpu.cycles - cycles,
+            VluJob::Transform {
+                dest: 1,
+                vec: 0,
+                matrix: 0
+            }
+            .cycles()
+        );
+        let dest = vlu::MMIO_VECTOR_BASE + vlu::MMIO_VECTOR_STRIDE;
+        let result = [0, 1, 2].map(|i| read_f32(&emu, dest + i * 4));
+
+        let mut direct = Vlu::new();
+        direct.set_vector(0, vector).unwrap();
+        direct.set_matrix(0, matrix).unwrap();
+        let (expected, _) = direct
+            .compute(VluJob::Transform {
+                dest: 1,
+                vec: 0,
+                matrix: 0,
+            })
+            .unwrap();
+        assert_eq!(VluResult::Vector(result), expected);
+        assert_eq!(emu.vlu.vector(1).unwrap(), result);
+        assert_eq!(emu.read_memory(Bus24::VLU_IO_BASE + vlu::MMIO_STATUS), 0);
+
+        // Dot(V0, V1) lands in the result register
+        run(&mut emu, 1, [0, 1, 0]);
+        let (dot, _) = direct.compute(VluJob::Dot { a: 0, b: 1 }).unwrap();
+        assert_eq!(VluResult::Scalar(read_f32(&emu, vlu::MMIO_RESULT)), dot);
+
+        // Bad register index reports failure
+        run(&mut emu, 3, [40, 0, 0]);
+        assert_eq!(emu.read_memory(Bus24::VLU_IO_BASE + vlu::MMIO_STATUS), 1);
+    }
+
+    fn framebuffer_checksum(emu: &Nexel24) -> u64 {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+        let mut hasher = DefaultHasher::new();
+        emu.vdp().framebuffer().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn load_state_replays_identical_frames() {
+        let mut emu = Nexel24::new();
+
+        // Spin in a loop that keeps the APU and work RAM busy
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x44, 0x10, // COP APU ack channel 0
+            0x30, 0xFC, // BRA -4
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.vdp_mut().set_display_enable(true);
+        emu.vdp_mut().set_backdrop_color(0x10, 0x20, 0x30);
+        emu.vdp_mut().set_sprite(
+            0,
+            SpriteAttr::from_parts(32, 48, 1, 0, SpriteSize::Size8x8, false, false, 0, true),
+        );
+        emu.vdp_mut().load_tile_data(32, &[0x11; 32]);
+
+        // Frames are shortened to keep the test quick; saves still land mid-frame
+        emu.target_cycles_per_frame = Nexel24::CYCLES_PER_FRAME / 64;
+        emu.run_frames(1000);
+        let state = emu.save_state();
+
+        let mut expected = Vec::new();
+        for _ in 0..100 {
+            emu.step_frame();
+            expected.push(framebuffer_checksum(&emu));
+        }
+        let end_state = emu.save_state();
+
+        emu.load_state(&state);
+        assert_eq!(emu.save_state(), state);
+        let replayed: Vec<_> = (0..100)
+            .map(|_| {
+                emu.step_frame();
+                framebuffer_checksum(&emu)
+            })
+            .collect();
+
+        assert_eq!(replayed, expected);
+        assert_eq!(emu.save_state(), end_state);
+    }
+
+    #[test]
+    fn gamepad_registers_report_buttons() {
+        let mut emu = Nexel24::new();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x07, 0x00, 0xA0, 0x10, // LDA $10A000
+            0xFF, // HLT
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        let pressed = Buttons::A | Buttons::UP | Buttons::START;
+        emu.set_buttons(0, pressed.bits() | 0xF000);
+        emu.set_buttons(1, Buttons::B.bits());
+        emu.step_instruction();
+
+        // Reserved bits are masked off
+        assert_eq!(emu.cpu.a, pressed.bits());
+        assert_eq!(
+            emu.bus.read_u16(Bus24::GAMEPAD_IO_BASE + 2),
+            Buttons::B.bits()
+        );
+
+        emu.set_buttons(0, 0);
+        assert_eq!(emu.bus.read_u16(Bus24::GAMEPAD_IO_BASE), 0);
+    }
+
+    #[test]
+    fn timer_interrupt_fires_every_period() {
+        let mut emu = Nexel24::new();
+        emu.bus.write_u16(Bus24::TIMER_IO_BASE, 100);
+        emu.bus.write_u16(
+            Bus24::TIMER_IO_BASE + 2,
+            (timer::CONTROL_RUN | timer::CONTROL_IRQ_ENABLE) as u16,
+        );
+
+        emu.advance_timers(99);
+        assert!(emu.cpu.pending_interrupts.is_empty());
+        emu.advance_timers(1);
+        assert_eq!(emu.cpu.pending_interrupts, vec![2]);
+        assert_eq!(
+            emu.bus.read_u8(Bus24::TIMER_IO_BASE + timer::TIMER_STATUS),
+            0x01
+        );
+
+        emu.cpu.pending_interrupts.clear();
+        emu.advance_timers(99);
+        assert!(emu.cpu.pending_interrupts.is_empty());
+        emu.advance_timers(1);
+        assert_eq!(emu.cpu.pending_interrupts, vec![2]);
+    }
+
+    #[test]
+    fn headless_keeps_timing_without_rendering() {
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        let run = |mut emu: Nexel24| {
+            emu.load_bios(&program);
+            emu.reset_cpu();
+            emu.cpu.sr.interrupt_disable = false;
+            emu.vdp_mut().set_display_enable(true);
+            emu.vdp_mut().set_backdrop_color(0x3F, 0x00, 0x00);
+            emu.vdp_mut()
+                .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::VBLANK.bits());
+            emu.run_frames(2);
+            emu
+        };
+
+        let normal = run(Nexel24::new());
+        let headless = run(Nexel24::new_headless());
+
+        assert!(headless.vdp().framebuffer().is_empty());
+        assert_eq!(headless.vdp().framebuffer_dimensions(), (0, 0));
+        assert_eq!(headless.vdp().active_dimensions(), (0, 0));
+        assert!(headless.vdp().framebuffer_rgba8888().is_empty());
+        assert_eq!(normal.vdp().framebuffer()[0], 0x00FF0000);
+        assert_eq!(headless.cpu.cycles, normal.cpu.cycles);
+        assert_eq!(headless.vdp().scanline(), normal.vdp().scanline());
+        assert_eq!(headless.vdp().frame_count(), normal.vdp().frame_count());
+        assert_eq!(headless.cpu.pc, normal.cpu.pc);
+    }
+
+    #[test]
+    fn step_instruction_and_step_to_vblank() {
+        let mut emu = Nexel24::new_headless();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        let start = emu.cpu.cycles;
+        for _ in 0..5 {
+            emu.step_instruction();
+        }
+        // BRA takes 2 cycles
+        assert_eq!(emu.cpu.cycles - start, 10);
+        assert_eq!(emu.cpu.pc, 0xFF0003);
+
+        let instructions = emu.step_to_vblank();
+        assert!(emu.vdp().in_vblank());
+        assert_eq!(emu.vdp().scanline(), Vdp::VBLANK_START);
+        let cycles = Vdp::VBLANK_START as u64 * Vdp::CYCLES_PER_SCANLINE;
+        assert_eq!(instructions, (cycles - 10) / 2);
+    }
+
+    #[test]
+    fn rewind_restores_earlier_frames() {
+        let mut emu = Nexel24::new_headless();
+
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.target_cycles_per_frame = Nexel24::CYCLES_PER_FRAME / 16;
+        assert!(!emu.rewind_one_frame());
+
+        emu.enable_rewind(8);
+        emu.run_frames(10);
+        let frame10_cycles = emu.cpu.cycles;
+
+        for _ in 0..5 {
+            assert!(emu.rewind_one_frame());
+        }
+        assert_eq!(emu.frame_count, 5);
+        emu.run_frames(5);
+        assert_eq!(emu.cpu.cycles, frame10_cycles);
+
+        // Only `max_frames` frames of history are kept
+        for _ in 0..8 {
+            assert!(emu.rewind_one_frame());
+        }
+        assert!(!emu.rewind_one_frame());
+        assert_eq!(emu.frame_count, 2);
+    }
+
+    /// Program whose timing depends on whether player 1 holds any button
+    fn input_loop_program() -> Vec<u8> {
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x07, 0x00, 0xA0, 0x10, // LDA $10A000
+            0x31, 0x01, // BEQ +1
+            0x00, // NOP
+            0x30, 0xF7, // BRA to the LDA
+        ]);
+        program
+    }
+
+    #[test]
+    fn recorded_movie_replays_identically() {
+        let mut emu = Nexel24::new_headless();
+        emu.load_bios(&input_loop_program());
+        emu.reset_cpu();
+        emu.target_cycles_per_frame = Nexel24::CYCLES_PER_FRAME / 16;
+
+        let recorder = emu.start_recording();
+        for frame in 0..10u16 {
+            emu.set_buttons(0, if frame % 3 == 0 { 0 } else { frame });
+            emu.set_buttons(1, frame << 4);
+            emu.step_frame();
+        }
+        let movie = recorder.finish();
+        assert_eq!(movie.frames.len(), 10);
+        assert_eq!(
+            movie.frames[4],
+            FrameInput {
+                player1: 4,
+                player2: 0x40
+            }
+        );
+        // Recording stops once the recorder is finished
+        emu.step_frame();
+
+        let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        let mut replay = Nexel24::new_headless();
+        replay.load_bios(&input_loop_program());
+        replay.reset_cpu();
+        replay.target_cycles_per_frame = Nexel24::CYCLES_PER_FRAME / 16;
+        replay.play_movie(&movie);
+        for _ in 0..10 {
+            // Host input is overridden while the movie plays
+            replay.set_buttons(0, Buttons::START.bits());
+            replay.step_frame();
+        }
+        assert!(!replay.is_playing_movie());
+        replay.step_frame();
+
+        assert_eq!(replay.cpu.save(), emu.cpu.save());
+    }
+
+    #[test]
+    fn audio_callback_receives_frame_samples() {
+        let mut emu = Nexel24::new_headless();
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        emu.load_bios(&program);
+        emu.reset_cpu();
+
+        let level = Rc::new(Cell::new(0u64));
+        let calls = Rc::new(Cell::new(0));
+        let (sum, count) = (Rc::clone(&level), Rc::clone(&calls));
+        emu.set_audio_callback(Box::new(move |samples| {
+            let total: u64 = samples.iter().map(|s| s.unsigned_abs() as u64).sum();
+            sum.set(sum.get() + total);
+            count.set(count.get() + 1);
+        }));
+
+        // PCM channel 0 plays a full-scale square wave from WorkRAM
+        for i in 0..0x800u32 {
+            emu.bus
+                .write_u8(0x1000 + i, if i & 8 == 0 { 0xFF } else { 0x00 });
+        }
+        let chan0 = Bus24::APU_IO_BASE;
+        emu.bus.write_u8(chan0 + 8, 0x00); // Sample address 0x001000
+        emu.bus.write_u8(chan0 + 9, 0x10);
+        emu.bus.write_u8(chan0 + 10, 0x00);
+        emu.bus.write_u8(chan0 + 11, 0x08); // Length 0x800
+        emu.bus.write_u8(chan0 + 12, 0x00);
+        emu.bus.write_u8(chan0, 0x01); // Enable, PCM voice
+
+        emu.step_frame();
+        assert_eq!(calls.get(), 1);
+        assert!(level.get() > 0);
+        // The buffer was handed to the callback
+        assert!(emu.apu_mut().drain_audio().is_empty());
+    }
+
+    #[test]
+    fn region_access_matches_byte_routing() {
+        let mut emu = Nexel24::new();
+        let pattern: Vec<u8> = (0..=255).collect();
+
+        emu.write_region(0x000100, &pattern);
+        assert_eq!(emu.read_region(0x000100, pattern.len()), pattern);
+
+        // VRAM only exists in the VDP
+        emu.write_region(Bus24::VRAM_BASE + 0x40, &pattern[..16]);
+        assert_eq!(emu.vdp().read_vram(0x4F), 15);
+        assert_eq!(emu.read_region(Bus24::VRAM_BASE + 0x40, 16), &pattern[..16]);
+        assert_eq!(
+            emu.read_region(Bus24::APU_IO_BASE, 4),
+            (0..4)
+                .map(|i| emu.read_memory(Bus24::APU_IO_BASE + i))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn perf_stats_track_step_frame() {
+        let mut emu = Nexel24::new_headless();
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x00, 0x30, 0xFD]); // NOP; BRA -3
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        assert_eq!(emu.perf_stats().ips, 0.0);
+
+        // NOP + BRA average 1.5 cycles, so this frame is 10,000 instructions
+        emu.target_cycles_per_frame = 15_000;
+        emu.step_frame();
+
+        let stats = emu.perf_stats();
+        assert_eq!(emu.perf.instruction_count, 10_000);
+        assert!(stats.cpi >= 1.0);
+        assert!((stats.cpi - 1.5).abs() < 1e-9);
+        assert!(stats.ips > 0.0);
+        assert!(stats.wall_clock_mhz > 0.0);
+
+        emu.reset_perf();
+        assert_eq!(emu.perf.instruction_count, 0);
+        assert_eq!(emu.perf_stats().cpi, 0.0);
+    }
+
+    #[test]
+    fn perf_stats_leave_out_wfi_idle_time() {
+        let mut emu = Nexel24::new_headless();
+        let mut program = vec![0u8; 0x31];
+        program[0..3].copy_from_slice(&[0x20, 0x00, 0xFF]); // Reset vector: 0xFF0020
+        program[0x15..0x18].copy_from_slice(&[0x30, 0x00, 0xFF]); // IRQ 6: 0xFF0030
+        program[0x20..0x24].copy_from_slice(&[
+            0x41, // CLI
+            0x43, // WFI
+            0x30, 0xFC, // BRA -4
+        ]);
+        program[0x30] = 0x42; // RTI
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.vdp_mut()
+            .write_reg(VdpRegister::IrqEnable as u32, IrqFlags::VBLANK.bits());
+
+        emu.step_frame();
+
+        // One pass of the loop plus the VBLANK handler, the rest is waiting
+        let stats = emu.perf_stats();
+        assert!(emu.perf.instruction_count < 10);
+        assert!(emu.perf.idle_cycles > emu.perf.total_cycles / 2);
+        assert!((1.0..16.0).contains(&stats.cpi), "cpi {}", stats.cpi);
+    }
+
+    #[test]
+    fn cheats_patch_memory_every_frame() {
+        let mut emu = Nexel24::new_headless();
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[
+            0x01, 0x34, 0x12, // LDA #$1234
+            0x02, 0x00, 0x10, 0x00, // STA $001000
+            0x30, 0xFE, // BRA -2
+        ]);
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.target_cycles_per_frame = 1_000;
+
+        emu.add_cheat(CheatCode {
+            addr: 0x1000,
+            value: 0x9999,
+            active: true,
+        });
+        // The CPU's store lands after the cheat in the first frame...
+        emu.step_frame();
+        assert_eq!(emu.bus.read_u16(0x1000), 0x1234);
+        // ...and the cheat overwrites it at the start of the next
+        emu.step_frame();
+        assert_eq!(emu.bus.read_u16(0x1000), 0x9999);
+
+        assert!(emu.set_cheat_active(0, false));
+        assert!(!emu.set_cheat_active(1, false));
+        emu.reset_cpu();
+        emu.run_frames(2);
+        assert_eq!(emu.bus.read_u16(0x1000), 0x1234);
+
+        assert_eq!(emu.remove_cheat(0).map(|cheat| cheat.value), Some(0x9999));
+        assert!(emu.remove_cheat(0).is_none());
+        assert!(emu.cheats().is_empty());
+    }
+
+    #[test]
+    fn frame_skip_runs_logic_but_renders_last_frame() {
+        let mut emu = Nexel24::new();
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.vdp_mut().set_display_enable(true);
+
+        emu.set_frame_skip(3);
+        emu.step_frame();
+        assert_eq!(emu.frame_count, 4);
+        assert_eq!(emu.vdp().rendered_frame_count(), 1);
+        assert!(emu.cpu.cycles >= 4 * Nexel24::CYCLES_PER_FRAME);
+    }
+
+    #[test]
+    fn frame_skip_presents_fully_drawn_frames() {
+        for skip in [1, 3] {
+            let mut emu = Nexel24::new();
+            let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+            program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+            emu.load_bios(&program);
+            emu.reset_cpu();
+            emu.vdp_mut().set_display_enable(true);
+            emu.set_frame_skip(skip);
+
+            // Each step draws in the other backdrop color; a frame drawn from
+            // mid-way down would keep rows of the previous one
+            for step in 1..=3u64 {
+                let color = if step % 2 == 1 {
+                    emu.vdp_mut().set_backdrop_color(0x3F, 0x00, 0x00);
+                    0x00FF0000
+                } else {
+                    emu.vdp_mut().set_backdrop_color(0x00, 0x3F, 0x00);
+                    0x0000FF00
+                };
+                emu.step_frame();
+                assert_eq!(emu.vdp().rendered_frame_count(), step, "skip {skip}");
+                assert!(
+                    emu.vdp().framebuffer().iter().all(|&pixel| pixel == color),
+                    "skip {skip}, step {step}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pal_region_runs_longer_frames() {
+        let mut emu = Nexel24::new_with_region(Region::Pal);
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        assert_eq!(emu.region(), Region::Pal);
+        assert_eq!(emu.vdp().scanlines_per_frame(), 312);
+
+        emu.step_frame();
+        let cycles = emu.cpu.cycles;
+        assert!((368_640..368_640 + 8).contains(&cycles));
+
+        // 360 scanlines in, the beam has wrapped after PAL's 312 lines
+        assert_eq!(
+            emu.vdp().scanline(),
+            (cycles / Vdp::CYCLES_PER_SCANLINE) as u16 % 312
+        );
+        assert_eq!(emu.vdp().frame_count(), 1);
+    }
+
+    #[test]
+    fn soft_reset_keeps_memory() {
+        let mut emu = Nexel24::new();
+        let mut program = vec![0x03, 0x00, 0xFF]; // Reset vector: 0xFF0003
+        program.extend_from_slice(&[0x30, 0xFE]); // BRA -2
+        emu.load_bios(&program);
+        emu.reset_cpu();
+        emu.target_cycles_per_frame = 1_000;
+        emu.step_frame();
+
+        emu.write_memory(0x1000, 0x5A);
+        emu.vdp_mut().write_vram(0x100, 0xA5);
+        emu.vdp_mut().set_display_enable(true);
+        emu.apu_mut().write_register(0x00, 0x12);
+
+        emu.soft_reset();
+        assert_eq!(emu.read_memory(0x1000), 0x5A);
+        assert_eq!(emu.vdp().read_vram(0x100), 0xA5);
+        assert_eq!(emu.cpu.pc, 0xFF0003);
+        assert_eq!(emu.vdp().read_reg(0x0000), 0);
+        assert_eq!(emu.apu().read_register(0x00), 0);
+    }
 }
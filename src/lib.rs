@@ -18,8 +18,11 @@ pub mod bios;
 pub mod bytecode;
 pub mod core;
 pub mod cpu;
+pub mod debug;
 pub mod emulator;
+pub mod movie;
 pub mod nraw;
+pub mod timer;
 pub mod vdp;
 pub mod vlu;
 pub mod vm; // <--- added module declaration
@@ -27,10 +30,16 @@ pub mod vm; // <--- added module declaration
 pub use apu::Apu;
 pub use bios::default_bios;
 // Re-export commonly used types
-pub use core::Bus24;
-pub use cpu::Cpu;
-pub use emulator::{EmulatorStats, Nexel24};
-pub use nraw::{AsmError, AssembledProgram, assemble};
-pub use vdp::Vdp;
+pub use core::{Bus24, BusState, MemoryRegion, MemoryRegionInfo};
+pub use cpu::{Cpu, DisasmEntry, disassemble};
+pub use emulator::{
+    AudioCallback, Buttons, CheatCode, EmulatorState, EmulatorStats, Nexel24, PerfCounter,
+    PerfStats, Region, RewindBuffer,
+};
+pub use movie::{FrameInput, Movie, MovieRecorder};
+pub use nraw::{
+    AsmError, AssembledProgram, assemble, assemble_file, assemble_with_listing, disassemble_nraw,
+};
+pub use vdp::{ActiveArea, PolygonQueue, Triangle, Vdp, VdpError, Window};
 pub use vlu::Vlu;
 pub use vm::BaseplateVm;
@@ -35,7 +35,7 @@ fn main() {
 
     println!("Loading demo program into BIOS...");
     emulator.load_bios(&demo_program);
-    emulator.reset();
+    emulator.reset_cpu();
 
     println!("Initial state:");
     println!("  PC: 0x{:06X}", emulator.cpu.pc);
@@ -48,7 +48,7 @@ fn main() {
     // Execute instruction by instruction
     let mut instruction_count = 0;
     while !emulator.cpu.halted && instruction_count < 100 {
-        emulator.step();
+        emulator.step_instruction();
         instruction_count += 1;
     }
 
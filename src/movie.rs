@@ -0,0 +1,133 @@
+// Copyright (C) 2025 Dayton Fishell
+// Nexel-24 Game Console Emulator
+// This file is part of Nexel-24.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version. See the LICENSE file in the project root for details.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Input movies for deterministic replay
+//!
+//! A movie is the gamepad state for each emulated frame. Recording is
+//! started with [`crate::Nexel24::start_recording`] and replayed with
+//! [`crate::Nexel24::play_movie`].
+//!
+//! The binary format is the magic `NXMV`, a little-endian u32 frame count,
+//! then the player 1 and player 2 button words (little-endian u16) for
+//! each frame.
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+const MAGIC: &[u8; 4] = b"NXMV";
+
+/// Buttons held on both gamepads during one frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameInput {
+    pub player1: u16,
+    pub player2: u16,
+}
+
+/// Recorded input, one entry per frame
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Movie {
+    pub frames: Vec<FrameInput>,
+}
+
+impl Movie {
+    /// Encode the movie in the binary movie format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(8 + self.frames.len() * 4);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            buf.extend_from_slice(&frame.player1.to_le_bytes());
+            buf.extend_from_slice(&frame.player2.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Decode a movie written by [`Movie::to_bytes`]
+    pub fn from_bytes(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() < 8 || &buf[0..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid movie header",
+            ));
+        }
+        let count = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+        let data = &buf[8..];
+        if data.len() != count * 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Movie length does not match frame count",
+            ));
+        }
+        let frames = data
+            .chunks_exact(4)
+            .map(|chunk| FrameInput {
+                player1: u16::from_le_bytes([chunk[0], chunk[1]]),
+                player2: u16::from_le_bytes([chunk[2], chunk[3]]),
+            })
+            .collect();
+        Ok(Self { frames })
+    }
+}
+
+/// Handle for an in-progress recording
+///
+/// The emulator appends to the recording every frame until [`finish`] is
+/// called or the recorder is dropped.
+///
+/// [`finish`]: MovieRecorder::finish
+pub struct MovieRecorder {
+    pub(crate) frames: Rc<RefCell<Vec<FrameInput>>>,
+}
+
+impl MovieRecorder {
+    /// Number of frames recorded so far
+    pub fn len(&self) -> usize {
+        self.frames.borrow().len()
+    }
+
+    /// Whether no frames have been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.frames.borrow().is_empty()
+    }
+
+    /// Stop recording and return the movie
+    pub fn finish(self) -> Movie {
+        Movie {
+            frames: self.frames.take(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movie_bytes_round_trip() {
+        let movie = Movie {
+            frames: vec![
+                FrameInput {
+                    player1: 0x0001,
+                    player2: 0x0800,
+                },
+                FrameInput::default(),
+            ],
+        };
+        let bytes = movie.to_bytes();
+        assert_eq!(&bytes[..8], b"NXMV\x02\x00\x00\x00");
+        assert_eq!(Movie::from_bytes(&bytes).unwrap(), movie);
+
+        assert!(Movie::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Movie::from_bytes(b"NXMW\x00\x00\x00\x00").is_err());
+    }
+}
@@ -1,4 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::cpu::{OperandKind, decode_opcode, register_name};
 
 /// Result of running the assembler.
 pub struct AssembledProgram {
@@ -15,26 +21,45 @@ pub enum AsmError {
     InvalidNumber { line: usize, operand: String },
     LabelNotFound { name: String },
     DuplicateLabel { line: usize, name: String },
+    InvalidIndexRegister { line: usize, operand: String },
     BranchOutOfRange { label: String, offset: i32 },
+    RecursiveMacro { line: usize, name: String },
+    UnterminatedMacro { line: usize, name: String },
+    UnterminatedConditional { line: usize },
+    UnterminatedRepeat { line: usize },
+    CircularInclude { line: usize, path: String },
+    Io { path: String, message: String },
+    DivisionByZero { line: usize },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum InstructionKind {
     Nop,
     Lda,
     LdaAbs,
+    LdaAbsX,
+    LdaAbsY,
     Sta,
+    StaAbsX,
+    StaAbsY,
     Ldx,
     LdxAbs,
+    LdxAbsY,
     Stx,
+    StxAbsY,
     Ldy,
     LdyAbs,
+    LdyAbsX,
     Sty,
+    StyAbsX,
     Add,
     Sub,
     And,
     Or,
     Xor,
+    Cmp,
+    Cpx,
+    Cpy,
     Mul,
     Div,
     Mov,
@@ -43,8 +68,19 @@ enum InstructionKind {
     Bit,
     Bset,
     Bclr,
+    Lsl,
+    Lsr,
+    Rol,
+    Ror,
+    Push,
+    Pop,
+    Exg,
     Jmp,
+    JmpInd,
     Jsr,
+    JsrInd,
+    Lcall,
+    Lret,
     Rts,
     Bra,
     Beq,
@@ -55,17 +91,122 @@ enum InstructionKind {
     Bpl,
     Bvs,
     Bvc,
+    Lbra,
+    Lbeq,
+    Lbne,
     Sei,
     Cli,
+    Sec,
+    Clc,
+    Sev,
+    Clv,
     Rti,
     Wfi,
     Cop,
     Hlt,
+    // Data directives: one value of 1, 2 or 3 bytes
+    Db,
+    Dw,
+    Dl,
+    // `.align N`: 0xFF padding up to the next multiple of N
+    Align,
+    // `.fill N, value`: N copies of the value's low byte
+    Fill(u32),
+    // `.incbin`: this many bytes, held in an `Operand::Bytes`
+    Bytes(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexRegister {
+    X,
+    Y,
 }
 
 enum Operand {
     Value(u32),
     Label(String),
+    Expr(Expr),
+    Bytes(Vec<u8>),
+}
+
+/// Operand expression, evaluated once label addresses are known.
+enum Expr {
+    Number(u32),
+    Symbol(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, labels: &HashMap<String, u32>, line: usize) -> Result<u32, AsmError> {
+        match self {
+            Expr::Number(value) => Ok(*value),
+            Expr::Symbol(name) => labels
+                .get(name)
+                .copied()
+                .ok_or(AsmError::LabelNotFound { name: name.clone() }),
+            Expr::Unary(op, operand) => {
+                let value = operand.eval(labels, line)?;
+                Ok(match *op {
+                    "-" => value.wrapping_neg(),
+                    _ => !value,
+                })
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(labels, line)?, rhs.eval(labels, line)?);
+                Ok(match *op {
+                    "+" => lhs.wrapping_add(rhs),
+                    "-" => lhs.wrapping_sub(rhs),
+                    "*" => lhs.wrapping_mul(rhs),
+                    "/" => lhs
+                        .checked_div(rhs)
+                        .ok_or(AsmError::DivisionByZero { line })?,
+                    "<<" => lhs.checked_shl(rhs).unwrap_or(0),
+                    ">>" => lhs.checked_shr(rhs).unwrap_or(0),
+                    "&" => lhs & rhs,
+                    "|" => lhs | rhs,
+                    "^" => lhs ^ rhs,
+                    _ => unreachable!("unknown operator {op}"),
+                })
+            }
+        }
+    }
+
+    /// Every symbol the expression refers to.
+    fn symbols_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Expr::Number(_) => Vec::new(),
+            Expr::Symbol(name) => vec![name],
+            Expr::Unary(_, operand) => operand.symbols_mut(),
+            Expr::Binary(_, lhs, rhs) => {
+                let mut symbols = lhs.symbols_mut();
+                symbols.extend(rhs.symbols_mut());
+                symbols
+            }
+        }
+    }
+}
+
+/// A `.macro` body with its parameter names.
+struct Macro {
+    params: Vec<String>,
+    body: Vec<String>,
+}
+
+/// One line of source after include splicing.
+struct SourceLine {
+    /// 0-based index in the file the line came from, used for errors
+    line: usize,
+    /// 0-based index of the top-level line it belongs to, used for listings
+    listing: usize,
+    text: String,
+}
+
+/// A pending `.checksum16 start, end, dest` fixup.
+struct Checksum {
+    labels: [String; 3],
+    line: usize,
+    scope: usize,
 }
 
 struct RawInstruction {
@@ -73,15 +214,100 @@ struct RawInstruction {
     operand: Option<Operand>,
     address: u32,
     line: usize,
+    listing: usize,
+    /// Index of the global label scope the instruction sits in
+    scope: usize,
 }
 
 /// Assemble a small NRAW program into bytes and label positions.
+///
+/// `.include` paths are resolved against the current directory.
 pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
-    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+    include_lines(
+        source,
+        Path::new("."),
+        None,
+        &mut HashSet::new(),
+        &mut lines,
+    )?;
+    assemble_lines(lines).map(|(program, _)| program)
+}
+
+/// Assemble like [`assemble`] and also return a listing: every source line
+/// prefixed with its address and up to four of the bytes it produced, e.g.
+/// `00000 01 34 12     LDA #0x1234`. Longer lines continue on extra rows.
+pub fn assemble_with_listing(source: &str) -> Result<(AssembledProgram, String), AsmError> {
+    let mut lines = Vec::new();
+    include_lines(
+        source,
+        Path::new("."),
+        None,
+        &mut HashSet::new(),
+        &mut lines,
+    )?;
+    let (program, spans) = assemble_lines(lines)?;
+
+    // Gather the bytes emitted for each top-level line
+    let mut emitted: HashMap<usize, (u32, Vec<u8>)> = HashMap::new();
+    for (listing, address, range) in spans {
+        emitted
+            .entry(listing)
+            .or_insert_with(|| (address, Vec::new()))
+            .1
+            .extend_from_slice(&program.bytes[range]);
+    }
+
+    let mut listing = String::new();
+    for (line_idx, text) in source.lines().enumerate() {
+        let Some((address, bytes)) = emitted.get(&line_idx) else {
+            listing.push_str(format!("{:5} {:11}  {}", "", "", text).trim_end());
+            listing.push('\n');
+            continue;
+        };
+        for (row, chunk) in bytes.chunks(4).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02X}")).collect();
+            let text = if row == 0 { text } else { "" };
+            let address = address + row as u32 * 4;
+            listing.push_str(format!("{address:05X} {:11}  {text}", hex.join(" ")).trim_end());
+            listing.push('\n');
+        }
+    }
+    Ok((program, listing))
+}
+
+/// Assemble an NRAW source file, resolving `.include` paths relative to the
+/// including file.
+pub fn assemble_file(path: &Path) -> Result<AssembledProgram, AsmError> {
+    let mut lines = Vec::new();
+    include_file(path, 0, None, &mut HashSet::new(), &mut lines)?;
+    assemble_lines(lines).map(|(program, _)| program)
+}
+
+/// Bytes produced by one instruction: its listing line, address and range in
+/// the output.
+type Span = (usize, u32, Range<usize>);
+
+fn assemble_lines(lines: Vec<SourceLine>) -> Result<(AssembledProgram, Vec<Span>), AsmError> {
+    // Labels are recorded as instruction indices; addresses are assigned once
+    // branch sizes have been resolved.
+    let mut label_indices = HashMap::new();
     let mut instructions = Vec::new();
-    let mut address = 0u32;
+    // `.org` addresses keyed by the index of the next instruction
+    let mut origins = HashMap::new();
+    let mut constants = HashMap::new();
+    // Global labels in definition order; `.local` labels are stored as
+    // `global.local`. Scope 0 covers lines before the first global label.
+    let mut scopes = vec![String::new()];
+    // `.checksum16` fixups, applied once all bytes are generated
+    let mut checksums = Vec::new();
 
-    for (line_idx, line) in source.lines().enumerate() {
+    for SourceLine {
+        line: line_idx,
+        listing,
+        text: line,
+    } in expand_repeats(expand_macros(apply_conditionals(lines)?)?)?
+    {
         let stripped = line.split(';').next().unwrap_or("").trim();
         if stripped.is_empty() {
             continue;
@@ -92,13 +318,19 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             if let Some(colon) = working.find(':') {
                 let label = working[..colon].trim();
                 if !label.is_empty() {
-                    if labels.contains_key(label) {
+                    let label = if label.starts_with('.') {
+                        format!("{}{}", scopes[scopes.len() - 1], label)
+                    } else {
+                        scopes.push(label.to_string());
+                        label.to_string()
+                    };
+                    if label_indices.contains_key(&label) || constants.contains_key(&label) {
                         return Err(AsmError::DuplicateLabel {
                             line: line_idx + 1,
-                            name: label.to_string(),
+                            name: label,
                         });
                     }
-                    labels.insert(label.to_string(), address);
+                    label_indices.insert(label, instructions.len());
                 }
                 working = working[colon + 1..].trim();
                 if working.is_empty() {
@@ -113,71 +345,196 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             continue;
         }
 
-        let mut parts = working.split_whitespace();
-        let op = parts.next().unwrap();
-        let name = op.to_uppercase();
-        let operand_text = parts.next();
-        let extra = parts.next();
-        if extra.is_some() {
-            return Err(AsmError::UnexpectedOperand {
-                line: line_idx + 1,
-                instruction: name.clone(),
-            });
-        }
-
-        // Determine addressing mode for load instructions based on operand prefix
-        let kind = match name.as_str() {
-            "NOP" => InstructionKind::Nop,
-            "LDA" => {
-                if let Some(op_text) = operand_text {
-                    if op_text.starts_with('#') {
-                        InstructionKind::Lda
-                    } else {
-                        InstructionKind::LdaAbs
-                    }
-                } else {
+        // `.org addr` moves the assembly address without emitting bytes;
+        // `.equ NAME value` (or `NAME .equ value`) defines a constant
+        let words: Vec<&str> = working.split_whitespace().collect();
+        match words.as_slice() {
+            [directive, addr] if directive.eq_ignore_ascii_case(".org") => {
+                origins.insert(instructions.len(), parse_number(addr, line_idx + 1)?);
+                continue;
+            }
+            [directive, name, value] | [name, directive, value]
+                if directive.eq_ignore_ascii_case(".equ") =>
+            {
+                if label_indices.contains_key(*name) || constants.contains_key(*name) {
+                    return Err(AsmError::DuplicateLabel {
+                        line: line_idx + 1,
+                        name: name.to_string(),
+                    });
+                }
+                constants.insert(name.to_string(), parse_number(value, line_idx + 1)?);
+                continue;
+            }
+            [directive, alignment] if directive.eq_ignore_ascii_case(".align") => {
+                let alignment = parse_number(alignment, line_idx + 1)?;
+                if alignment == 0 {
+                    return Err(AsmError::InvalidNumber {
+                        line: line_idx + 1,
+                        operand: alignment.to_string(),
+                    });
+                }
+                instructions.push(RawInstruction {
+                    kind: InstructionKind::Align,
+                    operand: Some(Operand::Value(alignment)),
+                    address: 0,
+                    line: line_idx + 1,
+                    listing,
+                    scope: scopes.len() - 1,
+                });
+                continue;
+            }
+            // `.fill N[, value]` emits N copies of a byte (0xFF by default)
+            [directive, ..] if directive.eq_ignore_ascii_case(".fill") => {
+                let args = working[directive.len()..].trim();
+                let (count, value) = args.split_once(',').unwrap_or((args, "0xFF"));
+                if count.is_empty() {
                     return Err(AsmError::MissingOperand {
                         line: line_idx + 1,
-                        instruction: name.clone(),
+                        instruction: directive.to_string(),
+                    });
+                }
+                let count = parse_number(count.trim(), line_idx + 1)?;
+                if count > 1 << 24 {
+                    return Err(AsmError::InvalidNumber {
+                        line: line_idx + 1,
+                        operand: count.to_string(),
                     });
                 }
+                let value = parse_number(value.trim(), line_idx + 1)?;
+                instructions.push(RawInstruction {
+                    kind: InstructionKind::Fill(count),
+                    operand: Some(Operand::Value(value)),
+                    address: 0,
+                    line: line_idx + 1,
+                    listing,
+                    scope: scopes.len() - 1,
+                });
+                continue;
             }
-            "LDX" => {
-                if let Some(op_text) = operand_text {
-                    if op_text.starts_with('#') {
-                        InstructionKind::Ldx
-                    } else {
-                        InstructionKind::LdxAbs
-                    }
-                } else {
+            // `.checksum16 start, end, dest` patches the 16-bit sum of the
+            // bytes in start..end over the word at dest
+            [directive, ..] if directive.eq_ignore_ascii_case(".checksum16") => {
+                let names: Vec<&str> = working[directive.len()..]
+                    .split(',')
+                    .map(str::trim)
+                    .collect();
+                if names.len() > 3 {
+                    return Err(AsmError::UnexpectedOperand {
+                        line: line_idx + 1,
+                        instruction: directive.to_string(),
+                    });
+                }
+                if names.len() < 3 || names.iter().any(|name| name.is_empty()) {
                     return Err(AsmError::MissingOperand {
                         line: line_idx + 1,
-                        instruction: name.clone(),
+                        instruction: directive.to_string(),
                     });
                 }
+                checksums.push(Checksum {
+                    labels: [names[0], names[1], names[2]].map(str::to_string),
+                    line: line_idx + 1,
+                    scope: scopes.len() - 1,
+                });
+                continue;
             }
-            "LDY" => {
-                if let Some(op_text) = operand_text {
-                    if op_text.starts_with('#') {
-                        InstructionKind::Ldy
-                    } else {
-                        InstructionKind::LdyAbs
-                    }
-                } else {
+            [directive, ..] if directive.eq_ignore_ascii_case(".incbin") => {
+                let data = read_incbin(&working[directive.len()..], line_idx + 1)?;
+                if data.len() > 1 << 24 {
+                    return Err(AsmError::InvalidNumber {
+                        line: line_idx + 1,
+                        operand: data.len().to_string(),
+                    });
+                }
+                instructions.push(RawInstruction {
+                    kind: InstructionKind::Bytes(data.len() as u32),
+                    operand: Some(Operand::Bytes(data)),
+                    address: 0,
+                    line: line_idx + 1,
+                    listing,
+                    scope: scopes.len() - 1,
+                });
+                continue;
+            }
+            [directive, ..] if data_kind(directive).is_some() => {
+                let kind = data_kind(directive).unwrap();
+                let values = working[directive.len()..].trim();
+                if values.is_empty() {
                     return Err(AsmError::MissingOperand {
                         line: line_idx + 1,
-                        instruction: name.clone(),
+                        instruction: directive.to_string(),
+                    });
+                }
+                for expr in values.split(',') {
+                    instructions.push(RawInstruction {
+                        kind,
+                        operand: Some(parse_expression(expr.trim(), line_idx + 1)?),
+                        address: 0,
+                        line: line_idx + 1,
+                        listing,
+                        scope: scopes.len() - 1,
                     });
                 }
+                continue;
+            }
+            _ => {}
+        }
+
+        // Everything after the mnemonic is the operand, which may be an
+        // expression containing spaces
+        let (op, operand_text) = working
+            .split_once(char::is_whitespace)
+            .unwrap_or((working, ""));
+        let name = op.to_uppercase();
+        let operand_text = Some(operand_text.trim()).filter(|text| !text.is_empty());
+
+        // Determine addressing mode for load instructions based on operand prefix
+        let kind = match name.as_str() {
+            "NOP" => InstructionKind::Nop,
+            "LDA" | "LDX" | "LDY" | "STA" | "STX" | "STY" => {
+                let op_text = operand_text.ok_or(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: name.clone(),
+                })?;
+                let immediate = op_text.starts_with('#');
+                let index = if immediate {
+                    None
+                } else {
+                    split_indexed(op_text, line_idx + 1)?.1
+                };
+                match (name.as_str(), index) {
+                    ("LDA", None) if immediate => InstructionKind::Lda,
+                    ("LDA", None) => InstructionKind::LdaAbs,
+                    ("LDA", Some(IndexRegister::X)) => InstructionKind::LdaAbsX,
+                    ("LDA", Some(IndexRegister::Y)) => InstructionKind::LdaAbsY,
+                    ("LDX", None) if immediate => InstructionKind::Ldx,
+                    ("LDX", None) => InstructionKind::LdxAbs,
+                    ("LDX", Some(IndexRegister::Y)) => InstructionKind::LdxAbsY,
+                    ("LDY", None) if immediate => InstructionKind::Ldy,
+                    ("LDY", None) => InstructionKind::LdyAbs,
+                    ("LDY", Some(IndexRegister::X)) => InstructionKind::LdyAbsX,
+                    ("STA", None) => InstructionKind::Sta,
+                    ("STA", Some(IndexRegister::X)) => InstructionKind::StaAbsX,
+                    ("STA", Some(IndexRegister::Y)) => InstructionKind::StaAbsY,
+                    ("STX", None) => InstructionKind::Stx,
+                    ("STX", Some(IndexRegister::Y)) => InstructionKind::StxAbsY,
+                    ("STY", None) => InstructionKind::Sty,
+                    ("STY", Some(IndexRegister::X)) => InstructionKind::StyAbsX,
+                    _ => {
+                        return Err(AsmError::InvalidIndexRegister {
+                            line: line_idx + 1,
+                            operand: op_text.to_string(),
+                        });
+                    }
+                }
             }
-            "STA" => InstructionKind::Sta,
-            "STX" => InstructionKind::Stx,
-            "STY" => InstructionKind::Sty,
             "ADD" => InstructionKind::Add,
             "SUB" => InstructionKind::Sub,
             "AND" => InstructionKind::And,
             "OR" => InstructionKind::Or,
             "XOR" => InstructionKind::Xor,
+            "CMP" => InstructionKind::Cmp,
+            "CPX" => InstructionKind::Cpx,
+            "CPY" => InstructionKind::Cpy,
             "MUL" => InstructionKind::Mul,
             "DIV" => InstructionKind::Div,
             "MOV" => InstructionKind::Mov,
@@ -186,9 +543,20 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             "BIT" => InstructionKind::Bit,
             "BSET" => InstructionKind::Bset,
             "BCLR" => InstructionKind::Bclr,
+            "LSL" => InstructionKind::Lsl,
+            "LSR" => InstructionKind::Lsr,
+            "ROL" => InstructionKind::Rol,
+            "ROR" => InstructionKind::Ror,
+            "PUSH" => InstructionKind::Push,
+            "POP" => InstructionKind::Pop,
+            "EXG" => InstructionKind::Exg,
+            "JMP" if operand_text.is_some_and(|t| t.starts_with('[')) => InstructionKind::JmpInd,
             "JMP" => InstructionKind::Jmp,
+            "JSR" if operand_text.is_some_and(|t| t.starts_with('[')) => InstructionKind::JsrInd,
             "JSR" => InstructionKind::Jsr,
             "RTS" => InstructionKind::Rts,
+            "LCALL" => InstructionKind::Lcall,
+            "LRET" => InstructionKind::Lret,
             "BRA" => InstructionKind::Bra,
             "BEQ" => InstructionKind::Beq,
             "BNE" => InstructionKind::Bne,
@@ -198,8 +566,15 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             "BPL" => InstructionKind::Bpl,
             "BVS" => InstructionKind::Bvs,
             "BVC" => InstructionKind::Bvc,
+            "LBRA" => InstructionKind::Lbra,
+            "LBEQ" => InstructionKind::Lbeq,
+            "LBNE" => InstructionKind::Lbne,
             "SEI" => InstructionKind::Sei,
             "CLI" => InstructionKind::Cli,
+            "SEC" => InstructionKind::Sec,
+            "CLC" => InstructionKind::Clc,
+            "SEV" => InstructionKind::Sev,
+            "CLV" => InstructionKind::Clv,
             "RTI" => InstructionKind::Rti,
             "WFI" => InstructionKind::Wfi,
             "COP" => InstructionKind::Cop,
@@ -215,8 +590,13 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
         let operand = match kind {
             InstructionKind::Nop
             | InstructionKind::Rts
+            | InstructionKind::Lret
             | InstructionKind::Sei
             | InstructionKind::Cli
+            | InstructionKind::Sec
+            | InstructionKind::Clc
+            | InstructionKind::Sev
+            | InstructionKind::Clv
             | InstructionKind::Rti
             | InstructionKind::Wfi
             | InstructionKind::Hlt => {
@@ -228,6 +608,19 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                 }
                 None
             }
+            InstructionKind::Lsl
+            | InstructionKind::Lsr
+            | InstructionKind::Rol
+            | InstructionKind::Ror => {
+                // Accumulator is the only operand; `A` may be written explicitly
+                if operand_text.is_some_and(|t| !t.eq_ignore_ascii_case("A")) {
+                    return Err(AsmError::UnexpectedOperand {
+                        line: line_idx + 1,
+                        instruction: name.clone(),
+                    });
+                }
+                None
+            }
             InstructionKind::Lda
             | InstructionKind::Ldx
             | InstructionKind::Ldy
@@ -236,6 +629,9 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             | InstructionKind::And
             | InstructionKind::Or
             | InstructionKind::Xor
+            | InstructionKind::Cmp
+            | InstructionKind::Cpx
+            | InstructionKind::Cpy
             | InstructionKind::Mul
             | InstructionKind::Div
             | InstructionKind::Bit
@@ -252,26 +648,48 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                         operand: operand_text.to_string(),
                     });
                 }
-                let raw = operand_text[1..].trim();
-                Some(Operand::Value(parse_number(raw, line_idx + 1)?))
+                Some(parse_expression(operand_text[1..].trim(), line_idx + 1)?)
             }
             InstructionKind::LdaAbs
+            | InstructionKind::LdaAbsX
+            | InstructionKind::LdaAbsY
             | InstructionKind::LdxAbs
+            | InstructionKind::LdxAbsY
             | InstructionKind::LdyAbs
+            | InstructionKind::LdyAbsX
             | InstructionKind::Sta
+            | InstructionKind::StaAbsX
+            | InstructionKind::StaAbsY
             | InstructionKind::Stx
+            | InstructionKind::StxAbsY
             | InstructionKind::Sty
+            | InstructionKind::StyAbsX
             | InstructionKind::Jmp
-            | InstructionKind::Jsr => {
+            | InstructionKind::Jsr
+            | InstructionKind::Lcall => {
                 let operand_text = operand_text.ok_or(AsmError::MissingOperand {
                     line: line_idx + 1,
                     instruction: name.clone(),
                 })?;
-                if let Ok(value) = parse_number(operand_text, line_idx + 1) {
-                    Some(Operand::Value(value))
-                } else {
-                    Some(Operand::Label(operand_text.to_string()))
-                }
+                // Indexed forms carry the index register after a comma
+                let (operand_text, _) = split_indexed(operand_text, line_idx + 1)?;
+                Some(parse_expression(operand_text, line_idx + 1)?)
+            }
+            InstructionKind::JmpInd | InstructionKind::JsrInd => {
+                // Pointer operand is written as [addr] or [label]
+                let operand_text = operand_text.ok_or(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: name.clone(),
+                })?;
+                let pointer = operand_text
+                    .strip_prefix('[')
+                    .and_then(|t| t.strip_suffix(']'))
+                    .map(str::trim)
+                    .ok_or(AsmError::InvalidNumber {
+                        line: line_idx + 1,
+                        operand: operand_text.to_string(),
+                    })?;
+                Some(parse_expression(pointer, line_idx + 1)?)
             }
             InstructionKind::Bra 
             | InstructionKind::Beq 
@@ -281,16 +699,15 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             | InstructionKind::Bmi
             | InstructionKind::Bpl
             | InstructionKind::Bvs
-            | InstructionKind::Bvc => {
+            | InstructionKind::Bvc
+            | InstructionKind::Lbra
+            | InstructionKind::Lbeq
+            | InstructionKind::Lbne => {
                 let operand_text = operand_text.ok_or(AsmError::MissingOperand {
                     line: line_idx + 1,
                     instruction: name.clone(),
                 })?;
-                if let Ok(value) = parse_number(operand_text, line_idx + 1) {
-                    Some(Operand::Value(value))
-                } else {
-                    Some(Operand::Label(operand_text.to_string()))
-                }
+                Some(parse_expression(operand_text, line_idx + 1)?)
             }
             InstructionKind::Mov | InstructionKind::Inc | InstructionKind::Dec => {
                 // These take register names as operands, stored as values
@@ -301,21 +718,122 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                 // Store register encoding as operand value
                 Some(Operand::Value(parse_register(operand_text, line_idx + 1)?))
             }
+            InstructionKind::Push | InstructionKind::Pop => {
+                let operand_text = operand_text.ok_or(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: name.clone(),
+                })?;
+                // Any register except SP can be pushed/popped
+                let reg = parse_register(operand_text, line_idx + 1)?;
+                if reg == 3 {
+                    return Err(AsmError::InvalidNumber {
+                        line: line_idx + 1,
+                        operand: operand_text.to_string(),
+                    });
+                }
+                Some(Operand::Value(reg))
+            }
+            InstructionKind::Exg => {
+                let operand_text = operand_text.ok_or(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: name.clone(),
+                })?;
+                // Register pair is encoded in the opcode: 0=AX, 1=AY
+                let pair = match operand_text.to_uppercase().as_str() {
+                    "AX" => 0,
+                    "AY" => 1,
+                    _ => {
+                        return Err(AsmError::InvalidNumber {
+                            line: line_idx + 1,
+                            operand: operand_text.to_string(),
+                        });
+                    }
+                };
+                Some(Operand::Value(pair))
+            }
+            InstructionKind::Db
+            | InstructionKind::Dw
+            | InstructionKind::Dl
+            | InstructionKind::Align
+            | InstructionKind::Fill(_)
+            | InstructionKind::Bytes(_) => {
+                unreachable!("data directives are parsed with the other directives")
+            }
         };
 
-        let inst_length = instruction_length(&kind);
         instructions.push(RawInstruction {
             kind,
             operand,
-            address,
+            address: 0,
             line: line_idx + 1,
+            listing,
+            scope: scopes.len() - 1,
         });
-        address = address.wrapping_add(inst_length);
     }
 
-    let mut bytes = Vec::with_capacity(address as usize);
+    for inst in &mut instructions {
+        let symbols = match &mut inst.operand {
+            Some(Operand::Label(name)) => vec![name],
+            Some(Operand::Expr(expr)) => expr.symbols_mut(),
+            _ => Vec::new(),
+        };
+        for name in symbols {
+            if let Some(qualified) = resolve_local(name, inst.scope, &scopes, &label_indices) {
+                *name = qualified;
+            }
+        }
+    }
+
+    // Start every BRA/BEQ/BNE in its short form and promote the ones whose
+    // target is out of 8-bit range. Promotion only grows the program, so this
+    // settles after a few passes.
+    let labels = loop {
+        let labels = layout(&mut instructions, &label_indices, &origins, &constants);
+        let mut promoted = false;
+        for inst in &mut instructions {
+            let long_kind = match inst.kind {
+                InstructionKind::Bra => InstructionKind::Lbra,
+                InstructionKind::Beq => InstructionKind::Lbeq,
+                InstructionKind::Bne => InstructionKind::Lbne,
+                _ => continue,
+            };
+            if let Ok(target) = operand_value(inst, &labels) {
+                let offset = target as i32 - (inst.address + instruction_length(&inst.kind)) as i32;
+                if !(-128..=127).contains(&offset) {
+                    inst.kind = long_kind;
+                    promoted = true;
+                }
+            }
+        }
+        if !promoted {
+            break labels;
+        }
+    };
+
+    let mut bytes = Vec::new();
+    let mut spans = Vec::new();
     for inst in instructions {
+        let start = bytes.len();
         match inst.kind {
+            InstructionKind::Db | InstructionKind::Dw | InstructionKind::Dl => {
+                let value = operand_value(&inst, &labels)?;
+                let width = instruction_length(&inst.kind) as usize;
+                bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+            }
+            InstructionKind::Align => {
+                let padding = align_padding(inst.address, operand_value(&inst, &labels)?);
+                bytes.resize(bytes.len() + padding as usize, 0xFF);
+            }
+            InstructionKind::Fill(count) => {
+                let value = operand_value(&inst, &labels)?;
+                bytes.resize(bytes.len() + count as usize, value as u8);
+            }
+            InstructionKind::Bytes(_) => {
+                let Some(Operand::Bytes(data)) = &inst.operand else {
+                    unreachable!(".incbin always carries its bytes");
+                };
+                bytes.extend_from_slice(data);
+            }
             InstructionKind::Nop => {
                 bytes.push(0x00);
             }
@@ -325,18 +843,59 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
             InstructionKind::Rts => {
                 bytes.push(0x22);
             }
+            InstructionKind::Lret => {
+                bytes.push(0x26);
+            }
             InstructionKind::Sei => {
                 bytes.push(0x40);
             }
             InstructionKind::Cli => {
                 bytes.push(0x41);
             }
+            InstructionKind::Sec => {
+                bytes.push(0x82);
+            }
+            InstructionKind::Clc => {
+                bytes.push(0x83);
+            }
+            InstructionKind::Sev => {
+                bytes.push(0x84);
+            }
+            InstructionKind::Clv => {
+                bytes.push(0x85);
+            }
             InstructionKind::Rti => {
                 bytes.push(0x42);
             }
             InstructionKind::Wfi => {
                 bytes.push(0x43);
             }
+            InstructionKind::Lsl => {
+                bytes.push(0x60);
+            }
+            InstructionKind::Lsr => {
+                bytes.push(0x61);
+            }
+            InstructionKind::Rol => {
+                bytes.push(0x62);
+            }
+            InstructionKind::Ror => {
+                bytes.push(0x63);
+            }
+            InstructionKind::Push | InstructionKind::Pop => {
+                let pop = matches!(inst.kind, InstructionKind::Pop);
+                let opcode = match operand_value(&inst, &labels)? as u8 {
+                    // A, X, Y: 0x90 + 2*reg (+1 for POP)
+                    reg @ 0..=2 => 0x90 + reg * 2 + pop as u8,
+                    // R0-R7: 0x70 + n for PUSH, 0x78 + n for POP
+                    reg => 0x70 + (reg - 4) + if pop { 8 } else { 0 },
+                };
+                bytes.push(opcode);
+            }
+            InstructionKind::Exg => {
+                let pair = operand_value(&inst, &labels)? as u8;
+                bytes.push(0x80 + pair);
+            }
             InstructionKind::Lda => {
                 bytes.push(0x01);
                 let value = operand_value(&inst, &labels)? as u16;
@@ -392,6 +951,21 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                 let value = operand_value(&inst, &labels)? as u16;
                 bytes.extend_from_slice(&value.to_le_bytes());
             }
+            InstructionKind::Cmp => {
+                bytes.push(0x50);
+                let value = operand_value(&inst, &labels)? as u16;
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            InstructionKind::Cpx => {
+                bytes.push(0x51);
+                let value = operand_value(&inst, &labels)? as u16;
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            InstructionKind::Cpy => {
+                bytes.push(0x52);
+                let value = operand_value(&inst, &labels)? as u16;
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
             InstructionKind::Mul => {
                 bytes.push(0x15);
                 let value = operand_value(&inst, &labels)? as u16;
@@ -452,6 +1026,29 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                 let addr = operand_address(&inst, &labels)?;
                 bytes.extend_from_slice(&addr.to_le_bytes()[..3]);
             }
+            InstructionKind::LdaAbsX
+            | InstructionKind::LdaAbsY
+            | InstructionKind::StaAbsX
+            | InstructionKind::StaAbsY
+            | InstructionKind::LdxAbsY
+            | InstructionKind::LdyAbsX
+            | InstructionKind::StxAbsY
+            | InstructionKind::StyAbsX => {
+                let opcode = match inst.kind {
+                    InstructionKind::LdaAbsX => 0x0A,
+                    InstructionKind::LdaAbsY => 0x0B,
+                    InstructionKind::StaAbsX => 0x0C,
+                    InstructionKind::StaAbsY => 0x0D,
+                    InstructionKind::LdxAbsY => 0x0E,
+                    InstructionKind::LdyAbsX => 0x0F,
+                    InstructionKind::StxAbsY => 0x1D,
+                    InstructionKind::StyAbsX => 0x1E,
+                    _ => unreachable!(),
+                };
+                bytes.push(opcode);
+                let addr = operand_address(&inst, &labels)?;
+                bytes.extend_from_slice(&addr.to_le_bytes()[..3]);
+            }
             InstructionKind::Jmp => {
                 bytes.push(0x20);
                 let addr = operand_address(&inst, &labels)?;
@@ -462,6 +1059,21 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                 let addr = operand_address(&inst, &labels)?;
                 bytes.extend_from_slice(&addr.to_le_bytes()[..3]);
             }
+            InstructionKind::Lcall => {
+                bytes.push(0x25);
+                let addr = operand_address(&inst, &labels)?;
+                bytes.extend_from_slice(&addr.to_le_bytes()[..3]);
+            }
+            InstructionKind::JmpInd => {
+                bytes.push(0x23);
+                let addr = operand_address(&inst, &labels)?;
+                bytes.extend_from_slice(&addr.to_le_bytes()[..3]);
+            }
+            InstructionKind::JsrInd => {
+                bytes.push(0x24);
+                let addr = operand_address(&inst, &labels)?;
+                bytes.extend_from_slice(&addr.to_le_bytes()[..3]);
+            }
             InstructionKind::Bra 
             | InstructionKind::Beq 
             | InstructionKind::Bne
@@ -487,34 +1099,584 @@ pub fn assemble(source: &str) -> Result<AssembledProgram, AsmError> {
                 let offset = branch_offset(&inst, &labels)?;
                 bytes.push(offset as u8);
             }
+            InstructionKind::Lbra | InstructionKind::Lbeq | InstructionKind::Lbne => {
+                let opcode = match inst.kind {
+                    InstructionKind::Lbra => 0x39,
+                    InstructionKind::Lbeq => 0x3A,
+                    InstructionKind::Lbne => 0x3B,
+                    _ => unreachable!(),
+                };
+                bytes.push(opcode);
+                let offset = long_branch_offset(&inst, &labels)?;
+                bytes.extend_from_slice(&offset.to_le_bytes());
+            }
+        }
+        spans.push((inst.listing, inst.address, start..bytes.len()));
+    }
+
+    for checksum in checksums {
+        // Labels map to byte offsets through the instruction they precede
+        let offset = |name: &String| {
+            let name = resolve_local(name, checksum.scope, &scopes, &label_indices)
+                .unwrap_or(name.clone());
+            let index = *label_indices
+                .get(&name)
+                .ok_or(AsmError::LabelNotFound { name })?;
+            Ok(spans.get(index).map_or(bytes.len(), |span| span.2.start))
+        };
+        let [start, end, dest] = [
+            offset(&checksum.labels[0])?,
+            offset(&checksum.labels[1])?,
+            offset(&checksum.labels[2])?,
+        ];
+        if start > end || dest + 2 > bytes.len() {
+            return Err(AsmError::InvalidNumber {
+                line: checksum.line,
+                operand: checksum.labels.join(", "),
+            });
         }
+        let sum = bytes[start..end]
+            .iter()
+            .fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+        bytes[dest..dest + 2].copy_from_slice(&sum.to_le_bytes());
     }
 
-    Ok(AssembledProgram { bytes, labels })
+    Ok((AssembledProgram { bytes, labels }, spans))
 }
 
-fn parse_number(token: &str, line: usize) -> Result<u32, AsmError> {
-    if let Some(stripped) = token.strip_prefix("0x") {
-        u32::from_str_radix(stripped, 16).map_err(|_| AsmError::InvalidNumber {
-            line,
-            operand: token.to_string(),
-        })
-    } else if token.starts_with('$') {
-        u32::from_str_radix(&token[1..], 16).map_err(|_| AsmError::InvalidNumber {
+/// Splice `.include "file"` directives into the line stream.
+///
+/// Lines are returned with comments stripped. `parent` is the top-level
+/// `.include` line for included files, and `active` holds the files currently
+/// being read so circular includes are caught.
+fn include_lines(
+    source: &str,
+    dir: &Path,
+    parent: Option<usize>,
+    active: &mut HashSet<PathBuf>,
+    out: &mut Vec<SourceLine>,
+) -> Result<(), AsmError> {
+    for (line_idx, line) in source.lines().enumerate() {
+        let stripped = line.split(';').next().unwrap_or("").trim();
+        let (directive, rest) = stripped
+            .split_once(char::is_whitespace)
+            .unwrap_or((stripped, ""));
+        let listing = parent.unwrap_or(line_idx);
+        if directive.eq_ignore_ascii_case(".incbin") {
+            // Resolve the path now; the bytes are read during assembly
+            let (name, range) = split_quoted(rest, line_idx + 1)?;
+            out.push(SourceLine {
+                line: line_idx,
+                listing,
+                text: format!(".incbin \"{}\"{}", dir.join(name).display(), range),
+            });
+            continue;
+        }
+        if !directive.eq_ignore_ascii_case(".include") {
+            out.push(SourceLine {
+                line: line_idx,
+                listing,
+                text: stripped.to_string(),
+            });
+            continue;
+        }
+        let (name, extra) = split_quoted(rest, line_idx + 1)?;
+        if !extra.is_empty() {
+            return Err(AsmError::InvalidNumber {
+                line: line_idx + 1,
+                operand: rest.trim().to_string(),
+            });
+        }
+        include_file(&dir.join(name), line_idx + 1, Some(listing), active, out)?;
+    }
+    Ok(())
+}
+
+/// Split `"name" rest` into the quoted name and whatever follows it.
+fn split_quoted(text: &str, line: usize) -> Result<(&str, &str), AsmError> {
+    let text = text.trim();
+    text.strip_prefix('"')
+        .and_then(|quoted| quoted.split_once('"'))
+        .map(|(name, rest)| (name, rest.trim()))
+        .ok_or(AsmError::InvalidNumber {
             line,
-            operand: token.to_string(),
+            operand: text.to_string(),
         })
-    } else {
-        token.parse::<u32>().map_err(|_| AsmError::InvalidNumber {
+}
+
+/// Read the bytes for `.incbin "path"[, offset[, length]]`.
+fn read_incbin(args: &str, line: usize) -> Result<Vec<u8>, AsmError> {
+    let (path, range) = split_quoted(args, line)?;
+    let mut data = fs::read(path).map_err(|err| AsmError::Io {
+        path: path.to_string(),
+        message: err.to_string(),
+    })?;
+    let invalid = || AsmError::InvalidNumber {
+        line,
+        operand: range.to_string(),
+    };
+    if range.is_empty() {
+        return Ok(data);
+    }
+    let mut bounds = range.strip_prefix(',').ok_or_else(invalid)?.split(',');
+    let offset = parse_number(bounds.next().unwrap_or("").trim(), line)? as usize;
+    let length = match bounds.next() {
+        Some(length) => parse_number(length.trim(), line)? as usize,
+        None => data.len().saturating_sub(offset),
+    };
+    if bounds.next().is_some() || offset + length > data.len() {
+        return Err(invalid());
+    }
+    data.truncate(offset + length);
+    data.drain(..offset);
+    Ok(data)
+}
+
+/// Read and splice one file; `line` is the `.include` that asked for it.
+fn include_file(
+    path: &Path,
+    line: usize,
+    parent: Option<usize>,
+    active: &mut HashSet<PathBuf>,
+    out: &mut Vec<SourceLine>,
+) -> Result<(), AsmError> {
+    let io_error = |err: std::io::Error| AsmError::Io {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    };
+    let canonical = path.canonicalize().map_err(io_error)?;
+    if !active.insert(canonical.clone()) {
+        return Err(AsmError::CircularInclude {
             line,
-            operand: token.to_string(),
-        })
+            path: path.display().to_string(),
+        });
     }
+    let source = fs::read_to_string(&canonical).map_err(io_error)?;
+    let dir = canonical.parent().unwrap_or(Path::new("."));
+    include_lines(&source, dir, parent, active, out)?;
+    active.remove(&canonical);
+    Ok(())
 }
 
-fn parse_register(token: &str, line: usize) -> Result<u32, AsmError> {
-    let upper = token.to_uppercase();
-    match upper.as_str() {
+/// Apply `.define`, `.ifdef` / `.ifndef`, `.else` and `.endif`, dropping the
+/// lines of branches that are not taken.
+fn apply_conditionals(source: Vec<SourceLine>) -> Result<Vec<SourceLine>, AsmError> {
+    let mut defined = HashSet::new();
+    // One entry per open block: whether its current branch is taken and the
+    // line it was opened on
+    let mut stack: Vec<(bool, usize)> = Vec::new();
+    let mut lines = Vec::new();
+
+    for source_line in source {
+        let line_idx = source_line.line;
+        let (directive, rest) = source_line
+            .text
+            .split_once(char::is_whitespace)
+            .unwrap_or((source_line.text.as_str(), ""));
+        let symbol = rest.trim();
+        let active = stack.iter().all(|(taken, _)| *taken);
+        match directive.to_lowercase().as_str() {
+            name @ (".ifdef" | ".ifndef" | ".define") if symbol.is_empty() => {
+                return Err(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: name.to_string(),
+                });
+            }
+            name @ (".ifdef" | ".ifndef") => {
+                let taken = defined.contains(symbol) == (name == ".ifdef");
+                stack.push((taken, line_idx));
+            }
+            ".else" | ".endif" if stack.is_empty() => {
+                return Err(AsmError::UnknownInstruction {
+                    line: line_idx + 1,
+                    token: directive.to_string(),
+                });
+            }
+            ".else" => {
+                let (taken, _) = stack.last_mut().unwrap();
+                *taken = !*taken;
+            }
+            ".endif" => {
+                stack.pop();
+            }
+            ".define" if active => {
+                defined.insert(symbol.to_string());
+            }
+            _ if active => lines.push(source_line),
+            _ => {}
+        }
+    }
+    if let Some((_, line_idx)) = stack.pop() {
+        return Err(AsmError::UnterminatedConditional { line: line_idx + 1 });
+    }
+    Ok(lines)
+}
+
+/// Unroll `.repeat N` ... `.endr` blocks, replacing `\@` in the body with
+/// the 0-based iteration. Inner blocks are unrolled first so each keeps its
+/// own counter.
+fn expand_repeats(source: Vec<SourceLine>) -> Result<Vec<SourceLine>, AsmError> {
+    // Open blocks: repeat count, opening line and body collected so far
+    let mut stack: Vec<(u32, usize, Vec<SourceLine>)> = Vec::new();
+    let mut lines = Vec::new();
+
+    for source_line in source {
+        let line_idx = source_line.line;
+        let (directive, rest) = source_line
+            .text
+            .split_once(char::is_whitespace)
+            .unwrap_or((source_line.text.as_str(), ""));
+        match directive.to_lowercase().as_str() {
+            ".repeat" if rest.trim().is_empty() => {
+                return Err(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: directive.to_string(),
+                });
+            }
+            ".repeat" => {
+                let count = parse_number(rest.trim(), line_idx + 1)?;
+                if count > 1 << 24 {
+                    return Err(AsmError::InvalidNumber {
+                        line: line_idx + 1,
+                        operand: count.to_string(),
+                    });
+                }
+                stack.push((count, line_idx, Vec::new()));
+            }
+            ".endr" => {
+                let (count, repeat_idx, body) =
+                    stack.pop().ok_or(AsmError::UnknownInstruction {
+                        line: line_idx + 1,
+                        token: directive.to_string(),
+                    })?;
+                let out = stack.last_mut().map_or(&mut lines, |(_, _, body)| body);
+                // Bound the unrolled source like `.fill` bounds its bytes,
+                // before allocating it
+                if out.len() as u64 + count as u64 * body.len() as u64 > 1 << 24 {
+                    return Err(AsmError::InvalidNumber {
+                        line: repeat_idx + 1,
+                        operand: count.to_string(),
+                    });
+                }
+                for iteration in 0..count {
+                    out.extend(body.iter().map(|body_line| SourceLine {
+                        text: body_line.text.replace("\\@", &iteration.to_string()),
+                        ..*body_line
+                    }));
+                }
+            }
+            _ => match stack.last_mut() {
+                Some((_, _, body)) => body.push(source_line),
+                None => lines.push(source_line),
+            },
+        }
+    }
+    if let Some((_, line_idx, _)) = stack.pop() {
+        return Err(AsmError::UnterminatedRepeat { line: line_idx + 1 });
+    }
+    Ok(lines)
+}
+
+/// Collect `.macro NAME params` ... `.endm` definitions, then expand every
+/// invocation inline. Expanded lines take the position of the invocation so
+/// errors and listings point at it.
+fn expand_macros(source: Vec<SourceLine>) -> Result<Vec<SourceLine>, AsmError> {
+    let mut macros = HashMap::new();
+    let mut lines = Vec::new();
+    let mut open: Option<(usize, String, Macro)> = None;
+
+    for source_line in source {
+        let line_idx = source_line.line;
+        let stripped = &source_line.text;
+        let (directive, rest) = stripped
+            .split_once(char::is_whitespace)
+            .unwrap_or((stripped.as_str(), ""));
+        match directive.to_lowercase().as_str() {
+            ".macro" => {
+                let mut words = split_args(rest).into_iter();
+                let name = words.next().ok_or(AsmError::MissingOperand {
+                    line: line_idx + 1,
+                    instruction: ".macro".to_string(),
+                })?;
+                let params = words.collect();
+                let body = Vec::new();
+                open = Some((line_idx, name.to_uppercase(), Macro { params, body }));
+            }
+            ".endm" => {
+                let (_, name, mac) = open.take().ok_or(AsmError::UnknownInstruction {
+                    line: line_idx + 1,
+                    token: directive.to_string(),
+                })?;
+                macros.insert(name, mac);
+            }
+            _ => match open.as_mut() {
+                Some((_, _, mac)) => mac.body.push(source_line.text),
+                None => lines.push(source_line),
+            },
+        }
+    }
+    if let Some((line_idx, name, _)) = open {
+        return Err(AsmError::UnterminatedMacro {
+            line: line_idx + 1,
+            name,
+        });
+    }
+
+    let mut expanded = Vec::new();
+    for line in lines {
+        expand_line(&macros, line, &mut Vec::new(), &mut expanded)?;
+    }
+    Ok(expanded)
+}
+
+fn expand_line(
+    macros: &HashMap<String, Macro>,
+    source_line: SourceLine,
+    active: &mut Vec<String>,
+    out: &mut Vec<SourceLine>,
+) -> Result<(), AsmError> {
+    let line_idx = source_line.line;
+    let text = &source_line.text;
+    // Labels ahead of a macro call stay on their own line
+    let body_start = text.rfind(':').map_or(0, |colon| colon + 1);
+    let (labels, statement) = text.split_at(body_start);
+    let statement = statement.trim();
+    let (name, args) = statement
+        .split_once(char::is_whitespace)
+        .unwrap_or((statement, ""));
+    let name = name.to_uppercase();
+    let Some(mac) = macros.get(&name) else {
+        out.push(source_line);
+        return Ok(());
+    };
+
+    if active.contains(&name) {
+        return Err(AsmError::RecursiveMacro {
+            line: line_idx + 1,
+            name,
+        });
+    }
+    let args = split_args(args);
+    if args.len() < mac.params.len() {
+        return Err(AsmError::MissingOperand {
+            line: line_idx + 1,
+            instruction: name,
+        });
+    }
+    if args.len() > mac.params.len() {
+        return Err(AsmError::UnexpectedOperand {
+            line: line_idx + 1,
+            instruction: name,
+        });
+    }
+    if !labels.is_empty() {
+        out.push(SourceLine {
+            text: labels.to_string(),
+            ..source_line
+        });
+    }
+
+    active.push(name);
+    for body_line in &mac.body {
+        let substituted = substitute_params(body_line, &mac.params, &args);
+        let body_line = SourceLine {
+            line: line_idx,
+            listing: source_line.listing,
+            text: substituted,
+        };
+        expand_line(macros, body_line, active, out)?;
+    }
+    active.pop();
+    Ok(())
+}
+
+/// Split macro parameters or arguments on commas and whitespace.
+fn split_args(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Replace whole-word occurrences of each parameter with its argument.
+fn substitute_params(line: &str, params: &[String], args: &[String]) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, result: &mut String| {
+        match params.iter().position(|param| param == word) {
+            Some(index) => result.push_str(&args[index]),
+            None => result.push_str(word),
+        }
+        word.clear();
+    };
+    for c in line.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut result);
+            result.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+    result
+}
+
+fn parse_number(token: &str, line: usize) -> Result<u32, AsmError> {
+    if let Some(stripped) = token.strip_prefix("0x") {
+        u32::from_str_radix(stripped, 16).map_err(|_| AsmError::InvalidNumber {
+            line,
+            operand: token.to_string(),
+        })
+    } else if let Some(stripped) = token.strip_prefix('$') {
+        u32::from_str_radix(stripped, 16).map_err(|_| AsmError::InvalidNumber {
+            line,
+            operand: token.to_string(),
+        })
+    } else {
+        token.parse::<u32>().map_err(|_| AsmError::InvalidNumber {
+            line,
+            operand: token.to_string(),
+        })
+    }
+}
+
+/// Map `.db` / `.dw` / `.dl` to their instruction kind.
+fn data_kind(directive: &str) -> Option<InstructionKind> {
+    match directive.to_lowercase().as_str() {
+        ".db" => Some(InstructionKind::Db),
+        ".dw" => Some(InstructionKind::Dw),
+        ".dl" => Some(InstructionKind::Dl),
+        _ => None,
+    }
+}
+
+/// Parse an operand expression. Plain numbers and symbols come back as
+/// [`Operand::Value`] / [`Operand::Label`]; anything else as an [`Expr`] over
+/// `+ - * / << >> & | ^ ~` and parentheses, with C precedence.
+fn parse_expression(expr: &str, line: usize) -> Result<Operand, AsmError> {
+    let invalid = || AsmError::InvalidNumber {
+        line,
+        operand: expr.to_string(),
+    };
+    let tokens = tokenize_expression(expr).ok_or_else(invalid)?;
+    let mut parser = ExprParser { tokens, pos: 0 };
+    let parsed = parser.binary(0);
+    if parser.pos != parser.tokens.len() {
+        return Err(invalid());
+    }
+    match parsed.ok_or_else(invalid)? {
+        Expr::Number(value) => Ok(Operand::Value(value)),
+        Expr::Symbol(name) => Ok(Operand::Label(name)),
+        expr => Ok(Operand::Expr(expr)),
+    }
+}
+
+/// Split an expression into numbers, symbols and operators.
+fn tokenize_expression(expr: &str) -> Option<Vec<&str>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while !rest.is_empty() {
+        let len = if rest.starts_with("<<") || rest.starts_with(">>") {
+            2
+        } else if rest.starts_with(['+', '-', '*', '/', '&', '|', '^', '~', '(', ')']) {
+            1
+        } else {
+            let word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '$');
+            rest.find(|c: char| !word(c)).unwrap_or(rest.len())
+        };
+        if len == 0 {
+            return None;
+        }
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    Some(tokens)
+}
+
+/// Recursive-descent parser over [`tokenize_expression`] output.
+struct ExprParser<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    /// Binary operators from loosest to tightest binding.
+    const PRECEDENCE: [&'static [&'static str]; 6] = [
+        &["|"],
+        &["^"],
+        &["&"],
+        &["<<", ">>"],
+        &["+", "-"],
+        &["*", "/"],
+    ];
+
+    fn binary(&mut self, level: usize) -> Option<Expr> {
+        let Some(ops) = Self::PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(&op) = ops.iter().find(|&&op| self.peek() == Some(op)) {
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        let token = self.peek()?;
+        self.pos += 1;
+        match token {
+            "-" => Some(Expr::Unary("-", Box::new(self.unary()?))),
+            "~" => Some(Expr::Unary("~", Box::new(self.unary()?))),
+            "(" => {
+                let inner = self.binary(0)?;
+                (self.peek() == Some(")")).then(|| self.pos += 1)?;
+                Some(inner)
+            }
+            _ if is_symbol(token) => Some(Expr::Symbol(token.to_string())),
+            _ => parse_number(token, 0).ok().map(Expr::Number),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+}
+
+/// Find the definition a `.local` reference means: the one in the
+/// reference's own scope, otherwise the one in the closest other scope.
+fn resolve_local(
+    name: &str,
+    scope: usize,
+    scopes: &[String],
+    label_indices: &HashMap<String, usize>,
+) -> Option<String> {
+    if !name.starts_with('.') {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..scopes.len()).collect();
+    order.sort_by_key(|&other| (other.abs_diff(scope), other > scope));
+    order
+        .into_iter()
+        .map(|other| format!("{}{}", scopes[other], name))
+        .find(|qualified| label_indices.contains_key(qualified))
+}
+
+/// Whether `token` can name a label or constant. A leading `.` marks a
+/// local label.
+fn is_symbol(token: &str) -> bool {
+    let token = token.strip_prefix('.').unwrap_or(token);
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && token.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn parse_register(token: &str, line: usize) -> Result<u32, AsmError> {
+    let upper = token.to_uppercase();
+    match upper.as_str() {
         "A" => Ok(0),
         "X" => Ok(1),
         "Y" => Ok(2),
@@ -534,15 +1696,54 @@ fn parse_register(token: &str, line: usize) -> Result<u32, AsmError> {
     }
 }
 
+/// Split an `addr,X` / `addr,Y` operand into its base and index register.
+fn split_indexed(token: &str, line: usize) -> Result<(&str, Option<IndexRegister>), AsmError> {
+    let Some((base, reg)) = token.split_once(',') else {
+        return Ok((token, None));
+    };
+    let index = match reg.trim().to_uppercase().as_str() {
+        "X" => IndexRegister::X,
+        "Y" => IndexRegister::Y,
+        _ => {
+            return Err(AsmError::InvalidIndexRegister {
+                line,
+                operand: token.to_string(),
+            });
+        }
+    };
+    Ok((base.trim(), Some(index)))
+}
+
 fn instruction_length(kind: &InstructionKind) -> u32 {
     match kind {
         InstructionKind::Nop
         | InstructionKind::Rts
+        | InstructionKind::Lret
         | InstructionKind::Sei
         | InstructionKind::Cli
+        | InstructionKind::Sec
+        | InstructionKind::Clc
+        | InstructionKind::Sev
+        | InstructionKind::Clv
         | InstructionKind::Rti
         | InstructionKind::Wfi
         | InstructionKind::Hlt => 1,
+        // Data directives emit only their value
+        InstructionKind::Db => 1,
+        InstructionKind::Dw => 2,
+        InstructionKind::Dl => 3,
+        // Depends on the address; see `align_padding`
+        InstructionKind::Align => 0,
+        InstructionKind::Fill(count) | InstructionKind::Bytes(count) => *count,
+        // Accumulator shifts/rotates: opcode only
+        InstructionKind::Lsl
+        | InstructionKind::Lsr
+        | InstructionKind::Rol
+        | InstructionKind::Ror => 1,
+        // Register stack operations: register index is encoded in the opcode
+        InstructionKind::Push | InstructionKind::Pop => 1,
+        // Register exchange: register pair is encoded in the opcode
+        InstructionKind::Exg => 1,
         // Branch instructions: 1 byte opcode + 1 byte signed offset
         InstructionKind::Bra
         | InstructionKind::Beq
@@ -553,6 +1754,8 @@ fn instruction_length(kind: &InstructionKind) -> u32 {
         | InstructionKind::Bpl
         | InstructionKind::Bvs
         | InstructionKind::Bvc => 2,
+        // Long branches: 1 byte opcode + 2 bytes signed offset
+        InstructionKind::Lbra | InstructionKind::Lbeq | InstructionKind::Lbne => 3,
         // Register operations: 1 byte opcode + 1 byte register spec
         InstructionKind::Inc
         | InstructionKind::Dec
@@ -568,6 +1771,9 @@ fn instruction_length(kind: &InstructionKind) -> u32 {
         | InstructionKind::And
         | InstructionKind::Or
         | InstructionKind::Xor
+        | InstructionKind::Cmp
+        | InstructionKind::Cpx
+        | InstructionKind::Cpy
         | InstructionKind::Mul
         | InstructionKind::Div
         | InstructionKind::Bit
@@ -580,8 +1786,21 @@ fn instruction_length(kind: &InstructionKind) -> u32 {
         | InstructionKind::Sta
         | InstructionKind::Stx
         | InstructionKind::Sty
+        // Indexed absolute addressing uses the same encoding as absolute
+        | InstructionKind::LdaAbsX
+        | InstructionKind::LdaAbsY
+        | InstructionKind::StaAbsX
+        | InstructionKind::StaAbsY
+        | InstructionKind::LdxAbsY
+        | InstructionKind::LdyAbsX
+        | InstructionKind::StxAbsY
+        | InstructionKind::StyAbsX
         | InstructionKind::Jmp
-        | InstructionKind::Jsr => 4,
+        | InstructionKind::Jsr
+        | InstructionKind::Lcall
+        // Indirect jumps: 1 byte opcode + 3 bytes for 24-bit pointer address
+        | InstructionKind::JmpInd
+        | InstructionKind::JsrInd => 4,
     }
 }
 
@@ -592,7 +1811,9 @@ fn operand_value(inst: &RawInstruction, labels: &HashMap<String, u32>) -> Result
             .get(lbl)
             .copied()
             .ok_or(AsmError::LabelNotFound { name: lbl.clone() }),
-        None => Err(AsmError::MissingOperand {
+        Some(Operand::Expr(ref expr)) => expr.eval(labels, inst.line),
+        // Only `.incbin` carries bytes, and it never needs a value
+        Some(Operand::Bytes(_)) | None => Err(AsmError::MissingOperand {
             line: inst.line,
             instruction: format!("{:?}", inst.kind),
         }),
@@ -610,11 +1831,48 @@ fn operand_address(inst: &RawInstruction, labels: &HashMap<String, u32>) -> Resu
     Ok(value)
 }
 
+/// Assign instruction addresses and resolve label indices to addresses.
+///
+/// The returned map also holds the `.equ` constants so operands can refer
+/// to either.
+fn layout(
+    instructions: &mut [RawInstruction],
+    label_indices: &HashMap<String, usize>,
+    origins: &HashMap<usize, u32>,
+    constants: &HashMap<String, u32>,
+) -> HashMap<String, u32> {
+    let mut starts = Vec::with_capacity(instructions.len() + 1);
+    let mut address = 0u32;
+    for (index, inst) in instructions.iter_mut().enumerate() {
+        address = origins.get(&index).copied().unwrap_or(address);
+        inst.address = address;
+        starts.push(address);
+        let length = match (inst.kind, &inst.operand) {
+            (InstructionKind::Align, Some(Operand::Value(alignment))) => {
+                align_padding(address, *alignment)
+            }
+            _ => instruction_length(&inst.kind),
+        };
+        address = address.wrapping_add(length);
+    }
+    starts.push(origins.get(&instructions.len()).copied().unwrap_or(address));
+    label_indices
+        .iter()
+        .map(|(name, &index)| (name.clone(), starts[index]))
+        .chain(constants.iter().map(|(name, &value)| (name.clone(), value)))
+        .collect()
+}
+
+/// Bytes needed to bring `address` up to a multiple of `alignment`.
+fn align_padding(address: u32, alignment: u32) -> u32 {
+    (alignment - address % alignment) % alignment
+}
+
 fn branch_offset(inst: &RawInstruction, labels: &HashMap<String, u32>) -> Result<i8, AsmError> {
     let target = operand_value(inst, labels)?;
     let pc_after_operand = inst.address + instruction_length(&inst.kind);
     let offset = target as i32 - pc_after_operand as i32;
-    if offset < -128 || offset > 127 {
+    if !(-128..=127).contains(&offset) {
         return Err(AsmError::BranchOutOfRange {
             label: match inst.operand {
                 Some(Operand::Label(ref name)) => name.clone(),
@@ -626,6 +1884,120 @@ fn branch_offset(inst: &RawInstruction, labels: &HashMap<String, u32>) -> Result
     Ok(offset as i8)
 }
 
+fn long_branch_offset(
+    inst: &RawInstruction,
+    labels: &HashMap<String, u32>,
+) -> Result<i16, AsmError> {
+    let target = operand_value(inst, labels)?;
+    let pc_after_operand = inst.address + instruction_length(&inst.kind);
+    let offset = target as i32 - pc_after_operand as i32;
+    if !(-32768..=32767).contains(&offset) {
+        return Err(AsmError::BranchOutOfRange {
+            label: match inst.operand {
+                Some(Operand::Label(ref name)) => name.clone(),
+                _ => format!("0x{:02X}", target),
+            },
+            offset,
+        });
+    }
+    Ok(offset as i16)
+}
+
+/// Turn raw bytes back into NRAW source that reassembles to the same bytes.
+///
+/// Branch targets inside the block get `label_xxxx` labels; anything the
+/// assembler can't express (unassigned opcodes, odd register specs, a
+/// truncated final instruction) is emitted as `.db`.
+pub fn disassemble_nraw(bytes: &[u8], base_addr: u32) -> String {
+    // Decode once to find instruction boundaries and branch targets
+    let mut decoded = Vec::new();
+    let mut cursor = 0;
+    while cursor < bytes.len() {
+        let addr = base_addr.wrapping_add(cursor as u32) & 0xFFFFFF;
+        let len = match decode_opcode(bytes[cursor]) {
+            Some((_, kind)) if cursor + 1 + kind.len() <= bytes.len() => 1 + kind.len(),
+            Some(_) => bytes.len() - cursor,
+            None => 1,
+        };
+        decoded.push((addr, &bytes[cursor..cursor + len]));
+        cursor += len;
+    }
+    let starts: HashSet<u32> = decoded.iter().map(|(addr, _)| *addr).collect();
+    let labels: HashSet<u32> = decoded
+        .iter()
+        .filter_map(|(addr, inst)| branch_target(*addr, inst))
+        .filter(|target| starts.contains(target))
+        .collect();
+
+    let mut out = String::new();
+    if base_addr != 0 {
+        let _ = writeln!(out, ".org ${:06X}", base_addr);
+    }
+    for (addr, inst) in decoded {
+        if labels.contains(&addr) {
+            let _ = writeln!(out, "label_{:04x}:", addr);
+        }
+        let text = instruction_text(addr, inst, &labels).unwrap_or_else(|| {
+            let values: Vec<String> = inst.iter().map(|b| format!("0x{:02X}", b)).collect();
+            format!(".db {}", values.join(", "))
+        });
+        let _ = writeln!(out, "    {}", text);
+    }
+    out
+}
+
+/// Absolute target of a BRA/LBRA-style instruction.
+fn branch_target(addr: u32, inst: &[u8]) -> Option<u32> {
+    let (_, kind) = decode_opcode(inst[0])?;
+    let next_pc = addr.wrapping_add(inst.len() as u32);
+    let offset = match kind {
+        OperandKind::Rel8 if inst.len() == 2 => inst[1] as i8 as u32,
+        OperandKind::Rel16 if inst.len() == 3 => {
+            u16::from_le_bytes([inst[1], inst[2]]) as i16 as u32
+        }
+        _ => return None,
+    };
+    Some(next_pc.wrapping_add(offset) & 0xFFFFFF)
+}
+
+/// Assembler text for one decoded instruction, or `None` if it needs `.db`.
+fn instruction_text(addr: u32, inst: &[u8], labels: &HashSet<u32>) -> Option<String> {
+    let (mnemonic, kind) = decode_opcode(inst[0])?;
+    if inst.len() != 1 + kind.len() {
+        return None;
+    }
+    let operand = &inst[1..];
+    let u24_operand = || u32::from_le_bytes([operand[0], operand[1], operand[2], 0]);
+    let operand_text = match kind {
+        OperandKind::None | OperandKind::Accumulator => String::new(),
+        OperandKind::StackReg => format!("R{}", inst[0] & 0x07),
+        OperandKind::Fixed(text) => text.to_string(),
+        OperandKind::Imm8 => format!("#0x{:02X}", operand[0]),
+        OperandKind::Imm16 => format!("#0x{:04X}", u16::from_le_bytes([operand[0], operand[1]])),
+        OperandKind::Abs24 => format!("${:06X}", u24_operand()),
+        OperandKind::AbsX => format!("${:06X},X", u24_operand()),
+        OperandKind::AbsY => format!("${:06X},Y", u24_operand()),
+        OperandKind::Indirect => format!("[${:06X}]", u24_operand()),
+        OperandKind::Rel8 | OperandKind::Rel16 => {
+            let target = branch_target(addr, inst)?;
+            if labels.contains(&target) {
+                format!("label_{:04x}", target)
+            } else {
+                format!("${:06X}", target)
+            }
+        }
+        // The assembler only encodes INC/DEC R and MOV A -> R
+        OperandKind::Reg if operand[0] <= 11 => register_name(operand[0]),
+        OperandKind::RegPair if operand[0] <= 11 => register_name(operand[0]),
+        OperandKind::Reg | OperandKind::RegPair => return None,
+    };
+    if operand_text.is_empty() {
+        Some(mnemonic.to_string())
+    } else {
+        Some(format!("{} {}", mnemonic, operand_text))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -652,8 +2024,8 @@ data:
 
     #[test]
     fn branch_out_of_range_error() {
-        // Create a program where the branch target is more than 127 bytes away
-        let mut source = String::from("start:\n    BRA far\n");
+        // BCS has no long form, so a target more than 127 bytes away is an error
+        let mut source = String::from("start:\n    BCS far\n");
         // Add enough NOPs to make 'far' label unreachable (>127 bytes away)
         for _ in 0..130 {
             source.push_str("    NOP\n");
@@ -662,4 +2034,733 @@ data:
         let result = assemble(&source);
         assert!(matches!(result, Err(AsmError::BranchOutOfRange { .. })));
     }
+
+    #[test]
+    fn assembles_indexed_addressing() {
+        let source = r#"
+    LDA $1000,X
+    LDA $1000,Y
+    STA table,X
+    STA table,Y
+    LDX $FFFFFF,Y
+    LDY $000010,X
+    STX $2000,Y
+    STY $2000,X
+table:
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.labels.get("table"), Some(&32));
+        assert_eq!(
+            program.bytes,
+            vec![
+                0x0A, 0x00, 0x10, 0x00, 0x0B, 0x00, 0x10, 0x00, 0x0C, 0x20, 0x00, 0x00, 0x0D,
+                0x20, 0x00, 0x00, 0x0E, 0xFF, 0xFF, 0xFF, 0x0F, 0x10, 0x00, 0x00, 0x1D, 0x00,
+                0x20, 0x00, 0x1E, 0x00, 0x20, 0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_index_register() {
+        assert!(matches!(
+            assemble("LDX $1000,X"),
+            Err(AsmError::InvalidIndexRegister { line: 1, .. })
+        ));
+        assert!(matches!(
+            assemble("LDA $1000,R0"),
+            Err(AsmError::InvalidIndexRegister { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_indirect_jumps() {
+        let source = r#"
+    JMP [$001000]
+    JSR [vector]
+vector:
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(
+            program.bytes,
+            vec![0x23, 0x00, 0x10, 0x00, 0x24, 0x08, 0x00, 0x00]
+        );
+        assert!(matches!(
+            assemble("JMP [$1000"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_compare_instructions() {
+        let program = assemble("CMP #5\nCPX #0x10\nCPY #$FFFF").expect("assemble");
+        assert_eq!(
+            program.bytes,
+            vec![0x50, 0x05, 0x00, 0x51, 0x10, 0x00, 0x52, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn assembles_shift_instructions() {
+        let program = assemble("LSL A\nLSR\nrol a\nROR A").expect("assemble");
+        assert_eq!(program.bytes, vec![0x60, 0x61, 0x62, 0x63]);
+        assert!(matches!(
+            assemble("LSL X"),
+            Err(AsmError::UnexpectedOperand { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_push_pop() {
+        let program = assemble("PUSH R0\nPUSH r7\nPOP R7\nPOP R0").expect("assemble");
+        assert_eq!(program.bytes, vec![0x70, 0x77, 0x7F, 0x78]);
+        assert!(matches!(
+            assemble("PUSH SP"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn far_branches_use_long_form() {
+        let mut source = String::from("start:\n    BRA near\nnear:\n    BEQ far\n");
+        for _ in 0..130 {
+            source.push_str("    NOP\n");
+        }
+        source.push_str("far:\n    BNE start\n");
+        let program = assemble(&source).expect("assemble");
+
+        // BRA stays short, BEQ and the backwards BNE are promoted
+        assert_eq!(program.labels.get("near"), Some(&2));
+        assert_eq!(program.labels.get("far"), Some(&135));
+        assert_eq!(&program.bytes[..5], &[0x30, 0x00, 0x3A, 0x82, 0x00]);
+        assert_eq!(&program.bytes[135..], &[0x3B, 0x76, 0xFF]);
+    }
+
+    #[test]
+    fn long_branch_out_of_range_error() {
+        let mut source = String::from("start:\n    LBRA far\n");
+        for _ in 0..32768 {
+            source.push_str("NOP\n");
+        }
+        source.push_str("far:\n");
+        let result = assemble(&source);
+        assert!(matches!(
+            result,
+            Err(AsmError::BranchOutOfRange { offset: 32768, .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_exg() {
+        let program = assemble("EXG AX\nexg ay").expect("assemble");
+        assert_eq!(program.bytes, vec![0x80, 0x81]);
+        assert!(matches!(
+            assemble("EXG XY"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_flag_instructions() {
+        let program = assemble("SEC\nCLC\nSEV\nCLV").expect("assemble");
+        assert_eq!(program.bytes, vec![0x82, 0x83, 0x84, 0x85]);
+        assert!(matches!(
+            assemble("SEC #1"),
+            Err(AsmError::UnexpectedOperand { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_push_pop_special_registers() {
+        let program = assemble("PUSH A\nPOP A\nPUSH X\nPOP X\nPUSH Y\nPOP Y").expect("assemble");
+        assert_eq!(program.bytes, vec![0x90, 0x91, 0x92, 0x93, 0x94, 0x95]);
+    }
+
+    #[test]
+    fn assembles_lcall_lret() {
+        let program = assemble("LCALL far\nHLT\nfar:\n    LRET").expect("assemble");
+        assert_eq!(program.bytes, vec![0x25, 0x05, 0x00, 0x00, 0xFF, 0x26]);
+    }
+
+    #[test]
+    fn expands_macros_inline() {
+        let source = r#"
+.macro SWAP a, b
+    PUSH a
+    PUSH b
+    POP a
+    POP b
+.endm
+
+start:
+    SWAP A, X
+    SWAP X, Y
+loop: SWAP R0, A
+    HLT
+"#;
+        let expanded = r#"
+start:
+    PUSH A
+    PUSH X
+    POP A
+    POP X
+    PUSH X
+    PUSH Y
+    POP X
+    POP Y
+loop:
+    PUSH R0
+    PUSH A
+    POP R0
+    POP A
+    HLT
+"#;
+        let program = assemble(source).expect("assemble");
+        let reference = assemble(expanded).expect("assemble");
+        assert_eq!(program.bytes, reference.bytes);
+        assert_eq!(program.bytes.len(), 13);
+        assert_eq!(program.labels.get("loop"), Some(&8));
+    }
+
+    #[test]
+    fn rejects_recursive_and_malformed_macros() {
+        let recursive =
+            ".macro PING\n    PONG\n.endm\n.macro PONG\n    PING\n.endm\n    NOP\n    PING\n";
+        assert_eq!(
+            assemble(recursive).err(),
+            Some(AsmError::RecursiveMacro {
+                line: 8,
+                name: "PING".to_string()
+            })
+        );
+
+        let unterminated = "NOP\n.macro TWICE x\n    INC x\n";
+        assert_eq!(
+            assemble(unterminated).err(),
+            Some(AsmError::UnterminatedMacro {
+                line: 2,
+                name: "TWICE".to_string()
+            })
+        );
+
+        let missing_arg = ".macro TWICE x\n    INC x\n    INC x\n.endm\n    TWICE\n";
+        assert_eq!(
+            assemble(missing_arg).err(),
+            Some(AsmError::MissingOperand {
+                line: 5,
+                instruction: "TWICE".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn org_sets_assembly_address() {
+        let source = r#"
+.org 0x8000
+start:
+    NOP
+    BRA start
+.org 0x9000
+handler:
+    JMP start
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.labels["start"], 0x8000);
+        assert_eq!(program.labels["handler"], 0x9000);
+        // No padding is emitted; branches and jumps use the new addresses
+        assert_eq!(
+            program.bytes,
+            vec![0x00, 0x30, 0xFD, 0x20, 0x00, 0x80, 0x00]
+        );
+    }
+
+    #[test]
+    fn equ_defines_constants() {
+        let source = r#"
+SCREEN_WIDTH .equ 320
+.equ VRAM 0x200000
+    LDA #SCREEN_WIDTH
+    STA VRAM
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.labels["SCREEN_WIDTH"], 320);
+        assert_eq!(
+            program.bytes,
+            vec![0x01, 0x40, 0x01, 0x02, 0x00, 0x00, 0x20]
+        );
+
+        assert_eq!(
+            assemble("start:\n    NOP\nstart .equ 4").err(),
+            Some(AsmError::DuplicateLabel {
+                line: 3,
+                name: "start".to_string()
+            })
+        );
+        assert!(matches!(
+            assemble("LDA #UNDEFINED"),
+            Err(AsmError::LabelNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn assembles_data_directives() {
+        let source = r#"
+start:
+    LDA #1
+table:
+    .dw 0x1234
+    .db 1, 2, 0xFF
+    .dl start, handler
+    .dl table + 2, handler - 1
+handler:
+    NOP
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.labels["table"], 3);
+        // 2 + 3 + 6 + 6 bytes of data precede the handler
+        assert_eq!(program.labels["handler"], 20);
+        assert_eq!(
+            &program.bytes[3..],
+            &[
+                0x34, 0x12, // .dw
+                0x01, 0x02, 0xFF, // .db
+                0x00, 0x00, 0x00, 0x14, 0x00, 0x00, // .dl start, handler
+                0x05, 0x00, 0x00, 0x13, 0x00, 0x00, // .dl table + 2, handler - 1
+                0x00, // NOP
+            ]
+        );
+
+        assert!(matches!(
+            assemble(".db"),
+            Err(AsmError::MissingOperand { line: 1, .. })
+        ));
+        assert!(matches!(
+            assemble(".dw 1 +"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    /// Fresh scratch directory for file-based tests.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nraw-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create scratch dir");
+        dir
+    }
+
+    #[test]
+    fn include_splices_labels_into_parent() {
+        let dir = scratch_dir("include");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("main.nraw"),
+            "start:\n    BRA helper\n.include \"lib/helper.nraw\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("lib/helper.nraw"),
+            "; shared routine\nhelper:\n    NOP\n.include \"tail.nraw\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("lib/tail.nraw"), "    BRA start\n").unwrap();
+
+        let program = assemble_file(&dir.join("main.nraw")).expect("assemble");
+        assert_eq!(program.labels["helper"], 2);
+        assert_eq!(program.bytes, vec![0x30, 0x00, 0x00, 0x30, 0xFB]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_detects_cycles_and_missing_files() {
+        let dir = scratch_dir("cycle");
+        fs::write(dir.join("a.nraw"), "NOP\n.include \"b.nraw\"\n").unwrap();
+        fs::write(dir.join("b.nraw"), "NOP\n\n.include \"a.nraw\"\n").unwrap();
+
+        assert!(matches!(
+            assemble_file(&dir.join("a.nraw")),
+            Err(AsmError::CircularInclude { line: 3, .. })
+        ));
+        assert!(matches!(
+            assemble_file(&dir.join("missing.nraw")),
+            Err(AsmError::Io { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn listing_shows_addresses_and_bytes() {
+        let source = r#"
+start:
+    LDA #0x1234
+    STA data
+    BRA start
+
+data:
+    NOP
+"#;
+        let (program, listing) = assemble_with_listing(source).expect("assemble");
+        assert_eq!(program.bytes.len(), 10);
+        let expected = [
+            "",
+            "                   start:",
+            "00000 01 34 12         LDA #0x1234",
+            "00003 02 09 00 00      STA data",
+            "00007 30 F7            BRA start",
+            "",
+            "                   data:",
+            "00009 00               NOP",
+        ];
+        assert_eq!(listing.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn listing_wraps_long_lines_and_keeps_comments() {
+        let source = "table: .dw 1, 2, 3 ; lookup\n    HLT\n";
+        let (_, listing) = assemble_with_listing(source).expect("assemble");
+        let expected = [
+            "00000 01 00 02 00  table: .dw 1, 2, 3 ; lookup",
+            "00004 03 00",
+            "00006 FF               HLT",
+        ];
+        assert_eq!(listing.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn disassembly_round_trips() {
+        let source = r#"
+start:
+    LDA #0x1234
+    STA data
+    LDX $001000,Y
+loop:
+    INC R3
+    MOV R0
+    DEC X
+    BNE loop
+    PUSH R5
+    POP A
+    EXG AY
+    LSL
+    COP #0x07
+    JSR [$002000]
+    LBRA start
+    BEQ done
+    JMP $FF0003
+done:
+    HLT
+data:
+    .db 0x02, 0xEE
+"#;
+        let program = assemble(source).expect("assemble");
+        let text = disassemble_nraw(&program.bytes, 0);
+        let reassembled = assemble(&text).expect("reassemble");
+        assert_eq!(reassembled.bytes, program.bytes);
+
+        assert!(text.contains("    LDA #0x1234\n"));
+        assert!(text.contains("    JMP $FF0003\n"));
+        assert!(text.contains("label_000b:\n    INC R3\n"));
+        assert!(text.contains("    BNE label_000b\n"));
+        // Trailing data decodes as a truncated STA
+        assert!(text.ends_with("    .db 0x02, 0xEE\n"));
+    }
+
+    #[test]
+    fn disassembly_keeps_base_address_and_unknown_opcodes() {
+        let bytes = [0x30, 0x01, 0xEE, 0x17, 0x45, 0xFF];
+        let text = disassemble_nraw(&bytes, 0x8000);
+        assert_eq!(
+            text,
+            ".org $008000\n    BRA label_8003\n    .db 0xEE\nlabel_8003:\n    .db 0x17, 0x45\n    HLT\n"
+        );
+        let program = assemble(&text).expect("reassemble");
+        assert_eq!(program.bytes, bytes);
+    }
+
+    #[test]
+    fn local_labels_are_scoped_to_global_labels() {
+        let source = r#"
+first:
+    LDX #3
+.loop:
+    DEC X
+    BNE .loop
+    RTS
+second:
+    LDY #3
+.loop:
+    DEC Y
+    BNE .loop
+    BRA .done
+.done:
+    RTS
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.labels.get("first.loop"), Some(&3));
+        assert_eq!(program.labels.get("second.loop"), Some(&11));
+        // first: BNE at 5 jumps back to 3; second: BNE at 13 jumps back to 11
+        assert_eq!(&program.bytes[5..7], &[0x32, 0xFC]);
+        assert_eq!(&program.bytes[13..15], &[0x32, 0xFC]);
+        assert_eq!(program.labels.get("second.done"), Some(&17));
+    }
+
+    #[test]
+    fn local_labels_fall_back_to_nearest_scope() {
+        let source = "first:\n.exit:\n    RTS\nsecond:\n    JMP .exit\n";
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.bytes, vec![0x22, 0x20, 0x00, 0x00, 0x00]);
+
+        let duplicate = "first:\n.exit:\n.exit:\n    RTS\n";
+        assert!(matches!(
+            assemble(duplicate),
+            Err(AsmError::DuplicateLabel { name, .. }) if name == "first.exit"
+        ));
+    }
+
+    #[test]
+    fn conditional_assembly_follows_defines() {
+        let body = r#"
+.ifdef DEBUG
+    LDA #0x1234
+.else
+    NOP
+.endif
+.ifndef DEBUG
+    HLT
+.endif
+    RTS
+"#;
+        let debug = assemble(&format!(".define DEBUG\n{body}")).expect("assemble");
+        assert_eq!(debug.bytes, vec![0x01, 0x34, 0x12, 0x22]);
+
+        let release = assemble(body).expect("assemble");
+        assert_eq!(release.bytes, vec![0x00, 0xFF, 0x22]);
+    }
+
+    #[test]
+    fn conditional_blocks_nest_and_must_close() {
+        let source = r#"
+.ifdef MISSING
+.ifndef MISSING
+.define INNER
+    NOP
+.endif
+.endif
+.ifdef INNER
+    NOP
+.endif
+    HLT
+"#;
+        assert_eq!(assemble(source).expect("assemble").bytes, vec![0xFF]);
+        assert!(matches!(
+            assemble("\n.ifdef DEBUG\n    NOP\n"),
+            Err(AsmError::UnterminatedConditional { line: 2 })
+        ));
+        assert!(matches!(
+            assemble(".endif\n"),
+            Err(AsmError::UnknownInstruction { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn operands_accept_expressions() {
+        let source = r#"
+.equ SCREEN_WIDTH 320
+    LDA #(0x0100 * 3)
+    LDX #(SCREEN_WIDTH * 2)
+    STA (some_label + 4)
+    LDY #~0 & (1 << 4 | 0x0F) ^ 3
+    ADD #-(10 - 4) / 2
+some_label:
+    NOP
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(&program.bytes[0..3], &[0x01, 0x00, 0x03]);
+        assert_eq!(&program.bytes[3..6], &[0x03, 0x80, 0x02]);
+        // some_label is at 16, so the store targets 20
+        assert_eq!(&program.bytes[6..10], &[0x02, 20, 0x00, 0x00]);
+        assert_eq!(&program.bytes[10..13], &[0x05, 0x1C, 0x00]);
+        assert_eq!(&program.bytes[13..16], &[0x10, 0xFD, 0xFF]);
+    }
+
+    #[test]
+    fn expression_errors() {
+        assert!(matches!(
+            assemble("    LDA #(4 / (2 - 2))"),
+            Err(AsmError::DivisionByZero { line: 1 })
+        ));
+        assert!(matches!(
+            assemble("    LDA #(1 + 2"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+        assert!(matches!(
+            assemble("    JMP missing * 2"),
+            Err(AsmError::LabelNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn repeat_blocks_unroll_with_counter() {
+        let program =
+            assemble("start:\n.repeat 4\n    NOP\n.endr\nafter:\n    HLT\n").expect("assemble");
+        assert_eq!(program.bytes, vec![0x00, 0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(program.labels["after"], program.labels["start"] + 4);
+
+        let table = assemble(".repeat 8\n    .db \\@ * 32\n.endr\n").expect("assemble");
+        assert_eq!(table.bytes, vec![0, 32, 64, 96, 128, 160, 192, 224]);
+    }
+
+    #[test]
+    fn nested_repeat_blocks_keep_their_own_counter() {
+        let source = r#"
+.repeat 2
+    .db 0xA0 + \@
+.repeat 3
+    .db \@
+.endr
+.endr
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.bytes, vec![0xA0, 0, 1, 2, 0xA1, 0, 1, 2]);
+        assert!(matches!(
+            assemble(".repeat 2\n    NOP\n"),
+            Err(AsmError::UnterminatedRepeat { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn repeat_is_limited_to_the_address_space() {
+        assert!(matches!(
+            assemble(".repeat 0xFFFFFFFF\n    NOP\n.endr\n"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+        // Nested blocks that multiply past the limit are caught at the outer one
+        assert!(matches!(
+            assemble("NOP\n.repeat 0x10000\n.repeat 0x1000\n    NOP\n.endr\n.endr\n"),
+            Err(AsmError::InvalidNumber { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn align_and_fill_pad_the_output() {
+        let source = r#"
+    LDA #0x1234
+    INC X
+    .align 16
+aligned:
+    .fill 5, 0xAA
+after:
+    .fill 2
+    .align 4
+end:
+"#;
+        let program = assemble(source).expect("assemble");
+        assert_eq!(program.labels["aligned"], 0x10);
+        assert_eq!(&program.bytes[5..16], &[0xFF; 11]);
+        assert_eq!(&program.bytes[16..21], &[0xAA; 5]);
+        assert_eq!(program.labels["after"], 0x15);
+        // Two default fill bytes, then one byte of padding to 0x18
+        assert_eq!(&program.bytes[21..], &[0xFF; 3]);
+        assert_eq!(program.labels["end"], 0x18);
+
+        assert!(matches!(
+            assemble(".align 0"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn fill_is_limited_to_the_address_space() {
+        let program = assemble(".fill 0x1000000, 0x12").expect("assemble");
+        assert_eq!(program.bytes.len(), 1 << 24);
+        assert!(program.bytes.iter().all(|&byte| byte == 0x12));
+
+        assert!(matches!(
+            assemble("NOP\n.fill 0x1000001"),
+            Err(AsmError::InvalidNumber { line: 2, .. })
+        ));
+        assert!(matches!(
+            assemble(".fill 0xFFFFFFFF, 0"),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn incbin_embeds_file_bytes() {
+        let dir = scratch_dir("incbin");
+        fs::create_dir_all(dir.join("data")).unwrap();
+        let tiles: Vec<u8> = (0..16).map(|i| i * 17).collect();
+        fs::write(dir.join("data/tiles.bin"), &tiles).unwrap();
+        fs::write(
+            dir.join("main.nraw"),
+            "    NOP\ntiles:\n    .incbin \"data/tiles.bin\"\npart:\n    .incbin \"data/tiles.bin\", 4, 3\nend:\n",
+        )
+        .unwrap();
+
+        let program = assemble_file(&dir.join("main.nraw")).expect("assemble");
+        assert_eq!(program.labels["tiles"], 1);
+        assert_eq!(&program.bytes[1..17], tiles.as_slice());
+        assert_eq!(program.labels["part"], 17);
+        assert_eq!(&program.bytes[17..], &tiles[4..7]);
+        assert_eq!(program.labels["end"], 20);
+
+        fs::write(dir.join("bad.nraw"), ".incbin \"data/tiles.bin\", 10, 7\n").unwrap();
+        assert!(matches!(
+            assemble_file(&dir.join("bad.nraw")),
+            Err(AsmError::InvalidNumber { line: 1, .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn incbin_emits_one_item_however_large() {
+        let dir = scratch_dir("incbin_large");
+        fs::create_dir_all(&dir).unwrap();
+        let data: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.join("big.bin"), &data).unwrap();
+
+        let mut lines = Vec::new();
+        include_lines(
+            "    NOP\n    .incbin \"big.bin\"\nend:\n",
+            &dir,
+            None,
+            &mut HashSet::new(),
+            &mut lines,
+        )
+        .unwrap();
+        let (program, spans) = assemble_lines(lines).expect("assemble");
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].2, 1..1 + data.len());
+        assert_eq!(&program.bytes[1..], data.as_slice());
+        assert_eq!(program.labels["end"], 1 + data.len() as u32);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checksum16_patches_header_word() {
+        let source = r#"
+header:
+    .db 0x4E, 0x58
+sum:
+    .dw 0
+body:
+    .db 0xFF, 0xFF, 0x80
+    .dw 0x1234
+end:
+    .checksum16 body, end, sum
+"#;
+        let program = assemble(source).expect("assemble");
+        let body = &program.bytes[4..];
+        let expected = body.iter().map(|&b| b as u16).sum::<u16>();
+        assert_eq!(expected, 0xFF + 0xFF + 0x80 + 0x34 + 0x12);
+        assert_eq!(&program.bytes[2..4], &expected.to_le_bytes());
+        assert_eq!(&program.bytes[..2], &[0x4E, 0x58]);
+
+        assert!(matches!(
+            assemble("start:\n    NOP\n.checksum16 start, missing, start"),
+            Err(AsmError::LabelNotFound { .. })
+        ));
+        assert!(matches!(
+            assemble(".checksum16 a, b"),
+            Err(AsmError::MissingOperand { line: 1, .. })
+        ));
+    }
 }
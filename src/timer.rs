@@ -0,0 +1,178 @@
+// Copyright (C) 2025 Dayton Fishell
+// Nexel-24 Game Console Emulator
+// This file is part of Nexel-24.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version. See the LICENSE file in the project root for details.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Programmable interval timers
+//!
+//! Two down-counting timers are mapped at 0x10B000. Each has a 16-bit
+//! period register and a counter/status register:
+//! - +0: timer0 period
+//! - +2: timer0 counter (read) / control (write)
+//! - +4: timer1 period
+//! - +6: timer1 counter (read) / control (write)
+//! - +8: expiry status, bit n set when timer n reloaded (write 1 to clear)
+//!
+//! Control bit 0 runs the timer and bit 1 enables its interrupt. Starting a
+//! stopped timer loads the counter from the period. Both timers raise
+//! interrupt 2 (TIMER0); handlers tell them apart through the status
+//! register.
+
+/// Number of hardware timers
+pub const TIMER_COUNT: usize = 2;
+
+/// Offset of the shared expiry status register
+pub const TIMER_STATUS: u32 = 0x08;
+
+/// Control bit that runs the timer
+pub const CONTROL_RUN: u8 = 0x01;
+/// Control bit that raises an interrupt when the timer reloads
+pub const CONTROL_IRQ_ENABLE: u8 = 0x02;
+
+/// A single down-counting timer, clocked by CPU cycles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timer {
+    pub period: u16,
+    pub counter: u16,
+    pub running: bool,
+    pub irq_enable: bool,
+}
+
+impl Timer {
+    /// Apply a control register write
+    fn write_control(&mut self, value: u8) {
+        let run = value & CONTROL_RUN != 0;
+        if run && !self.running {
+            self.counter = self.period;
+        }
+        self.running = run;
+        self.irq_enable = value & CONTROL_IRQ_ENABLE != 0;
+    }
+
+    /// Count down by `cycles`, reloading from the period each time the
+    /// counter reaches zero. Returns true if the timer reloaded.
+    ///
+    /// A period of zero keeps the timer from expiring.
+    pub fn step(&mut self, cycles: u64) -> bool {
+        if !self.running || self.period == 0 {
+            return false;
+        }
+        let mut remaining = cycles;
+        let mut expired = false;
+        while remaining >= self.counter as u64 {
+            remaining -= self.counter as u64;
+            self.counter = self.period;
+            expired = true;
+        }
+        self.counter -= remaining as u16;
+        expired
+    }
+}
+
+/// Both timers plus the shared status register
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timers {
+    timers: [Timer; TIMER_COUNT],
+    status: u8,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Access a timer by index
+    pub fn timer(&self, index: usize) -> Option<&Timer> {
+        self.timers.get(index)
+    }
+
+    /// Read a byte from the timer register window
+    pub fn read_register(&self, offset: u32) -> u8 {
+        let shift = (offset & 1) * 8;
+        match offset {
+            TIMER_STATUS => self.status,
+            o if o < TIMER_STATUS => {
+                let timer = &self.timers[(o / 4) as usize];
+                let value = if o & 2 == 0 {
+                    timer.period
+                } else {
+                    timer.counter
+                };
+                (value >> shift) as u8
+            }
+            _ => 0,
+        }
+    }
+
+    /// Write a byte to the timer register window
+    pub fn write_register(&mut self, offset: u32, value: u8) {
+        match offset {
+            TIMER_STATUS => self.status &= !value,
+            o if o < TIMER_STATUS => {
+                let timer = &mut self.timers[(o / 4) as usize];
+                match o & 3 {
+                    0 => timer.period = (timer.period & 0xFF00) | value as u16,
+                    1 => timer.period = (timer.period & 0x00FF) | ((value as u16) << 8),
+                    2 => timer.write_control(value),
+                    _ => {} // Upper control byte is reserved
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Advance both timers by `cycles` and return true if an interrupt
+    /// should be raised
+    pub fn step(&mut self, cycles: u64) -> bool {
+        let mut irq = false;
+        for (index, timer) in self.timers.iter_mut().enumerate() {
+            if timer.step(cycles) {
+                self.status |= 1 << index;
+                irq |= timer.irq_enable;
+            }
+        }
+        irq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_reloads_every_period() {
+        let mut timers = Timers::new();
+        timers.write_register(4, 10); // timer1 period = 10
+        timers.write_register(6, CONTROL_RUN);
+        assert_eq!(timers.timer(1).unwrap().counter, 10);
+
+        assert!(!timers.step(9));
+        assert_eq!(timers.read_register(6), 1);
+        // Expiry is flagged even without the interrupt enabled
+        assert!(!timers.step(1));
+        assert_eq!(timers.read_register(TIMER_STATUS), 0x02);
+        assert_eq!(timers.read_register(6), 10);
+
+        timers.write_register(TIMER_STATUS, 0x02);
+        assert_eq!(timers.read_register(TIMER_STATUS), 0);
+    }
+
+    #[test]
+    fn stopped_or_zero_period_timer_never_fires() {
+        let mut timers = Timers::new();
+        timers.write_register(2, CONTROL_RUN | CONTROL_IRQ_ENABLE);
+        assert!(!timers.step(1000));
+
+        timers.write_register(0, 5);
+        timers.write_register(2, 0);
+        assert!(!timers.step(1000));
+        assert_eq!(timers.read_register(TIMER_STATUS), 0);
+    }
+}
@@ -48,6 +48,13 @@ pub enum VdpRegister {
     Bg1ScrollY = 0x0034,
     Bg1TilemapAddr = 0x0036,
 
+    // Active area (overscan crop); edges are staged until ActiveApply
+    ActiveLeft = 0x0040,
+    ActiveTop = 0x0042,
+    ActiveRight = 0x0044,
+    ActiveBottom = 0x0046,
+    ActiveApply = 0x0048,
+
     // Sprite control
     SpriteControl = 0x0050,
     SpriteOamAddr = 0x0052,
@@ -57,6 +64,7 @@ pub enum VdpRegister {
     DmaDestination = 0x0074,
     DmaLength = 0x0078,
     DmaControl = 0x007A,
+    DmaMode = 0x007C,
 
     // Interrupt control
     IrqEnable = 0x0080,
@@ -67,6 +75,20 @@ pub enum VdpRegister {
     PaletteIndex = 0x0090,
     PaletteData = 0x0092,
     BackdropColor = 0x0094,
+    MosaicSize = 0x0096,
+    BlendControl = 0x0098,
+    BlendAlpha = 0x009A,
+
+    // Windows; layer masks for both windows share WindowLayers
+    Win0Left = 0x00A0,
+    Win0Top = 0x00A2,
+    Win0Right = 0x00A4,
+    Win0Bottom = 0x00A6,
+    Win1Left = 0x00A8,
+    Win1Top = 0x00AA,
+    Win1Right = 0x00AC,
+    Win1Bottom = 0x00AE,
+    WindowLayers = 0x00B0,
 }
 
 bitflags! {
@@ -78,6 +100,8 @@ bitflags! {
         const BG1_ENABLE = 1 << 2;      // Enable BG1 layer
         const SPRITE_ENABLE = 1 << 3;   // Enable sprites
         const POLYGON_ENABLE = 1 << 4;  // Enable polygon rendering
+        const WIN0_ENABLE = 1 << 5;     // Enable window 0
+        const WIN1_ENABLE = 1 << 6;     // Enable window 1
         const HBLANK_IRQ = 1 << 8;      // Enable HBLANK interrupt
         const VBLANK_IRQ = 1 << 9;      // Enable VBLANK interrupt
         const LINECMP_IRQ = 1 << 10;    // Enable line compare interrupt
@@ -95,6 +119,8 @@ bitflags! {
         const LINECMP = 1 << 2;         // Line compare match
         const DMA_BUSY = 1 << 3;        // DMA in progress
         const CMDLIST_BUSY = 1 << 4;    // Command list processing
+        const SPRITE_COLLISION = 1 << 5; // Two sprites overlapped this frame
+        const POLYGON_OVERFLOW = 1 << 6; // Triangles past the per-frame limit were dropped
     }
 }
 
@@ -112,6 +138,125 @@ bitflags! {
         const SIZE_32x32 = 0 << 10;
         const SIZE_64x64 = 1 << 10;
         const SIZE_128x128 = 2 << 10;
+        const SCROLL_TABLE = 1 << 12;   // Per-scanline scroll from the scroll table
+    }
+}
+
+bitflags! {
+    /// Alpha blending layer selection (BLENDCNT register)
+    ///
+    /// A pixel from a source layer is blended with the pixel underneath when
+    /// the layer that drew that pixel is selected as a destination.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct BlendControl: u16 {
+        const SRC_BG0 = 1 << 0;
+        const SRC_BG1 = 1 << 1;
+        const SRC_SPRITES = 1 << 2;
+        const DST_BG0 = 1 << 8;
+        const DST_BG1 = 1 << 9;
+        const DST_SPRITES = 1 << 10;
+        const DST_BACKDROP = 1 << 11;
+    }
+}
+
+/// Layer that produced a framebuffer pixel, used to resolve blending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Bg0,
+    Bg1,
+    Sprites,
+    Backdrop,
+}
+
+impl Layer {
+    /// Bit for this layer in a [`Window`] layer mask
+    fn window_bit(self) -> u8 {
+        match self {
+            Layer::Bg0 => Window::BG0,
+            Layer::Bg1 => Window::BG1,
+            Layer::Sprites => Window::SPRITES,
+            Layer::Backdrop => Window::ALL_LAYERS,
+        }
+    }
+
+    fn as_source(self) -> BlendControl {
+        match self {
+            Layer::Bg0 => BlendControl::SRC_BG0,
+            Layer::Bg1 => BlendControl::SRC_BG1,
+            Layer::Sprites => BlendControl::SRC_SPRITES,
+            Layer::Backdrop => BlendControl::empty(),
+        }
+    }
+
+    fn as_destination(self) -> BlendControl {
+        match self {
+            Layer::Bg0 => BlendControl::DST_BG0,
+            Layer::Bg1 => BlendControl::DST_BG1,
+            Layer::Sprites => BlendControl::DST_SPRITES,
+            Layer::Backdrop => BlendControl::DST_BACKDROP,
+        }
+    }
+}
+
+/// Number of direct-mapped slots in the [`TileCache`]
+const TILE_CACHE_SLOTS: usize = 1024;
+
+/// Decoded 8x8 tiles, one color index per pixel, indexed by tile number
+///
+/// Slots are direct-mapped by the low 10 bits of the tile index. The tag
+/// holds the tile index within VRAM plus bit 15 for 16-color tiles, since
+/// the same bytes decode differently at each depth.
+struct TileCache {
+    entries: Box<[Option<[u8; 64]>; TILE_CACHE_SLOTS]>,
+    tag: [u16; TILE_CACHE_SLOTS],
+}
+
+impl TileCache {
+    fn new() -> Self {
+        let entries = vec![None; TILE_CACHE_SLOTS].into_boxed_slice();
+        Self {
+            entries: entries.try_into().expect("slot count matches"),
+            tag: [0; TILE_CACHE_SLOTS],
+        }
+    }
+
+    /// Slot and tag for a tile, wrapping the index at the end of VRAM
+    fn key(tile_index: u16, bpp4: bool) -> (usize, u16) {
+        let tag = if bpp4 {
+            0x8000 | (tile_index as usize % (Vdp::VRAM_SIZE / 32)) as u16
+        } else {
+            (tile_index as usize % (Vdp::VRAM_SIZE / 64)) as u16
+        };
+        (tile_index as usize % TILE_CACHE_SLOTS, tag)
+    }
+
+    fn get(&self, tile_index: u16, bpp4: bool) -> Option<&[u8; 64]> {
+        let (slot, tag) = Self::key(tile_index, bpp4);
+        if self.tag[slot] == tag {
+            self.entries[slot].as_ref()
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, tile_index: u16, bpp4: bool, pixels: [u8; 64]) {
+        let (slot, tag) = Self::key(tile_index, bpp4);
+        self.tag[slot] = tag;
+        self.entries[slot] = Some(pixels);
+    }
+
+    /// Drop the 16- and 256-color tiles that contain a VRAM byte
+    fn invalidate(&mut self, vram_offset: usize) {
+        for (tile, bpp4) in [(vram_offset / 32, true), (vram_offset / 64, false)] {
+            let (slot, tag) = Self::key(tile as u16, bpp4);
+            if self.tag[slot] == tag {
+                self.entries[slot] = None;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.fill(None);
     }
 }
 
@@ -121,6 +266,7 @@ bitflags! {
     pub struct SpriteControl: u16 {
         const ENABLE = 1 << 0; // Placeholder flag
         const SIZE_16 = 1 << 1; // Placeholder
+        const COLOR_256 = 1 << 7; // 256-color sprites (vs 16-color)
     }
 }
 
@@ -135,9 +281,41 @@ bitflags! {
     }
 }
 
+/// DMA transfer direction (DMA_MODE register)
+///
+/// Main-memory addresses are full 24-bit bus addresses; VRAM addresses are
+/// offsets into VRAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DmaMode {
+    VramToVram = 0,
+    MainToVram = 1,
+    VramToMain = 2,
+}
+
+impl DmaMode {
+    fn from_bits(bits: u16) -> Self {
+        match bits & 0x03 {
+            1 => DmaMode::MainToVram,
+            2 => DmaMode::VramToMain,
+            _ => DmaMode::VramToVram,
+        }
+    }
+}
+
 /// Sprite attribute entry (8 bytes in OAM)
+///
+/// Attribute word layout:
+/// - bit 15: enable
+/// - bit 13: vertical flip
+/// - bit 12: horizontal flip
+/// - bits 11-8: palette
+/// - bits 5-4: priority
+/// - bits 2-0: size (bit 2 clear: square sizes in bits 1-0; bit 2 set:
+///   bit 0 selects 8x16 (0) or 16x8 (1))
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpriteAttr {
     pub y_pos: u16,      // Y position (0-511)
     pub x_pos: u16,      // X position (0-511)
@@ -146,6 +324,57 @@ pub struct SpriteAttr {
 }
 
 impl SpriteAttr {
+    /// Build a sprite from typed fields, packing them into the attribute word
+    ///
+    /// `palette` is clamped to 0-15 and `priority` to 0-3.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_parts(
+        x: u16,
+        y: u16,
+        tile: u16,
+        palette: u8,
+        size: SpriteSize,
+        flip_h: bool,
+        flip_v: bool,
+        priority: u8,
+        enabled: bool,
+    ) -> Self {
+        let mut attr = size as u16;
+        attr |= (palette.min(0xF) as u16) << 8;
+        attr |= (priority.min(3) as u16) << 4;
+        if flip_h {
+            attr |= 0x1000;
+        }
+        if flip_v {
+            attr |= 0x2000;
+        }
+        if enabled {
+            attr |= 0x8000;
+        }
+        Self {
+            y_pos: y,
+            x_pos: x,
+            tile_index: tile,
+            attr,
+        }
+    }
+
+    /// Unpack the sprite into the same fields accepted by [`Self::from_parts`]
+    #[allow(clippy::type_complexity)]
+    pub fn to_parts(&self) -> (u16, u16, u16, u8, SpriteSize, bool, bool, u8, bool) {
+        (
+            self.x_pos,
+            self.y_pos,
+            self.tile_index,
+            self.palette(),
+            self.size(),
+            self.flip_h(),
+            self.flip_v(),
+            self.priority(),
+            self.is_enabled(),
+        )
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.attr & 0x8000 != 0
     }
@@ -163,10 +392,17 @@ impl SpriteAttr {
     }
 
     pub fn priority(&self) -> u8 {
-        ((self.attr >> 10) & 0x3) as u8
+        ((self.attr >> 4) & 0x3) as u8
     }
 
     pub fn size(&self) -> SpriteSize {
+        if self.attr & 0x4 != 0 {
+            return if self.attr & 0x1 == 0 {
+                SpriteSize::Size8x16
+            } else {
+                SpriteSize::Size16x8
+            };
+        }
         match self.attr & 0x3 {
             0 => SpriteSize::Size8x8,
             1 => SpriteSize::Size16x16,
@@ -178,11 +414,14 @@ impl SpriteAttr {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
 pub enum SpriteSize {
-    Size8x8,
-    Size16x16,
-    Size32x32,
-    Size64x64,
+    Size8x8 = 0,
+    Size16x16 = 1,
+    Size32x32 = 2,
+    Size64x64 = 3,
+    Size8x16 = 4, // 8 wide, 16 tall
+    Size16x8 = 5, // 16 wide, 8 tall
 }
 
 impl SpriteSize {
@@ -192,10 +431,264 @@ impl SpriteSize {
             SpriteSize::Size16x16 => (16, 16),
             SpriteSize::Size32x32 => (32, 32),
             SpriteSize::Size64x64 => (64, 64),
+            SpriteSize::Size8x16 => (8, 16),
+            SpriteSize::Size16x8 => (16, 8),
         }
     }
 }
 
+/// Visible rectangle of the native frame; `right` and `bottom` are exclusive
+///
+/// The rest of the frame is overscan that a TV would hide, and is cropped
+/// from [`Vdp::framebuffer_rgba8888`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActiveArea {
+    pub left: u16,
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+}
+
+impl ActiveArea {
+    /// The whole 384x288 frame
+    pub const FULL: Self = Self {
+        left: 0,
+        top: 0,
+        right: Vdp::NATIVE_WIDTH as u16,
+        bottom: Vdp::NATIVE_HEIGHT as u16,
+    };
+
+    pub fn width(&self) -> usize {
+        (self.right - self.left) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.bottom - self.top) as usize
+    }
+}
+
+/// A window rectangle and the layers drawn inside and outside it
+///
+/// Right and bottom are exclusive. A pixel inside window 0 uses its
+/// `inside_layers`, then one inside window 1 uses that window's; pixels
+/// outside every enabled window draw only the layers allowed by all of their
+/// `outside_layers` masks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Window {
+    pub left: u16,
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+    pub inside_layers: u8,
+    pub outside_layers: u8,
+}
+
+impl Window {
+    pub const BG0: u8 = 1 << 0;
+    pub const BG1: u8 = 1 << 1;
+    pub const SPRITES: u8 = 1 << 2;
+    pub const POLYGONS: u8 = 1 << 3;
+    pub const ALL_LAYERS: u8 = Self::BG0 | Self::BG1 | Self::SPRITES | Self::POLYGONS;
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.left as usize..self.right as usize).contains(&x)
+            && (self.top as usize..self.bottom as usize).contains(&y)
+    }
+
+    fn edges(&self) -> [u16; 4] {
+        [self.left, self.top, self.right, self.bottom]
+    }
+
+    fn edge_mut(&mut self, edge: usize) -> &mut u16 {
+        match edge {
+            0 => &mut self.left,
+            1 => &mut self.top,
+            2 => &mut self.right,
+            _ => &mut self.bottom,
+        }
+    }
+}
+
+/// Errors reported by VDP configuration calls
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum VdpError {
+    #[error("active area {0:?} does not fit within the 384x288 frame")]
+    InvalidActiveArea(ActiveArea),
+}
+
+/// Snapshot of the VDP register file and timing, used for save states
+///
+/// VRAM, CRAM and OAM contents are saved separately. Flag registers are
+/// stored as their raw bit patterns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VdpRegisterDump {
+    pub regs: Vec<u8>,
+    pub display_control: u16,
+    pub display_status: u16,
+    pub v_count: u16,
+    pub h_count: u16,
+    pub bg0_control: u16,
+    pub bg0_scroll_x: i16,
+    pub bg0_scroll_y: i16,
+    pub bg0_affine: [i16; 4],
+    pub bg0_ref_x: i32,
+    pub bg0_ref_y: i32,
+    pub bg0_tilemap_addr: u32,
+    pub bg1_control: u16,
+    pub bg1_scroll_x: i16,
+    pub bg1_scroll_y: i16,
+    pub bg1_tilemap_addr: u32,
+    pub scroll_table_x: [Vec<i16>; 2],
+    pub scroll_table_y: [Vec<i16>; 2],
+    pub sprite_control: u16,
+    pub sprite_oam_addr: u16,
+    pub sprite_collision: bool,
+    pub dma_source: u32,
+    pub dma_dest: u32,
+    pub dma_length: u16,
+    pub dma_active: bool,
+    pub dma_mode: u8,
+    pub dma_progress: u16,
+    pub irq_enable: u16,
+    pub irq_status: u16,
+    pub irq_line_compare: u16,
+    pub palette_index: u8,
+    pub palette_data: u8,
+    pub backdrop_color: u16,
+    pub mosaic_size: u8,
+    pub blend_control: u16,
+    pub blend_alpha: u8,
+    pub active_area: ActiveArea,
+    pub active_area_latch: [u16; 4],
+    pub windows: [Window; 2],
+    pub cycles: u64,
+    pub frame_count: u64,
+}
+
+/// Snapshot of VDP memory, used for save states alongside [`VdpRegisterDump`]
+///
+/// Both framebuffers are included: the completed frame on display, and the
+/// frame in progress with the lines already drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VdpMemoryDump {
+    pub vram: Vec<u8>,
+    pub cram: Vec<u8>,
+    pub oam: Vec<SpriteAttr>,
+    pub framebuffer: Vec<u32>,
+    pub back_buffer: Vec<u32>,
+}
+
+/// Screen areas redrawn with different content, as `(x, y, width, height)`
+/// rectangles in framebuffer coordinates
+///
+/// Horizontally adjacent rectangles of equal height are merged as they are
+/// added, and marking the whole screen replaces the list with one rectangle.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyRects {
+    pub rects: Vec<(u16, u16, u16, u16)>,
+    full: bool,
+}
+
+impl DirtyRects {
+    /// Add a rectangle, extending the previous one when they touch on a row
+    pub fn add(&mut self, x: u16, y: u16, width: u16, height: u16) {
+        if self.full || width == 0 || height == 0 {
+            return;
+        }
+        if let Some(last) = self.rects.last_mut()
+            && last.1 == y
+            && last.3 == height
+            && last.0 + last.2 == x
+        {
+            last.2 += width;
+            return;
+        }
+        self.rects.push((x, y, width, height));
+    }
+
+    /// Mark the whole `width` x `height` screen as dirty
+    pub fn mark_all(&mut self, width: u16, height: u16) {
+        self.rects.clear();
+        self.rects.push((0, 0, width, height));
+        self.full = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+        self.full = false;
+    }
+}
+
+/// A flat-shaded triangle in screen coordinates
+///
+/// `color` is a CRAM color number, `palette * 256 + index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Triangle {
+    pub v0: (i16, i16),
+    pub v1: (i16, i16),
+    pub v2: (i16, i16),
+    pub color: u16,
+}
+
+impl Triangle {
+    /// Leftmost and rightmost x covered on scanline `y`, if any
+    ///
+    /// Edges are included, so a triangle always covers its vertices.
+    fn span(&self, y: i32) -> Option<(i32, i32)> {
+        let mut span: Option<(i32, i32)> = None;
+        let mut include = |x: i32| {
+            span = Some(span.map_or((x, x), |(l, r)| (l.min(x), r.max(x))));
+        };
+        for (a, b) in [(self.v0, self.v1), (self.v1, self.v2), (self.v2, self.v0)] {
+            let (ax, ay, bx, by) = (a.0 as i32, a.1 as i32, b.0 as i32, b.1 as i32);
+            if y < ay.min(by) || y > ay.max(by) {
+                continue;
+            }
+            if ay == by {
+                include(ax);
+                include(bx);
+            } else {
+                include(ax + (y - ay) * (bx - ax) / (by - ay));
+            }
+        }
+        span
+    }
+}
+
+/// Triangles submitted for the next frame
+///
+/// The queue is latched as the beam enters scanline 0; at most
+/// [`PolygonQueue::MAX_TRIANGLES`] are accepted per frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolygonQueue {
+    pub triangles: Vec<Triangle>,
+}
+
+impl PolygonQueue {
+    pub const MAX_TRIANGLES: usize = 4000;
+}
+
+/// An 8x8 screen cell of a scrolling BG layer and the tilemap entry drawn in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BgCell {
+    rect: (u16, u16, u16, u16),
+    entry: u16,
+}
+
+/// State that produced the last rendered frame, compared at the end of the
+/// next one to find what changed
+struct FrameSnapshot {
+    layout: VdpRegisterDump,
+    oam: Vec<SpriteAttr>,
+    bg_cells: [Vec<BgCell>; 2],
+    polygons: Vec<Triangle>,
+}
+
 /// Main VDP-T state
 pub struct Vdp {
     // Video RAM (512KB) - tiles, sprites, framebuffer
@@ -229,24 +722,31 @@ pub struct Vdp {
     bg1_scroll_y: i16,
     bg1_tilemap_addr: u32,
 
+    // Per-scanline scroll for each BG layer, used with BgControl::SCROLL_TABLE
+    scroll_table_x: [Vec<i16>; 2],
+    scroll_table_y: [Vec<i16>; 2],
+
     // Sprite OAM (Object Attribute Memory) - 128 sprites * 8 bytes
     oam: Vec<SpriteAttr>,
 
     // Sprite and OAM control
     sprite_control: SpriteControl,
     sprite_oam_addr: u16,
+    sprite_collision: bool, // Opaque sprite pixels overlapped since frame start
 
     // DMA state
     dma_source: u32,
     dma_dest: u32,
     dma_length: u16,
     dma_active: bool,
-
+    dma_mode: DmaMode,
+    dma_progress: u16,    // Bytes transferred so far
+    dma_done_latch: bool, // Set when a transfer completes
     // IRQ registers
     irq_enable: IrqFlags,
     irq_status: IrqFlags,
     irq_line_compare: u16,
-
+    line_compare_latch: bool, // Set when v_count reaches irq_line_compare
     // Palette registers
     palette_index: u8,
     palette_data: u8,
@@ -254,12 +754,47 @@ pub struct Vdp {
     // Backdrop color (cached 16-bit value)
     backdrop_color: u16,
 
-    // Framebuffer for rendering (384x288, 18-bit color stored as u32)
-    framebuffer: Vec<u32>,
+    // Mosaic block size in pixels (1-16) for layers with MOSAIC set
+    mosaic_size: u8,
+    active_area: ActiveArea,
+    active_area_latch: [u16; 4], // Edges written to 0x40-0x46, applied by 0x48
+    windows: [Window; 2],
+    window_mask: Vec<u8>, // Window layer mask for each pixel of the current scanline
+
+    // Alpha blending
+    blend_control: BlendControl,
+    blend_alpha: u8,
+    line_layers: Vec<Layer>, // Layer owning each pixel of the current scanline
+    polygon_queue: PolygonQueue,
+    polygons: Vec<Triangle>, // Triangles drawn in the current frame
+    tile_cache: TileCache,
+    palette_rgb: Vec<u32>, // Each 3-byte CRAM entry converted to RGB888
+    dirty: DirtyRects,
+    snapshot: Option<FrameSnapshot>,
+    modified_tiles: Vec<bool>, // 32-byte VRAM blocks written since the last frame
+    cram_modified: bool,
+
+    // Framebuffers (384x288, 18-bit color stored as u32). Scanlines are drawn
+    // into the back buffer, which is swapped to the front once complete.
+    front_buffer: Vec<u32>,
+    back_buffer: Vec<u32>,
+    headless: bool,           // Skip rendering; the framebuffer is left empty
+    render_from_line: u64,    // Absolute scanline drawing resumes at, for frame skip
+    scalar_bg1: bool,         // Draw BG1 pixel by pixel; benchmarks compare the paths
+    bypass_tile_cache: bool,  // Decode every tile row from VRAM; for benchmarks
+    scanlines_per_frame: u16, // Total lines including blanking (NTSC 288, PAL 312)
+    rendered_frames: u64,     // Frames whose last visible line was drawn
 
     // Timing
     cycles: u64,
     frame_count: u64,
+    next_render_line: u64, // Absolute index of the next scanline to render
+}
+
+impl Default for Vdp {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Vdp {
@@ -276,10 +811,20 @@ impl Vdp {
 
     // Timing constants (for 18.432 MHz system clock)
     pub const CYCLES_PER_SCANLINE: u64 = 1024;
-    pub const SCANLINES_PER_FRAME: u16 = 288;
+    pub const SCANLINES_PER_FRAME: u16 = 288; // NTSC default
+    pub const PAL_SCANLINES_PER_FRAME: u16 = 312;
     pub const VBLANK_START: u16 = 240; // Start of VBLANK
 
     pub fn new() -> Self {
+        Self::with_rendering(true)
+    }
+
+    fn with_rendering(render: bool) -> Self {
+        let pixels = if render {
+            Self::NATIVE_WIDTH * Self::NATIVE_HEIGHT
+        } else {
+            0
+        };
         Self {
             vram: vec![0; Self::VRAM_SIZE],
             cram: vec![0; Self::CRAM_SIZE],
@@ -299,6 +844,8 @@ impl Vdp {
             bg1_scroll_x: 0,
             bg1_scroll_y: 0,
             bg1_tilemap_addr: 0,
+            scroll_table_x: [vec![0; Self::NATIVE_HEIGHT], vec![0; Self::NATIVE_HEIGHT]],
+            scroll_table_y: [vec![0; Self::NATIVE_HEIGHT], vec![0; Self::NATIVE_HEIGHT]],
             oam: vec![
                 SpriteAttr {
                     y_pos: 0,
@@ -310,30 +857,95 @@ impl Vdp {
             ],
             sprite_control: SpriteControl::empty(),
             sprite_oam_addr: 0,
+            sprite_collision: false,
             dma_source: 0,
             dma_dest: 0,
             dma_length: 0,
             dma_active: false,
+            dma_mode: DmaMode::VramToVram,
+            dma_progress: 0,
+            dma_done_latch: false,
             irq_enable: IrqFlags::empty(),
             irq_status: IrqFlags::empty(),
             irq_line_compare: 0,
+            line_compare_latch: false,
             palette_index: 0,
             palette_data: 0,
             backdrop_color: 0,
-            framebuffer: vec![0; Self::NATIVE_WIDTH * Self::NATIVE_HEIGHT],
+            mosaic_size: 1,
+            active_area: ActiveArea::FULL,
+            active_area_latch: [0, 0, Self::NATIVE_WIDTH as u16, Self::NATIVE_HEIGHT as u16],
+            windows: [Window::default(); 2],
+            window_mask: vec![Window::ALL_LAYERS; Self::NATIVE_WIDTH],
+            blend_control: BlendControl::empty(),
+            blend_alpha: 0,
+            line_layers: vec![Layer::Backdrop; Self::NATIVE_WIDTH],
+            polygon_queue: PolygonQueue::default(),
+            polygons: Vec::new(),
+            tile_cache: TileCache::new(),
+            palette_rgb: vec![0; Self::CRAM_SIZE / 3],
+            dirty: DirtyRects::default(),
+            snapshot: None,
+            modified_tiles: vec![false; Self::VRAM_SIZE / 32],
+            cram_modified: false,
+            front_buffer: vec![0; pixels],
+            back_buffer: vec![0; pixels],
+            headless: !render,
+            render_from_line: 0,
+            scalar_bg1: false,
+            bypass_tile_cache: false,
+            scanlines_per_frame: Self::SCANLINES_PER_FRAME,
+            rendered_frames: 0,
             cycles: 0,
             frame_count: 0,
+            next_render_line: 0,
         }
     }
 
+    /// Create a VDP that keeps timing, DMA and interrupts but never renders
+    ///
+    /// No framebuffer is allocated, so [`Vdp::framebuffer`] is empty, its
+    /// dimensions are `(0, 0)` and sprite collisions are not detected.
+    pub fn new_headless() -> Self {
+        Self::with_rendering(false)
+    }
+
+    /// Whether this VDP was created with [`Vdp::new_headless`]
+    pub fn is_headless(&self) -> bool {
+        self.headless
+    }
+
+    /// Return registers, timing and DMA state to their power-on values
+    ///
+    /// VRAM, CRAM, OAM and the framebuffer keep their contents, as do the
+    /// headless and scanline count settings.
+    pub fn reset_registers(&mut self) {
+        *self = Self {
+            vram: std::mem::take(&mut self.vram),
+            cram: std::mem::take(&mut self.cram),
+            palette_rgb: std::mem::take(&mut self.palette_rgb),
+            oam: std::mem::take(&mut self.oam),
+            front_buffer: std::mem::take(&mut self.front_buffer),
+            back_buffer: std::mem::take(&mut self.back_buffer),
+            headless: self.headless,
+            scanlines_per_frame: self.scanlines_per_frame,
+            ..Self::with_rendering(false)
+        };
+    }
+
     /// Advance VDP timing by the specified number of cycles
+    ///
+    /// Each scanline is rendered as the beam enters it, so register writes
+    /// made between steps take effect from the next scanline on.
     pub fn step(&mut self, cycles: u64) -> bool {
         self.cycles += cycles;
+        self.run_vram_dma(cycles);
+        self.render_pending_scanlines();
 
         // Update scanline position
         let old_v = self.v_count;
         let scanline_cycles = self.cycles / Self::CYCLES_PER_SCANLINE;
-        self.v_count = (scanline_cycles % Self::SCANLINES_PER_FRAME as u64) as u16;
+        self.v_count = (scanline_cycles % self.scanlines_per_frame as u64) as u16;
         self.h_count = (self.cycles % Self::CYCLES_PER_SCANLINE) as u16;
 
         // Update display status flags
@@ -342,19 +954,76 @@ impl Vdp {
         self.display_status
             .set(DisplayStatus::HBLANK, self.h_count >= 768);
 
+        // Line compare fires once as the beam reaches the target scanline
+        let line_match = self.v_count == self.irq_line_compare;
+        self.display_status.set(DisplayStatus::LINECMP, line_match);
+        if line_match && old_v != self.irq_line_compare {
+            self.irq_status.insert(IrqFlags::LINECMP);
+            self.line_compare_latch = true;
+        }
+
         // Check for VBLANK transition
         let entered_vblank = old_v < Self::VBLANK_START && self.v_count >= Self::VBLANK_START;
 
         if entered_vblank {
             self.frame_count += 1;
-            if self.display_control.contains(DisplayControl::ENABLE) {
-                self.render_frame();
-            }
         }
 
         entered_vblank
     }
 
+    /// Render every scanline the beam has entered since the last step
+    fn render_pending_scanlines(&mut self) {
+        let current_line = self.cycles / Self::CYCLES_PER_SCANLINE;
+        // Lines more than a frame behind would be overwritten anyway
+        let frame_lines = self.scanlines_per_frame as u64;
+        if current_line >= self.next_render_line + frame_lines {
+            self.next_render_line = current_line + 1 - frame_lines;
+        }
+
+        while self.next_render_line <= current_line {
+            let y = (self.next_render_line % frame_lines) as usize;
+            if y == 0 {
+                self.latch_polygons();
+            }
+            if self.display_control.contains(DisplayControl::ENABLE)
+                && self.next_render_line >= self.render_from_line
+            {
+                self.render_scanline(y);
+                if !self.headless && y + 1 == self.display_dimensions().1 {
+                    self.rendered_frames += 1;
+                }
+            }
+            self.next_render_line += 1;
+        }
+    }
+
+    // Register access reference:
+    //
+    // Read-only:
+    //   0x0002 DISPSTAT (driven by v_count/h_count and DMA state)
+    //   0x0004 VCOUNT, 0x0006 HCOUNT
+    // Read / write-1-to-clear:
+    //   0x0082 IRQ_STATUS (reads return pending flags, writes clear the given bits)
+    // Write-only (read back as 0):
+    //   0x007A DMA_CONTROL (bit 15 starts a transfer)
+    //   0x0048 ACTIVE_APPLY (applies the staged edges; an invalid area is ignored)
+    //   0x0096 MOSAIC_SIZE (block size 1-16, clamped)
+    // Read-write:
+    //   0x0000 DISPCTL
+    //   0x0010-0x0026 BG0 control, scroll, affine matrix, reference point, tilemap
+    //   0x0030-0x0036 BG1 control, scroll, tilemap
+    //   0x0040-0x0046 staged active area left, top, right, bottom
+    //   0x0050 SPRITE_CONTROL, 0x0052 SPRITE_OAM_ADDR
+    //   0x0070-0x0078 DMA source, destination, length, 0x007C DMA_MODE
+    //   0x0080 IRQ_ENABLE, 0x0084 IRQ_LINE_COMPARE
+    //   0x0090 PALETTE_INDEX, 0x0092 PALETTE_DATA, 0x0094 BACKDROP_COLOR
+    //   0x0098 BLEND_CONTROL, 0x009A BLEND_ALPHA
+    //   0x00A0-0x00A6 WIN0 left, top, right, bottom; 0x00A8-0x00AE WIN1
+    //   0x00B0 WINDOW_LAYERS (per window: inside mask low nibble, outside high;
+    //          WIN0 in the low byte)
+    // Anything else is backed by the raw register array.
+
     /// Read a 16-bit register
     pub fn read_reg(&self, offset: u32) -> u16 {
         match offset {
@@ -378,13 +1047,18 @@ impl Vdp {
             0x0032 => self.bg1_scroll_x as u16,
             0x0034 => self.bg1_scroll_y as u16,
             0x0036 => self.bg1_tilemap_addr as u16,
+            0x0040 | 0x0042 | 0x0044 | 0x0046 => {
+                self.active_area_latch[((offset - 0x0040) / 2) as usize]
+            }
+            0x0048 => 0, // Active area apply is write-only
             0x0050 => self.sprite_control.bits(),
-            0x0052 => self.sprite_oam_addr as u16,
+            0x0052 => self.sprite_oam_addr,
             0x0070 => (self.dma_source & 0xFFFF) as u16,
             0x0072 => ((self.dma_source >> 16) & 0xFF) as u16,
             0x0074 => (self.dma_dest & 0xFFFF) as u16,
             0x0076 => ((self.dma_dest >> 16) & 0xFF) as u16,
             0x0078 => self.dma_length,
+            0x007C => self.dma_mode as u16,
             0x007A => {
                 // DMA control - read as 0 (not used)
                 0
@@ -394,7 +1068,17 @@ impl Vdp {
             0x0084 => self.irq_line_compare,
             0x0090 => self.palette_index as u16,
             0x0092 => self.palette_data as u16,
-            0x0094 => self.backdrop_color as u16,
+            0x0094 => self.backdrop_color,
+            0x0096 => 0, // Mosaic size is write-only
+            0x0098 => self.blend_control.bits(),
+            0x009A => self.blend_alpha as u16,
+            0x00A0 | 0x00A2 | 0x00A4 | 0x00A6 | 0x00A8 | 0x00AA | 0x00AC | 0x00AE => {
+                let reg = ((offset - 0x00A0) / 2) as usize;
+                self.windows[reg / 4].edges()[reg % 4]
+            }
+            0x00B0 => self.windows.iter().rev().fold(0, |bits, w| {
+                bits << 8 | (w.outside_layers as u16) << 4 | w.inside_layers as u16
+            }),
             _ => {
                 // Default to reading from raw register array
                 let idx = (offset as usize) % self.regs.len();
@@ -413,6 +1097,7 @@ impl Vdp {
             0x0000 => {
                 self.display_control = DisplayControl::from_bits_truncate(value);
             }
+            0x0002 => {} // DisplayStatus is read-only
             0x0004 => {} // VCount is read-only
             0x0006 => {} // HCount is read-only
             0x0010 => {
@@ -447,12 +1132,19 @@ impl Vdp {
             0x0032 => self.bg1_scroll_x = value as i16,
             0x0034 => self.bg1_scroll_y = value as i16,
             0x0036 => self.bg1_tilemap_addr = value as u32,
+            0x0040 | 0x0042 | 0x0044 | 0x0046 => {
+                self.active_area_latch[((offset - 0x0040) / 2) as usize] = value;
+            }
+            0x0048 => {
+                let [left, top, right, bottom] = self.active_area_latch;
+                let _ = self.set_active_area(left, top, right, bottom);
+            }
             0x0050 => {
                 self.sprite_control = SpriteControl::from_bits_truncate(value);
             }
             0x0052 => {
                 // Sprite OAM base address
-                self.sprite_oam_addr = value as u16;
+                self.sprite_oam_addr = value;
             }
             0x0070 => {
                 // DMA source low word
@@ -471,6 +1163,7 @@ impl Vdp {
                 self.dma_dest = (self.dma_dest & 0x0000FFFF) | ((value as u32 & 0xFF) << 16);
             }
             0x0078 => self.dma_length = value,
+            0x007C => self.dma_mode = DmaMode::from_bits(value),
             0x007A => {
                 // DMA control - writing initiates transfer
                 if value & 0x8000 != 0 {
@@ -496,7 +1189,7 @@ impl Vdp {
                 // Writing palette data stores into CRAM at current index
                 let idx = (self.palette_index as u32) * 3;
                 // For simplicity, write the low byte of value into the palette data
-                self.write_cram(idx, (self.palette_data & 0x3F) as u8);
+                self.write_cram(idx, self.palette_data & 0x3F);
             }
             0x0094 => {
                 self.backdrop_color = value;
@@ -506,6 +1199,26 @@ impl Vdp {
                 let b = ((value >> 12) & 0x3F) as u8;
                 self.set_backdrop_color(r, g, b);
             }
+            0x0096 => {
+                self.mosaic_size = value.clamp(1, 16) as u8;
+            }
+            0x0098 => {
+                self.blend_control = BlendControl::from_bits_truncate(value);
+            }
+            0x009A => {
+                self.blend_alpha = value as u8;
+            }
+            0x00A0 | 0x00A2 | 0x00A4 | 0x00A6 | 0x00A8 | 0x00AA | 0x00AC | 0x00AE => {
+                let reg = ((offset - 0x00A0) / 2) as usize;
+                *self.windows[reg / 4].edge_mut(reg % 4) = value;
+            }
+            0x00B0 => {
+                for (i, window) in self.windows.iter_mut().enumerate() {
+                    let bits = (value >> (i * 8)) as u8;
+                    window.inside_layers = bits & 0x0F;
+                    window.outside_layers = bits >> 4;
+                }
+            }
             _ => {
                 // Write to raw register array
                 let idx = (offset as usize) % self.regs.len();
@@ -518,6 +1231,141 @@ impl Vdp {
         }
     }
 
+    /// Capture every register and the beam position for a save state
+    pub fn dump_registers(&self) -> VdpRegisterDump {
+        VdpRegisterDump {
+            regs: self.regs.to_vec(),
+            display_control: self.display_control.bits(),
+            display_status: self.display_status.bits(),
+            v_count: self.v_count,
+            h_count: self.h_count,
+            bg0_control: self.bg0_control.bits(),
+            bg0_scroll_x: self.bg0_scroll_x,
+            bg0_scroll_y: self.bg0_scroll_y,
+            bg0_affine: self.bg0_affine,
+            bg0_ref_x: self.bg0_ref_x,
+            bg0_ref_y: self.bg0_ref_y,
+            bg0_tilemap_addr: self.bg0_tilemap_addr,
+            bg1_control: self.bg1_control.bits(),
+            bg1_scroll_x: self.bg1_scroll_x,
+            bg1_scroll_y: self.bg1_scroll_y,
+            bg1_tilemap_addr: self.bg1_tilemap_addr,
+            scroll_table_x: self.scroll_table_x.clone(),
+            scroll_table_y: self.scroll_table_y.clone(),
+            sprite_control: self.sprite_control.bits(),
+            sprite_oam_addr: self.sprite_oam_addr,
+            sprite_collision: self.sprite_collision,
+            dma_source: self.dma_source,
+            dma_dest: self.dma_dest,
+            dma_length: self.dma_length,
+            dma_active: self.dma_active,
+            dma_mode: self.dma_mode as u8,
+            dma_progress: self.dma_progress,
+            irq_enable: self.irq_enable.bits(),
+            irq_status: self.irq_status.bits(),
+            irq_line_compare: self.irq_line_compare,
+            palette_index: self.palette_index,
+            palette_data: self.palette_data,
+            backdrop_color: self.backdrop_color,
+            mosaic_size: self.mosaic_size,
+            blend_control: self.blend_control.bits(),
+            blend_alpha: self.blend_alpha,
+            active_area: self.active_area,
+            active_area_latch: self.active_area_latch,
+            windows: self.windows,
+            cycles: self.cycles,
+            frame_count: self.frame_count,
+        }
+    }
+
+    /// Restore registers from a dump; VRAM, CRAM and OAM are left untouched
+    pub fn restore_registers(&mut self, dump: &VdpRegisterDump) {
+        let len = dump.regs.len().min(self.regs.len());
+        self.regs[..len].copy_from_slice(&dump.regs[..len]);
+        self.display_control = DisplayControl::from_bits_truncate(dump.display_control);
+        self.display_status = DisplayStatus::from_bits_truncate(dump.display_status);
+        self.v_count = dump.v_count;
+        self.h_count = dump.h_count;
+        self.bg0_control = BgControl::from_bits_truncate(dump.bg0_control);
+        self.bg0_scroll_x = dump.bg0_scroll_x;
+        self.bg0_scroll_y = dump.bg0_scroll_y;
+        self.bg0_affine = dump.bg0_affine;
+        self.bg0_ref_x = dump.bg0_ref_x;
+        self.bg0_ref_y = dump.bg0_ref_y;
+        self.bg0_tilemap_addr = dump.bg0_tilemap_addr;
+        self.bg1_control = BgControl::from_bits_truncate(dump.bg1_control);
+        self.bg1_scroll_x = dump.bg1_scroll_x;
+        self.bg1_scroll_y = dump.bg1_scroll_y;
+        self.bg1_tilemap_addr = dump.bg1_tilemap_addr;
+        for layer in 0..2 {
+            self.set_scroll_table(
+                layer,
+                &dump.scroll_table_x[layer],
+                &dump.scroll_table_y[layer],
+            );
+        }
+        self.sprite_control = SpriteControl::from_bits_truncate(dump.sprite_control);
+        self.sprite_oam_addr = dump.sprite_oam_addr;
+        self.sprite_collision = dump.sprite_collision;
+        self.dma_source = dump.dma_source;
+        self.dma_dest = dump.dma_dest;
+        self.dma_length = dump.dma_length;
+        self.dma_active = dump.dma_active;
+        self.dma_mode = DmaMode::from_bits(dump.dma_mode as u16);
+        self.dma_progress = dump.dma_progress;
+        self.irq_enable = IrqFlags::from_bits_truncate(dump.irq_enable);
+        self.irq_status = IrqFlags::from_bits_truncate(dump.irq_status);
+        self.irq_line_compare = dump.irq_line_compare;
+        self.palette_index = dump.palette_index;
+        self.palette_data = dump.palette_data;
+        self.backdrop_color = dump.backdrop_color;
+        self.mosaic_size = dump.mosaic_size.clamp(1, 16);
+        self.blend_control = BlendControl::from_bits_truncate(dump.blend_control);
+        self.blend_alpha = dump.blend_alpha;
+        if self.set_active_area_rect(dump.active_area).is_err() {
+            self.active_area = ActiveArea::FULL;
+        }
+        self.active_area_latch = dump.active_area_latch;
+        self.windows = dump.windows;
+        self.cycles = dump.cycles;
+        self.frame_count = dump.frame_count;
+        // The line the beam is on has already been drawn
+        self.next_render_line = self.cycles / Self::CYCLES_PER_SCANLINE + 1;
+        self.render_from_line = 0;
+        self.line_compare_latch = false;
+    }
+
+    /// Capture VRAM, CRAM, OAM and the framebuffer for a save state
+    pub fn dump_memory(&self) -> VdpMemoryDump {
+        VdpMemoryDump {
+            vram: self.vram.clone(),
+            cram: self.cram.clone(),
+            oam: self.oam.clone(),
+            framebuffer: self.front_buffer.clone(),
+            back_buffer: self.back_buffer.clone(),
+        }
+    }
+
+    /// Restore memory from a dump; sizes are clamped to the current regions
+    pub fn restore_memory(&mut self, dump: &VdpMemoryDump) {
+        fn restore<T: Copy>(dst: &mut [T], src: &[T]) {
+            let len = src.len().min(dst.len());
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+        restore(&mut self.vram, &dump.vram);
+        self.tile_cache.clear();
+        self.snapshot = None;
+        restore(&mut self.cram, &dump.cram);
+        self.palette_rgb = self
+            .cram
+            .chunks_exact(3)
+            .map(|entry| self.rgb666_to_rgb888(entry[0], entry[1], entry[2]))
+            .collect();
+        restore(&mut self.oam, &dump.oam);
+        restore(&mut self.front_buffer, &dump.framebuffer);
+        restore(&mut self.back_buffer, &dump.back_buffer);
+    }
+
     /// Read a byte from VRAM
     pub fn read_vram(&self, offset: u32) -> u8 {
         self.vram
@@ -530,6 +1378,10 @@ impl Vdp {
     pub fn write_vram(&mut self, offset: u32, value: u8) {
         let idx = (offset as usize) % Self::VRAM_SIZE;
         if let Some(cell) = self.vram.get_mut(idx) {
+            if *cell != value {
+                self.tile_cache.invalidate(idx);
+                self.modified_tiles[idx / 32] = true;
+            }
             *cell = value;
         }
     }
@@ -546,264 +1398,652 @@ impl Vdp {
     pub fn write_cram(&mut self, offset: u32, value: u8) {
         let idx = (offset as usize) % Self::CRAM_SIZE;
         if let Some(cell) = self.cram.get_mut(idx) {
+            self.cram_modified |= *cell != value;
             *cell = value;
         }
+        let entry = idx / 3;
+        if let Some(&[r, g, b]) = self.cram.get(entry * 3..entry * 3 + 3) {
+            let rgb = self.rgb666_to_rgb888(r, g, b);
+            self.palette_rgb[entry] = rgb;
+        }
     }
 
     /// Start a DMA transfer
+    ///
+    /// Bytes move one per cycle: VRAM-to-VRAM copies advance in `step`, and
+    /// transfers touching main memory are driven by the emulator through the bus.
     fn start_dma(&mut self) {
-        self.dma_active = true;
-        self.display_status.insert(DisplayStatus::DMA_BUSY);
-        // TODO: Implement actual DMA transfer logic
-        // For now, just mark as complete immediately
-        self.dma_active = false;
-        self.display_status.remove(DisplayStatus::DMA_BUSY);
+        self.dma_progress = 0;
+        self.dma_active = self.dma_length > 0;
+        self.display_status
+            .set(DisplayStatus::DMA_BUSY, self.dma_active);
     }
 
-    /// Render the current frame to the framebuffer
-    fn render_frame(&mut self) {
-        // Clear framebuffer to backdrop color
-        let backdrop = self.read_backdrop_color();
-        for pixel in self.framebuffer.iter_mut() {
-            *pixel = backdrop;
+    /// Claim the next byte of the active transfer as `(mode, source, dest)`
+    pub(crate) fn next_dma_transfer(&mut self) -> Option<(DmaMode, u32, u32)> {
+        if !self.dma_active {
+            return None;
+        }
+        let offset = self.dma_progress as u32;
+        let transfer = (
+            self.dma_mode,
+            (self.dma_source + offset) & 0x00FF_FFFF,
+            (self.dma_dest + offset) & 0x00FF_FFFF,
+        );
+        self.dma_progress += 1;
+        if self.dma_progress == self.dma_length {
+            self.dma_active = false;
+            self.display_status.remove(DisplayStatus::DMA_BUSY);
+            self.irq_status.insert(IrqFlags::DMA_DONE);
+            self.dma_done_latch = true;
         }
+        Some(transfer)
+    }
 
-        // Render layers in priority order
-        if self.display_control.contains(DisplayControl::BG1_ENABLE) {
-            self.render_bg1();
+    /// Whether a DMA transfer between main memory and VRAM is in progress
+    pub(crate) fn main_dma_active(&self) -> bool {
+        self.dma_active && self.dma_mode != DmaMode::VramToVram
+    }
+
+    /// Consume the DMA-complete latch and report whether an interrupt should fire
+    pub(crate) fn take_dma_done(&mut self) -> bool {
+        let done = std::mem::take(&mut self.dma_done_latch);
+        done && self.irq_enable.contains(IrqFlags::DMA_DONE)
+    }
+
+    /// Copy up to `cycles` bytes of an active VRAM-to-VRAM transfer
+    fn run_vram_dma(&mut self, cycles: u64) {
+        if self.dma_mode != DmaMode::VramToVram {
+            return;
+        }
+        for _ in 0..cycles {
+            let Some((_, src, dst)) = self.next_dma_transfer() else {
+                break;
+            };
+            let value = self.read_vram(src);
+            self.write_vram(dst, value);
         }
+    }
 
-        if self.display_control.contains(DisplayControl::BG0_ENABLE) {
-            self.render_bg0();
+    /// Render the whole frame at once using the current register state
+    pub(crate) fn render_frame(&mut self) {
+        if self.headless {
+            return;
+        }
+        self.latch_polygons();
+        let (_, height) = self.display_dimensions();
+        for y in 0..height {
+            self.render_scanline(y);
         }
+    }
 
-        if self.display_control.contains(DisplayControl::SPRITE_ENABLE) {
-            self.render_sprites();
+    /// Start drawing the queued triangles and accept new ones for the next
+    /// frame
+    fn latch_polygons(&mut self) {
+        self.polygons.clear();
+        self.polygons.append(&mut self.polygon_queue.triangles);
+        self.display_status.remove(DisplayStatus::POLYGON_OVERFLOW);
+    }
+
+    /// Queue a flat triangle for the next frame
+    ///
+    /// Triangles past [`PolygonQueue::MAX_TRIANGLES`] in one frame are
+    /// dropped and set [`DisplayStatus::POLYGON_OVERFLOW`].
+    pub fn submit_triangle(&mut self, tri: Triangle) {
+        if self.polygon_queue.triangles.len() >= PolygonQueue::MAX_TRIANGLES {
+            self.display_status.insert(DisplayStatus::POLYGON_OVERFLOW);
+            return;
         }
+        self.polygon_queue.triangles.push(tri);
     }
 
-    /// Read the backdrop (background) color from CRAM
-    fn read_backdrop_color(&self) -> u32 {
-        // Backdrop color stored at CRAM offset 0 (18-bit RGB666)
-        let r = self.cram[0];
-        let g = self.cram[1];
-        let b = self.cram[2];
-        self.rgb666_to_rgb888(r, g, b)
+    /// Triangles waiting for the next frame
+    pub fn polygon_queue(&self) -> &PolygonQueue {
+        &self.polygon_queue
     }
 
-    /// Convert RGB666 (18-bit) to RGB888 (24-bit) for framebuffer
-    fn rgb666_to_rgb888(&self, r: u8, g: u8, b: u8) -> u32 {
-        let r8 = ((r & 0x3F) << 2) | ((r & 0x3F) >> 4);
-        let g8 = ((g & 0x3F) << 2) | ((g & 0x3F) >> 4);
-        let b8 = ((b & 0x3F) << 2) | ((b & 0x3F) >> 4);
-        ((r8 as u32) << 16) | ((g8 as u32) << 8) | (b8 as u32)
+    /// Fill the queued triangles' spans on scanline `y`
+    ///
+    /// Polygons sit directly above the backdrop and take part in blending as
+    /// backdrop pixels.
+    fn render_polygons(&mut self, y: usize) {
+        let (width, _) = self.display_dimensions();
+        let row = y * width;
+        for tri in &self.polygons {
+            let Some((left, right)) = tri.span(y as i32) else {
+                continue;
+            };
+            let left = left.max(0) as usize;
+            let right = right.min(width as i32 - 1);
+            if right < left as i32 {
+                continue;
+            }
+            let color = self
+                .palette_rgb
+                .get(tri.color as usize)
+                .copied()
+                .unwrap_or(0);
+            let pixels = &mut self.back_buffer[row + left..=row + right as usize];
+            for (pixel, &layers) in pixels.iter_mut().zip(&self.window_mask[left..]) {
+                if layers & Window::POLYGONS != 0 {
+                    *pixel = color;
+                }
+            }
+        }
     }
 
-    /// Render BG0 layer (affine-capable background)
-    fn render_bg0(&mut self) {
-        if !self.bg0_control.contains(BgControl::ENABLE) {
+    /// Render a single scanline to the framebuffer
+    ///
+    /// Scroll, affine and palette state are read when the line is drawn, so
+    /// changing them between scanlines produces raster effects.
+    pub(crate) fn render_scanline(&mut self, y: usize) {
+        let (width, height) = self.display_dimensions();
+        if self.headless || y >= height {
             return;
         }
 
-        let (width, height) = self.display_dimensions();
+        // Collision detection restarts with each frame
+        if y == 0 {
+            self.sprite_collision = false;
+        }
 
-        // Determine tilemap size based on control flags
-        let tile_map_size = if self.bg0_control.contains(BgControl::SIZE_128x128) {
-            128
-        } else if self.bg0_control.contains(BgControl::SIZE_64x64) {
-            64
-        } else {
-            32
-        };
+        // Clear the line to the backdrop color
+        let backdrop = self.read_backdrop_color();
+        self.back_buffer[y * width..(y + 1) * width].fill(backdrop);
+        self.line_layers[..width].fill(Layer::Backdrop);
+        self.update_window_mask(y);
+
+        if self
+            .display_control
+            .contains(DisplayControl::POLYGON_ENABLE)
+        {
+            self.render_polygons(y);
+        }
 
-        // Check if affine transformation is enabled
-        if self.bg0_control.contains(BgControl::AFFINE) {
-            // Affine mode: apply 2D transformation
-            // Matrix parameters are in 8.8 fixed point format
-            let pa = self.bg0_affine[0] as i32; // A (dx/dx)
-            let pb = self.bg0_affine[1] as i32; // B (dx/dy)
-            let pc = self.bg0_affine[2] as i32; // C (dy/dx)
-            let pd = self.bg0_affine[3] as i32; // D (dy/dy)
+        // Render layers in priority order
+        if self.display_control.contains(DisplayControl::BG1_ENABLE) {
+            self.render_bg1(y);
+        }
 
-            // Reference points store the texture coordinate (in 8.8 fixed point)
-            // that should appear at the screen center
-            let ref_x = self.bg0_ref_x;
-            let ref_y = self.bg0_ref_y;
+        if self.display_control.contains(DisplayControl::BG0_ENABLE) {
+            self.render_bg0(y);
+        }
 
-            let wraparound = self.bg0_control.contains(BgControl::WRAPAROUND);
+        if self.display_control.contains(DisplayControl::SPRITE_ENABLE) {
+            self.render_sprites(y);
+        }
+        self.display_status
+            .set(DisplayStatus::SPRITE_COLLISION, self.sprite_collision);
 
-            // Screen center coordinates
-            let center_x = (width / 2) as i32;
-            let center_y = (height / 2) as i32;
+        if y + 1 == height {
+            std::mem::swap(&mut self.front_buffer, &mut self.back_buffer);
+            self.track_dirty_rects();
+        }
+    }
 
-            // For each screen pixel, apply affine transformation
-            for screen_y in 0..height {
-                for screen_x in 0..width {
-                    // Calculate offset from screen center
-                    let dx = screen_x as i32 - center_x;
-                    let dy = screen_y as i32 - center_y;
-
-                    // Apply transformation matrix (8.8 fixed point math)
-                    // Formula: [tex_x, tex_y] = [ref_x, ref_y] + Matrix * [dx, dy]
-                    let tex_x = ref_x + ((pa * dx + pb * dy) >> 8);
-                    let tex_y = ref_y + ((pc * dx + pd * dy) >> 8);
-
-                    // Convert from 8.8 fixed point to integer pixel coordinates
-                    let mut pixel_x = (tex_x >> 8) as i32;
-                    let mut pixel_y = (tex_y >> 8) as i32;
-
-                    // Handle wraparound or clipping
-                    if wraparound {
-                        let map_size = (tile_map_size * 8) as i32;
-                        pixel_x = pixel_x.rem_euclid(map_size);
-                        pixel_y = pixel_y.rem_euclid(map_size);
-                    } else {
-                        // Clip to tilemap bounds
-                        if pixel_x < 0
-                            || pixel_x >= (tile_map_size * 8) as i32
-                            || pixel_y < 0
-                            || pixel_y >= (tile_map_size * 8) as i32
+    /// Areas of the framebuffer that changed in frames rendered since the
+    /// last [`Vdp::clear_dirty_rects`], as `(x, y, width, height)`
+    ///
+    /// Changes are found by comparing each finished frame with the one
+    /// before: moved or edited sprites mark their old and new bounds, and
+    /// changed BG tiles mark their 8x8 cell. Palette or register changes,
+    /// and affine or mosaic BG layers, mark the whole screen.
+    pub fn dirty_rects(&self) -> &[(u16, u16, u16, u16)] {
+        &self.dirty.rects
+    }
+
+    /// Forget the dirty areas, e.g. after the frontend has flushed them
+    pub fn clear_dirty_rects(&mut self) {
+        self.dirty.clear();
+    }
+
+    /// Compare the frame just rendered with the previous one and record the
+    /// areas that differ
+    fn track_dirty_rects(&mut self) {
+        let (width, height) = self.display_dimensions();
+        let layout = self.layout_registers();
+        let bg_cells = [self.bg_cells(0), self.bg_cells(1)];
+
+        match self.snapshot.take() {
+            Some(prev)
+                if !self.cram_modified
+                    && prev.layout == layout
+                    && prev.polygons == self.polygons
+                    && bg_cells.iter().all(Option::is_some) =>
+            {
+                let bpp4 = !self.sprite_control.contains(SpriteControl::COLOR_256);
+                for (layer, cells) in bg_cells.iter().flatten().enumerate() {
+                    let bg_bpp4 = !self.bg_control(layer).contains(BgControl::COLOR_256);
+                    for (cell, old) in cells.iter().zip(&prev.bg_cells[layer]) {
+                        if cell.entry != old.entry
+                            || self.tiles_modified(cell.entry & 0x3FF, 1, bg_bpp4)
                         {
-                            continue; // Out of bounds, skip pixel
+                            let (x, y, w, h) = cell.rect;
+                            self.dirty.add(x, y, w, h);
                         }
                     }
+                }
+                for (old, new) in prev.oam.iter().zip(&self.oam) {
+                    let (w, h) = new.size().dimensions();
+                    let tiles = (w / 8) * (h / 8);
+                    if old == new && !self.tiles_modified(new.tile_index, tiles, bpp4) {
+                        continue;
+                    }
+                    let bounds = [self.sprite_bounds(old), self.sprite_bounds(new)];
+                    if let Some((x0, y0, x1, y1)) = bounds
+                        .into_iter()
+                        .flatten()
+                        .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+                    {
+                        self.dirty.add(x0, y0, x1 - x0, y1 - y0);
+                    }
+                }
+            }
+            _ => self.dirty.mark_all(width as u16, height as u16),
+        }
 
-                    // Calculate tile coordinates
-                    let tile_x = (pixel_x / 8) as u16;
-                    let tile_y = (pixel_y / 8) as u16;
-                    let px = (pixel_x % 8) as u16;
-                    let py = (pixel_y % 8) as u16;
-
-                    // Read tile index from tilemap
-                    let tile_map_offset = ((tile_y * tile_map_size as u16 + tile_x) * 2) as u32;
-                    let tilemap_offset = self.bg0_tilemap_addr + tile_map_offset;
-                    let tile_entry = self.read_vram(tilemap_offset) as u16
-                        | ((self.read_vram(tilemap_offset + 1) as u16) << 8);
-
-                    let tile_index = tile_entry & 0x3FF; // 10-bit tile index
-                    let palette = ((tile_entry >> 12) & 0xF) as u8;
+        self.snapshot = Some(FrameSnapshot {
+            layout,
+            oam: self.oam.clone(),
+            bg_cells: bg_cells.map(Option::unwrap_or_default),
+            polygons: self.polygons.clone(),
+        });
+        self.modified_tiles.fill(false);
+        self.cram_modified = false;
+    }
 
-                    // Note: In affine mode, flip flags are typically ignored
-                    // Read pixel from tile data (8x8 tiles, 8 bits per pixel)
-                    let tile_data_offset = (tile_index as u32 * 64) + (py as u32 * 8) + px as u32;
-                    let color_index = self.read_vram(tile_data_offset);
+    /// Registers that affect how the frame is drawn, with timing, DMA,
+    /// interrupt and palette port state zeroed
+    fn layout_registers(&self) -> VdpRegisterDump {
+        VdpRegisterDump {
+            display_status: 0,
+            v_count: 0,
+            h_count: 0,
+            sprite_collision: false,
+            dma_source: 0,
+            dma_dest: 0,
+            dma_length: 0,
+            dma_active: false,
+            dma_mode: 0,
+            dma_progress: 0,
+            irq_enable: 0,
+            irq_status: 0,
+            irq_line_compare: 0,
+            palette_index: 0,
+            palette_data: 0,
+            cycles: 0,
+            frame_count: 0,
+            ..self.dump_registers()
+        }
+    }
 
-                    // Skip transparent pixels (color 0)
-                    if color_index == 0 {
-                        continue;
-                    }
+    fn bg_control(&self, layer: usize) -> BgControl {
+        if layer == 0 {
+            self.bg0_control
+        } else {
+            self.bg1_control
+        }
+    }
 
-                    // Read color from palette
-                    let palette_offset = (palette as u32 * 256 * 3) + (color_index as u32 * 3);
-                    let r = self.read_cram(palette_offset);
-                    let g = self.read_cram(palette_offset + 1);
-                    let b = self.read_cram(palette_offset + 2);
+    /// Scroll offset of a BG layer on scanline `y`, taken from the scroll
+    /// table when [`BgControl::SCROLL_TABLE`] is set
+    fn bg_scroll(&self, layer: usize, y: usize) -> (i16, i16) {
+        if self.bg_control(layer).contains(BgControl::SCROLL_TABLE) {
+            let layer = layer.min(1);
+            if let (Some(&x), Some(&y)) = (
+                self.scroll_table_x[layer].get(y),
+                self.scroll_table_y[layer].get(y),
+            ) {
+                return (x, y);
+            }
+        }
+        if layer == 0 {
+            (self.bg0_scroll_x, self.bg0_scroll_y)
+        } else {
+            (self.bg1_scroll_x, self.bg1_scroll_y)
+        }
+    }
 
-                    let color = self.rgb666_to_rgb888(r, g, b);
+    /// Tilemap entry drawn in each 8x8 screen cell of a BG layer
+    ///
+    /// Returns an empty list for a disabled layer and `None` for affine,
+    /// mosaic or scroll-table layers, whose pixels do not map onto cells.
+    fn bg_cells(&self, layer: usize) -> Option<Vec<BgCell>> {
+        let (enable, scroll_x, scroll_y, tilemap_addr) = if layer == 0 {
+            let flags = DisplayControl::BG0_ENABLE;
+            (
+                flags,
+                self.bg0_scroll_x,
+                self.bg0_scroll_y,
+                self.bg0_tilemap_addr,
+            )
+        } else {
+            let flags = DisplayControl::BG1_ENABLE;
+            (
+                flags,
+                self.bg1_scroll_x,
+                self.bg1_scroll_y,
+                self.bg1_tilemap_addr,
+            )
+        };
+        let control = self.bg_control(layer);
+        if !self.display_control.contains(enable) || !control.contains(BgControl::ENABLE) {
+            return Some(Vec::new());
+        }
+        if control.intersects(BgControl::AFFINE | BgControl::MOSAIC | BgControl::SCROLL_TABLE) {
+            return None;
+        }
+        let map_size: i32 = if control.contains(BgControl::SIZE_128x128) {
+            128
+        } else if control.contains(BgControl::SIZE_64x64) {
+            64
+        } else {
+            32
+        };
 
-                    // Write to framebuffer
-                    let fb_offset = screen_y * width + screen_x;
-                    if let Some(pixel) = self.framebuffer.get_mut(fb_offset) {
-                        *pixel = color;
-                    }
+        let (width, height) = self.display_dimensions();
+        let (off_x, off_y) = (
+            (scroll_x as i32).rem_euclid(8),
+            (scroll_y as i32).rem_euclid(8),
+        );
+        let mut cells = Vec::new();
+        for cy in 0..=(height as i32 + off_y) / 8 {
+            for cx in 0..=(width as i32 + off_x) / 8 {
+                let (x0, y0) = (cx * 8 - off_x, cy * 8 - off_y);
+                let (x1, y1) = ((x0 + 8).min(width as i32), (y0 + 8).min(height as i32));
+                let (x0, y0) = (x0.max(0), y0.max(0));
+                if x0 >= x1 || y0 >= y1 {
+                    continue;
                 }
+                let tile_x = ((x0 + scroll_x as i32) / 8).rem_euclid(map_size);
+                let tile_y = ((y0 + scroll_y as i32) / 8).rem_euclid(map_size);
+                let offset = tilemap_addr + ((tile_y * map_size + tile_x) * 2) as u32;
+                cells.push(BgCell {
+                    rect: (x0 as u16, y0 as u16, (x1 - x0) as u16, (y1 - y0) as u16),
+                    entry: self.read_vram(offset) as u16
+                        | ((self.read_vram(offset + 1) as u16) << 8),
+                });
             }
+        }
+        Some(cells)
+    }
+
+    /// Whether VRAM holding `count` tiles from `tile_index` was written
+    /// since the last frame
+    fn tiles_modified(&self, tile_index: u16, count: u16, bpp4: bool) -> bool {
+        let blocks = self.modified_tiles.len();
+        let (first, len) = if bpp4 {
+            (tile_index as usize, count as usize)
         } else {
-            // Non-affine mode: simple scrolling like BG1
-            let scroll_x = self.bg0_scroll_x;
-            let scroll_y = self.bg0_scroll_y;
-
-            for screen_y in 0..height {
-                for screen_x in 0..width {
-                    // Apply scrolling
-                    let world_x = (screen_x as i16).wrapping_add(scroll_x) as u16;
-                    let world_y = (screen_y as i16).wrapping_add(scroll_y) as u16;
-
-                    // Calculate tile coordinates
-                    let tile_x = (world_x / 8) % tile_map_size as u16;
-                    let tile_y = (world_y / 8) % tile_map_size as u16;
-                    let pixel_x = world_x % 8;
-                    let pixel_y = world_y % 8;
-
-                    // Read tile index from tilemap
-                    let tile_map_offset = ((tile_y * tile_map_size as u16 + tile_x) * 2) as u32;
-                    let tilemap_offset = self.bg0_tilemap_addr + tile_map_offset;
-                    let tile_entry = self.read_vram(tilemap_offset) as u16
-                        | ((self.read_vram(tilemap_offset + 1) as u16) << 8);
-
-                    let tile_index = tile_entry & 0x3FF; // 10-bit tile index
-                    let palette = ((tile_entry >> 12) & 0xF) as u8;
-                    let flip_h = (tile_entry & 0x0400) != 0;
-                    let flip_v = (tile_entry & 0x0800) != 0;
-
-                    // Apply flipping
-                    let px = if flip_h { 7 - pixel_x } else { pixel_x };
-                    let py = if flip_v { 7 - pixel_y } else { pixel_y };
-
-                    // Read pixel from tile data (8x8 tiles, 8 bits per pixel)
-                    let tile_data_offset = (tile_index as u32 * 64) + (py as u32 * 8) + px as u32;
-                    let color_index = self.read_vram(tile_data_offset);
-
-                    // Skip transparent pixels (color 0)
-                    if color_index == 0 {
-                        continue;
-                    }
+            (tile_index as usize * 2, count as usize * 2)
+        };
+        (first..first + len).any(|block| self.modified_tiles[block % blocks])
+    }
+
+    /// On-screen bounds of a sprite as `(x0, y0, x1, y1)`, exclusive
+    fn sprite_bounds(&self, sprite: &SpriteAttr) -> Option<(u16, u16, u16, u16)> {
+        let (width, height) = self.display_dimensions();
+        let (w, h) = sprite.size().dimensions();
+        if !sprite.is_enabled() || sprite.x_pos >= width as u16 || sprite.y_pos >= height as u16 {
+            return None;
+        }
+        let x1 = (sprite.x_pos + w).min(width as u16);
+        let y1 = (sprite.y_pos + h).min(height as u16);
+        Some((sprite.x_pos, sprite.y_pos, x1, y1))
+    }
 
-                    // Read color from palette
-                    let palette_offset = (palette as u32 * 256 * 3) + (color_index as u32 * 3);
-                    let r = self.read_cram(palette_offset);
-                    let g = self.read_cram(palette_offset + 1);
-                    let b = self.read_cram(palette_offset + 2);
+    /// Read the backdrop (background) color from CRAM
+    fn read_backdrop_color(&self) -> u32 {
+        // Backdrop color stored at CRAM offset 0 (18-bit RGB666)
+        let r = self.cram[0];
+        let g = self.cram[1];
+        let b = self.cram[2];
+        self.rgb666_to_rgb888(r, g, b)
+    }
 
-                    let color = self.rgb666_to_rgb888(r, g, b);
+    /// Convert RGB666 (18-bit) to RGB888 (24-bit) for framebuffer
+    fn rgb666_to_rgb888(&self, r: u8, g: u8, b: u8) -> u32 {
+        let r8 = ((r & 0x3F) << 2) | ((r & 0x3F) >> 4);
+        let g8 = ((g & 0x3F) << 2) | ((g & 0x3F) >> 4);
+        let b8 = ((b & 0x3F) << 2) | ((b & 0x3F) >> 4);
+        ((r8 as u32) << 16) | ((g8 as u32) << 8) | (b8 as u32)
+    }
 
-                    // Write to framebuffer
-                    let fb_offset = screen_y * width + screen_x;
-                    if let Some(pixel) = self.framebuffer.get_mut(fb_offset) {
-                        *pixel = color;
-                    }
-                }
+    /// Draw a layer pixel, blending with the pixel underneath if selected
+    fn plot_pixel(&mut self, x: usize, y: usize, color: u32, layer: Layer) {
+        let (width, _) = self.display_dimensions();
+        let Some(pixel) = self.back_buffer.get_mut(y * width + x) else {
+            return;
+        };
+        if self.window_mask[x] & layer.window_bit() == 0 {
+            return;
+        }
+        let below = self.line_layers[x];
+        *pixel = if self.blend_control.contains(layer.as_source())
+            && self.blend_control.contains(below.as_destination())
+            && layer != below
+        {
+            Self::blend_pixels(color, *pixel, self.blend_alpha)
+        } else {
+            color
+        };
+        self.line_layers[x] = layer;
+    }
+
+    fn windows_enabled(&self) -> bool {
+        self.display_control
+            .intersects(DisplayControl::WIN0_ENABLE | DisplayControl::WIN1_ENABLE)
+    }
+
+    /// Work out which layers each pixel of scanline `y` may draw
+    fn update_window_mask(&mut self, y: usize) {
+        let (width, _) = self.display_dimensions();
+        if !self.windows_enabled() {
+            self.window_mask[..width].fill(Window::ALL_LAYERS);
+            return;
+        }
+        let mut windows = self.windows.map(Some);
+        for (window, flag) in windows
+            .iter_mut()
+            .zip([DisplayControl::WIN0_ENABLE, DisplayControl::WIN1_ENABLE])
+        {
+            if !self.display_control.contains(flag) {
+                *window = None;
             }
         }
+        let outside = windows
+            .iter()
+            .flatten()
+            .fold(Window::ALL_LAYERS, |layers, w| layers & w.outside_layers);
+        for (x, layers) in self.window_mask[..width].iter_mut().enumerate() {
+            *layers = windows
+                .iter()
+                .flatten()
+                .find(|w| w.contains(x, y))
+                .map_or(outside, |w| w.inside_layers);
+        }
     }
 
-    /// Render BG1 layer (static tilemap background)
-    fn render_bg1(&mut self) {
-        if !self.bg1_control.contains(BgControl::ENABLE) {
+    /// Mix two RGB888 colors: `(src * alpha + dst * (256 - alpha)) >> 8`
+    fn blend_pixels(src: u32, dst: u32, alpha: u8) -> u32 {
+        let alpha = alpha as u32;
+        let mix = |shift: u32| {
+            let s = (src >> shift) & 0xFF;
+            let d = (dst >> shift) & 0xFF;
+            ((s * alpha + d * (256 - alpha)) >> 8) << shift
+        };
+        mix(16) | mix(8) | mix(0)
+    }
+
+    /// Read one pixel's color index from an 8x8 tile in VRAM
+    ///
+    /// 256-color tiles take 64 bytes, one byte per pixel. 16-color tiles take
+    /// 32 bytes, two pixels per byte with the left pixel in the low nibble.
+    fn read_tile_pixel(&self, tile_index: u16, px: u16, py: u16, bpp4: bool) -> u8 {
+        if bpp4 {
+            let offset = tile_index as u32 * 32 + py as u32 * 4 + px as u32 / 2;
+            let byte = self.read_vram(offset);
+            if px & 1 == 0 { byte & 0x0F } else { byte >> 4 }
+        } else {
+            let offset = tile_index as u32 * 64 + py as u32 * 8 + px as u32;
+            self.read_vram(offset)
+        }
+    }
+
+    /// Color indices for one row of a tile, decoding it into the tile cache
+    /// on a miss
+    fn tile_row(&mut self, tile_index: u16, py: u16, bpp4: bool) -> [u8; 8] {
+        let row = py as usize * 8;
+        if let Some(pixels) = self.tile_cache.get(tile_index, bpp4) {
+            return pixels[row..row + 8].try_into().unwrap();
+        }
+        let mut pixels = [0; 64];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = self.read_tile_pixel(tile_index, i as u16 % 8, i as u16 / 8, bpp4);
+        }
+        self.tile_cache.insert(tile_index, bpp4, pixels);
+        pixels[row..row + 8].try_into().unwrap()
+    }
+
+    /// Look up a palette entry in CRAM and convert it to RGB888
+    ///
+    /// Palettes are 256 colors (768 bytes) apart in 256-color mode and 16
+    /// colors (48 bytes) apart in 16-color mode.
+    fn palette_color(&self, palette: u8, color_index: u8, bpp4: bool) -> u32 {
+        let colors_per_palette: usize = if bpp4 { 16 } else { 256 };
+        let entry = palette as usize * colors_per_palette + color_index as usize;
+        self.palette_rgb.get(entry).copied().unwrap_or(0xFFFFFF)
+    }
+
+    /// Render BG0 layer (affine-capable background)
+    fn render_bg0(&mut self, screen_y: usize) {
+        if !self.bg0_control.contains(BgControl::ENABLE) {
             return;
         }
+        let bpp4 = !self.bg0_control.contains(BgControl::COLOR_256);
+        let mosaic = if self.bg0_control.contains(BgControl::MOSAIC) {
+            self.mosaic_size as u16
+        } else {
+            1
+        };
 
         let (width, height) = self.display_dimensions();
-        let scroll_x = self.bg1_scroll_x;
-        let scroll_y = self.bg1_scroll_y;
 
         // Determine tilemap size based on control flags
-        let tile_map_width = if self.bg1_control.contains(BgControl::SIZE_128x128) {
+        let tile_map_size = if self.bg0_control.contains(BgControl::SIZE_128x128) {
             128
-        } else if self.bg1_control.contains(BgControl::SIZE_64x64) {
+        } else if self.bg0_control.contains(BgControl::SIZE_64x64) {
             64
         } else {
             32
         };
 
-        let tile_map_height = tile_map_width; // Square tilemaps for now
+        // Check if affine transformation is enabled
+        if self.bg0_control.contains(BgControl::AFFINE) {
+            // Affine mode: apply 2D transformation
+            // Matrix parameters are in 8.8 fixed point format
+            let pa = self.bg0_affine[0] as i32; // A (dx/dx)
+            let pb = self.bg0_affine[1] as i32; // B (dx/dy)
+            let pc = self.bg0_affine[2] as i32; // C (dy/dx)
+            let pd = self.bg0_affine[3] as i32; // D (dy/dy)
+
+            // Reference points store the texture coordinate (in 8.8 fixed point)
+            // that should appear at the screen center
+            let ref_x = self.bg0_ref_x;
+            let ref_y = self.bg0_ref_y;
+
+            let wraparound = self.bg0_control.contains(BgControl::WRAPAROUND);
+
+            // Screen center coordinates
+            let center_x = (width / 2) as i32;
+            let center_y = (height / 2) as i32;
+
+            // For each screen pixel, apply affine transformation
+            for screen_x in 0..width {
+                // Calculate offset from screen center
+                let dx = screen_x as i32 - center_x;
+                let dy = screen_y as i32 - center_y;
+
+                // Apply transformation matrix (8.8 fixed point math)
+                // Formula: [tex_x, tex_y] = [ref_x, ref_y] + Matrix * [dx, dy]
+                let tex_x = ref_x + ((pa * dx + pb * dy) >> 8);
+                let tex_y = ref_y + ((pc * dx + pd * dy) >> 8);
+
+                // Convert from 8.8 fixed point to integer pixel coordinates
+                let mut pixel_x = tex_x >> 8;
+                let mut pixel_y = tex_y >> 8;
+
+                // Handle wraparound or clipping
+                if wraparound {
+                    let map_size = tile_map_size * 8;
+                    pixel_x = pixel_x.rem_euclid(map_size);
+                    pixel_y = pixel_y.rem_euclid(map_size);
+                } else {
+                    // Clip to tilemap bounds
+                    if pixel_x < 0
+                        || pixel_x >= tile_map_size * 8
+                        || pixel_y < 0
+                        || pixel_y >= tile_map_size * 8
+                    {
+                        continue; // Out of bounds, skip pixel
+                    }
+                }
+
+                // Snap to the top-left of the mosaic block
+                pixel_x -= pixel_x % mosaic as i32;
+                pixel_y -= pixel_y % mosaic as i32;
+
+                // Calculate tile coordinates
+                let tile_x = (pixel_x / 8) as u16;
+                let tile_y = (pixel_y / 8) as u16;
+                let px = (pixel_x % 8) as u16;
+                let py = (pixel_y % 8) as u16;
+
+                // Read tile index from tilemap
+                let tile_map_offset = ((tile_y * tile_map_size as u16 + tile_x) * 2) as u32;
+                let tilemap_offset = self.bg0_tilemap_addr + tile_map_offset;
+                let tile_entry = self.read_vram(tilemap_offset) as u16
+                    | ((self.read_vram(tilemap_offset + 1) as u16) << 8);
+
+                let tile_index = tile_entry & 0x3FF; // 10-bit tile index
+                let palette = ((tile_entry >> 12) & 0xF) as u8;
+
+                // Read pixel from tile data (8x8 tiles, 4 or 8 bits per pixel)
+                let color_index = self.read_tile_pixel(tile_index, px, py, bpp4);
+
+                // Skip transparent pixels (color 0)
+                if color_index == 0 {
+                    continue;
+                }
+
+                // Read color from palette
+                let color = self.palette_color(palette, color_index, bpp4);
+
+                // Write to framebuffer
+                self.plot_pixel(screen_x, screen_y, color, Layer::Bg0);
+            }
+        } else {
+            // Non-affine mode: simple scrolling like BG1
+            let (scroll_x, scroll_y) = self.bg_scroll(0, screen_y);
 
-        // Render each visible tile
-        for screen_y in 0..height {
             for screen_x in 0..width {
                 // Apply scrolling
-                let world_x = (screen_x as i16).wrapping_add(scroll_x) as u16;
-                let world_y = (screen_y as i16).wrapping_add(scroll_y) as u16;
+                let mut world_x = (screen_x as i16).wrapping_add(scroll_x) as u16;
+                let mut world_y = (screen_y as i16).wrapping_add(scroll_y) as u16;
+
+                // Snap to the top-left of the mosaic block
+                world_x -= world_x % mosaic;
+                world_y -= world_y % mosaic;
 
                 // Calculate tile coordinates
-                let tile_x = (world_x / 8) % tile_map_width as u16;
-                let tile_y = (world_y / 8) % tile_map_height as u16;
+                let tile_x = (world_x / 8) % tile_map_size as u16;
+                let tile_y = (world_y / 8) % tile_map_size as u16;
                 let pixel_x = world_x % 8;
                 let pixel_y = world_y % 8;
 
                 // Read tile index from tilemap
-                let tile_map_offset = ((tile_y * tile_map_width as u16 + tile_x) * 2) as u32;
-                let tilemap_offset = self.bg1_tilemap_addr + tile_map_offset;
+                let tile_map_offset = ((tile_y * tile_map_size as u16 + tile_x) * 2) as u32;
+                let tilemap_offset = self.bg0_tilemap_addr + tile_map_offset;
                 let tile_entry = self.read_vram(tilemap_offset) as u16
                     | ((self.read_vram(tilemap_offset + 1) as u16) << 8);
 
@@ -816,9 +2056,8 @@ impl Vdp {
                 let px = if flip_h { 7 - pixel_x } else { pixel_x };
                 let py = if flip_v { 7 - pixel_y } else { pixel_y };
 
-                // Read pixel from tile data (8x8 tiles, 8 bits per pixel for 256-color mode)
-                let tile_data_offset = (tile_index * 64 + py * 8 + px) as u32;
-                let color_index = self.read_vram(tile_data_offset);
+                // Read pixel from tile data (8x8 tiles, 4 or 8 bits per pixel)
+                let color_index = self.read_tile_pixel(tile_index, px, py, bpp4);
 
                 // Skip transparent pixels (color 0)
                 if color_index == 0 {
@@ -826,139 +2065,442 @@ impl Vdp {
                 }
 
                 // Read color from palette
-                let palette_offset = (palette as u32 * 256 * 3) + (color_index as u32 * 3);
-                let r = self.read_cram(palette_offset);
-                let g = self.read_cram(palette_offset + 1);
-                let b = self.read_cram(palette_offset + 2);
-
-                let color = self.rgb666_to_rgb888(r, g, b);
+                let color = self.palette_color(palette, color_index, bpp4);
 
                 // Write to framebuffer
-                let fb_offset = screen_y * width + screen_x;
-                if let Some(pixel) = self.framebuffer.get_mut(fb_offset) {
-                    *pixel = color;
+                self.plot_pixel(screen_x, screen_y, color, Layer::Bg0);
+            }
+        }
+    }
+
+    /// Render BG1 layer (static tilemap background)
+    ///
+    /// Each tile's row is drawn as one span of up to 8 pixels. Mosaic and
+    /// blending need per-pixel work and take the slower path.
+    fn render_bg1(&mut self, screen_y: usize) {
+        if !self.bg1_control.contains(BgControl::ENABLE) {
+            return;
+        }
+        let bpp4 = !self.bg1_control.contains(BgControl::COLOR_256);
+        let mosaic = if self.bg1_control.contains(BgControl::MOSAIC) {
+            self.mosaic_size as u16
+        } else {
+            1
+        };
+
+        let (width, _) = self.display_dimensions();
+        let (scroll_x, scroll_y) = self.bg_scroll(1, screen_y);
+
+        // Determine tilemap size based on control flags
+        let tile_map_width: u16 = if self.bg1_control.contains(BgControl::SIZE_128x128) {
+            128
+        } else if self.bg1_control.contains(BgControl::SIZE_64x64) {
+            64
+        } else {
+            32
+        };
+
+        let tile_map_height = tile_map_width; // Square tilemaps for now
+
+        // The row within the tilemap is the same for the whole scanline
+        let mut world_y = (screen_y as i16).wrapping_add(scroll_y) as u16;
+        world_y -= world_y % mosaic;
+        let tile_y = (world_y / 8) % tile_map_height;
+        let pixel_y = world_y % 8;
+
+        if mosaic > 1
+            || self.blend_control.contains(BlendControl::SRC_BG1)
+            || self.windows_enabled()
+            || self.scalar_bg1
+            || self.bypass_tile_cache
+        {
+            // Tilemap entry and decoded row of the tile under the previous pixel
+            let mut current: Option<(u16, u8, [u8; 8])> = None;
+
+            for screen_x in 0..width {
+                // Apply scrolling, snapping to the top-left of the mosaic block
+                let mut world_x = (screen_x as i16).wrapping_add(scroll_x) as u16;
+                world_x -= world_x % mosaic;
+
+                // Calculate tile coordinates; map sizes are powers of two
+                let tile_x = (world_x / 8) & (tile_map_width - 1);
+                let (palette, color_index) = if self.bypass_tile_cache {
+                    self.bg1_pixel_uncached(
+                        tile_map_width,
+                        tile_x,
+                        tile_y,
+                        world_x % 8,
+                        pixel_y,
+                        bpp4,
+                    )
+                } else {
+                    let (_, palette, row) = match current {
+                        Some(tile) if tile.0 == tile_x => tile,
+                        _ => {
+                            let (palette, row) =
+                                self.bg1_tile_row(tile_map_width, tile_x, tile_y, pixel_y, bpp4);
+                            current = Some((tile_x, palette, row));
+                            (tile_x, palette, row)
+                        }
+                    };
+                    (palette, row[(world_x % 8) as usize])
+                };
+
+                // Skip transparent pixels (color 0)
+                if color_index == 0 {
+                    continue;
                 }
+
+                let color = self.palette_color(palette, color_index, bpp4);
+                self.plot_pixel(screen_x, screen_y, color, Layer::Bg1);
             }
+            return;
+        }
+
+        let mut screen_x = 0;
+        while screen_x < width {
+            let world_x = (screen_x as i16).wrapping_add(scroll_x) as u16;
+            let tile_x = (world_x / 8) & (tile_map_width - 1);
+            let pixel_x = (world_x % 8) as usize;
+            let span = (8 - pixel_x).min(width - screen_x);
+
+            let (palette, row) = self.bg1_tile_row(tile_map_width, tile_x, tile_y, pixel_y, bpp4);
+            let palette_base = palette as usize * if bpp4 { 16 } else { 256 };
+            self.draw_span(
+                screen_x,
+                screen_y,
+                &row[pixel_x..pixel_x + span],
+                palette_base,
+                Layer::Bg1,
+            );
+            screen_x += span;
         }
     }
 
-    /// Render all active sprites
-    fn render_sprites(&mut self) {
-        let (width, height) = self.display_dimensions();
+    /// Tile index, palette and horizontal/vertical flips of a BG1 tilemap entry
+    fn bg1_tile_entry(
+        &self,
+        tile_map_width: u16,
+        tile_x: u16,
+        tile_y: u16,
+    ) -> (u16, u8, bool, bool) {
+        let tile_map_offset = ((tile_y * tile_map_width + tile_x) * 2) as u32;
+        let tilemap_offset = self.bg1_tilemap_addr + tile_map_offset;
+        let tile_entry = self.read_vram(tilemap_offset) as u16
+            | ((self.read_vram(tilemap_offset + 1) as u16) << 8);
+
+        let tile_index = tile_entry & 0x3FF; // 10-bit tile index
+        let palette = ((tile_entry >> 12) & 0xF) as u8;
+        let flip_h = (tile_entry & 0x0400) != 0;
+        let flip_v = (tile_entry & 0x0800) != 0;
+        (tile_index, palette, flip_h, flip_v)
+    }
+
+    /// Palette and color index of one BG1 pixel read straight from VRAM, as
+    /// the renderer did before the tile cache; see [`Vdp::set_tile_cache_bypass`]
+    fn bg1_pixel_uncached(
+        &self,
+        tile_map_width: u16,
+        tile_x: u16,
+        tile_y: u16,
+        pixel_x: u16,
+        pixel_y: u16,
+        bpp4: bool,
+    ) -> (u8, u8) {
+        let (tile_index, palette, flip_h, flip_v) =
+            self.bg1_tile_entry(tile_map_width, tile_x, tile_y);
+        let px = if flip_h { 7 - pixel_x } else { pixel_x };
+        let py = if flip_v { 7 - pixel_y } else { pixel_y };
+        (palette, self.read_tile_pixel(tile_index, px, py, bpp4))
+    }
+
+    /// Palette and color indices of one row of a BG1 tile, with the tile's
+    /// flips applied
+    fn bg1_tile_row(
+        &mut self,
+        tile_map_width: u16,
+        tile_x: u16,
+        tile_y: u16,
+        pixel_y: u16,
+        bpp4: bool,
+    ) -> (u8, [u8; 8]) {
+        let (tile_index, palette, flip_h, flip_v) =
+            self.bg1_tile_entry(tile_map_width, tile_x, tile_y);
+
+        // Decoded row of the tile (8x8 tiles, 4 or 8 bits per pixel)
+        let py = if flip_v { 7 - pixel_y } else { pixel_y };
+        let mut row = self.tile_row(tile_index, py, bpp4);
+        if flip_h {
+            row.reverse();
+        }
+        (palette, row)
+    }
+
+    /// Draw up to 8 color indices from `palette_base` without blending,
+    /// skipping transparent ones
+    ///
+    /// The opaque pixels come from a single vector compare (see
+    /// [`opaque_mask`]); a fully opaque row is written without per-pixel
+    /// branches.
+    fn draw_span(&mut self, x: usize, y: usize, indices: &[u8], palette_base: usize, layer: Layer) {
+        let (width, _) = self.display_dimensions();
+        let start = y * width + x;
+        let colors = &self.palette_rgb[palette_base..];
+        let pixels = &mut self.back_buffer[start..start + indices.len()];
+        let owners = &mut self.line_layers[x..x + indices.len()];
+
+        // Short spans are padded with transparent pixels
+        let mut row = [0u8; 8];
+        row[..indices.len()].copy_from_slice(indices);
+        let mask = opaque_mask(row);
+        if mask == 0xFF {
+            for (pixel, &index) in pixels.iter_mut().zip(&row) {
+                *pixel = colors[index as usize];
+            }
+            owners.fill(layer);
+            return;
+        }
+        let mut bits = mask;
+        while bits != 0 {
+            let i = bits.trailing_zeros() as usize;
+            pixels[i] = colors[row[i] as usize];
+            owners[i] = layer;
+            bits &= bits - 1;
+        }
+    }
+
+    /// Render the active sprites that intersect one scanline
+    fn render_sprites(&mut self, screen_y: usize) {
+        let (width, _) = self.display_dimensions();
+        let bpp4 = !self.sprite_control.contains(SpriteControl::COLOR_256);
 
         // Sort sprites by priority (lower priority values render first, higher values on top)
-        let mut sorted_sprites: Vec<(usize, &SpriteAttr)> = self
+        let mut sorted_sprites: Vec<(usize, SpriteAttr)> = self
             .oam
             .iter()
+            .copied()
             .enumerate()
             .filter(|(_, sprite)| sprite.is_enabled())
             .collect();
 
         sorted_sprites.sort_by_key(|(_, sprite)| sprite.priority());
 
-        // Track sprites per scanline for hardware limit (64 per scanline)
-        let mut scanline_sprite_counts = vec![0u8; height];
+        // Hardware limit of 64 sprites per scanline
+        let mut scanline_sprite_count = 0;
+
+        // Sprite that owns each opaque pixel on this scanline
+        let mut claimed: Vec<Option<usize>> = vec![None; width];
 
-        for (_, sprite) in sorted_sprites.iter() {
+        for &(index, ref sprite) in sorted_sprites.iter() {
             let (sprite_width, sprite_height) = sprite.size().dimensions();
 
             // Check if sprite is visible
             if sprite.x_pos >= width as u16 && sprite.x_pos < 512 {
                 continue; // Off-screen right
             }
-            if sprite.y_pos >= height as u16 && sprite.y_pos < 512 {
-                continue; // Off-screen bottom
+
+            // Row of the sprite that falls on this scanline
+            let sprite_y = (screen_y as u16).wrapping_sub(sprite.y_pos);
+            if sprite_y >= sprite_height {
+                continue;
             }
 
-            // Check scanline limit
-            let y_start = sprite.y_pos.min(height as u16) as usize;
-            let y_end = (sprite.y_pos + sprite_height).min(height as u16) as usize;
+            if scanline_sprite_count >= 64 {
+                break; // Remaining sprites are dropped on this scanline
+            }
+            scanline_sprite_count += 1;
 
-            let mut scanline_limited = false;
-            for y in y_start..y_end {
-                if scanline_sprite_counts[y] >= 64 {
-                    scanline_limited = true;
-                    break;
+            for sprite_x in 0..sprite_width {
+                let screen_x = sprite.x_pos.wrapping_add(sprite_x) as usize;
+                if screen_x >= width {
+                    continue;
                 }
-            }
 
-            if scanline_limited {
-                continue; // Skip this sprite due to scanline limit
-            }
+                // Apply flipping
+                let px = if sprite.flip_h() {
+                    sprite_width - 1 - sprite_x
+                } else {
+                    sprite_x
+                };
+                let py = if sprite.flip_v() {
+                    sprite_height - 1 - sprite_y
+                } else {
+                    sprite_y
+                };
+
+                // Read pixel from sprite tile data
+                // Sprite tiles are stored as 8x8 tiles, arranged in sprite_width/8 x sprite_height/8 grid
+                let tile_x = px / 8;
+                let tile_y = py / 8;
+                let pixel_x = px % 8;
+                let pixel_y = py % 8;
+
+                let tiles_per_row = sprite_width / 8;
+                let tile_offset = tile_y * tiles_per_row + tile_x;
+                let tile_index = sprite.tile_index + tile_offset;
+
+                // Read pixel from tile data (8x8 tiles, 4 or 8 bits per pixel)
+                let color_index = self.read_tile_pixel(tile_index, pixel_x, pixel_y, bpp4);
 
-            // Render sprite pixels
-            for sprite_y in 0..sprite_height {
-                let screen_y = sprite.y_pos.wrapping_add(sprite_y) as usize;
-                if screen_y >= height {
+                // Skip transparent pixels (color 0)
+                if color_index == 0 {
                     continue;
                 }
 
-                // Increment scanline sprite count
-                if scanline_sprite_counts[screen_y] < 64 {
-                    scanline_sprite_counts[screen_y] += 1;
+                match claimed[screen_x] {
+                    Some(owner) if owner != index => self.sprite_collision = true,
+                    _ => claimed[screen_x] = Some(index),
                 }
 
-                for sprite_x in 0..sprite_width {
-                    let screen_x = sprite.x_pos.wrapping_add(sprite_x) as usize;
-                    if screen_x >= width {
-                        continue;
-                    }
+                // Read color from sprite palette
+                let color = self.palette_color(sprite.palette(), color_index, bpp4);
 
-                    // Apply flipping
-                    let px = if sprite.flip_h() {
-                        sprite_width - 1 - sprite_x
-                    } else {
-                        sprite_x
-                    };
-                    let py = if sprite.flip_v() {
-                        sprite_height - 1 - sprite_y
-                    } else {
-                        sprite_y
-                    };
+                // Write to framebuffer
+                self.plot_pixel(screen_x, screen_y, color, Layer::Sprites);
+            }
+        }
+    }
 
-                    // Read pixel from sprite tile data
-                    // Sprite tiles are stored as 8x8 tiles, arranged in sprite_width/8 x sprite_height/8 grid
-                    let tile_x = px / 8;
-                    let tile_y = py / 8;
-                    let pixel_x = px % 8;
-                    let pixel_y = py % 8;
+    /// Get a reference to the framebuffer
+    ///
+    /// This is the last completed frame; scanlines of the frame in progress
+    /// are drawn to a back buffer that replaces it once the last visible line
+    /// is rendered. Pixels are `0x00RRGGBB`, laid out row-major with a stride
+    /// equal to the current display width.
+    pub fn framebuffer(&self) -> &[u32] {
+        &self.front_buffer
+    }
 
-                    let tiles_per_row = sprite_width / 8;
-                    let tile_offset = tile_y * tiles_per_row + tile_x;
-                    let tile_index = sprite.tile_index + tile_offset;
+    /// Width and height of the rendered image in the framebuffer
+    ///
+    /// A headless VDP has no framebuffer and reports `(0, 0)`.
+    pub fn framebuffer_dimensions(&self) -> (usize, usize) {
+        if self.headless {
+            return (0, 0);
+        }
+        self.display_dimensions()
+    }
 
-                    // Read pixel from tile data (8 bits per pixel)
-                    let tile_data_offset =
-                        (tile_index as u32 * 64) + (pixel_y as u32 * 8) + pixel_x as u32;
-                    let color_index = self.read_vram(tile_data_offset);
+    /// Set the visible rectangle; `right` and `bottom` are exclusive
+    ///
+    /// Fails if the rectangle is empty or extends past the native 384x288
+    /// frame, leaving the previous area in place.
+    pub fn set_active_area(
+        &mut self,
+        left: u16,
+        top: u16,
+        right: u16,
+        bottom: u16,
+    ) -> Result<(), VdpError> {
+        self.set_active_area_rect(ActiveArea {
+            left,
+            top,
+            right,
+            bottom,
+        })
+    }
 
-                    // Skip transparent pixels (color 0)
-                    if color_index == 0 {
-                        continue;
-                    }
+    fn set_active_area_rect(&mut self, area: ActiveArea) -> Result<(), VdpError> {
+        if area.left >= area.right
+            || area.top >= area.bottom
+            || area.right as usize > Self::NATIVE_WIDTH
+            || area.bottom as usize > Self::NATIVE_HEIGHT
+        {
+            return Err(VdpError::InvalidActiveArea(area));
+        }
+        self.active_area = area;
+        Ok(())
+    }
 
-                    // Read color from sprite palette
-                    let palette_offset =
-                        (sprite.palette() as u32 * 256 * 3) + (color_index as u32 * 3);
-                    let r = self.read_cram(palette_offset);
-                    let g = self.read_cram(palette_offset + 1);
-                    let b = self.read_cram(palette_offset + 2);
+    /// Current visible rectangle
+    pub fn active_area(&self) -> ActiveArea {
+        self.active_area
+    }
 
-                    let color = self.rgb666_to_rgb888(r, g, b);
+    /// Configure window 0 or 1; an invalid index is ignored
+    ///
+    /// The window takes effect once enabled with
+    /// [`DisplayControl::WIN0_ENABLE`] or [`DisplayControl::WIN1_ENABLE`].
+    pub fn set_window(&mut self, index: usize, window: Window) {
+        if let Some(slot) = self.windows.get_mut(index) {
+            *slot = window;
+        }
+    }
 
-                    // Write to framebuffer
-                    let fb_offset = screen_y * width + screen_x;
-                    if let Some(pixel) = self.framebuffer.get_mut(fb_offset) {
-                        *pixel = color;
-                    }
-                }
+    pub fn window(&self, index: usize) -> Option<Window> {
+        self.windows.get(index).copied()
+    }
+
+    /// Width and height of [`Vdp::framebuffer_rgba8888`]: the active area
+    /// clipped to the current display mode
+    pub fn active_dimensions(&self) -> (usize, usize) {
+        let (width, height) = self.framebuffer_dimensions();
+        let area = self.active_area;
+        (
+            width
+                .min(area.right as usize)
+                .saturating_sub(area.left as usize),
+            height
+                .min(area.bottom as usize)
+                .saturating_sub(area.top as usize),
+        )
+    }
+
+    /// Export the active area as RGBA8888 bytes (alpha always 0xFF)
+    ///
+    /// Overscan outside [`Vdp::active_area`] is cropped. Rows are packed
+    /// with no padding, so the result holds exactly `width * height * 4`
+    /// bytes for the dimensions from [`Vdp::active_dimensions`].
+    #[inline]
+    pub fn framebuffer_rgba8888(&self) -> Vec<u8> {
+        let (stride, _) = self.framebuffer_dimensions();
+        let (width, height) = self.active_dimensions();
+        let (left, top) = (
+            self.active_area.left as usize,
+            self.active_area.top as usize,
+        );
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in top..top + height {
+            let start = y * stride + left;
+            let Some(row) = self.front_buffer.get(start..start + width) else {
+                break;
+            };
+            for &pixel in row {
+                let [b, g, r, _] = pixel.to_le_bytes();
+                rgba.extend_from_slice(&[r, g, b, 0xFF]);
             }
         }
+        rgba
     }
 
-    /// Get a reference to the framebuffer
-    pub fn framebuffer(&self) -> &[u32] {
-        &self.framebuffer
+    /// Stream the rendered frame pixel-by-pixel as `cb(x, y, r, g, b)`
+    ///
+    /// Walks the active display area row by row without allocating, so
+    /// frontends can push pixels straight into their own framebuffer.
+    pub fn render_to_rgba_callback(&self, mut cb: impl FnMut(usize, usize, u8, u8, u8)) {
+        let (_, height) = self.display_dimensions();
+        for y in 0..height {
+            self.render_scanline_to_callback(y, |x, r, g, b| cb(x, y, r, g, b));
+        }
+    }
+
+    /// Stream a single rendered scanline as `cb(x, r, g, b)`
+    ///
+    /// Scanlines outside the active display area produce no callbacks.
+    pub fn render_scanline_to_callback(&self, y: usize, mut cb: impl FnMut(usize, u8, u8, u8)) {
+        let (width, height) = self.display_dimensions();
+        if y >= height {
+            return;
+        }
+        let Some(row) = self.front_buffer.get(y * width..(y + 1) * width) else {
+            return;
+        };
+        for (x, &pixel) in row.iter().enumerate() {
+            let r = ((pixel >> 16) & 0xFF) as u8;
+            let g = ((pixel >> 8) & 0xFF) as u8;
+            let b = (pixel & 0xFF) as u8;
+            cb(x, r, g, b);
+        }
     }
 
     /// Get current display dimensions based on mode
@@ -982,6 +2524,18 @@ impl Vdp {
         self.display_status.contains(DisplayStatus::HBLANK)
     }
 
+    /// Consume the line-compare latch and report whether an interrupt should fire
+    pub(crate) fn take_line_compare(&mut self) -> bool {
+        let fired = std::mem::take(&mut self.line_compare_latch);
+        fired && self.irq_enable.contains(IrqFlags::LINECMP)
+    }
+
+    /// Latch an interrupt source in IRQ_STATUS and report whether it is enabled
+    pub(crate) fn raise_irq(&mut self, flag: IrqFlags) -> bool {
+        self.irq_status.insert(flag);
+        self.irq_enable.contains(flag)
+    }
+
     /// Get current scanline
     pub fn scanline(&self) -> u16 {
         self.v_count
@@ -992,6 +2546,41 @@ impl Vdp {
         self.frame_count
     }
 
+    /// Number of frames drawn through to their last visible scanline
+    ///
+    /// Unlike [`Vdp::frame_count`] this does not advance while rendering is
+    /// skipped or the display is disabled.
+    pub fn rendered_frame_count(&self) -> u64 {
+        self.rendered_frames
+    }
+
+    /// Total scanlines per frame, including blanking
+    pub fn scanlines_per_frame(&self) -> u16 {
+        self.scanlines_per_frame
+    }
+
+    /// Change the frame length, e.g. to [`Vdp::PAL_SCANLINES_PER_FRAME`]
+    ///
+    /// Lines past the visible area and the VBLANK start are blanking, so
+    /// the value is clamped to at least [`Vdp::SCANLINES_PER_FRAME`].
+    pub fn set_scanlines_per_frame(&mut self, lines: u16) {
+        self.scanlines_per_frame = lines.max(Self::SCANLINES_PER_FRAME);
+    }
+
+    /// Leave the frames of the next `cycles` cycles undrawn except the last
+    /// one completed by then, which is drawn from its first line
+    ///
+    /// Drawing resumes at a frame boundary so the presented frame is never
+    /// partly stale. Timing and interrupts are unaffected.
+    pub fn skip_rendering_for(&mut self, cycles: u64) {
+        let end_line = (self.cycles + cycles) / Self::CYCLES_PER_SCANLINE;
+        let frame_lines = self.scanlines_per_frame as u64;
+        let height = self.display_dimensions().1 as u64;
+        // Start of the last frame whose visible lines are all reached
+        let last_start = (end_line + 1).saturating_sub(height) / frame_lines * frame_lines;
+        self.render_from_line = last_start.max(self.next_render_line);
+    }
+
     /// Set display mode
     pub fn set_display_mode(&mut self, width: usize, height: usize) {
         self.display_control
@@ -1012,6 +2601,24 @@ impl Vdp {
             .set(DisplayControl::SPRITE_ENABLE, sprites);
     }
 
+    /// Load per-scanline scroll offsets for BG layer 0 or 1
+    ///
+    /// Entry `n` of each table is used for scanline `n` while the layer's
+    /// [`BgControl::SCROLL_TABLE`] flag is set. Entries past the end of a
+    /// shorter slice are left unchanged; an invalid layer is ignored.
+    pub fn set_scroll_table(&mut self, layer: usize, x: &[i16], y: &[i16]) {
+        let (Some(table_x), Some(table_y)) = (
+            self.scroll_table_x.get_mut(layer),
+            self.scroll_table_y.get_mut(layer),
+        ) else {
+            return;
+        };
+        for (dst, src) in [(table_x, x), (table_y, y)] {
+            let len = src.len().min(dst.len());
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+    }
+
     /// Enable master display
     pub fn set_display_enable(&mut self, enable: bool) {
         self.display_control.set(DisplayControl::ENABLE, enable);
@@ -1047,14 +2654,102 @@ impl Vdp {
         }
     }
 
+    /// Load a 16-color palette into CRAM for use with 4bpp tiles
+    pub fn load_palette_16(&mut self, palette_index: u8, colors: &[(u8, u8, u8)]) {
+        let offset = palette_index as u32 * 16 * 3;
+        for (i, &(r, g, b)) in colors.iter().take(16).enumerate() {
+            let color_offset = offset + (i as u32 * 3);
+            self.write_cram(color_offset, r & 0x3F);
+            self.write_cram(color_offset + 1, g & 0x3F);
+            self.write_cram(color_offset + 2, b & 0x3F);
+        }
+    }
+
+    /// Draw BG1 one pixel at a time instead of in 8-pixel spans
+    ///
+    /// Output is identical; this only exists so benchmarks can measure the
+    /// span path against the per-pixel one.
+    #[doc(hidden)]
+    pub fn set_scalar_bg1(&mut self, scalar: bool) {
+        self.scalar_bg1 = scalar;
+    }
+
+    /// Draw BG1 pixel by pixel, reading the tilemap entry and the pixel
+    /// straight from VRAM each time instead of through the tile cache
+    ///
+    /// Output is identical; this only exists so benchmarks can measure what
+    /// the cache saves over the per-pixel path.
+    #[doc(hidden)]
+    pub fn set_tile_cache_bypass(&mut self, bypass: bool) {
+        self.bypass_tile_cache = bypass;
+    }
+
     /// Set backdrop color
     pub fn set_backdrop_color(&mut self, r: u8, g: u8, b: u8) {
-        self.cram[0] = r & 0x3F;
-        self.cram[1] = g & 0x3F;
-        self.cram[2] = b & 0x3F;
+        self.write_cram(0, r & 0x3F);
+        self.write_cram(1, g & 0x3F);
+        self.write_cram(2, b & 0x3F);
     }
 }
 
+/// Bit `i` is set when `row[i]` is an opaque (non-zero) color index
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+fn opaque_mask(row: [u8; 8]) -> u8 {
+    // SAFETY: the cfg above guarantees SSE2 on the running CPU
+    unsafe { opaque_mask_sse2(row) }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+#[target_feature(enable = "sse2")]
+fn opaque_mask_sse2(row: [u8; 8]) -> u8 {
+    use std::arch::x86_64::{_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set_epi64x, _mm_setzero_si128};
+
+    let lanes = _mm_set_epi64x(0, u64::from_le_bytes(row) as i64);
+    let transparent = _mm_movemask_epi8(_mm_cmpeq_epi8(lanes, _mm_setzero_si128()));
+    !(transparent as u8)
+}
+
+/// Bit `i` is set when `row[i]` is an opaque (non-zero) color index
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+fn opaque_mask(row: [u8; 8]) -> u8 {
+    // SAFETY: the cfg above guarantees NEON on the running CPU
+    unsafe { opaque_mask_neon(row) }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+#[target_feature(enable = "neon")]
+fn opaque_mask_neon(row: [u8; 8]) -> u8 {
+    use std::arch::aarch64::{vaddv_u8, vand_u8, vcreate_u8, vtst_u8};
+
+    let lanes = vcreate_u8(u64::from_le_bytes(row));
+    // 0xFF in every non-zero lane, narrowed to one bit per lane
+    let opaque = vtst_u8(lanes, lanes);
+    vaddv_u8(vand_u8(opaque, vcreate_u8(0x8040_2010_0804_0201)))
+}
+
+/// Bit `i` is set when `row[i]` is an opaque (non-zero) color index
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "sse2"),
+    all(target_arch = "aarch64", target_feature = "neon")
+)))]
+fn opaque_mask(row: [u8; 8]) -> u8 {
+    opaque_mask_scalar(row)
+}
+
+/// Portable version of [`opaque_mask`]
+#[cfg(any(
+    test,
+    not(any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "aarch64", target_feature = "neon")
+    ))
+))]
+fn opaque_mask_scalar(row: [u8; 8]) -> u8 {
+    row.iter()
+        .enumerate()
+        .fold(0, |mask, (i, &index)| mask | u8::from(index != 0) << i)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1114,15 +2809,41 @@ mod tests {
     }
 
     #[test]
-    fn vdp_register_access() {
+    fn vdp_register_access() {
+        let mut vdp = Vdp::new();
+
+        // Write display control
+        vdp.write_reg(VdpRegister::DisplayControl as u32, 0x0007);
+        assert_eq!(vdp.read_reg(VdpRegister::DisplayControl as u32), 0x0007);
+        assert!(vdp.display_control.contains(DisplayControl::ENABLE));
+        assert!(vdp.display_control.contains(DisplayControl::BG0_ENABLE));
+        assert!(vdp.display_control.contains(DisplayControl::BG1_ENABLE));
+    }
+
+    #[test]
+    fn vdp_display_status_is_read_only() {
+        let mut vdp = Vdp::new();
+
+        // Advance into VBLANK so the hardware status is non-zero
+        vdp.step(Vdp::VBLANK_START as u64 * Vdp::CYCLES_PER_SCANLINE);
+        let status = vdp.read_reg(VdpRegister::DisplayStatus as u32);
+        assert_eq!(status, DisplayStatus::VBLANK.bits());
+
+        vdp.write_reg(VdpRegister::DisplayStatus as u32, 0xFFFF);
+        assert_eq!(vdp.read_reg(VdpRegister::DisplayStatus as u32), status);
+        assert_ne!(vdp.read_reg(VdpRegister::DisplayStatus as u32), 0xFFFF);
+    }
+
+    #[test]
+    fn vdp_irq_status_write_clears_bits() {
         let mut vdp = Vdp::new();
+        vdp.irq_status = IrqFlags::VBLANK | IrqFlags::HBLANK;
 
-        // Write display control
-        vdp.write_reg(VdpRegister::DisplayControl as u32, 0x0007);
-        assert_eq!(vdp.read_reg(VdpRegister::DisplayControl as u32), 0x0007);
-        assert!(vdp.display_control.contains(DisplayControl::ENABLE));
-        assert!(vdp.display_control.contains(DisplayControl::BG0_ENABLE));
-        assert!(vdp.display_control.contains(DisplayControl::BG1_ENABLE));
+        vdp.write_reg(VdpRegister::IrqStatus as u32, IrqFlags::VBLANK.bits());
+        assert_eq!(
+            vdp.read_reg(VdpRegister::IrqStatus as u32),
+            IrqFlags::HBLANK.bits()
+        );
     }
 
     #[test]
@@ -1168,7 +2889,7 @@ mod tests {
 
     #[test]
     fn vdp_sprite_attributes() {
-        // Attribute bits: [15: enable] [14-13: flip] [12-10: priority] [11-8: palette] [1-0: size]
+        // Attribute bits: [15: enable] [13-12: flip] [11-8: palette] [5-4: priority] [1-0: size]
         // 0x8101: enabled (bit 15), palette 1 (bits 11-8), priority 0, size 1 (16x16)
         let sprite = SpriteAttr {
             y_pos: 100,
@@ -1201,6 +2922,64 @@ mod tests {
         assert_eq!(sprite_32x32.size(), SpriteSize::Size32x32);
     }
 
+    #[test]
+    fn vdp_sprite_from_parts() {
+        let sprite =
+            SpriteAttr::from_parts(150, 100, 42, 9, SpriteSize::Size32x32, true, false, 2, true);
+
+        assert_eq!(sprite.x_pos, 150);
+        assert_eq!(sprite.y_pos, 100);
+        assert_eq!(sprite.tile_index, 42);
+        assert!(sprite.is_enabled());
+        assert_eq!(sprite.palette(), 9);
+        assert!(sprite.flip_h());
+        assert!(!sprite.flip_v());
+        assert_eq!(sprite.priority(), 2);
+        assert_eq!(sprite.size(), SpriteSize::Size32x32);
+
+        // Matches the hand-packed encoding used elsewhere
+        let legacy =
+            SpriteAttr::from_parts(0, 0, 0, 1, SpriteSize::Size16x16, false, false, 0, true);
+        assert_eq!(legacy.attr, 0x8101);
+
+        // Out-of-range palette and priority are clamped
+        let clamped =
+            SpriteAttr::from_parts(0, 0, 0, 0xFF, SpriteSize::Size8x8, false, true, 7, false);
+        assert_eq!(clamped.palette(), 0xF);
+        assert_eq!(clamped.priority(), 3);
+        assert!(clamped.flip_v());
+        assert!(!clamped.is_enabled());
+    }
+
+    #[test]
+    fn vdp_sprite_parts_round_trip() {
+        let sizes = [
+            SpriteSize::Size8x8,
+            SpriteSize::Size16x16,
+            SpriteSize::Size32x32,
+            SpriteSize::Size64x64,
+            SpriteSize::Size8x16,
+            SpriteSize::Size16x8,
+        ];
+        for (i, &size) in sizes.iter().enumerate() {
+            let parts = (
+                10 + i as u16,
+                20 + i as u16,
+                300 + i as u16,
+                ((i * 5) % 16) as u8,
+                size,
+                i % 2 == 0,
+                i % 2 == 1,
+                (i % 4) as u8,
+                i != 3,
+            );
+            let sprite = SpriteAttr::from_parts(
+                parts.0, parts.1, parts.2, parts.3, parts.4, parts.5, parts.6, parts.7, parts.8,
+            );
+            assert_eq!(sprite.to_parts(), parts);
+        }
+    }
+
     #[test]
     fn vdp_bg0_affine_registers() {
         let mut vdp = Vdp::new();
@@ -1298,10 +3077,7 @@ mod tests {
         vdp.write_reg(VdpRegister::Bg0TilemapAddr as u32, 0x0000);
 
         // Create a simple tile (8x8 red square)
-        let mut tile_data = vec![0u8; 64];
-        for i in 0..64 {
-            tile_data[i] = 1; // Color index 1
-        }
+        let tile_data = vec![1u8; 64]; // Color index 1
         vdp.load_tile_data(0, &tile_data);
 
         // Set up a simple palette
@@ -1347,10 +3123,7 @@ mod tests {
         vdp.write_reg(VdpRegister::Bg0TilemapAddr as u32, 0x0000);
 
         // Create a simple tile
-        let mut tile_data = vec![0u8; 64];
-        for i in 0..64 {
-            tile_data[i] = 1; // Color index 1
-        }
+        let tile_data = vec![1u8; 64]; // Color index 1
         vdp.load_tile_data(0, &tile_data);
 
         // Set up palette
@@ -1374,4 +3147,879 @@ mod tests {
         let fb = vdp.framebuffer();
         assert_eq!(fb.len(), Vdp::NATIVE_WIDTH * Vdp::NATIVE_HEIGHT);
     }
+
+    #[test]
+    fn vdp_render_to_rgba_callback_streams_framebuffer() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_backdrop_color(0x3F, 0x10, 0x00);
+
+        // Run past the last visible line so the completed frame is displayed
+        vdp.step(Vdp::VBLANK_START as u64 * Vdp::CYCLES_PER_SCANLINE);
+        vdp.step((Vdp::NATIVE_HEIGHT as u64 - Vdp::VBLANK_START as u64) * Vdp::CYCLES_PER_SCANLINE);
+
+        let mut pixels = Vec::new();
+        vdp.render_to_rgba_callback(|x, y, r, g, b| pixels.push((x, y, r, g, b)));
+
+        assert_eq!(pixels.len(), Vdp::NATIVE_WIDTH * Vdp::NATIVE_HEIGHT);
+        for &(x, y, r, g, b) in &pixels {
+            let expected = vdp.framebuffer()[y * Vdp::NATIVE_WIDTH + x];
+            assert_eq!(
+                (r, g, b),
+                (
+                    (expected >> 16) as u8,
+                    (expected >> 8) as u8,
+                    expected as u8
+                )
+            );
+        }
+        assert_eq!(pixels[0], (0, 0, 0xFF, 0x41, 0x00));
+
+        // Per-scanline streaming covers exactly one row
+        let mut row = Vec::new();
+        vdp.render_scanline_to_callback(10, |x, r, g, b| row.push((x, r, g, b)));
+        assert_eq!(row.len(), Vdp::NATIVE_WIDTH);
+        assert_eq!(row[5], (5, 0xFF, 0x41, 0x00));
+
+        let mut count = 0;
+        vdp.render_scanline_to_callback(Vdp::NATIVE_HEIGHT, |_, _, _, _| count += 1);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn vdp_scroll_change_mid_frame_splits_screen() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        vdp.write_reg(
+            VdpRegister::Bg1Control as u32,
+            (BgControl::ENABLE | BgControl::COLOR_256).bits(),
+        );
+
+        // Tile 0: left half color 1, right half color 2; tilemap all zeros
+        let tile: Vec<u8> = (0..64).map(|i| if i % 8 < 4 { 1 } else { 2 }).collect();
+        vdp.load_tile_data(0, &tile);
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+        vdp.load_palette(0, &[(0, 0, 0), (0x3F, 0, 0), (0, 0x3F, 0)]);
+        let red = vdp.rgb666_to_rgb888(0x3F, 0, 0);
+        let green = vdp.rgb666_to_rgb888(0, 0x3F, 0);
+
+        // The beam enters scanline 120, which latches the old scroll
+        vdp.step(120 * Vdp::CYCLES_PER_SCANLINE);
+        vdp.write_reg(VdpRegister::Bg1ScrollX as u32, 4);
+        vdp.step((Vdp::NATIVE_HEIGHT as u64 - 120) * Vdp::CYCLES_PER_SCANLINE);
+
+        let fb = vdp.framebuffer();
+        let width = Vdp::NATIVE_WIDTH;
+        for y in [0, 60, 120] {
+            assert_eq!(fb[y * width], red, "scanline {y} should use the old scroll");
+        }
+        for y in [121, 180, Vdp::VBLANK_START as usize] {
+            assert_eq!(
+                fb[y * width],
+                green,
+                "scanline {y} should use the new scroll"
+            );
+        }
+    }
+
+    #[test]
+    fn vdp_overlapping_sprites_set_collision_flag() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, false, true);
+        vdp.write_reg(
+            VdpRegister::SpriteControl as u32,
+            SpriteControl::COLOR_256.bits(),
+        );
+        vdp.load_tile_data(0, &[1u8; 64]);
+
+        let sprite = |x, y| SpriteAttr {
+            y_pos: y,
+            x_pos: x,
+            tile_index: 0,
+            attr: 0x8000, // Enabled, 8x8
+        };
+        vdp.set_sprite(0, sprite(10, 10));
+        vdp.set_sprite(1, sprite(100, 10));
+        vdp.render_frame();
+        assert!(!vdp.sprite_collision);
+        assert_eq!(
+            vdp.read_reg(VdpRegister::DisplayStatus as u32) & (1 << 5),
+            0
+        );
+
+        vdp.set_sprite(1, sprite(14, 14));
+        vdp.render_frame();
+        assert!(vdp.sprite_collision);
+        assert_ne!(
+            vdp.read_reg(VdpRegister::DisplayStatus as u32) & (1 << 5),
+            0
+        );
+    }
+
+    #[test]
+    fn vdp_bg1_16_color_tiles() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        // COLOR_256 clear: 4bpp tiles with 16-color palettes
+        vdp.write_reg(VdpRegister::Bg1Control as u32, BgControl::ENABLE.bits());
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+
+        // Tile 1, each row: pixels 3,5,0,15,...
+        let tile: Vec<u8> = (0..32)
+            .map(|i| if i % 4 == 0 { 0x53 } else { 0xF0 })
+            .collect();
+        vdp.load_tile_data(32, &tile);
+        assert_eq!(vdp.read_tile_pixel(1, 0, 0, true), 3);
+        assert_eq!(vdp.read_tile_pixel(1, 1, 0, true), 5);
+        assert_eq!(vdp.read_tile_pixel(1, 3, 7, true), 15);
+
+        // Tilemap entry 0: tile 1, palette 2
+        vdp.write_vram(0x1000, 0x01);
+        vdp.write_vram(0x1001, 0x20);
+
+        let mut colors = [(0u8, 0u8, 0u8); 16];
+        colors[3] = (0x3F, 0, 0);
+        colors[5] = (0, 0x3F, 0);
+        colors[15] = (0, 0, 0x3F);
+        vdp.load_palette_16(2, &colors);
+        vdp.set_backdrop_color(0x10, 0x10, 0x10);
+
+        vdp.render_frame();
+        let fb = vdp.framebuffer();
+        assert_eq!(fb[0], vdp.rgb666_to_rgb888(0x3F, 0, 0));
+        assert_eq!(fb[1], vdp.rgb666_to_rgb888(0, 0x3F, 0));
+        assert_eq!(fb[2], vdp.rgb666_to_rgb888(0x10, 0x10, 0x10)); // Transparent
+        assert_eq!(fb[3], vdp.rgb666_to_rgb888(0, 0, 0x3F));
+        // Palette 2 sits 96 bytes into CRAM in 16-color mode
+        assert_eq!(vdp.read_cram(2 * 48 + 3 * 3), 0x3F);
+    }
+
+    #[test]
+    fn vdp_bg1_mosaic_repeats_block_origin() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        vdp.write_reg(
+            VdpRegister::Bg1Control as u32,
+            (BgControl::ENABLE | BgControl::COLOR_256 | BgControl::MOSAIC).bits(),
+        );
+        vdp.write_reg(VdpRegister::MosaicSize as u32, 4);
+        assert_eq!(vdp.mosaic_size, 4);
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+
+        // Every pixel of tile 0 gets a distinct color index
+        let tile: Vec<u8> = (1..=64).collect();
+        vdp.load_tile_data(0, &tile);
+        let colors: Vec<(u8, u8, u8)> = (0..65).map(|i| (i as u8, 0, 0)).collect();
+        vdp.load_palette(0, &colors);
+
+        vdp.render_frame();
+        let fb = vdp.framebuffer();
+        let top_left = vdp.rgb666_to_rgb888(1, 0, 0);
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(fb[y * Vdp::NATIVE_WIDTH + x], top_left, "pixel ({x}, {y})");
+            }
+        }
+        // The next block starts at tile pixel (4, 0)
+        assert_eq!(fb[4], vdp.rgb666_to_rgb888(5, 0, 0));
+
+        // Without MOSAIC the tile is sampled per pixel
+        vdp.write_reg(
+            VdpRegister::Bg1Control as u32,
+            (BgControl::ENABLE | BgControl::COLOR_256).bits(),
+        );
+        vdp.render_frame();
+        let expected = vdp.rgb666_to_rgb888(28, 0, 0);
+        assert_eq!(vdp.framebuffer()[3 * Vdp::NATIVE_WIDTH + 3], expected);
+    }
+
+    #[test]
+    fn vdp_sprite_blends_over_background() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, true);
+        vdp.write_reg(
+            VdpRegister::Bg1Control as u32,
+            (BgControl::ENABLE | BgControl::COLOR_256).bits(),
+        );
+        vdp.write_reg(
+            VdpRegister::SpriteControl as u32,
+            SpriteControl::COLOR_256.bits(),
+        );
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+
+        // Tile 0 is solid red background, tile 1 solid blue sprite
+        vdp.load_tile_data(0, &[1u8; 64]);
+        vdp.load_tile_data(64, &[2u8; 64]);
+        vdp.load_palette(0, &[(0, 0, 0), (0x3F, 0, 0), (0, 0, 0x3F)]);
+        vdp.set_sprite(
+            0,
+            SpriteAttr {
+                y_pos: 8,
+                x_pos: 8,
+                tile_index: 1,
+                attr: 0x8000,
+            },
+        );
+
+        vdp.write_reg(
+            VdpRegister::BlendControl as u32,
+            (BlendControl::SRC_SPRITES | BlendControl::DST_BG1).bits(),
+        );
+        vdp.write_reg(VdpRegister::BlendAlpha as u32, 128);
+        vdp.render_frame();
+
+        let fb = vdp.framebuffer();
+        assert_eq!(fb[8 * Vdp::NATIVE_WIDTH + 8], 0x7F007F);
+        // Background outside the sprite is untouched
+        assert_eq!(fb[0], 0xFF0000);
+
+        // Without a destination layer the sprite overwrites the background
+        vdp.write_reg(
+            VdpRegister::BlendControl as u32,
+            BlendControl::SRC_SPRITES.bits(),
+        );
+        vdp.render_frame();
+        assert_eq!(vdp.framebuffer()[8 * Vdp::NATIVE_WIDTH + 8], 0x0000FF);
+    }
+
+    #[test]
+    fn vdp_blend_pixels_formula() {
+        assert_eq!(Vdp::blend_pixels(0x0000FF, 0xFF0000, 128), 0x7F007F);
+        assert_eq!(Vdp::blend_pixels(0xFFFFFF, 0x000000, 0), 0x000000);
+        assert_eq!(Vdp::blend_pixels(0xFFFFFF, 0x000000, 255), 0xFEFEFE);
+    }
+
+    #[test]
+    fn vdp_line_compare_fires_once() {
+        let mut vdp = Vdp::new();
+        vdp.write_reg(VdpRegister::IrqLineCompare as u32, 120);
+        vdp.write_reg(VdpRegister::IrqEnable as u32, IrqFlags::LINECMP.bits());
+
+        vdp.step(119 * Vdp::CYCLES_PER_SCANLINE);
+        assert!(!vdp.irq_status.contains(IrqFlags::LINECMP));
+        assert!(!vdp.take_line_compare());
+
+        vdp.step(Vdp::CYCLES_PER_SCANLINE);
+        assert_eq!(vdp.scanline(), 120);
+        assert!(vdp.irq_status.contains(IrqFlags::LINECMP));
+        assert!(vdp.display_status.contains(DisplayStatus::LINECMP));
+        assert!(vdp.take_line_compare());
+
+        // Staying on the same scanline does not retrigger
+        vdp.write_reg(VdpRegister::IrqStatus as u32, IrqFlags::LINECMP.bits());
+        vdp.step(16);
+        assert!(!vdp.irq_status.contains(IrqFlags::LINECMP));
+        assert!(!vdp.take_line_compare());
+
+        vdp.step(Vdp::CYCLES_PER_SCANLINE);
+        assert!(!vdp.display_status.contains(DisplayStatus::LINECMP));
+    }
+
+    #[test]
+    fn vdp_framebuffer_rgba8888_export() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_mode(256, 224);
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        vdp.write_reg(
+            VdpRegister::Bg1Control as u32,
+            (BgControl::ENABLE | BgControl::COLOR_256).bits(),
+        );
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+        vdp.load_tile_data(0, &[1u8; 64]);
+        vdp.load_palette(0, &[(0, 0, 0), (0x3F, 0x20, 0x01)]);
+        vdp.render_frame();
+
+        assert_eq!(vdp.framebuffer_dimensions(), (256, 224));
+        let rgba = vdp.framebuffer_rgba8888();
+        assert_eq!(rgba.len(), 256 * 224 * 4);
+        assert_eq!(rgba.capacity(), rgba.len());
+        // RGB666 channels expand to 8 bits by replicating the top bits
+        assert_eq!(&rgba[..4], &[0xFF, 0x82, 0x04, 0xFF]);
+    }
+
+    #[test]
+    fn vdp_register_dump_round_trip() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_mode(320, 240);
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(true, true, true);
+        vdp.write_reg(
+            VdpRegister::Bg0Control as u32,
+            (BgControl::ENABLE | BgControl::AFFINE | BgControl::MOSAIC).bits(),
+        );
+        vdp.write_reg(VdpRegister::Bg0ScrollX as u32, 0xFFF0);
+        vdp.write_reg(VdpRegister::Bg0AffineB as u32, 0x0040);
+        vdp.write_reg(VdpRegister::Bg0RefX as u32, 0x1234);
+        vdp.write_reg(VdpRegister::Bg0RefY as u32 + 2, 0x0056);
+        vdp.write_reg(VdpRegister::Bg0TilemapAddr as u32, 0x4000);
+        vdp.write_reg(VdpRegister::Bg1Control as u32, BgControl::ENABLE.bits());
+        vdp.write_reg(VdpRegister::Bg1ScrollY as u32, 77);
+        vdp.write_reg(VdpRegister::SpriteOamAddr as u32, 9);
+        vdp.write_reg(VdpRegister::DmaSource as u32, 0xBEEF);
+        vdp.write_reg(VdpRegister::DmaLength as u32, 0x200);
+        vdp.write_reg(VdpRegister::IrqEnable as u32, 0x0007);
+        vdp.write_reg(VdpRegister::IrqLineCompare as u32, 100);
+        vdp.write_reg(VdpRegister::PaletteIndex as u32, 5);
+        vdp.write_reg(VdpRegister::BackdropColor as u32, 0x0FC3);
+        vdp.write_reg(VdpRegister::MosaicSize as u32, 6);
+        vdp.write_reg(VdpRegister::BlendAlpha as u32, 200);
+        vdp.write_reg(0x00C0, 0xA5A5); // Raw register storage
+        vdp.step(130 * Vdp::CYCLES_PER_SCANLINE + 800);
+
+        let dump = vdp.dump_registers();
+        let mut restored = Vdp::new();
+        restored.write_vram(0, 0x42);
+        restored.restore_registers(&dump);
+
+        assert_eq!(restored.dump_registers(), dump);
+        for offset in (0..0x100).step_by(2) {
+            assert_eq!(
+                restored.read_reg(offset),
+                vdp.read_reg(offset),
+                "register {offset:#06X}"
+            );
+        }
+        assert_eq!(restored.scanline(), 130);
+        assert!(restored.in_hblank());
+        // Memory is not part of the register dump
+        assert_eq!(restored.read_vram(0), 0x42);
+        assert_eq!(restored.read_cram(0), 0);
+    }
+
+    #[test]
+    fn vdp_vram_to_vram_dma_runs_over_steps() {
+        let mut vdp = Vdp::new();
+        vdp.load_tile_data(0x100, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        vdp.write_reg(VdpRegister::DmaSource as u32, 0x100);
+        vdp.write_reg(VdpRegister::DmaDestination as u32, 0x800);
+        vdp.write_reg(VdpRegister::DmaLength as u32, 8);
+        vdp.write_reg(VdpRegister::DmaMode as u32, DmaMode::VramToVram as u16);
+        vdp.write_reg(VdpRegister::DmaControl as u32, 0x8000);
+        assert!(vdp.display_status.contains(DisplayStatus::DMA_BUSY));
+
+        vdp.step(3);
+        assert_eq!(vdp.read_vram(0x802), 3);
+        assert_eq!(vdp.read_vram(0x803), 0);
+        assert!(vdp.display_status.contains(DisplayStatus::DMA_BUSY));
+
+        vdp.step(10);
+        assert_eq!(vdp.read_vram(0x807), 8);
+        assert!(!vdp.display_status.contains(DisplayStatus::DMA_BUSY));
+        assert!(vdp.irq_status.contains(IrqFlags::DMA_DONE));
+    }
+
+    #[test]
+    fn vdp_non_square_sprites() {
+        let tall = SpriteAttr::from_parts(0, 0, 0, 0, SpriteSize::Size8x16, false, false, 0, true);
+        assert_eq!(tall.attr & 0x7, 0x4);
+        assert_eq!(tall.size().dimensions(), (8, 16));
+        let wide = SpriteAttr::from_parts(0, 0, 0, 0, SpriteSize::Size16x8, false, false, 0, true);
+        assert_eq!(wide.attr & 0x7, 0x5);
+        assert_eq!(wide.size().dimensions(), (16, 8));
+
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, false, true);
+        vdp.write_reg(
+            VdpRegister::SpriteControl as u32,
+            SpriteControl::COLOR_256.bits(),
+        );
+        // Tiles 1-2 hold colors 1 and 2
+        vdp.load_tile_data(64, &[1u8; 64]);
+        vdp.load_tile_data(128, &[2u8; 64]);
+        vdp.load_palette(0, &[(0, 0, 0), (0x3F, 0, 0), (0, 0x3F, 0)]);
+        let red = vdp.rgb666_to_rgb888(0x3F, 0, 0);
+        let green = vdp.rgb666_to_rgb888(0, 0x3F, 0);
+        let backdrop = vdp.read_backdrop_color();
+
+        // 8x16: tiles stacked vertically
+        vdp.set_sprite(
+            0,
+            SpriteAttr {
+                tile_index: 1,
+                ..tall
+            },
+        );
+        vdp.render_frame();
+        let fb = vdp.framebuffer();
+        let w = Vdp::NATIVE_WIDTH;
+        assert_eq!(fb[7 * w + 7], red);
+        assert_eq!(fb[8 * w + 7], green);
+        assert_eq!(fb[15 * w + 7], green);
+        assert_eq!(fb[8], backdrop);
+        assert_eq!(fb[16 * w], backdrop);
+
+        // 16x8: tiles side by side
+        vdp.set_sprite(
+            0,
+            SpriteAttr {
+                tile_index: 1,
+                ..wide
+            },
+        );
+        vdp.render_frame();
+        let fb = vdp.framebuffer();
+        assert_eq!(fb[7], red);
+        assert_eq!(fb[8], green);
+        assert_eq!(fb[15], green);
+        assert_eq!(fb[16], backdrop);
+        assert_eq!(fb[8 * w], backdrop);
+    }
+
+    #[test]
+    fn active_area_crops_rgba_export() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.write_reg(VdpRegister::BackdropColor as u32, 0x0FC0); // Green
+        vdp.set_active_area(32, 24, 352, 264).unwrap();
+        vdp.render_frame();
+
+        assert_eq!(vdp.active_dimensions(), (320, 240));
+        let rgba = vdp.framebuffer_rgba8888();
+        assert_eq!(rgba.len(), 320 * 240 * 4);
+        assert!(
+            rgba.chunks_exact(4)
+                .all(|px| px == [0x00, 0xFF, 0x00, 0xFF])
+        );
+
+        assert_eq!(
+            vdp.set_active_area(0, 0, 385, 288),
+            Err(VdpError::InvalidActiveArea(ActiveArea {
+                left: 0,
+                top: 0,
+                right: 385,
+                bottom: 288,
+            }))
+        );
+        assert!(vdp.set_active_area(10, 0, 10, 288).is_err());
+        assert_eq!(vdp.active_dimensions(), (320, 240));
+    }
+
+    #[test]
+    fn active_area_registers_apply_staged_edges() {
+        let mut vdp = Vdp::new();
+        for (reg, value) in [
+            (VdpRegister::ActiveLeft, 8),
+            (VdpRegister::ActiveTop, 8),
+            (VdpRegister::ActiveRight, 376),
+        ] {
+            vdp.write_reg(reg as u32, value);
+        }
+        assert_eq!(vdp.active_area(), ActiveArea::FULL);
+        assert_eq!(vdp.read_reg(VdpRegister::ActiveRight as u32), 376);
+
+        vdp.write_reg(VdpRegister::ActiveApply as u32, 1);
+        assert_eq!(vdp.active_dimensions(), (368, 280));
+
+        // Out-of-range edges are rejected and the current area kept
+        vdp.write_reg(VdpRegister::ActiveBottom as u32, 300);
+        vdp.write_reg(VdpRegister::ActiveApply as u32, 1);
+        assert_eq!(vdp.active_dimensions(), (368, 280));
+    }
+
+    #[test]
+    fn tile_cache_invalidated_by_vram_writes() {
+        let mut vdp = Vdp::new();
+        vdp.load_tile_data(32, &[0x21; 32]); // 16-color tile 1
+        assert_eq!(vdp.tile_row(1, 0, true), [1, 2, 1, 2, 1, 2, 1, 2]);
+        assert!(vdp.tile_cache.get(1, true).is_some());
+        // The same bytes are the second half of 256-color tile 0
+        assert_eq!(vdp.tile_row(0, 4, false), [0x21; 8]);
+
+        vdp.write_vram(32 + 3 * 4, 0x43);
+        assert!(vdp.tile_cache.get(1, true).is_none());
+        assert!(vdp.tile_cache.get(0, false).is_none());
+        assert_eq!(vdp.tile_row(1, 3, true), [3, 4, 1, 2, 1, 2, 1, 2]);
+
+        // Tile 1025 shares a slot with tile 1 but has a different tag
+        assert_eq!(vdp.tile_row(1025, 0, true), [0; 8]);
+        assert!(vdp.tile_cache.get(1, true).is_none());
+    }
+
+    #[test]
+    fn moved_sprite_marks_old_and_new_bounds() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, false, true);
+        vdp.load_tile_data(0, &[0x11; 32]);
+        vdp.load_palette_16(0, &[(0, 0, 0), (0x3F, 0, 0)]);
+        let sprite =
+            SpriteAttr::from_parts(10, 10, 0, 0, SpriteSize::Size8x8, false, false, 0, true);
+        vdp.set_sprite(0, sprite);
+        vdp.set_sprite(
+            1,
+            SpriteAttr {
+                x_pos: 100,
+                ..sprite
+            },
+        );
+
+        // The first frame has nothing to compare against
+        vdp.render_frame();
+        assert_eq!(vdp.dirty_rects(), &[(0, 0, 384, 288)]);
+        vdp.clear_dirty_rects();
+
+        vdp.render_frame();
+        assert!(vdp.dirty_rects().is_empty());
+
+        vdp.set_sprite(
+            0,
+            SpriteAttr {
+                x_pos: 11,
+                ..sprite
+            },
+        );
+        vdp.render_frame();
+        assert_eq!(vdp.dirty_rects(), &[(10, 10, 9, 8)]);
+    }
+
+    #[test]
+    fn changed_bg_tile_marks_its_cell() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        vdp.write_reg(VdpRegister::Bg1Control as u32, BgControl::ENABLE.bits());
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+        vdp.write_reg(VdpRegister::Bg1ScrollX as u32, 4);
+        vdp.render_frame();
+        vdp.clear_dirty_rects();
+
+        // Map column 1 is drawn from x = 4 with the 4-pixel scroll, and the
+        // 256-pixel map repeats across the 384x288 screen
+        let repeats = [
+            (4, 0, 8, 8),
+            (260, 0, 8, 8),
+            (4, 256, 8, 8),
+            (260, 256, 8, 8),
+        ];
+        vdp.load_tile_data(0x1000 + 2, &[0x02, 0x00]);
+        vdp.render_frame();
+        assert_eq!(vdp.dirty_rects(), &repeats);
+        vdp.clear_dirty_rects();
+
+        // Editing tile 2's pixels dirties every cell that shows it
+        vdp.write_vram(2 * 32, 0x33);
+        vdp.render_frame();
+        assert_eq!(vdp.dirty_rects(), &repeats);
+        vdp.clear_dirty_rects();
+
+        vdp.write_reg(VdpRegister::Bg1ScrollY as u32, 1);
+        vdp.render_frame();
+        assert_eq!(vdp.dirty_rects(), &[(0, 0, 384, 288)]);
+    }
+
+    #[test]
+    fn bg1_span_path_matches_per_pixel_path() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        vdp.write_reg(VdpRegister::Bg1Control as u32, BgControl::ENABLE.bits());
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x1000);
+        vdp.write_reg(VdpRegister::Bg1ScrollX as u32, 3);
+        vdp.write_reg(VdpRegister::Bg1ScrollY as u32, 0xFFFE);
+
+        // Tile 1 is opaque, tile 2 has transparent columns
+        vdp.load_tile_data(32, &[0x21; 32]);
+        vdp.load_tile_data(64, &[0x30, 0x04, 0x50, 0x06].repeat(8));
+        vdp.load_palette_16(0, &[(0, 0, 0), (0x3F, 0, 0), (0, 0x3F, 0), (0, 0, 0x3F)]);
+        // Color 0 is never drawn, so make it stand out from the backdrop
+        let unused = (0x3F, 0x3F, 0x3F);
+        vdp.load_palette_16(1, &[unused, (0, 0, 0), (0, 0, 0), (0x20, 0x20, 0)]);
+        for entry in 0..32 * 32u32 {
+            let tile = match entry % 5 {
+                0 => 0x0401, // Flipped horizontally
+                1 => 0x1802, // Palette 1, flipped vertically
+                2 => 0,      // Blank
+                _ => 1 + (entry % 2) as u16,
+            };
+            vdp.load_tile_data(0x1000 + entry * 2, &tile.to_le_bytes());
+        }
+        vdp.render_frame();
+        let spans = vdp.framebuffer().to_vec();
+
+        // Selecting BG1 as a blend source with no destination forces the
+        // per-pixel path without changing any colors
+        vdp.write_reg(
+            VdpRegister::BlendControl as u32,
+            BlendControl::SRC_BG1.bits(),
+        );
+        vdp.render_frame();
+        assert_eq!(vdp.framebuffer(), &spans[..]);
+    }
+
+    #[test]
+    fn vdp_framebuffer_only_shows_completed_frames() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        let lines = |n: u64| n * Vdp::CYCLES_PER_SCANLINE;
+
+        // A line is drawn as the beam enters it, so the frame completes on
+        // reaching the last visible line
+        let last_line = Vdp::NATIVE_HEIGHT as u64 - 1;
+        vdp.set_backdrop_color(0x3F, 0, 0);
+        vdp.step(lines(last_line));
+        let red = vdp.rgb666_to_rgb888(0x3F, 0, 0);
+        assert!(vdp.framebuffer().iter().all(|&p| p == red));
+
+        // During the next VBLANK the new color is only in the back buffer
+        vdp.set_backdrop_color(0, 0, 0x3F);
+        vdp.step(lines(Vdp::VBLANK_START as u64 + 1));
+        assert!(vdp.in_vblank());
+        assert!(vdp.framebuffer().iter().all(|&p| p == red));
+
+        vdp.step(lines(last_line - Vdp::VBLANK_START as u64));
+        let blue = vdp.rgb666_to_rgb888(0, 0, 0x3F);
+        assert!(vdp.framebuffer().iter().all(|&p| p == blue));
+    }
+
+    #[test]
+    fn vdp_scroll_table_sets_scroll_per_scanline() {
+        let mut vdp = Vdp::new();
+        vdp.set_display_enable(true);
+        vdp.set_layer_enable(false, true, false);
+        vdp.write_reg(
+            VdpRegister::Bg1Control as u32,
+            (BgControl::ENABLE | BgControl::COLOR_256 | BgControl::SCROLL_TABLE).bits(),
+        );
+        // The scroll register is ignored while the table is enabled
+        vdp.write_reg(VdpRegister::Bg1ScrollX as u32, 100);
+
+        // Tile n is solid color n + 1 and sits in tilemap column n
+        for tile in 0..32u32 {
+            vdp.load_tile_data(tile * 64, &[tile as u8 + 1; 64]);
+        }
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x4000);
+        for row in 0..32u32 {
+            for col in 0..32u32 {
+                vdp.load_tile_data(0x4000 + (row * 32 + col) * 2, &[col as u8, 0]);
+            }
+        }
+        let colors: Vec<_> = (0..33u8).map(|i| (i, 0x3F - i, 0)).collect();
+        vdp.load_palette(0, &colors);
+
+        let wave: Vec<i16> = (0..Vdp::NATIVE_HEIGHT)
+            .map(|y| ((y as f32 * std::f32::consts::TAU / 96.0).sin() * 40.0).round() as i16)
+            .collect();
+        vdp.set_scroll_table(1, &wave, &[0; Vdp::NATIVE_HEIGHT]);
+        vdp.step((Vdp::NATIVE_HEIGHT as u64 - 1) * Vdp::CYCLES_PER_SCANLINE);
+
+        let fb = vdp.framebuffer();
+        let width = Vdp::NATIVE_WIDTH;
+        let column_at = |x: usize, y: usize| {
+            let world_x = (x as i16).wrapping_add(wave[y]) as u16;
+            (world_x / 8) % 32
+        };
+        // Scanline 120 is at the crest of the wave
+        assert_eq!(wave[120], 40);
+        assert_eq!(column_at(0, 120), 5);
+        for y in [0, 30, 120, 200] {
+            for x in 0..width {
+                let column = column_at(x, y) as u8;
+                let (r, g, b) = colors[column as usize + 1];
+                assert_eq!(
+                    fb[y * width + x],
+                    vdp.rgb666_to_rgb888(r, g, b),
+                    "pixel ({x}, {y}) should show tile column {column}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn vdp_polygon_triangle_is_filled() {
+        let mut vdp = Vdp::new();
+        vdp.write_reg(
+            VdpRegister::DisplayControl as u32,
+            (DisplayControl::ENABLE | DisplayControl::POLYGON_ENABLE).bits(),
+        );
+        vdp.load_palette(0, &[(0, 0, 0), (0, 0x3F, 0)]);
+        vdp.submit_triangle(Triangle {
+            v0: (10, 10),
+            v1: (100, 10),
+            v2: (10, 110),
+            color: 1,
+        });
+        vdp.render_frame();
+
+        let green = vdp.rgb666_to_rgb888(0, 0x3F, 0);
+        let fb = vdp.framebuffer();
+        let width = Vdp::NATIVE_WIDTH;
+        for y in 10..=60 {
+            for x in 10..=50 {
+                assert_eq!(fb[y * width + x], green, "pixel ({x}, {y})");
+            }
+        }
+        for (x, y) in [(9, 10), (10, 9), (100, 100), (10, 111)] {
+            assert_eq!(fb[y * width + x], 0, "pixel ({x}, {y}) is outside");
+        }
+        assert!(vdp.polygon_queue().triangles.is_empty());
+    }
+
+    #[test]
+    fn vdp_polygon_queue_drops_triangles_past_frame_limit() {
+        let mut vdp = Vdp::new();
+        let tri = Triangle {
+            v0: (0, 0),
+            v1: (1, 0),
+            v2: (0, 1),
+            color: 0,
+        };
+        for _ in 0..PolygonQueue::MAX_TRIANGLES {
+            vdp.submit_triangle(tri);
+        }
+        assert!(!vdp.display_status.contains(DisplayStatus::POLYGON_OVERFLOW));
+
+        vdp.submit_triangle(tri);
+        assert_eq!(
+            vdp.polygon_queue().triangles.len(),
+            PolygonQueue::MAX_TRIANGLES
+        );
+        assert!(vdp.display_status.contains(DisplayStatus::POLYGON_OVERFLOW));
+
+        // The next frame takes the queue and accepts new triangles again
+        vdp.step(Vdp::SCANLINES_PER_FRAME as u64 * Vdp::CYCLES_PER_SCANLINE);
+        assert!(vdp.polygon_queue().triangles.is_empty());
+        assert!(!vdp.display_status.contains(DisplayStatus::POLYGON_OVERFLOW));
+    }
+
+    #[test]
+    fn vdp_window_splits_layers_between_screen_halves() {
+        let mut vdp = Vdp::new();
+        vdp.write_reg(
+            VdpRegister::DisplayControl as u32,
+            (DisplayControl::ENABLE
+                | DisplayControl::BG0_ENABLE
+                | DisplayControl::BG1_ENABLE
+                | DisplayControl::WIN0_ENABLE)
+                .bits(),
+        );
+        let bg_control = (BgControl::ENABLE | BgControl::COLOR_256).bits();
+        vdp.write_reg(VdpRegister::Bg0Control as u32, bg_control);
+        vdp.write_reg(VdpRegister::Bg1Control as u32, bg_control);
+
+        // BG0 shows solid tile 0 in color 1, BG1 solid tile 1 in color 2
+        vdp.load_tile_data(0, &[1; 64]);
+        vdp.load_tile_data(64, &[2; 64]);
+        vdp.write_reg(VdpRegister::Bg0TilemapAddr as u32, 0x4000);
+        vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x6000);
+        for entry in 0..32 * 32u32 {
+            vdp.load_tile_data(0x6000 + entry * 2, &[1, 0]);
+        }
+        vdp.load_palette(0, &[(0, 0, 0), (0x3F, 0, 0), (0, 0, 0x3F)]);
+
+        // Left half: BG0 only; everywhere else: BG1 only
+        let half = (Vdp::NATIVE_WIDTH / 2) as u16;
+        for (reg, value) in [
+            (VdpRegister::Win0Left, 0),
+            (VdpRegister::Win0Top, 0),
+            (VdpRegister::Win0Right, half),
+            (VdpRegister::Win0Bottom, Vdp::NATIVE_HEIGHT as u16),
+            (
+                VdpRegister::WindowLayers,
+                (Window::BG1 << 4 | Window::BG0) as u16,
+            ),
+        ] {
+            vdp.write_reg(reg as u32, value);
+        }
+        assert_eq!(
+            vdp.window(0),
+            Some(Window {
+                left: 0,
+                top: 0,
+                right: half,
+                bottom: Vdp::NATIVE_HEIGHT as u16,
+                inside_layers: Window::BG0,
+                outside_layers: Window::BG1,
+            })
+        );
+        assert_eq!(vdp.read_reg(VdpRegister::WindowLayers as u32), 0x21);
+        vdp.render_frame();
+
+        let red = vdp.rgb666_to_rgb888(0x3F, 0, 0);
+        let blue = vdp.rgb666_to_rgb888(0, 0, 0x3F);
+        let fb = vdp.framebuffer();
+        let width = Vdp::NATIVE_WIDTH;
+        for y in [0, 143, 287] {
+            for x in [0, 100, half as usize - 1] {
+                assert_eq!(fb[y * width + x], red, "pixel ({x}, {y}) shows BG0");
+            }
+            for x in [half as usize, 300, width - 1] {
+                assert_eq!(fb[y * width + x], blue, "pixel ({x}, {y}) shows BG1");
+            }
+        }
+    }
+
+    #[test]
+    fn vdp_window_zero_takes_priority_over_window_one() {
+        let mut vdp = Vdp::new();
+        vdp.write_reg(
+            VdpRegister::DisplayControl as u32,
+            (DisplayControl::WIN0_ENABLE | DisplayControl::WIN1_ENABLE).bits(),
+        );
+        let window = |left, right, inside_layers, outside_layers| Window {
+            left,
+            top: 0,
+            right,
+            bottom: 10,
+            inside_layers,
+            outside_layers,
+        };
+        vdp.set_window(0, window(0, 20, Window::BG0, Window::SPRITES | Window::BG1));
+        vdp.set_window(1, window(10, 30, Window::BG1, Window::SPRITES));
+        vdp.set_window(2, window(0, 0, 0, 0));
+        vdp.update_window_mask(5);
+
+        assert_eq!(vdp.window_mask[15], Window::BG0);
+        assert_eq!(vdp.window_mask[25], Window::BG1);
+        assert_eq!(vdp.window_mask[40], Window::SPRITES);
+        assert_eq!(vdp.window(2), None);
+    }
+
+    #[test]
+    fn opaque_mask_marks_non_zero_indices() {
+        assert_eq!(opaque_mask([0; 8]), 0x00);
+        assert_eq!(opaque_mask([1; 8]), 0xFF);
+        assert_eq!(opaque_mask([0, 5, 0, 0, 0, 0, 0, 0xFF]), 0b1000_0010);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn opaque_mask_matches_scalar(row in proptest::prelude::any::<[u8; 8]>()) {
+            proptest::prop_assert_eq!(opaque_mask(row), opaque_mask_scalar(row));
+        }
+    }
+
+    #[test]
+    fn bg1_spans_match_per_pixel_drawing() {
+        let render = |scalar: bool, bypass: bool| {
+            let mut vdp = Vdp::new();
+            vdp.set_display_enable(true);
+            vdp.set_layer_enable(false, true, false);
+            vdp.write_reg(VdpRegister::Bg1Control as u32, BgControl::ENABLE.bits());
+            vdp.write_reg(VdpRegister::Bg1TilemapAddr as u32, 0x8000);
+            vdp.write_reg(VdpRegister::Bg1ScrollX as u32, 3);
+            // Tiles with scattered transparent pixels, some flipped
+            let tiles: Vec<u8> = (0..16 * 32u32).map(|i| (i * 37 % 256) as u8).collect();
+            vdp.load_tile_data(0, &tiles);
+            let palette: Vec<_> = (0..16u8).map(|i| (i * 4, 0x3F - i * 4, i)).collect();
+            vdp.load_palette_16(0, &palette);
+            for entry in 0..32 * 32u32 {
+                let tile = (entry % 16) as u16 | if entry % 3 == 0 { 0x0400 } else { 0 };
+                vdp.load_tile_data(0x8000 + entry * 2, &tile.to_le_bytes());
+            }
+            vdp.set_scalar_bg1(scalar);
+            vdp.set_tile_cache_bypass(bypass);
+            vdp.step(Vdp::CYCLES_PER_SCANLINE * Vdp::SCANLINES_PER_FRAME as u64);
+            vdp.framebuffer().to_vec()
+        };
+        let spans = render(false, false);
+        assert_eq!(spans, render(true, false));
+        assert_eq!(spans, render(false, true));
+    }
 }
@@ -10,26 +10,59 @@
 
 //! VLU-24 vector coprocessor implementation.
 //!
-//! The VLU exposes sixteen vector registers and four matrix registers.  All
+//! The VLU exposes sixteen vector registers and four matrix registers.  Most
 //! operations are 3D and operate on 32-bit floating point data which mirrors the
-//! behaviour of the original hardware's 24-bit fixed point units.  The
-//! implementation favours determinism and correctness over raw throughput.
+//! behaviour of the original hardware's 24-bit fixed point units.  A separate
+//! bank of sixteen homogeneous vectors and two 4×4 matrices supports perspective
+//! projection.  The implementation favours determinism and correctness over raw
+//! throughput.
 //!
-//! Each invocation of [`Vlu::compute`] performs a single vector job and then
-//! raises the `VLU_DONE` interrupt (interrupt id 4).  Callers can load registers
+//! Each invocation of [`Vlu::compute`] performs a single vector job and returns
+//! whether the `VLU_DONE` interrupt (interrupt id 4) is now pending; the VLU
+//! never touches the CPU itself, and `Nexel24::run_vlu_job` raises the
+//! interrupt and charges the job's cycles.  Callers can load registers
 //! via [`Self::set_vector`] and [`Self::set_matrix`] prior to scheduling jobs, and then
 //! inspect the results using [`Self::vector`], [`Self::scalar_result`] or the returned
 //! [`VluResult`].
+//!
+//! Emulated programs reach the VLU through a register window at
+//! `Bus24::VLU_IO_BASE`; see the `MMIO_*` offsets below.
 
 use std::fmt;
 
 use thiserror::Error;
 
+use crate::core::Bus24;
+
 const VECTOR_REGISTER_COUNT: usize = 16;
 const MATRIX_REGISTER_COUNT: usize = 4;
+const VECTOR4_REGISTER_COUNT: usize = 16;
+const MATRIX4_REGISTER_COUNT: usize = 2;
+/// Register window offsets relative to `Bus24::VLU_IO_BASE`.
+///
+/// Vectors are three little-endian f32 words and matrices nine, row-major.
+/// Only V0-V7 and M0-M1 fit below the job register.
+pub const MMIO_VECTOR_BASE: u32 = 0x00;
+pub const MMIO_VECTOR_STRIDE: u32 = 12;
+pub const MMIO_VECTOR_COUNT: usize = 8;
+pub const MMIO_MATRIX_BASE: u32 = 0x60;
+pub const MMIO_MATRIX_STRIDE: u32 = 36;
+pub const MMIO_MATRIX_COUNT: usize = 2;
+/// Writing a job code here runs the job; operands 0-2 follow in the next bytes
+pub const MMIO_JOB: u32 = 0xC0;
+/// Last scalar result as a little-endian f32
+pub const MMIO_RESULT: u32 = 0xC4;
+/// Bit 0 is set when the last job failed (bad code or register index)
+pub const MMIO_STATUS: u32 = 0xC8;
+/// Fourth operand for `Lerp` and `AabbIntersect`
+pub const MMIO_OPERAND3: u32 = 0xC9;
+
+/// Entries in a trig lookup table covering one full turn of sine
+pub const TRIG_LUT_SIZE: usize = 512;
 
 /// An individual 3D vector used by the VLU.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Vec3 {
     x: f32,
     y: f32,
@@ -61,6 +94,16 @@ impl Vec3 {
         }
     }
 
+    /// Linear interpolation `self + (rhs - self) * t`, with `t` clamped to [0, 1].
+    fn lerp(self, rhs: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        Self {
+            x: (rhs.x - self.x).mul_add(t, self.x),
+            y: (rhs.y - self.y).mul_add(t, self.y),
+            z: (rhs.z - self.z).mul_add(t, self.z),
+        }
+    }
+
     fn normalize(self) -> Self {
         let magnitude_sq = self.dot(self);
         if magnitude_sq <= f32::EPSILON {
@@ -83,6 +126,7 @@ impl Vec3 {
 
 /// 3×3 matrix register used for affine transforms.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Mat3 {
     rows: [Vec3; 3],
 }
@@ -113,6 +157,139 @@ impl Mat3 {
             self.rows[2].dot(vec),
         )
     }
+
+    fn transpose(self) -> Self {
+        let m = self.to_array();
+        Self::from_array([0, 1, 2].map(|col| [m[0][col], m[1][col], m[2][col]]))
+    }
+
+    /// Matrix product `self × rhs`.
+    fn mul_mat(self, rhs: Self) -> Self {
+        let cols = rhs.transpose();
+        Self {
+            rows: self.rows.map(|row| cols.mul_vec(row)),
+        }
+    }
+
+    fn identity() -> Self {
+        Self::from_array([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    fn determinant(self) -> f32 {
+        let [r0, r1, r2] = self.rows;
+        r0.dot(r1.cross(r2))
+    }
+
+    /// Inverse via the adjugate (transposed cofactors), or `None` when singular.
+    fn inverse(self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() <= f32::EPSILON {
+            return None;
+        }
+        let [r0, r1, r2] = self.rows;
+        let inv_det = 1.0 / det;
+        let scale = |v: Vec3| Vec3::new(v.x * inv_det, v.y * inv_det, v.z * inv_det);
+        let cofactors = Self {
+            rows: [
+                scale(r1.cross(r2)),
+                scale(r2.cross(r0)),
+                scale(r0.cross(r1)),
+            ],
+        };
+        Some(cofactors.transpose())
+    }
+}
+
+/// Homogeneous 4D vector used by the 4×4 transform unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Vec4 {
+    x: f32,
+    y: f32,
+    z: f32,
+    w: f32,
+}
+
+impl Vec4 {
+    const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    fn from_array(value: [f32; 4]) -> Self {
+        Self::new(value[0], value[1], value[2], value[3])
+    }
+
+    fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+
+    fn dot(self, rhs: Self) -> f32 {
+        self.x.mul_add(
+            rhs.x,
+            self.y.mul_add(rhs.y, self.z.mul_add(rhs.z, self.w * rhs.w)),
+        )
+    }
+
+    /// Hamilton product `self * rhs` of quaternions stored as `[x, y, z, w]`.
+    fn quat_mul(self, rhs: Self) -> Self {
+        let (a, b) = (self, rhs);
+        Self::new(
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        )
+    }
+
+    /// Project back to 3D by dividing through by `w`.
+    fn perspective_divide(self) -> Vec3 {
+        if self.w.abs() <= f32::EPSILON {
+            return Vec3::default();
+        }
+        let inv_w = 1.0 / self.w;
+        Vec3::new(self.x * inv_w, self.y * inv_w, self.z * inv_w)
+    }
+}
+
+/// 4×4 matrix register used for projective transforms.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Mat4 {
+    rows: [Vec4; 4],
+}
+
+impl Mat4 {
+    fn from_array(value: [[f32; 4]; 4]) -> Self {
+        Self {
+            rows: value.map(Vec4::from_array),
+        }
+    }
+
+    fn to_array(self) -> [[f32; 4]; 4] {
+        self.rows.map(Vec4::to_array)
+    }
+
+    fn mul_vec(self, vec: Vec4) -> Vec4 {
+        Vec4::new(
+            self.rows[0].dot(vec),
+            self.rows[1].dot(vec),
+            self.rows[2].dot(vec),
+            self.rows[3].dot(vec),
+        )
+    }
+
+    fn transpose(self) -> Self {
+        let m = self.to_array();
+        Self::from_array([0, 1, 2, 3].map(|col| [m[0][col], m[1][col], m[2][col], m[3][col]]))
+    }
+
+    /// Matrix product `self × rhs`.
+    fn mul_mat(self, rhs: Self) -> Self {
+        let cols = rhs.transpose();
+        Self {
+            rows: self.rows.map(|row| cols.mul_vec(row)),
+        }
+    }
 }
 
 /// Job description supplied to the VLU.
@@ -130,12 +307,141 @@ pub enum VluJob {
     Cross { dest: usize, a: usize, b: usize },
     /// Normalize vector `src` and write it into `dest`.
     Normalize { dest: usize, src: usize },
+    /// Apply 4×4 matrix `matrix` to homogeneous vector `vec`, writing homogeneous
+    /// vector `dest`.
+    Transform4 {
+        dest: usize,
+        vec: usize,
+        matrix: usize,
+    },
+    /// Divide the xyz of homogeneous vector `src` by its w, writing vector `dest`.
+    PerspectiveDivide { dest: usize, src: usize },
+    /// Multiply 3×3 matrices `a` × `b` storing the product in `dest`.
+    MatMul { dest: usize, a: usize, b: usize },
+    /// Multiply 4×4 matrices `a` × `b` storing the product in `dest`.
+    MatMul4 { dest: usize, a: usize, b: usize },
+    /// Interpolate from `a` to `b` by the x component of vector `t_reg`.
+    Lerp {
+        dest: usize,
+        a: usize,
+        b: usize,
+        t_reg: usize,
+    },
+    /// Interpolate from `a` to `b` by the immediate `t`.
+    LerpScalar {
+        dest: usize,
+        a: usize,
+        b: usize,
+        t: f32,
+    },
+    /// Test two axis-aligned boxes for overlap; the scalar result is 1.0 on
+    /// intersection (touching faces count) and 0.0 otherwise.
+    AabbIntersect {
+        a_min: usize,
+        a_max: usize,
+        b_min: usize,
+        b_max: usize,
+    },
+    /// Hamilton product of quaternions held in homogeneous registers as
+    /// `[x, y, z, w]`, storing `a * b` in `dest`.
+    QuatMul { dest: usize, a: usize, b: usize },
+    /// Store `[sin, cos, 0.0]` of the angle (radians) in the x component of
+    /// `angle_reg` into `dest`.
+    SinCos { dest: usize, angle_reg: usize },
+    /// Invert 3×3 matrix `src` into `dest` and store the determinant in the
+    /// scalar register. A singular matrix writes the identity instead and is
+    /// reported by [`Vlu::last_inverse_singular`] (the CPU sees carry set).
+    MatInverse { dest: usize, src: usize },
+}
+
+impl VluJob {
+    /// Decode a job code and register operands as issued by COP or the
+    /// register window. `LerpScalar` has no code since it needs an immediate.
+    pub fn from_code(code: u8, operands: [usize; 4]) -> Option<Self> {
+        let [r0, r1, r2, r3] = operands;
+        let job = match code {
+            0 => Self::Transform {
+                dest: r0,
+                vec: r1,
+                matrix: r2,
+            },
+            1 => Self::Dot { a: r0, b: r1 },
+            2 => Self::Cross {
+                dest: r0,
+                a: r1,
+                b: r2,
+            },
+            3 => Self::Normalize { dest: r0, src: r1 },
+            4 => Self::Transform4 {
+                dest: r0,
+                vec: r1,
+                matrix: r2,
+            },
+            5 => Self::PerspectiveDivide { dest: r0, src: r1 },
+            6 => Self::MatMul {
+                dest: r0,
+                a: r1,
+                b: r2,
+            },
+            7 => Self::MatMul4 {
+                dest: r0,
+                a: r1,
+                b: r2,
+            },
+            8 => Self::Lerp {
+                dest: r0,
+                a: r1,
+                b: r2,
+                t_reg: r3,
+            },
+            9 => Self::AabbIntersect {
+                a_min: r0,
+                a_max: r1,
+                b_min: r2,
+                b_max: r3,
+            },
+            10 => Self::QuatMul {
+                dest: r0,
+                a: r1,
+                b: r2,
+            },
+            11 => Self::SinCos {
+                dest: r0,
+                angle_reg: r1,
+            },
+            12 => Self::MatInverse { dest: r0, src: r1 },
+            _ => return None,
+        };
+        Some(job)
+    }
+
+    /// CPU cycles the job takes to complete.
+    pub fn cycles(&self) -> u64 {
+        match self {
+            Self::Transform { .. } => 12,
+            Self::Dot { .. } => 3,
+            Self::Cross { .. } => 6,
+            Self::Normalize { .. } => 8,
+            Self::Transform4 { .. } => 16,
+            Self::PerspectiveDivide { .. } => 6,
+            Self::MatMul { .. } => 24,
+            Self::MatMul4 { .. } => 48,
+            Self::Lerp { .. } | Self::LerpScalar { .. } => 6,
+            Self::AabbIntersect { .. } => 4,
+            Self::QuatMul { .. } => 16,
+            Self::SinCos { .. } => 4, // Trig table lookup
+            Self::MatInverse { .. } => 30,
+        }
+    }
 }
 
 /// Result of a VLU computation.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VluResult {
     Vector([f32; 3]),
+    Vector4([f32; 4]),
+    Matrix([[f32; 3]; 3]),
+    Matrix4([[f32; 4]; 4]),
     Scalar(f32),
 }
 
@@ -143,6 +449,27 @@ impl fmt::Display for VluResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Vector(v) => write!(f, "[{:.6}, {:.6}, {:.6}]", v[0], v[1], v[2]),
+            Self::Vector4(v) => write!(f, "[{:.6}, {:.6}, {:.6}, {:.6}]", v[0], v[1], v[2], v[3]),
+            Self::Matrix(m) => {
+                write!(f, "[")?;
+                for (i, row) in m.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{sep}[{:.6}, {:.6}, {:.6}]", row[0], row[1], row[2])?;
+                }
+                write!(f, "]")
+            }
+            Self::Matrix4(m) => {
+                write!(f, "[")?;
+                for (i, row) in m.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(
+                        f,
+                        "{sep}[{:.6}, {:.6}, {:.6}, {:.6}]",
+                        row[0], row[1], row[2], row[3]
+                    )?;
+                }
+                write!(f, "]")
+            }
             Self::Scalar(s) => write!(f, "{:.6}", s),
         }
     }
@@ -155,13 +482,34 @@ pub enum VluError {
     InvalidVectorRegister(usize),
     #[error("invalid matrix register {0}")]
     InvalidMatrixRegister(usize),
+    #[error("invalid homogeneous vector register {0}")]
+    InvalidVector4Register(usize),
+    #[error("invalid 4x4 matrix register {0}")]
+    InvalidMatrix4Register(usize),
+}
+
+/// Snapshot of the VLU-24 register file, used for save states
+///
+/// The trig lookup table is derived data and is not saved.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VluState {
+    vectors: [Vec3; VECTOR_REGISTER_COUNT],
+    matrices: [Mat3; MATRIX_REGISTER_COUNT],
+    vectors4: [Vec4; VECTOR4_REGISTER_COUNT],
+    matrices4: [Mat4; MATRIX4_REGISTER_COUNT],
+    last_scalar: f32,
 }
 
 /// VLU-24 vector coprocessor.
 pub struct Vlu {
     vectors: [Vec3; VECTOR_REGISTER_COUNT],
     matrices: [Mat3; MATRIX_REGISTER_COUNT],
+    vectors4: [Vec4; VECTOR4_REGISTER_COUNT],
+    matrices4: [Mat4; MATRIX4_REGISTER_COUNT],
     last_scalar: f32,
+    singular: bool,
+    trig_lut: Option<Box<[f32; TRIG_LUT_SIZE]>>,
 }
 
 impl Vlu {
@@ -170,10 +518,34 @@ impl Vlu {
         Self {
             vectors: [Vec3::default(); VECTOR_REGISTER_COUNT],
             matrices: [Mat3::default(); MATRIX_REGISTER_COUNT],
+            vectors4: [Vec4::default(); VECTOR4_REGISTER_COUNT],
+            matrices4: [Mat4::default(); MATRIX4_REGISTER_COUNT],
             last_scalar: 0.0,
+            singular: false,
+            trig_lut: None,
+        }
+    }
+
+    /// Capture the register file for a save state.
+    pub fn save(&self) -> VluState {
+        VluState {
+            vectors: self.vectors,
+            matrices: self.matrices,
+            vectors4: self.vectors4,
+            matrices4: self.matrices4,
+            last_scalar: self.last_scalar,
         }
     }
 
+    /// Restore registers previously captured with [`Vlu::save`].
+    pub fn load(&mut self, state: &VluState) {
+        self.vectors = state.vectors;
+        self.matrices = state.matrices;
+        self.vectors4 = state.vectors4;
+        self.matrices4 = state.matrices4;
+        self.last_scalar = state.last_scalar;
+    }
+
     /// Load a vector register.
     pub fn set_vector(&mut self, index: usize, value: [f32; 3]) -> Result<(), VluError> {
         let slot = self
@@ -212,17 +584,129 @@ impl Vlu {
             .map(Mat3::to_array)
     }
 
+    /// Load a homogeneous vector register.
+    pub fn set_vector4(&mut self, index: usize, value: [f32; 4]) -> Result<(), VluError> {
+        let slot = self
+            .vectors4
+            .get_mut(index)
+            .ok_or(VluError::InvalidVector4Register(index))?;
+        *slot = Vec4::from_array(value);
+        Ok(())
+    }
+
+    /// Read a homogeneous vector register.
+    pub fn vector4(&self, index: usize) -> Result<[f32; 4], VluError> {
+        self.vectors4
+            .get(index)
+            .copied()
+            .ok_or(VluError::InvalidVector4Register(index))
+            .map(Vec4::to_array)
+    }
+
+    /// Load a 4×4 matrix register.
+    pub fn set_matrix4(&mut self, index: usize, value: [[f32; 4]; 4]) -> Result<(), VluError> {
+        let slot = self
+            .matrices4
+            .get_mut(index)
+            .ok_or(VluError::InvalidMatrix4Register(index))?;
+        *slot = Mat4::from_array(value);
+        Ok(())
+    }
+
+    /// Read a 4×4 matrix register.
+    pub fn matrix4(&self, index: usize) -> Result<[[f32; 4]; 4], VluError> {
+        self.matrices4
+            .get(index)
+            .copied()
+            .ok_or(VluError::InvalidMatrix4Register(index))
+            .map(Mat4::to_array)
+    }
+
+    /// Replace the sine/cosine unit with a lookup table.
+    ///
+    /// `table` holds one full period of sine; cosine reads it a quarter turn
+    /// ahead and lookups interpolate linearly between entries.
+    pub fn set_trig_lut(&mut self, table: &[f32; TRIG_LUT_SIZE]) {
+        self.trig_lut = Some(Box::new(*table));
+    }
+
+    /// Sine and cosine of `angle`, from the lookup table if one is loaded.
+    fn sin_cos(&self, angle: f32) -> (f32, f32) {
+        let Some(table) = &self.trig_lut else {
+            return angle.sin_cos();
+        };
+        let lookup = |position: f32| {
+            let position = position.rem_euclid(TRIG_LUT_SIZE as f32);
+            let index = position as usize % TRIG_LUT_SIZE;
+            let next = (index + 1) % TRIG_LUT_SIZE;
+            let frac = position.fract();
+            table[index] + (table[next] - table[index]) * frac
+        };
+        let position = angle / std::f32::consts::TAU * TRIG_LUT_SIZE as f32;
+        (
+            lookup(position),
+            lookup(position + (TRIG_LUT_SIZE / 4) as f32),
+        )
+    }
+
+    /// Copy the register window's vectors and matrices into the VLU.
+    pub fn load_mmio(&mut self, bus: &Bus24) {
+        let read_f32 = |addr: u32| {
+            f32::from_le_bytes([0, 1, 2, 3].map(|i| bus.read_u8(Bus24::VLU_IO_BASE + addr + i)))
+        };
+        for (n, vec) in self.vectors[..MMIO_VECTOR_COUNT].iter_mut().enumerate() {
+            let base = MMIO_VECTOR_BASE + n as u32 * MMIO_VECTOR_STRIDE;
+            *vec = Vec3::from_array([0, 1, 2].map(|i| read_f32(base + i * 4)));
+        }
+        for (n, mat) in self.matrices[..MMIO_MATRIX_COUNT].iter_mut().enumerate() {
+            let base = MMIO_MATRIX_BASE + n as u32 * MMIO_MATRIX_STRIDE;
+            *mat = Mat3::from_array(
+                [0, 1, 2].map(|row| [0, 1, 2].map(|col| read_f32(base + (row * 3 + col) * 4))),
+            );
+        }
+    }
+
+    /// Write the VLU's vectors, matrices, scalar result and status back to
+    /// the register window.
+    pub fn store_mmio(&self, bus: &mut Bus24, failed: bool) {
+        let mut write_f32 = |addr: u32, value: f32| {
+            for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+                bus.write_u8(Bus24::VLU_IO_BASE + addr + i as u32, byte);
+            }
+        };
+        for (n, vec) in self.vectors[..MMIO_VECTOR_COUNT].iter().enumerate() {
+            let base = MMIO_VECTOR_BASE + n as u32 * MMIO_VECTOR_STRIDE;
+            for (i, value) in vec.to_array().into_iter().enumerate() {
+                write_f32(base + i as u32 * 4, value);
+            }
+        }
+        for (n, mat) in self.matrices[..MMIO_MATRIX_COUNT].iter().enumerate() {
+            let base = MMIO_MATRIX_BASE + n as u32 * MMIO_MATRIX_STRIDE;
+            for (i, value) in mat.to_array().into_iter().flatten().enumerate() {
+                write_f32(base + i as u32 * 4, value);
+            }
+        }
+        write_f32(MMIO_RESULT, self.last_scalar);
+        bus.write_u8(Bus24::VLU_IO_BASE + MMIO_STATUS, failed as u8);
+    }
+
     /// Last scalar result produced by [`VluJob::Dot`].
     pub fn scalar_result(&self) -> f32 {
         self.last_scalar
     }
 
-    /// Perform a vector job and raise the VLU completion interrupt.
-    pub fn compute(
-        &mut self,
-        cpu: &mut crate::cpu::Cpu,
-        job: VluJob,
-    ) -> Result<VluResult, VluError> {
+    /// Whether the last job was a [`VluJob::MatInverse`] of a singular matrix.
+    pub fn last_inverse_singular(&self) -> bool {
+        self.singular
+    }
+
+    /// Perform a vector job.
+    ///
+    /// On success the result is returned with whether the VLU completion
+    /// interrupt (4) is now pending; the caller raises it on the CPU and
+    /// charges the job's [`VluJob::cycles`].
+    pub fn compute(&mut self, job: VluJob) -> Result<(VluResult, bool), VluError> {
+        self.singular = false;
         let result = match job {
             VluJob::Transform { dest, vec, matrix } => {
                 let vec = *self
@@ -238,7 +722,6 @@ impl Vlu {
                     .vectors
                     .get_mut(dest)
                     .ok_or(VluError::InvalidVectorRegister(dest))? = transformed;
-                cpu.cycles += 12; // Matrix transform takes 12 cycles
                 VluResult::Vector(transformed.to_array())
             }
             VluJob::Dot { a, b } => {
@@ -252,7 +735,6 @@ impl Vlu {
                     .ok_or(VluError::InvalidVectorRegister(b))?;
                 let dot = lhs.dot(rhs);
                 self.last_scalar = dot;
-                cpu.cycles += 3; // Dot product takes 3 cycles
                 VluResult::Scalar(dot)
             }
             VluJob::Cross { dest, a, b } => {
@@ -269,7 +751,6 @@ impl Vlu {
                     .vectors
                     .get_mut(dest)
                     .ok_or(VluError::InvalidVectorRegister(dest))? = cross;
-                cpu.cycles += 6; // Cross product takes 6 cycles
                 VluResult::Vector(cross.to_array())
             }
             VluJob::Normalize { dest, src } => {
@@ -282,14 +763,171 @@ impl Vlu {
                     .vectors
                     .get_mut(dest)
                     .ok_or(VluError::InvalidVectorRegister(dest))? = normalized;
-                cpu.cycles += 8; // Normalize takes 8 cycles
                 VluResult::Vector(normalized.to_array())
             }
+            VluJob::Transform4 { dest, vec, matrix } => {
+                let vec = *self
+                    .vectors4
+                    .get(vec)
+                    .ok_or(VluError::InvalidVector4Register(vec))?;
+                let mat = *self
+                    .matrices4
+                    .get(matrix)
+                    .ok_or(VluError::InvalidMatrix4Register(matrix))?;
+                let transformed = mat.mul_vec(vec);
+                *self
+                    .vectors4
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidVector4Register(dest))? = transformed;
+                VluResult::Vector4(transformed.to_array())
+            }
+            VluJob::PerspectiveDivide { dest, src } => {
+                let vec = *self
+                    .vectors4
+                    .get(src)
+                    .ok_or(VluError::InvalidVector4Register(src))?;
+                let projected = vec.perspective_divide();
+                *self
+                    .vectors
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidVectorRegister(dest))? = projected;
+                VluResult::Vector(projected.to_array())
+            }
+            VluJob::MatMul { dest, a, b } => {
+                let lhs = *self
+                    .matrices
+                    .get(a)
+                    .ok_or(VluError::InvalidMatrixRegister(a))?;
+                let rhs = *self
+                    .matrices
+                    .get(b)
+                    .ok_or(VluError::InvalidMatrixRegister(b))?;
+                let product = lhs.mul_mat(rhs);
+                *self
+                    .matrices
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidMatrixRegister(dest))? = product;
+                VluResult::Matrix(product.to_array())
+            }
+            VluJob::MatMul4 { dest, a, b } => {
+                let lhs = *self
+                    .matrices4
+                    .get(a)
+                    .ok_or(VluError::InvalidMatrix4Register(a))?;
+                let rhs = *self
+                    .matrices4
+                    .get(b)
+                    .ok_or(VluError::InvalidMatrix4Register(b))?;
+                let product = lhs.mul_mat(rhs);
+                *self
+                    .matrices4
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidMatrix4Register(dest))? = product;
+                VluResult::Matrix4(product.to_array())
+            }
+            VluJob::Lerp { dest, a, b, t_reg } => {
+                let t = self
+                    .vectors
+                    .get(t_reg)
+                    .ok_or(VluError::InvalidVectorRegister(t_reg))?
+                    .x;
+                self.lerp(dest, a, b, t)?
+            }
+            VluJob::LerpScalar { dest, a, b, t } => self.lerp(dest, a, b, t)?,
+            VluJob::AabbIntersect {
+                a_min,
+                a_max,
+                b_min,
+                b_max,
+            } => {
+                let [a_min, a_max, b_min, b_max] = [a_min, a_max, b_min, b_max].map(|index| {
+                    self.vectors
+                        .get(index)
+                        .copied()
+                        .ok_or(VluError::InvalidVectorRegister(index))
+                });
+                let (a_min, a_max, b_min, b_max) = (a_min?, a_max?, b_min?, b_max?);
+                let overlaps =
+                    |a_lo: f32, a_hi: f32, b_lo: f32, b_hi: f32| a_lo <= b_hi && b_lo <= a_hi;
+                let hit = overlaps(a_min.x, a_max.x, b_min.x, b_max.x)
+                    && overlaps(a_min.y, a_max.y, b_min.y, b_max.y)
+                    && overlaps(a_min.z, a_max.z, b_min.z, b_max.z);
+                let result = if hit { 1.0 } else { 0.0 };
+                self.last_scalar = result;
+                VluResult::Scalar(result)
+            }
+            VluJob::QuatMul { dest, a, b } => {
+                let lhs = *self
+                    .vectors4
+                    .get(a)
+                    .ok_or(VluError::InvalidVector4Register(a))?;
+                let rhs = *self
+                    .vectors4
+                    .get(b)
+                    .ok_or(VluError::InvalidVector4Register(b))?;
+                let product = lhs.quat_mul(rhs);
+                *self
+                    .vectors4
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidVector4Register(dest))? = product;
+                VluResult::Vector4(product.to_array())
+            }
+            VluJob::SinCos { dest, angle_reg } => {
+                let angle = self
+                    .vectors
+                    .get(angle_reg)
+                    .ok_or(VluError::InvalidVectorRegister(angle_reg))?
+                    .x;
+                let (sin, cos) = self.sin_cos(angle);
+                let result = Vec3::new(sin, cos, 0.0);
+                *self
+                    .vectors
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidVectorRegister(dest))? = result;
+                VluResult::Vector(result.to_array())
+            }
+            VluJob::MatInverse { dest, src } => {
+                let mat = *self
+                    .matrices
+                    .get(src)
+                    .ok_or(VluError::InvalidMatrixRegister(src))?;
+                let slot = self
+                    .matrices
+                    .get_mut(dest)
+                    .ok_or(VluError::InvalidMatrixRegister(dest))?;
+                let inverse = mat.inverse();
+                self.singular = inverse.is_none();
+                *slot = inverse.unwrap_or_else(Mat3::identity);
+                let result = slot.to_array();
+                self.last_scalar = mat.determinant();
+                VluResult::Matrix(result)
+            }
         };
 
-        cpu.request_interrupt(4);
+        Ok((result, true))
+    }
+
+    fn lerp(&mut self, dest: usize, a: usize, b: usize, t: f32) -> Result<VluResult, VluError> {
+        let from = *self
+            .vectors
+            .get(a)
+            .ok_or(VluError::InvalidVectorRegister(a))?;
+        let to = *self
+            .vectors
+            .get(b)
+            .ok_or(VluError::InvalidVectorRegister(b))?;
+        let blended = from.lerp(to, t);
+        *self
+            .vectors
+            .get_mut(dest)
+            .ok_or(VluError::InvalidVectorRegister(dest))? = blended;
+        Ok(VluResult::Vector(blended.to_array()))
+    }
+}
 
-        Ok(result)
+impl Default for Vlu {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -308,28 +946,20 @@ fn fast_inv_sqrt(value: f32) -> f32 {
 mod tests {
     use super::*;
 
-    fn cpu() -> crate::cpu::Cpu {
-        crate::cpu::Cpu::new()
-    }
-
     #[test]
     fn transform_applies_matrix() {
         let mut vlu = Vlu::new();
-        let mut cpu = cpu();
 
         vlu.set_vector(0, [1.0, 2.0, 3.0]).unwrap();
         vlu.set_matrix(0, [[1.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 3.0]])
             .unwrap();
 
-        let result = vlu
-            .compute(
-                &mut cpu,
-                VluJob::Transform {
-                    dest: 1,
-                    vec: 0,
-                    matrix: 0,
-                },
-            )
+        let (result, _) = vlu
+            .compute(VluJob::Transform {
+                dest: 1,
+                vec: 0,
+                matrix: 0,
+            })
             .unwrap();
 
         assert_eq!(result, VluResult::Vector([1.0, 4.0, 9.0]));
@@ -339,11 +969,10 @@ mod tests {
     #[test]
     fn dot_product_returns_scalar() {
         let mut vlu = Vlu::new();
-        let mut cpu = cpu();
         vlu.set_vector(0, [1.0, 3.0, -5.0]).unwrap();
         vlu.set_vector(1, [4.0, -2.0, -1.0]).unwrap();
 
-        let result = vlu.compute(&mut cpu, VluJob::Dot { a: 0, b: 1 }).unwrap();
+        let (result, _) = vlu.compute(VluJob::Dot { a: 0, b: 1 }).unwrap();
 
         assert_eq!(result, VluResult::Scalar(3.0));
         assert_eq!(vlu.scalar_result(), 3.0);
@@ -352,19 +981,15 @@ mod tests {
     #[test]
     fn cross_product_stores_vector() {
         let mut vlu = Vlu::new();
-        let mut cpu = cpu();
         vlu.set_vector(0, [1.0, 0.0, 0.0]).unwrap();
         vlu.set_vector(1, [0.0, 1.0, 0.0]).unwrap();
 
-        let result = vlu
-            .compute(
-                &mut cpu,
-                VluJob::Cross {
-                    dest: 2,
-                    a: 0,
-                    b: 1,
-                },
-            )
+        let (result, _) = vlu
+            .compute(VluJob::Cross {
+                dest: 2,
+                a: 0,
+                b: 1,
+            })
             .unwrap();
 
         assert_eq!(result, VluResult::Vector([0.0, 0.0, 1.0]));
@@ -374,12 +999,9 @@ mod tests {
     #[test]
     fn normalize_handles_zero_vector() {
         let mut vlu = Vlu::new();
-        let mut cpu = cpu();
         vlu.set_vector(0, [0.0, 0.0, 0.0]).unwrap();
 
-        let result = vlu
-            .compute(&mut cpu, VluJob::Normalize { dest: 1, src: 0 })
-            .unwrap();
+        let (result, _) = vlu.compute(VluJob::Normalize { dest: 1, src: 0 }).unwrap();
 
         assert_eq!(result, VluResult::Vector([0.0, 0.0, 0.0]));
     }
@@ -387,20 +1009,350 @@ mod tests {
     #[test]
     fn invalid_register_returns_error() {
         let mut vlu = Vlu::new();
-        let mut cpu = cpu();
         vlu.set_vector(0, [1.0, 0.0, 0.0]).unwrap();
 
         let err = vlu
-            .compute(
-                &mut cpu,
-                VluJob::Transform {
-                    dest: 16,
-                    vec: 0,
-                    matrix: 0,
-                },
-            )
+            .compute(VluJob::Transform {
+                dest: 16,
+                vec: 0,
+                matrix: 0,
+            })
             .unwrap_err();
 
         assert_eq!(err, VluError::InvalidVectorRegister(16));
     }
+
+    /// OpenGL-style projection: 90° FOV, square aspect, near 1, far 10.
+    fn perspective() -> [[f32; 4]; 4] {
+        let (near, far) = (1.0, 10.0);
+        [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [
+                0.0,
+                0.0,
+                (far + near) / (near - far),
+                2.0 * far * near / (near - far),
+            ],
+            [0.0, 0.0, -1.0, 0.0],
+        ]
+    }
+
+    fn project(vlu: &mut Vlu, point: [f32; 4]) -> [f32; 3] {
+        vlu.set_vector4(0, point).unwrap();
+        vlu.compute(VluJob::Transform4 {
+            dest: 1,
+            vec: 0,
+            matrix: 1,
+        })
+        .unwrap();
+        match vlu
+            .compute(VluJob::PerspectiveDivide { dest: 0, src: 1 })
+            .unwrap()
+        {
+            (VluResult::Vector(v), _) => v,
+            (other, _) => panic!("unexpected result {other}"),
+        }
+    }
+
+    #[test]
+    fn perspective_projection_and_divide() {
+        let mut vlu = Vlu::new();
+        vlu.set_matrix4(1, perspective()).unwrap();
+        assert_eq!(vlu.matrix4(1).unwrap(), perspective());
+        // 4×4 registers are separate from the 3×3 bank
+        assert_eq!(vlu.matrix(1).unwrap(), [[0.0; 3]; 3]);
+
+        // In front of the camera: lands inside the clip volume
+        let front = project(&mut vlu, [1.0, 1.0, -2.0, 1.0]);
+        assert_eq!(vlu.vector4(1).unwrap()[3], 2.0);
+        assert!(front.iter().all(|c| (-1.0..=1.0).contains(c)));
+        assert_eq!(vlu.vector(0).unwrap(), front);
+
+        // Behind the camera: w goes negative, flipping x/y and pushing z past
+        // the far plane
+        let behind = project(&mut vlu, [1.0, 1.0, 2.0, 1.0]);
+        assert_eq!(vlu.vector4(1).unwrap()[3], -2.0);
+        assert!(behind[0] < 0.0 && behind[1] < 0.0);
+        assert!(behind[2] > 1.0);
+    }
+
+    #[test]
+    fn matrix4_register_bounds() {
+        let mut vlu = Vlu::new();
+        assert_eq!(
+            vlu.set_matrix4(2, perspective()),
+            Err(VluError::InvalidMatrix4Register(2))
+        );
+        let err = vlu
+            .compute(VluJob::Transform4 {
+                dest: 0,
+                vec: 16,
+                matrix: 0,
+            })
+            .unwrap_err();
+        assert_eq!(err, VluError::InvalidVector4Register(16));
+    }
+
+    #[test]
+    fn matmul_concatenates_rotations() {
+        let mut vlu = Vlu::new();
+        let rot90 = [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+        vlu.set_matrix(0, rot90).unwrap();
+        vlu.set_matrix(1, rot90).unwrap();
+
+        let (result, interrupt) = vlu
+            .compute(VluJob::MatMul {
+                dest: 2,
+                a: 0,
+                b: 1,
+            })
+            .unwrap();
+
+        let rot180 = [[-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]];
+        let product = vlu.matrix(2).unwrap();
+        assert_eq!(result, VluResult::Matrix(product));
+        for (row, expected) in product.iter().zip(rot180) {
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-6);
+            }
+        }
+        assert!(interrupt);
+    }
+
+    #[test]
+    fn matmul4_with_identity_is_unchanged() {
+        let mut vlu = Vlu::new();
+        let identity = [0, 1, 2, 3].map(|i| [0, 1, 2, 3].map(|j| if i == j { 1.0 } else { 0.0 }));
+        vlu.set_matrix4(0, perspective()).unwrap();
+        vlu.set_matrix4(1, identity).unwrap();
+
+        let (result, _) = vlu
+            .compute(VluJob::MatMul4 {
+                dest: 1,
+                a: 1,
+                b: 0,
+            })
+            .unwrap();
+
+        assert_eq!(result, VluResult::Matrix4(perspective()));
+        assert_eq!(vlu.matrix4(1).unwrap(), perspective());
+    }
+
+    #[test]
+    fn matmul_rejects_invalid_registers() {
+        let mut vlu = Vlu::new();
+        for (dest, a, b, bad) in [(4, 0, 1, 4), (0, 4, 1, 4), (0, 1, 5, 5)] {
+            let err = vlu.compute(VluJob::MatMul { dest, a, b }).unwrap_err();
+            assert_eq!(err, VluError::InvalidMatrixRegister(bad));
+        }
+        let err = vlu
+            .compute(VluJob::MatMul4 {
+                dest: 2,
+                a: 0,
+                b: 1,
+            })
+            .unwrap_err();
+        assert_eq!(err, VluError::InvalidMatrix4Register(2));
+    }
+
+    #[test]
+    fn lerp_interpolates_between_vectors() {
+        let mut vlu = Vlu::new();
+        vlu.set_vector(0, [0.0, 2.0, -4.0]).unwrap();
+        vlu.set_vector(1, [10.0, 4.0, 4.0]).unwrap();
+
+        let mut lerp = |t: f32| {
+            vlu.set_vector(2, [t, 0.0, 0.0]).unwrap();
+            vlu.compute(VluJob::Lerp {
+                dest: 3,
+                a: 0,
+                b: 1,
+                t_reg: 2,
+            })
+            .unwrap()
+            .0
+        };
+        assert_eq!(lerp(0.0), VluResult::Vector([0.0, 2.0, -4.0]));
+        assert_eq!(lerp(1.0), VluResult::Vector([10.0, 4.0, 4.0]));
+        assert_eq!(lerp(0.5), VluResult::Vector([5.0, 3.0, 0.0]));
+        // t is clamped to [0, 1]
+        assert_eq!(lerp(2.0), VluResult::Vector([10.0, 4.0, 4.0]));
+        assert_eq!(lerp(-1.0), VluResult::Vector([0.0, 2.0, -4.0]));
+
+        let (result, _) = vlu
+            .compute(VluJob::LerpScalar {
+                dest: 3,
+                a: 0,
+                b: 1,
+                t: 0.5,
+            })
+            .unwrap();
+        assert_eq!(result, VluResult::Vector([5.0, 3.0, 0.0]));
+        assert_eq!(vlu.vector(3).unwrap(), [5.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn aabb_intersection_cases() {
+        let mut vlu = Vlu::new();
+        vlu.set_vector(0, [0.0, 0.0, 0.0]).unwrap();
+        vlu.set_vector(1, [1.0, 1.0, 1.0]).unwrap();
+
+        let mut test = |b_min: [f32; 3], b_max: [f32; 3]| {
+            vlu.set_vector(2, b_min).unwrap();
+            vlu.set_vector(3, b_max).unwrap();
+            let (result, interrupt) = vlu
+                .compute(VluJob::AabbIntersect {
+                    a_min: 0,
+                    a_max: 1,
+                    b_min: 2,
+                    b_max: 3,
+                })
+                .unwrap();
+            assert_eq!(result, VluResult::Scalar(vlu.scalar_result()));
+            assert!(interrupt);
+            vlu.scalar_result()
+        };
+
+        // Separated along z only
+        assert_eq!(test([0.5, 0.5, 2.0], [1.5, 1.5, 3.0]), 0.0);
+        // Faces touching at x = 1
+        assert_eq!(test([1.0, 0.0, 0.0], [2.0, 1.0, 1.0]), 1.0);
+        // Overlapping corner
+        assert_eq!(test([0.5, 0.5, 0.5], [1.5, 1.5, 1.5]), 1.0);
+        // Fully contained
+        assert_eq!(test([0.25, 0.25, 0.25], [0.75, 0.75, 0.75]), 1.0);
+    }
+
+    #[test]
+    fn quat_mul_composes_rotations() {
+        let mut vlu = Vlu::new();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let quat_mul = |vlu: &mut Vlu, a, b| {
+            vlu.set_vector4(0, a).unwrap();
+            vlu.set_vector4(1, b).unwrap();
+            match vlu
+                .compute(VluJob::QuatMul {
+                    dest: 2,
+                    a: 0,
+                    b: 1,
+                })
+                .unwrap()
+            {
+                (VluResult::Vector4(q), true) => q,
+                (other, _) => panic!("unexpected result {other}"),
+            }
+        };
+        let assert_close = |actual: [f32; 4], expected: [f32; 4]| {
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-6, "{actual:?} != {expected:?}");
+            }
+        };
+
+        // Two 90° turns about z make a 180° turn about z
+        let rot_z = [0.0, 0.0, half, half];
+        let q = quat_mul(&mut vlu, rot_z, rot_z);
+        assert_close(q, [0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(vlu.vector4(2).unwrap(), q);
+
+        // Same about x; quaternion products do not commute in general
+        let rot_x = [half, 0.0, 0.0, half];
+        assert_close(quat_mul(&mut vlu, rot_x, rot_x), [1.0, 0.0, 0.0, 0.0]);
+        let rot_y = [0.0, half, 0.0, half];
+        assert_close(quat_mul(&mut vlu, rot_x, rot_y), [0.5, 0.5, 0.5, 0.5]);
+        assert_close(quat_mul(&mut vlu, rot_y, rot_x), [0.5, 0.5, -0.5, 0.5]);
+    }
+
+    fn sin_cos_at(vlu: &mut Vlu, angle: f32) -> [f32; 3] {
+        vlu.set_vector(0, [angle, 0.0, 0.0]).unwrap();
+        vlu.compute(VluJob::SinCos {
+            dest: 1,
+            angle_reg: 0,
+        })
+        .unwrap();
+        vlu.vector(1).unwrap()
+    }
+
+    #[test]
+    fn sin_cos_quadrants() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+
+        let mut vlu = Vlu::new();
+        let cases = [
+            (0.0, 0.0, 1.0),
+            (FRAC_PI_2, 1.0, 0.0),
+            (PI, 0.0, -1.0),
+            (3.0 * FRAC_PI_2, -1.0, 0.0),
+        ];
+        for (angle, sin, cos) in cases {
+            let [s, c, z] = sin_cos_at(&mut vlu, angle);
+            assert!((s - sin).abs() < 1e-5, "sin({angle}) = {s}");
+            assert!((c - cos).abs() < 1e-5, "cos({angle}) = {c}");
+            assert_eq!(z, 0.0);
+        }
+    }
+
+    #[test]
+    fn sin_cos_uses_custom_lut() {
+        let mut vlu = Vlu::new();
+        let mut table = [0.0; TRIG_LUT_SIZE];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = (i as f32 / TRIG_LUT_SIZE as f32 * std::f32::consts::TAU).sin();
+        }
+        vlu.set_trig_lut(&table);
+        let [s, c, _] = sin_cos_at(&mut vlu, std::f32::consts::PI);
+        assert!(s.abs() < 1e-5 && (c + 1.0).abs() < 1e-5);
+        let [s, c, _] = sin_cos_at(&mut vlu, -std::f32::consts::FRAC_PI_2);
+        assert!((s + 1.0).abs() < 1e-5 && c.abs() < 1e-5);
+
+        // A deliberately wrong table shows the lookup path is in use
+        vlu.set_trig_lut(&[0.25; TRIG_LUT_SIZE]);
+        assert_eq!(sin_cos_at(&mut vlu, 1.0), [0.25, 0.25, 0.0]);
+    }
+
+    #[test]
+    fn mat_inverse_round_trips() {
+        let mut vlu = Vlu::new();
+        let matrices = [
+            [[2.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 0.5]],
+            [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            [[1.0, 2.0, 3.0], [0.0, 1.0, 4.0], [5.0, 6.0, 0.0]],
+            [[3.0, -1.5, 2.0], [0.25, 7.0, -3.0], [1.0, 1.0, 1.0]],
+        ];
+        for matrix in matrices {
+            vlu.set_matrix(0, matrix).unwrap();
+            vlu.compute(VluJob::MatInverse { dest: 1, src: 0 }).unwrap();
+            assert!(!vlu.last_inverse_singular());
+            vlu.compute(VluJob::MatMul {
+                dest: 2,
+                a: 0,
+                b: 1,
+            })
+            .unwrap();
+            let product = vlu.matrix(2).unwrap();
+            for (i, row) in product.iter().enumerate() {
+                for (j, value) in row.iter().enumerate() {
+                    let expected = if i == j { 1.0 } else { 0.0 };
+                    assert!((value - expected).abs() < 1e-5, "{product:?}");
+                }
+            }
+        }
+        // Determinant of the last matrix
+        vlu.compute(VluJob::MatInverse { dest: 1, src: 0 }).unwrap();
+        assert!((vlu.scalar_result() - 21.375).abs() < 1e-4);
+    }
+
+    #[test]
+    fn mat_inverse_singular_falls_back_to_identity() {
+        let mut vlu = Vlu::new();
+        vlu.set_matrix(1, [[9.0; 3]; 3]).unwrap();
+
+        let (result, _) = vlu.compute(VluJob::MatInverse { dest: 1, src: 0 }).unwrap();
+
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        assert!(vlu.last_inverse_singular());
+        assert_eq!(result, VluResult::Matrix(identity));
+        assert_eq!(vlu.matrix(1).unwrap(), identity);
+        assert_eq!(vlu.scalar_result(), 0.0);
+    }
 }
@@ -13,8 +13,59 @@
 // Remove module declaration
 // pub mod bytecode;
 // Use crate-level bytecode module
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use thiserror::Error;
+
 pub use crate::bytecode::{BytecodeModule, Value};
 
+/// Type tags accepted by TCHECK
+pub const TAG_INT24: u8 = 0;
+pub const TAG_FIXED16_16: u8 = 1;
+pub const TAG_BOOL: u8 = 2;
+pub const TAG_HANDLE: u8 = 3;
+
+/// Maximum nesting of CALL before the VM gives up
+pub const MAX_CALL_DEPTH: usize = 256;
+
+/// Saved state for one active CALL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+    /// Bytecode address to resume at on RET
+    pub return_pc: usize,
+    /// Index of the frame's slot 0 in the locals area
+    pub frame_base: usize,
+}
+
+/// Execution context of a fiber while it is suspended
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FiberState {
+    pub pc: usize,
+    pub stack: Vec<Value>,
+    pub call_stack: Vec<CallFrame>,
+    pub locals: Vec<Value>,
+    /// Set once the fiber has run to HALT or the end of the code
+    pub finished: bool,
+}
+
+/// Heap object a `Value::Handle` refers to
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    /// Array created by NEWARR
+    Array(Vec<Value>),
+    /// Fiber created by `spawn_fiber`; `None` while it is the one running
+    Fiber(Option<FiberState>),
+}
+
+/// Why the interpreter loop stopped
+enum Exit {
+    Halt,
+    End,
+    Yield,
+    /// RESUME popped this fiber handle; the driver runs it and continues
+    Resume(Value),
+}
+
 /// Simple VM state placeholder
 pub struct BaseplateVm {
     /// Loaded bytecode module
@@ -23,6 +74,13 @@ pub struct BaseplateVm {
     pc: usize,
     /// Operand stack
     stack: Vec<Value>,
+    /// Active calls, innermost last
+    call_stack: Vec<CallFrame>,
+    /// Local variable slots for every frame, addressed from each frame's base
+    locals: Vec<Value>,
+    /// Arrays and fibers, indexed by `Value::Handle`; sharing one table
+    /// keeps an array handle from naming a fiber and vice versa
+    heap: Vec<Object>,
 }
 
 impl BaseplateVm {
@@ -32,11 +90,101 @@ impl BaseplateVm {
             module: bytecode,
             pc: 0,
             stack: Vec::new(),
+            call_stack: Vec::new(),
+            locals: Vec::new(),
+            heap: Vec::new(),
+        }
+    }
+
+    /// Create a suspended fiber that will start at `entry_pc`
+    pub fn spawn_fiber(&mut self, entry_pc: usize) -> Result<Value, String> {
+        let handle = alloc(
+            &mut self.heap,
+            Object::Fiber(Some(FiberState {
+                pc: entry_pc,
+                ..FiberState::default()
+            })),
+        )?;
+        Ok(Value::Handle(handle))
+    }
+
+    /// The fiber slot behind `handle`
+    fn fiber_slot(&mut self, handle: Value) -> Result<&mut Option<FiberState>, String> {
+        let Value::Handle(index) = handle else {
+            return Err("Invalid fiber handle".into());
+        };
+        match self.heap.get_mut(index as usize) {
+            Some(Object::Fiber(slot)) => Ok(slot),
+            Some(Object::Array(_)) => Err("Handle is not a fiber".into()),
+            None => Err("Invalid fiber handle".into()),
         }
     }
 
-    /// Run until halt or error (placeholder)
+    /// Run a fiber until its next YIELD, returning the yielded value, or
+    /// until it finishes, returning `None`
+    pub fn run_fiber(&mut self, handle: Value) -> Result<Option<Value>, String> {
+        let slot = self.fiber_slot(handle)?;
+        let fiber = slot.take().ok_or("Fiber is already running")?;
+        if fiber.finished {
+            *slot = Some(fiber);
+            return Ok(None);
+        }
+
+        let caller = self.swap_context(fiber);
+        let exit = self.drive();
+        let mut fiber = self.swap_context(caller);
+        let yielded = match exit {
+            Ok(Exit::Yield) => Some(fiber.stack.pop().unwrap_or(Value::Nil)),
+            Ok(_) | Err(_) => {
+                fiber.finished = true;
+                None
+            }
+        };
+        *self.fiber_slot(handle)? = Some(fiber);
+        exit.map(|_| yielded)
+    }
+
+    /// Install `next` as the running context and return the previous one
+    fn swap_context(&mut self, next: FiberState) -> FiberState {
+        FiberState {
+            pc: std::mem::replace(&mut self.pc, next.pc),
+            stack: std::mem::replace(&mut self.stack, next.stack),
+            call_stack: std::mem::replace(&mut self.call_stack, next.call_stack),
+            locals: std::mem::replace(&mut self.locals, next.locals),
+            finished: false,
+        }
+    }
+
+    /// Values currently on the operand stack, top last
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    /// Base of the current frame's locals (0 outside any call)
+    fn frame_base(&self) -> usize {
+        self.call_stack.last().map_or(0, |frame| frame.frame_base)
+    }
+
+    /// Run until halt, YIELD or error (placeholder). After a YIELD, calling
+    /// `run` again continues from the next instruction.
     pub fn run(&mut self) -> Result<(), String> {
+        self.drive().map(|_| ())
+    }
+
+    /// Interpret, running fibers for RESUME and pushing what they yield
+    fn drive(&mut self) -> Result<Exit, String> {
+        loop {
+            match self.execute()? {
+                Exit::Resume(handle) => {
+                    let value = self.run_fiber(handle)?;
+                    self.stack.push(value.unwrap_or(Value::Nil));
+                }
+                exit => return Ok(exit),
+            }
+        }
+    }
+
+    fn execute(&mut self) -> Result<Exit, String> {
         let bytes = &self.module.bytecode();
         while self.pc < bytes.len() {
             let opcode = bytes[self.pc];
@@ -48,29 +196,69 @@ impl BaseplateVm {
                 1 => {
                     // HALT
                     self.pc += 3;
-                    return Ok(());
+                    return Ok(Exit::Halt);
                 }
                 2 => {
                     // JMP imm24
-                    let addr = ((bytes[self.pc + 1] as usize) << 16)
-                        | ((bytes[self.pc + 2] as usize) << 8)
-                        | (bytes[self.pc + 3] as usize);
-                    self.pc = addr;
+                    self.pc = operand_u24(bytes, self.pc)? as usize;
+                }
+                4 => {
+                    // CALL imm24
+                    if self.call_stack.len() >= MAX_CALL_DEPTH {
+                        return Err("Call stack overflow".into());
+                    }
+                    let target = operand_u24(bytes, self.pc)? as usize;
+                    self.call_stack.push(CallFrame {
+                        return_pc: self.pc + 6,
+                        frame_base: self.locals.len(),
+                    });
+                    self.pc = target;
+                }
+                5 => {
+                    // RET: the return value stays on the operand stack
+                    let frame = self.call_stack.pop().ok_or("Call stack underflow")?;
+                    self.locals.truncate(frame.frame_base);
+                    self.pc = frame.return_pc;
+                }
+                6 | 7 => {
+                    // IF_TRUE / IF_FALSE imm24
+                    let cond = self.stack.pop().ok_or("Stack underflow")?;
+                    let truthy = !matches!(cond, Value::Bool(false) | Value::Nil | Value::Int24(0));
+                    if truthy == (opcode == 6) {
+                        self.pc = operand_u24(bytes, self.pc)? as usize;
+                    } else {
+                        self.pc += 6;
+                    }
                 }
                 16 => {
                     // LDK kidx
-                    let kidx = ((bytes[self.pc + 2] as u16) << 8) | (bytes[self.pc + 1] as u16);
+                    let _kidx = ((bytes[self.pc + 2] as u16) << 8) | (bytes[self.pc + 1] as u16);
                     // TODO: lookup constant pool (not yet implemented)
                     self.stack.push(Value::Nil);
-                    self.pc += 3;
+                    self.pc += 6;
                 }
                 17 => {
-                    // LDI imm24
-                    let imm = ((bytes[self.pc + 2] as u32) << 16)
-                        | ((bytes[self.pc + 1] as u32) << 8)
-                        | (bytes[self.pc] as u32);
-                    self.stack.push(Value::Int24(imm as i32));
-                    self.pc += 3;
+                    // LDI imm24 (sign-extended)
+                    let imm = operand_u24(bytes, self.pc)?;
+                    self.stack.push(Value::Int24(((imm << 8) as i32) >> 8));
+                    self.pc += 6;
+                }
+                21 => {
+                    // LDLOCAL slot
+                    let slot = self.frame_base() + operand_u24(bytes, self.pc)? as usize;
+                    let value = self.locals.get(slot).copied().unwrap_or(Value::Nil);
+                    self.stack.push(value);
+                    self.pc += 6;
+                }
+                22 => {
+                    // STLOCAL slot
+                    let slot = self.frame_base() + operand_u24(bytes, self.pc)? as usize;
+                    let value = self.stack.pop().ok_or("Stack underflow")?;
+                    if slot >= self.locals.len() {
+                        self.locals.resize(slot + 1, Value::Nil);
+                    }
+                    self.locals[slot] = value;
+                    self.pc += 6;
                 }
                 32 => {
                     // ADD
@@ -83,12 +271,780 @@ impl BaseplateVm {
                     }
                     self.pc += 3;
                 }
+                33 => {
+                    // SUB
+                    let b = self.stack.pop().ok_or("Stack underflow")?;
+                    let a = self.stack.pop().ok_or("Stack underflow")?;
+                    if let (Value::Int24(ai), Value::Int24(bi)) = (a, b) {
+                        self.stack.push(Value::Int24(ai.wrapping_sub(bi)));
+                    } else {
+                        return Err("Type error in SUB".into());
+                    }
+                    self.pc += 3;
+                }
+                49 => {
+                    // CMP_LT
+                    let b = self.stack.pop().ok_or("Stack underflow")?;
+                    let a = self.stack.pop().ok_or("Stack underflow")?;
+                    if let (Value::Int24(ai), Value::Int24(bi)) = (a, b) {
+                        self.stack.push(Value::Bool(ai < bi));
+                    } else {
+                        return Err("Type error in CMP_LT".into());
+                    }
+                    self.pc += 3;
+                }
+                56 => {
+                    // TCHECK type_tag: leave the top value in place if it has the tag
+                    let tag = operand_u24(bytes, self.pc)? as u8;
+                    let value = *self.stack.last().ok_or("Stack underflow")?;
+                    let matches = match value {
+                        Value::Int24(_) => tag == TAG_INT24,
+                        Value::Fixed16_16(_) => tag == TAG_FIXED16_16,
+                        Value::Bool(_) => tag == TAG_BOOL,
+                        Value::Handle(_) => tag == TAG_HANDLE,
+                        Value::Nil => false,
+                    };
+                    if !matches {
+                        return Err("Type mismatch".into());
+                    }
+                    self.pc += 6;
+                }
+                57 => {
+                    // TCAST_INT: Fixed16_16 -> Int24, truncating toward zero
+                    let value = match self.stack.pop().ok_or("Stack underflow")? {
+                        Value::Fixed16_16(fixed) => Value::Int24(fixed / 0x10000),
+                        int @ Value::Int24(_) => int,
+                        _ => return Err("Type error in TCAST_INT".into()),
+                    };
+                    self.stack.push(value);
+                    self.pc += 3;
+                }
+                58 => {
+                    // TCAST_FIXED: Int24 -> Fixed16_16; the integer part only
+                    // holds -32768..=32767
+                    let value = match self.stack.pop().ok_or("Stack underflow")? {
+                        Value::Int24(int) => Value::Fixed16_16(
+                            int.checked_mul(0x10000).ok_or("Overflow in TCAST_FIXED")?,
+                        ),
+                        fixed @ Value::Fixed16_16(_) => fixed,
+                        _ => return Err("Type error in TCAST_FIXED".into()),
+                    };
+                    self.stack.push(value);
+                    self.pc += 3;
+                }
+                64 => {
+                    // NEWARR size: push a handle to `size` Nil elements
+                    let size = operand_u24(bytes, self.pc)? as usize & 0xFFFF;
+                    let handle = alloc(&mut self.heap, Object::Array(vec![Value::Nil; size]))?;
+                    self.stack.push(Value::Handle(handle));
+                    self.pc += 6;
+                }
+                65 => {
+                    // GETARR: pop index, handle -> push element
+                    let index = self.stack.pop().ok_or("Stack underflow")?;
+                    let handle = self.stack.pop().ok_or("Stack underflow")?;
+                    let value = *array_slot(&mut self.heap, self.pc, handle, index)?;
+                    self.stack.push(value);
+                    self.pc += 3;
+                }
+                66 => {
+                    // SETARR: pop index, value, handle -> element = value
+                    let index = self.stack.pop().ok_or("Stack underflow")?;
+                    let value = self.stack.pop().ok_or("Stack underflow")?;
+                    let handle = self.stack.pop().ok_or("Stack underflow")?;
+                    *array_slot(&mut self.heap, self.pc, handle, index)? = value;
+                    self.pc += 3;
+                }
+                80 => {
+                    // YIELD: hand the top of the stack back to whoever
+                    // resumed this fiber
+                    self.pc += 3;
+                    return Ok(Exit::Yield);
+                }
+                82 => {
+                    // RESUME: pop a fiber handle, push its next yielded value
+                    // (Nil once it has finished)
+                    let handle = self.stack.pop().ok_or("Stack underflow")?;
+                    self.pc += 3;
+                    return Ok(Exit::Resume(handle));
+                }
                 // ... other opcodes would be added similarly
                 _ => {
                     return Err(format!("Unknown opcode {} at pc {}", opcode, self.pc));
                 }
             }
         }
+        Ok(Exit::End)
+    }
+}
+
+/// One problem found by [`validate`]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum ValidationProblem {
+    #[error("unknown opcode {opcode} at pc {pc}")]
+    UnknownOpcode { pc: usize, opcode: u8 },
+    #[error("instruction at pc {pc} runs past the end of the code")]
+    TruncatedInstruction { pc: usize },
+    #[error("jump at pc {pc} targets {target}, which is not an instruction")]
+    InvalidJumpTarget { pc: usize, target: usize },
+    #[error("branch at pc {pc} leaves its function for {target}")]
+    BranchLeavesBlock { pc: usize, target: usize },
+    #[error("constant pool of {len} bytes ends in a truncated entry")]
+    TruncatedConstantPool { len: usize },
+    #[error("entry point {index} is not a function")]
+    InvalidEntryPoint { index: u16 },
+}
+
+/// Every problem found in a module, in the order they were detected
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("bytecode failed validation with {} problem(s)", .problems.len())]
+pub struct ValidationError {
+    pub problems: Vec<ValidationProblem>,
+}
+
+/// Encoded size of an instruction, or `None` for opcodes the VM doesn't run
+fn instruction_size(opcode: u8) -> Option<usize> {
+    match opcode {
+        0 | 1 | 5 | 32 | 33 | 49 | 57 | 58 | 65 | 66 | 80 | 82 => Some(3),
+        2 | 4 | 6 | 7 | 16 | 17 | 21 | 22 | 56 | 64 => Some(6),
+        _ => None,
+    }
+}
+
+/// Check a module before running it.
+///
+/// Functions start at address 0 and at every CALL target; JMP and IF_*
+/// must stay inside the function they are in, and every jump must land on
+/// an instruction. The entry point is an index into those functions.
+pub fn validate(module: &BytecodeModule) -> Result<(), ValidationError> {
+    let code = module.bytecode();
+    let mut problems = Vec::new();
+    let mut starts = HashSet::new();
+    let mut jumps = Vec::new();
+
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let Some(size) = instruction_size(opcode) else {
+            // Later instruction boundaries can't be known past this point
+            problems.push(ValidationProblem::UnknownOpcode { pc, opcode });
+            break;
+        };
+        if pc + size > code.len() {
+            problems.push(ValidationProblem::TruncatedInstruction { pc });
+            break;
+        }
+        starts.insert(pc);
+        if matches!(opcode, 2 | 4 | 6 | 7) {
+            let target = operand_u24(code, pc).unwrap_or_default() as usize;
+            jumps.push((pc, opcode, target));
+        }
+        pc += size;
+    }
+
+    let mut functions = BTreeSet::from([0]);
+    for &(pc, opcode, target) in &jumps {
+        if !starts.contains(&target) {
+            problems.push(ValidationProblem::InvalidJumpTarget { pc, target });
+        } else if opcode == 4 {
+            functions.insert(target);
+        }
+    }
+    let function_of = |addr: usize| functions.range(..=addr).next_back().copied();
+    for &(pc, opcode, target) in &jumps {
+        if opcode != 4 && starts.contains(&target) && function_of(pc) != function_of(target) {
+            problems.push(ValidationProblem::BranchLeavesBlock { pc, target });
+        }
+    }
+
+    let len = module.constant_pool_len();
+    if !len.is_multiple_of(3) {
+        problems.push(ValidationProblem::TruncatedConstantPool { len });
+    }
+    let index = module.entry_point();
+    if index as usize >= functions.len() {
+        problems.push(ValidationProblem::InvalidEntryPoint { index });
+    }
+
+    if problems.is_empty() {
         Ok(())
+    } else {
+        Err(ValidationError { problems })
+    }
+}
+
+/// Errors produced while linking modules
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("unresolved symbols: {}", .names.join(", "))]
+    UnresolvedSymbols { names: Vec<String> },
+    #[error("symbol {name} is exported more than once")]
+    DuplicateExport { name: String },
+    #[error("import in module {module} at offset {offset} is not a CALL")]
+    InvalidImportSite { module: usize, offset: usize },
+}
+
+/// Several modules merged into one code buffer with cross-module calls
+/// resolved
+pub struct LinkedModule {
+    pub modules: Vec<BytecodeModule>,
+    /// Export name -> (module index, index in that module's export table)
+    pub exports: HashMap<String, (usize, u16)>,
+    /// Start of each module's code in the merged buffer
+    bases: Vec<usize>,
+    code: Vec<u8>,
+}
+
+impl LinkedModule {
+    /// The merged code, with every jump target made absolute
+    pub fn bytecode(&self) -> &[u8] {
+        &self.code
+    }
+
+    /// Merged-buffer offset of module `index`'s code
+    pub fn base(&self, index: usize) -> Option<usize> {
+        self.bases.get(index).copied()
+    }
+
+    /// Absolute address of an exported function
+    pub fn export_address(&self, name: &str) -> Option<usize> {
+        let &(module, function) = self.exports.get(name)?;
+        let symbol = &self.modules[module].exports()[function as usize];
+        Some(self.bases[module] + symbol.offset as usize)
+    }
+
+    /// A module the VM can run, starting at the first module's code
+    pub fn to_module(&self) -> BytecodeModule {
+        BytecodeModule::from_code(self.code.clone())
+    }
+}
+
+/// Concatenate modules' code and resolve each module's imports against the
+/// others' exports. Jump targets inside every module are relocated by the
+/// module's position in the merged buffer; imported CALLs get the exporter's
+/// absolute address.
+pub fn link(modules: Vec<BytecodeModule>) -> Result<LinkedModule, LinkError> {
+    let mut exports = HashMap::new();
+    for (index, module) in modules.iter().enumerate() {
+        for (function, symbol) in module.exports().iter().enumerate() {
+            if exports
+                .insert(symbol.name.clone(), (index, function as u16))
+                .is_some()
+            {
+                return Err(LinkError::DuplicateExport {
+                    name: symbol.name.clone(),
+                });
+            }
+        }
+    }
+
+    let mut code = Vec::new();
+    let mut bases = Vec::new();
+    for module in &modules {
+        let base = code.len();
+        bases.push(base);
+        code.extend_from_slice(module.bytecode());
+        let mut pc = base;
+        while let Some(size) = code.get(pc).copied().and_then(instruction_size) {
+            if pc + size > code.len() {
+                break;
+            }
+            if matches!(code[pc], 2 | 4 | 6 | 7) {
+                let target = operand_u24(&code, pc).unwrap_or_default() as usize;
+                write_u24(&mut code, pc, (target + base) as u32);
+            }
+            pc += size;
+        }
+    }
+
+    let mut unresolved = Vec::new();
+    for (index, module) in modules.iter().enumerate() {
+        for symbol in module.imports() {
+            let site = bases[index] + symbol.offset as usize;
+            if symbol.offset as usize + 4 > module.bytecode().len() || code[site] != 4 {
+                return Err(LinkError::InvalidImportSite {
+                    module: index,
+                    offset: symbol.offset as usize,
+                });
+            }
+            match exports.get(&symbol.name) {
+                Some(&(exporter, function)) => {
+                    let offset = modules[exporter].exports()[function as usize].offset;
+                    write_u24(&mut code, site, bases[exporter] as u32 + offset);
+                }
+                None => unresolved.push(symbol.name.clone()),
+            }
+        }
+    }
+    if !unresolved.is_empty() {
+        return Err(LinkError::UnresolvedSymbols { names: unresolved });
+    }
+
+    Ok(LinkedModule {
+        modules,
+        exports,
+        bases,
+        code,
+    })
+}
+
+/// Add `object` to the heap and return its handle
+fn alloc(heap: &mut Vec<Object>, object: Object) -> Result<u16, String> {
+    let handle = u16::try_from(heap.len()).map_err(|_| "Heap exhausted")?;
+    heap.push(object);
+    Ok(handle)
+}
+
+/// Resolve an array handle and index popped by GETARR/SETARR
+fn array_slot(
+    heap: &mut [Object],
+    pc: usize,
+    handle: Value,
+    index: Value,
+) -> Result<&mut Value, String> {
+    let (Value::Handle(handle), Value::Int24(index)) = (handle, index) else {
+        return Err(format!("Type error in array access at pc {}", pc));
+    };
+    let array = match heap.get_mut(handle as usize) {
+        Some(Object::Array(array)) => array,
+        Some(Object::Fiber(_)) => return Err(format!("Handle is not an array at pc {}", pc)),
+        None => return Err(format!("Invalid array handle at pc {}", pc)),
+    };
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| array.get_mut(index))
+        .ok_or_else(|| format!("Array index out of bounds at pc {}", pc))
+}
+
+/// Overwrite the 24-bit operand of the instruction at `pc`
+fn write_u24(code: &mut [u8], pc: usize, value: u32) {
+    code[pc + 1..pc + 4].copy_from_slice(&value.to_le_bytes()[..3]);
+}
+
+/// Little-endian 24-bit operand following the opcode at `pc`
+fn operand_u24(bytes: &[u8], pc: usize) -> Result<u32, String> {
+    let operand = bytes
+        .get(pc + 1..pc + 4)
+        .ok_or_else(|| format!("Truncated operand at pc {}", pc))?;
+    Ok(u32::from_le_bytes([operand[0], operand[1], operand[2], 0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a W1 instruction (opcode + 2 pad bytes)
+    fn w1(code: &mut Vec<u8>, opcode: u8) {
+        code.extend_from_slice(&[opcode, 0, 0]);
+    }
+
+    /// Encode a W2 instruction with a 24-bit operand, padded to 6 bytes
+    fn w2(code: &mut Vec<u8>, opcode: u8, operand: u32) {
+        code.push(opcode);
+        code.extend_from_slice(&operand.to_le_bytes()[..3]);
+        code.extend_from_slice(&[0, 0]);
+    }
+
+    const HALT: u8 = 1;
+    const CALL: u8 = 4;
+    const RET: u8 = 5;
+    const IF_FALSE: u8 = 7;
+    const LDI: u8 = 17;
+    const LDLOCAL: u8 = 21;
+    const STLOCAL: u8 = 22;
+    const ADD: u8 = 32;
+    const SUB: u8 = 33;
+    const CMP_LT: u8 = 49;
+    const TCHECK: u8 = 56;
+    const TCAST_INT: u8 = 57;
+    const TCAST_FIXED: u8 = 58;
+    const NEWARR: u8 = 64;
+    const GETARR: u8 = 65;
+    const SETARR: u8 = 66;
+    const YIELD: u8 = 80;
+    const RESUME: u8 = 82;
+
+    #[test]
+    fn recursive_fibonacci() {
+        // main: push 10, call fib, halt
+        let mut code = Vec::new();
+        w2(&mut code, LDI, 10);
+        w2(&mut code, CALL, 15);
+        w1(&mut code, HALT);
+
+        // fib(n): n < 2 ? n : fib(n - 1) + fib(n - 2)
+        let fib = code.len() as u32;
+        assert_eq!(fib, 15);
+        w2(&mut code, STLOCAL, 0);
+        w2(&mut code, LDLOCAL, 0);
+        w2(&mut code, LDI, 2);
+        w1(&mut code, CMP_LT);
+        let recurse = code.len() as u32 + 6 + 6 + 3;
+        w2(&mut code, IF_FALSE, recurse);
+        w2(&mut code, LDLOCAL, 0);
+        w1(&mut code, RET);
+        assert_eq!(code.len() as u32, recurse);
+        w2(&mut code, LDLOCAL, 0);
+        w2(&mut code, LDI, 1);
+        w1(&mut code, SUB);
+        w2(&mut code, CALL, fib);
+        w2(&mut code, LDLOCAL, 0);
+        w2(&mut code, LDI, 2);
+        w1(&mut code, SUB);
+        w2(&mut code, CALL, fib);
+        w1(&mut code, ADD);
+        w1(&mut code, RET);
+
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        vm.run().expect("run");
+        assert_eq!(vm.stack(), &[Value::Int24(55)]);
+        assert!(vm.call_stack.is_empty());
+        assert!(vm.locals.is_empty());
+    }
+
+    #[test]
+    fn unbounded_recursion_overflows_call_stack() {
+        let mut code = Vec::new();
+        w2(&mut code, CALL, 0);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        assert_eq!(vm.run(), Err("Call stack overflow".to_string()));
+        assert_eq!(vm.call_stack.len(), MAX_CALL_DEPTH);
+    }
+
+    #[test]
+    fn locals_are_private_to_each_frame() {
+        let mut code = Vec::new();
+        w2(&mut code, LDI, 7);
+        w2(&mut code, STLOCAL, 0);
+        w2(&mut code, CALL, 30);
+        w2(&mut code, LDLOCAL, 0);
+        w1(&mut code, HALT);
+        // Callee at 30 (after a NOP) overwrites its own slot 0 and returns it
+        w1(&mut code, 0);
+        w2(&mut code, LDI, -3i32 as u32);
+        w2(&mut code, STLOCAL, 0);
+        w2(&mut code, LDLOCAL, 0);
+        w1(&mut code, RET);
+
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        vm.run().expect("run");
+        assert_eq!(vm.stack(), &[Value::Int24(-3), Value::Int24(7)]);
+        assert!(matches!(
+            BaseplateVm::new(BytecodeModule::from_code(vec![RET, 0, 0])).run(),
+            Err(msg) if msg == "Call stack underflow"
+        ));
+    }
+
+    #[test]
+    fn array_reads_and_writes_are_bounds_checked() {
+        let mut code = Vec::new();
+        w2(&mut code, NEWARR, 5);
+        w2(&mut code, STLOCAL, 0);
+        for i in 0..5 {
+            w2(&mut code, LDLOCAL, 0);
+            w2(&mut code, LDI, i * 10);
+            w2(&mut code, LDI, i);
+            w1(&mut code, SETARR);
+        }
+        for i in 0..5 {
+            w2(&mut code, LDLOCAL, 0);
+            w2(&mut code, LDI, i);
+            w1(&mut code, GETARR);
+        }
+        let in_bounds = code.clone();
+        w1(&mut code, HALT);
+
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        vm.run().expect("run");
+        let expected: Vec<Value> = (0..5).map(|i| Value::Int24(i * 10)).collect();
+        assert_eq!(vm.stack(), expected.as_slice());
+
+        let mut code = in_bounds;
+        w2(&mut code, LDLOCAL, 0);
+        w2(&mut code, LDI, 5);
+        let pc = code.len();
+        w1(&mut code, GETARR);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        assert_eq!(
+            vm.run(),
+            Err(format!("Array index out of bounds at pc {}", pc))
+        );
+    }
+
+    #[test]
+    fn type_checks_and_casts() {
+        // 1 + (1 < 1): ADD rejects the Bool left by CMP_LT
+        let mut code = Vec::new();
+        w2(&mut code, LDI, 1);
+        w2(&mut code, LDI, 1);
+        w2(&mut code, LDI, 1);
+        w1(&mut code, CMP_LT);
+        w1(&mut code, ADD);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        assert_eq!(vm.run(), Err("Type error in ADD".to_string()));
+
+        let mut code = Vec::new();
+        w2(&mut code, LDI, 1);
+        w1(&mut code, TCAST_FIXED);
+        w2(&mut code, TCHECK, TAG_FIXED16_16 as u32);
+        w2(&mut code, LDI, -7i32 as u32);
+        w1(&mut code, TCAST_FIXED);
+        w1(&mut code, TCAST_INT);
+        w2(&mut code, TCHECK, TAG_INT24 as u32);
+        w1(&mut code, HALT);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        vm.run().expect("run");
+        assert_eq!(
+            vm.stack(),
+            &[Value::Fixed16_16(0x0001_0000), Value::Int24(-7)]
+        );
+
+        let mut code = Vec::new();
+        w2(&mut code, NEWARR, 1);
+        w2(&mut code, TCHECK, TAG_INT24 as u32);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        assert_eq!(vm.run(), Err("Type mismatch".to_string()));
+    }
+
+    #[test]
+    fn tcast_fixed_rejects_values_out_of_range() {
+        let cast = |value: i32| {
+            let mut code = Vec::new();
+            w2(&mut code, LDI, value as u32);
+            w1(&mut code, TCAST_FIXED);
+            w1(&mut code, HALT);
+            let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+            vm.run().map(|_| vm.stack().to_vec())
+        };
+        assert_eq!(cast(32767), Ok(vec![Value::Fixed16_16(0x7FFF_0000)]));
+        assert_eq!(cast(-32768), Ok(vec![Value::Fixed16_16(i32::MIN)]));
+        assert_eq!(cast(40_000), Err("Overflow in TCAST_FIXED".to_string()));
+        assert_eq!(cast(-0x80_0000), Err("Overflow in TCAST_FIXED".to_string()));
+    }
+
+    /// Build a .bpx image with the given constant pool bytes, code and entry
+    fn bpx(constants: &[u8], code: &[u8], entry_point: u16) -> BytecodeModule {
+        bpx_with_meta(constants, code, entry_point, &[])
+    }
+
+    /// Like [`bpx`], with a metadata section holding link tables
+    fn bpx_with_meta(
+        constants: &[u8],
+        code: &[u8],
+        entry_point: u16,
+        meta: &[u8],
+    ) -> BytecodeModule {
+        let cp_offset = 23u32;
+        let code_offset = cp_offset + constants.len() as u32;
+        let meta_offset = if meta.is_empty() {
+            0
+        } else {
+            code_offset + code.len() as u32
+        };
+        let mut buf = b"BPX0".to_vec();
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(&cp_offset.to_le_bytes()[..3]);
+        buf.extend_from_slice(&code_offset.to_le_bytes()[..3]);
+        buf.extend_from_slice(&meta_offset.to_le_bytes()[..3]);
+        buf.extend_from_slice(&entry_point.to_le_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(constants);
+        buf.extend_from_slice(code);
+        buf.extend_from_slice(meta);
+        BytecodeModule::from_bytes(&buf).expect("parse module")
+    }
+
+    /// Encode a symbol table for the metadata section
+    fn symbols(entries: &[(&str, u32)]) -> Vec<u8> {
+        let mut table = vec![entries.len() as u8];
+        for (name, offset) in entries {
+            table.push(name.len() as u8);
+            table.extend_from_slice(name.as_bytes());
+            table.extend_from_slice(&offset.to_le_bytes()[..3]);
+        }
+        table
+    }
+
+    #[test]
+    fn validator_accepts_well_formed_code() {
+        let mut code = Vec::new();
+        w2(&mut code, CALL, 9);
+        w1(&mut code, HALT);
+        w2(&mut code, IF_FALSE, 15);
+        w1(&mut code, RET);
+        assert_eq!(validate(&bpx(&[1, 0, 0, 2, 0, 0], &code, 1)), Ok(()));
+    }
+
+    #[test]
+    fn validator_reports_every_problem() {
+        let mut code = Vec::new();
+        w2(&mut code, 2, 0x100); // JMP past the end
+        w2(&mut code, CALL, 21);
+        w2(&mut code, 2, 4); // JMP into the middle of the CALL
+        w1(&mut code, HALT);
+        w2(&mut code, 2, 18); // JMP from the function back into main
+        w1(&mut code, RET);
+
+        let problems = validate(&bpx(&[1, 0, 0, 2], &code, 2))
+            .unwrap_err()
+            .problems;
+        assert_eq!(
+            problems,
+            vec![
+                ValidationProblem::InvalidJumpTarget {
+                    pc: 0,
+                    target: 0x100
+                },
+                ValidationProblem::InvalidJumpTarget { pc: 12, target: 4 },
+                ValidationProblem::BranchLeavesBlock { pc: 21, target: 18 },
+                ValidationProblem::TruncatedConstantPool { len: 4 },
+                ValidationProblem::InvalidEntryPoint { index: 2 },
+            ]
+        );
+
+        let truncated = BytecodeModule::from_code(vec![LDI, 1]);
+        assert_eq!(
+            validate(&truncated).unwrap_err().problems,
+            vec![ValidationProblem::TruncatedInstruction { pc: 0 }]
+        );
+    }
+
+    #[test]
+    fn link_resolves_cross_module_calls() {
+        // main: push 20, call the imported `double`, halt
+        let mut main = Vec::new();
+        w2(&mut main, LDI, 20);
+        w2(&mut main, CALL, 0);
+        w1(&mut main, HALT);
+        let mut meta = symbols(&[]);
+        meta.extend(symbols(&[("double", 6)]));
+        let main = bpx_with_meta(&[], &main, 0, &meta);
+
+        // lib: a filler function, then double(n) = n + n with an internal jump
+        let mut lib = Vec::new();
+        w1(&mut lib, RET);
+        w2(&mut lib, STLOCAL, 0);
+        w2(&mut lib, 2, 15);
+        w2(&mut lib, LDLOCAL, 0);
+        w2(&mut lib, LDLOCAL, 0);
+        w1(&mut lib, ADD);
+        w1(&mut lib, RET);
+        let mut meta = symbols(&[("double", 3)]);
+        meta.extend(symbols(&[]));
+        let lib = bpx_with_meta(&[], &lib, 0, &meta);
+
+        let linked = link(vec![main, lib]).expect("link");
+        assert_eq!(linked.base(1), Some(15));
+        assert_eq!(linked.exports["double"], (1, 0));
+        assert_eq!(linked.export_address("double"), Some(18));
+        // The CALL now points into lib, and lib's JMP is relocated
+        assert_eq!(operand_u24(linked.bytecode(), 6), Ok(18));
+        assert_eq!(operand_u24(linked.bytecode(), 24), Ok(30));
+
+        let mut vm = BaseplateVm::new(linked.to_module());
+        vm.run().expect("run");
+        assert_eq!(vm.stack(), &[Value::Int24(40)]);
+    }
+
+    #[test]
+    fn link_reports_unresolved_symbols() {
+        let mut code = Vec::new();
+        w2(&mut code, CALL, 0);
+        w2(&mut code, CALL, 0);
+        let mut meta = symbols(&[]);
+        meta.extend(symbols(&[("missing", 0), ("absent", 6)]));
+        let module = bpx_with_meta(&[], &code, 0, &meta);
+        assert_eq!(
+            link(vec![module]).err(),
+            Some(LinkError::UnresolvedSymbols {
+                names: vec!["missing".to_string(), "absent".to_string()]
+            })
+        );
+    }
+
+    /// Fiber body at 0: yield 0..5 in order, then halt
+    fn counter_fiber() -> Vec<u8> {
+        let mut code = Vec::new();
+        w2(&mut code, LDI, 0);
+        w2(&mut code, STLOCAL, 0);
+        let top = code.len() as u32;
+        w2(&mut code, LDLOCAL, 0);
+        w1(&mut code, YIELD);
+        w2(&mut code, LDLOCAL, 0);
+        w2(&mut code, LDI, 1);
+        w1(&mut code, ADD);
+        w2(&mut code, STLOCAL, 0);
+        w2(&mut code, LDLOCAL, 0);
+        w2(&mut code, LDI, 5);
+        w1(&mut code, CMP_LT);
+        w2(&mut code, 6, top); // IF_TRUE
+        w1(&mut code, HALT);
+        code
+    }
+
+    #[test]
+    fn fiber_yields_values_to_host() {
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(counter_fiber()));
+        let fiber = vm.spawn_fiber(0).expect("spawn");
+        for expected in 0..5 {
+            assert_eq!(vm.run_fiber(fiber), Ok(Some(Value::Int24(expected))));
+        }
+        assert_eq!(vm.run_fiber(fiber), Ok(None));
+        assert_eq!(vm.run_fiber(fiber), Ok(None));
+        // The host context is untouched by the fiber's locals and stack
+        assert!(vm.stack().is_empty());
+        assert!(vm.locals.is_empty());
+    }
+
+    #[test]
+    fn resume_runs_fiber_from_bytecode() {
+        let mut code = counter_fiber();
+        let main = code.len();
+        w2(&mut code, STLOCAL, 0);
+        for _ in 0..2 {
+            w2(&mut code, LDLOCAL, 0);
+            w1(&mut code, RESUME);
+        }
+        w1(&mut code, HALT);
+
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        let fiber = vm.spawn_fiber(0).expect("spawn");
+        vm.pc = main;
+        vm.stack.push(fiber);
+        vm.run().expect("run");
+        assert_eq!(vm.stack(), &[Value::Int24(0), Value::Int24(1)]);
+        assert_eq!(vm.run_fiber(fiber), Ok(Some(Value::Int24(2))));
+    }
+
+    #[test]
+    fn handles_reject_the_wrong_kind_of_object() {
+        // RESUME on an array handle
+        let mut code = Vec::new();
+        w2(&mut code, NEWARR, 1);
+        w1(&mut code, RESUME);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        assert_eq!(vm.run(), Err("Handle is not a fiber".to_string()));
+
+        // GETARR on a fiber handle, pushed before the index
+        let mut code = Vec::new();
+        w2(&mut code, LDI, 0);
+        let pc = code.len();
+        w1(&mut code, GETARR);
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(code));
+        let fiber = vm.spawn_fiber(0).expect("spawn");
+        vm.stack.push(fiber);
+        assert_eq!(
+            vm.run(),
+            Err(format!("Handle is not an array at pc {}", pc))
+        );
+    }
+
+    #[test]
+    fn spawn_fiber_reports_exhausted_heap() {
+        let mut vm = BaseplateVm::new(BytecodeModule::from_code(vec![1, 0, 0]));
+        for _ in 0..=u16::MAX {
+            vm.spawn_fiber(0).expect("spawn");
+        }
+        assert_eq!(vm.spawn_fiber(0), Err("Heap exhausted".to_string()));
     }
 }